@@ -1,4 +1,8 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/indexer.proto")?;
+    // The descriptor set backs the gRPC reflection service.
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("indexer_descriptor.bin"))
+        .compile(&["proto/indexer.proto"], &["proto"])?;
     Ok(())
 }
@@ -0,0 +1,15 @@
+-- Migration: Persist CI events for failure correlation
+-- The temporal index keeps a bounded window of events in memory; this table
+-- keeps the full history so older runs can still be looked up.
+
+CREATE TABLE IF NOT EXISTS ci_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    test_name TEXT NOT NULL,
+    status TEXT NOT NULL,
+    commit_id TEXT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ci_events_commit ON ci_events(commit_id, timestamp);
@@ -0,0 +1,22 @@
+-- Migration: Reduced-precision embedding storage
+-- Requires: pgvector 0.7.0 or later (halfvec)
+-- Used when EMBEDDING_STORAGE_PRECISION is f16. Rows written under
+-- one precision are not visible to searches under another, so re-index after
+-- switching, or backfill from the full-precision column:
+--   UPDATE documents SET embedding_half = embedding_vector::halfvec(384);
+--   UPDATE symbols SET embedding_half = embedding_vector::halfvec(384);
+
+ALTER TABLE documents
+ADD COLUMN IF NOT EXISTS embedding_half halfvec(384);
+
+ALTER TABLE symbols
+ADD COLUMN IF NOT EXISTS embedding_half halfvec(384);
+
+CREATE INDEX IF NOT EXISTS idx_documents_embedding_half
+ON documents USING hnsw (embedding_half halfvec_cosine_ops);
+
+CREATE INDEX IF NOT EXISTS idx_symbols_embedding_half
+ON symbols USING hnsw (embedding_half halfvec_cosine_ops);
+
+COMMENT ON COLUMN documents.embedding_half IS 'Half-precision embedding (384 dimensions)';
+COMMENT ON COLUMN symbols.embedding_half IS 'Half-precision embedding (384 dimensions)';
@@ -0,0 +1,9 @@
+-- Migration: Fuzzy symbol-name lookup
+-- Requires: pg_trgm (ships with PostgreSQL contrib)
+-- Backs FindSymbolsByName: ILIKE substring matches and the trigram `%`
+-- operator both use this index instead of scanning every symbol.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_symbols_name_trgm
+ON symbols USING gin (name gin_trgm_ops);
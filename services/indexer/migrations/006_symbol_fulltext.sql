@@ -0,0 +1,9 @@
+-- Migration: Keyword and hybrid symbol search
+-- Backs SearchSymbols in KEYWORD and HYBRID mode. The expression must match
+-- SYMBOL_TSVECTOR in src/storage.rs exactly for the planner to use it. The
+-- 'simple' configuration keeps identifiers unstemmed.
+
+CREATE INDEX IF NOT EXISTS idx_symbols_fulltext
+ON symbols USING gin (
+    (setweight(to_tsvector('simple', name), 'A') || setweight(to_tsvector('simple', content), 'D'))
+);
@@ -0,0 +1,9 @@
+-- Migration: Keyword and hybrid document search
+-- Backs SearchDocuments in KEYWORD and HYBRID mode, which keeps document
+-- search available while the embedding provider is down. The expression must
+-- match DOCUMENT_TSVECTOR in src/storage.rs exactly for the planner to use it.
+
+CREATE INDEX IF NOT EXISTS idx_documents_fulltext
+ON documents USING gin (
+    (setweight(to_tsvector('simple', path), 'A') || setweight(to_tsvector('simple', content), 'D'))
+);
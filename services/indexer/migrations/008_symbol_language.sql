@@ -0,0 +1,38 @@
+-- Migration: Dedicated symbol language column
+-- Backs the `language` metadata filter of SearchSymbols with a plain,
+-- indexed column instead of extracting it from the metadata JSON. Every
+-- write path fills it with the canonical name (see ast::canonical_language):
+-- from metadata.language when present, otherwise from the file extension
+-- (ast::language_for_path).
+
+ALTER TABLE symbols
+ADD COLUMN IF NOT EXISTS language TEXT;
+
+-- Backfill rows written before this migration the same way.
+UPDATE symbols
+SET language = CASE lower(btrim(metadata->>'language'))
+    WHEN 'ts' THEN 'typescript'
+    WHEN 'tsx' THEN 'typescript'
+    WHEN 'js' THEN 'javascript'
+    WHEN 'jsx' THEN 'javascript'
+    WHEN 'rs' THEN 'rust'
+    WHEN 'py' THEN 'python'
+    WHEN 'golang' THEN 'go'
+    ELSE lower(btrim(metadata->>'language'))
+END
+WHERE language IS NULL
+  AND metadata->>'language' IS NOT NULL;
+
+UPDATE symbols
+SET language = CASE
+    WHEN path LIKE '%.rs' THEN 'rust'
+    WHEN path LIKE '%.ts' OR path LIKE '%.tsx' THEN 'typescript'
+    WHEN path LIKE '%.js' OR path LIKE '%.jsx' THEN 'javascript'
+    WHEN path LIKE '%.py' OR path LIKE '%.pyi' THEN 'python'
+    WHEN path LIKE '%.go' THEN 'go'
+END
+WHERE language IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_symbols_language ON symbols (language);
+
+COMMENT ON COLUMN symbols.language IS 'Canonical source language (rust, typescript, ...); NULL when unknown';
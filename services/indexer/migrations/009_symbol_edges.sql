@@ -0,0 +1,14 @@
+-- Migration: Symbol hierarchy
+-- Records which symbol contains which (a class and its methods, a module and
+-- its items) so the registry can rebuild `parent`/`children` after a
+-- restart. Rows go away with either symbol, so re-indexing a file replaces
+-- its edges along with its symbols.
+
+CREATE TABLE IF NOT EXISTS symbol_edges (
+    parent_id UUID NOT NULL REFERENCES symbols(id) ON DELETE CASCADE,
+    child_id UUID NOT NULL REFERENCES symbols(id) ON DELETE CASCADE,
+    relation TEXT NOT NULL CHECK (relation IN ('contains')),
+    PRIMARY KEY (parent_id, child_id, relation)
+);
+
+CREATE INDEX IF NOT EXISTS idx_symbol_edges_child ON symbol_edges (child_id);
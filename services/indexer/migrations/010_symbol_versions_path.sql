@@ -0,0 +1,18 @@
+-- Migration: Persist temporal symbol history
+-- `symbol_versions` (migration 001) had no writer: the temporal index kept
+-- the versions it found walking commits in memory, so history was empty
+-- after a restart until the range was walked again. Versions are now written
+-- here, keyed by the file they belong to so GetSymbolHistory can look them up
+-- by path. Re-walking a commit replaces its rows for a path instead of adding
+-- duplicates.
+
+ALTER TABLE symbol_versions
+ADD COLUMN IF NOT EXISTS path TEXT;
+
+-- The extracted symbol, when the version has one.
+ALTER TABLE symbol_versions
+ADD COLUMN IF NOT EXISTS symbol JSONB;
+
+CREATE INDEX IF NOT EXISTS symbol_versions_path_idx ON symbol_versions (path, timestamp DESC);
+
+COMMENT ON COLUMN symbol_versions.path IS 'File the version belongs to; the new path for renames (see previous_path)';
@@ -1,12 +1,69 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
 use crate::ast::Position;
+use regex::{Regex, RegexBuilder};
 use tree_sitter::{Node, Tree};
 
+/// Longest user-supplied reference pattern accepted.
+pub const MAX_MATCH_PATTERN_LENGTH: usize = 256;
+/// Upper bound on the compiled size of a user regex. The regex crate runs in
+/// linear time, so bounding program size is what keeps hostile patterns cheap.
+const MAX_MATCH_REGEX_SIZE: usize = 64 * 1024;
+/// Most matches returned by [`find_text_matches`].
+pub const MAX_TEXT_MATCHES: usize = 1_000;
+/// Longest name resolved from a cursor position; anything longer is not a name
+/// worth walking the tree for.
+pub const MAX_IDENTIFIER_LENGTH: usize = 256;
+/// Longest line shown in a snippet; the rest is replaced by an ellipsis.
+pub const MAX_SNIPPET_LINE_CHARS: usize = 200;
+
 #[derive(Debug, Clone)]
 pub struct Range {
     pub start: Position,
     pub end: Position,
 }
 
+/// Byte length of each line of a source, so client positions can be snapped
+/// onto the text without rescanning it per position.
+pub struct LineLengths(Vec<usize>);
+
+impl LineLengths {
+    pub fn new(source: &str) -> Self {
+        Self(source.split('\n').map(str::len).collect())
+    }
+
+    /// `line` and `column` moved onto the text: a line past the end goes to
+    /// the last line, and a column past the end of its line to the line's
+    /// end. Without this, a stray column on a minified one-line file resolves
+    /// to the node spanning the whole file.
+    pub fn clamp(&self, line: u32, column: u32) -> Position {
+        let last = self.0.len().saturating_sub(1);
+        let line = (line as usize).min(last);
+        let length = self.0.get(line).copied().unwrap_or(0);
+        Position {
+            line: line as u32,
+            column: column.min(u32::try_from(length).unwrap_or(u32::MAX)),
+        }
+    }
+}
+
+/// `text` with each line longer than `max_chars` cut short with an ellipsis.
+pub fn truncate_lines(text: &str, max_chars: usize) -> Cow<'_, str> {
+    let too_long = |line: &str| line.len() > max_chars && line.chars().nth(max_chars).is_some();
+    if !text.split('\n').any(too_long) {
+        return Cow::Borrowed(text);
+    }
+    let lines: Vec<Cow<str>> = text
+        .split('\n')
+        .map(|line| match line.char_indices().nth(max_chars) {
+            Some((end, _)) => Cow::Owned(format!("{}…", &line[..end])),
+            None => Cow::Borrowed(line),
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
 pub fn node_at_position<'a>(tree: &'a Tree, position: Position) -> Option<Node<'a>> {
     let point = tree_sitter::Point {
         row: position.line as usize,
@@ -21,6 +78,9 @@ pub fn identifier_at_position<'a>(
     position: Position,
 ) -> Option<(String, Node<'a>)> {
     let node = node_at_position(tree, position)?;
+    if is_within_literal(&node) {
+        return None;
+    }
     let identifier_node = if is_identifier(&node) {
         node
     } else {
@@ -41,13 +101,47 @@ pub fn identifier_at_position<'a>(
         .trim()
         .to_string();
 
-    if text.is_empty() {
+    if !is_valid_identifier(&text) {
         return None;
     }
 
     Some((text, identifier_node))
 }
 
+/// True when `node` is, or is a direct piece of, a string, comment or regex
+/// literal. The grandparent is not consulted so identifiers inside template
+/// substitutions still resolve.
+pub fn is_within_literal(node: &Node) -> bool {
+    let is_literal = |node: &Node| {
+        matches!(
+            node.kind(),
+            "string"
+                | "string_fragment"
+                | "string_literal"
+                | "raw_string_literal"
+                | "char_literal"
+                | "template_string"
+                | "escape_sequence"
+                | "comment"
+                | "line_comment"
+                | "block_comment"
+                | "regex"
+                | "regex_pattern"
+        )
+    };
+    is_literal(node) || node.parent().is_some_and(|parent| is_literal(&parent))
+}
+
+/// Rejects text that cannot be a source identifier before it is used as a
+/// search key.
+pub fn is_valid_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= MAX_IDENTIFIER_LENGTH
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
 pub fn is_identifier(node: &Node) -> bool {
     matches!(
         node.kind(),
@@ -55,16 +149,25 @@ pub fn is_identifier(node: &Node) -> bool {
             | "property_identifier"
             | "shorthand_property_identifier"
             | "type_identifier"
+            | "field_identifier"
             | "predefined_type"
     )
 }
 
 pub fn find_declaration(tree: &Tree, source: &str, name: &str) -> Option<Range> {
+    declaration_nodes(tree, source, name)
+        .first()
+        .map(|node| to_range(node.range()))
+}
+
+/// Every declaration of `name` in the file, in traversal order.
+fn declaration_nodes<'a>(tree: &'a Tree, source: &str, name: &str) -> Vec<Node<'a>> {
     let mut stack = vec![tree.root_node()];
+    let mut found = Vec::new();
 
     while let Some(node) = stack.pop() {
         if looks_like_declaration(&node, source.as_bytes(), name) {
-            return Some(to_range(node.range()));
+            found.push(node);
         }
         let mut child_cursor = node.walk();
         for child in node.children(&mut child_cursor) {
@@ -74,9 +177,134 @@ pub fn find_declaration(tree: &Tree, source: &str, name: &str) -> Option<Range>
         }
     }
 
+    found
+}
+
+/// Lexical scope a declaration is visible in: the enclosing block or function
+/// for locals, the whole file for top-level items. Class and struct members
+/// are reached through their owner from anywhere, so they count as top-level.
+pub fn resolve_scope(tree: &Tree, decl: Node) -> Range {
+    to_range(
+        scope_node(&decl)
+            .unwrap_or_else(|| tree.root_node())
+            .range(),
+    )
+}
+
+/// Node bounding a local declaration, or `None` when the declaration is
+/// visible file-wide.
+fn scope_node<'a>(decl: &Node<'a>) -> Option<Node<'a>> {
+    // `var` and Python names are function-scoped; everything else binds to
+    // the nearest block.
+    let function_scoped = matches!(
+        decl.kind(),
+        "assignment" | "variable_declaration" | "function_definition" | "class_definition"
+    ) || decl
+        .parent()
+        .is_some_and(|parent| parent.kind() == "variable_declaration");
+
+    let mut current = decl.parent();
+    while let Some(node) = current {
+        let kind = node.kind();
+        if matches!(
+            kind,
+            "class_body"
+                | "class_definition"
+                | "interface_body"
+                | "object_type"
+                | "field_declaration_list"
+                | "declaration_list"
+        ) {
+            return None;
+        }
+        if matches!(
+            kind,
+            "function_declaration"
+                | "function_expression"
+                | "function"
+                | "arrow_function"
+                | "generator_function_declaration"
+                | "method_definition"
+                | "function_definition"
+                | "lambda"
+                | "method_declaration"
+                | "func_literal"
+                | "function_item"
+                | "closure_expression"
+        ) {
+            return Some(node);
+        }
+        if !function_scoped && matches!(kind, "statement_block" | "block") {
+            return Some(node);
+        }
+        current = node.parent();
+    }
+
     None
 }
 
+fn contains(outer: &Node, inner: &Node) -> bool {
+    outer.start_byte() <= inner.start_byte() && inner.end_byte() <= outer.end_byte()
+}
+
+/// Declaration of `name` visible from `position`: of the declarations whose
+/// scope covers the position, the one with the innermost scope.
+pub fn declaration_in_scope<'a>(
+    tree: &'a Tree,
+    source: &str,
+    name: &str,
+    position: Position,
+) -> Option<Node<'a>> {
+    let at = node_at_position(tree, position)?;
+    let root = tree.root_node();
+
+    declaration_nodes(tree, source, name)
+        .into_iter()
+        .filter_map(|decl| {
+            let scope = scope_node(&decl).unwrap_or(root);
+            contains(&scope, &at).then_some((decl, scope))
+        })
+        .min_by_key(|(_, scope)| scope.end_byte() - scope.start_byte())
+        .map(|(decl, _)| decl)
+}
+
+/// References to the `name` visible from `position`: occurrences inside the
+/// declaration's scope (the whole file for top-level items), minus nested
+/// scopes that redeclare the name. A name with no declaration in this file
+/// matches everywhere.
+pub fn find_scoped_references(
+    tree: &Tree,
+    source: &str,
+    name: &str,
+    position: Position,
+) -> Vec<Range> {
+    let matcher = IdentifierMatcher::Exact(name.to_string());
+    let Some(decl) = declaration_in_scope(tree, source, name, position) else {
+        return find_references(tree, source, &matcher);
+    };
+    let scope = resolve_scope(tree, decl);
+
+    let shadowing: Vec<Range> = declaration_nodes(tree, source, name)
+        .into_iter()
+        .filter(|other| other.id() != decl.id())
+        .map(|other| resolve_scope(tree, other))
+        .filter(|inner| range_contains(&scope, inner) && !range_contains(inner, &scope))
+        .collect();
+
+    find_references(tree, source, &matcher)
+        .into_iter()
+        .filter(|range| {
+            range_contains(&scope, range)
+                && !shadowing.iter().any(|inner| range_contains(inner, range))
+        })
+        .collect()
+}
+
+fn range_contains(outer: &Range, inner: &Range) -> bool {
+    let key = |p: &Position| (p.line, p.column);
+    key(&outer.start) <= key(&inner.start) && key(&inner.end) <= key(&outer.end)
+}
+
 fn looks_like_declaration(node: &Node, source: &[u8], name: &str) -> bool {
     const DECL_KINDS: &[&str] = &[
         "function_declaration",
@@ -88,6 +316,17 @@ fn looks_like_declaration(node: &Node, source: &[u8], name: &str) -> bool {
         "interface_declaration",
         "type_alias_declaration",
         "enum_declaration",
+        // Python
+        "function_definition",
+        "class_definition",
+        "assignment",
+        // Go
+        "method_declaration",
+        "type_spec",
+        "type_alias",
+        "const_spec",
+        "var_spec",
+        "short_var_declaration",
     ];
 
     if !DECL_KINDS.contains(&node.kind()) {
@@ -111,14 +350,124 @@ fn looks_like_declaration(node: &Node, source: &[u8], name: &str) -> bool {
     false
 }
 
-pub fn find_references(tree: &Tree, source: &str, name: &str) -> Vec<Range> {
+/// Result of resolving one position in a batch lookup.
+#[derive(Debug, Clone)]
+pub struct DefinitionLookup {
+    pub identifier: Option<String>,
+    pub definition: Option<Range>,
+}
+
+/// Resolves the declaration for every position against a single parsed tree.
+/// Declarations are looked up once per distinct identifier.
+pub fn resolve_definitions(
+    tree: &Tree,
+    source: &str,
+    positions: &[Position],
+) -> Vec<DefinitionLookup> {
+    let mut declarations: HashMap<String, Option<Range>> = HashMap::new();
+
+    positions
+        .iter()
+        .map(|position| {
+            let Some((name, _)) = identifier_at_position(tree, source, position.clone()) else {
+                return DefinitionLookup {
+                    identifier: None,
+                    definition: None,
+                };
+            };
+            let definition = declarations
+                .entry(name.clone())
+                .or_insert_with(|| find_declaration(tree, source, &name))
+                .clone();
+            DefinitionLookup {
+                identifier: Some(name),
+                definition,
+            }
+        })
+        .collect()
+}
+
+/// How identifiers are compared when collecting references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    Exact,
+    Contains,
+    Regex,
+}
+
+impl MatchMode {
+    /// Parses a request's `match_mode`; blank means `exact`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "exact" => Ok(Self::Exact),
+            "contains" => Ok(Self::Contains),
+            "regex" => Ok(Self::Regex),
+            other => Err(format!(
+                "unsupported match_mode '{}'; expected exact, contains, or regex",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IdentifierMatcher {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl IdentifierMatcher {
+    pub fn new(mode: MatchMode, pattern: &str) -> Result<Self, String> {
+        if pattern.len() > MAX_MATCH_PATTERN_LENGTH {
+            return Err(format!(
+                "pattern exceeds maximum length of {} characters",
+                MAX_MATCH_PATTERN_LENGTH
+            ));
+        }
+        if pattern.trim().is_empty() {
+            return Err("pattern cannot be blank".to_string());
+        }
+
+        match mode {
+            MatchMode::Exact => Ok(Self::Exact(pattern.to_string())),
+            MatchMode::Contains => Ok(Self::Contains(pattern.to_string())),
+            MatchMode::Regex => RegexBuilder::new(pattern)
+                .size_limit(MAX_MATCH_REGEX_SIZE)
+                .dfa_size_limit(MAX_MATCH_REGEX_SIZE)
+                .build()
+                .map(Self::Regex)
+                .map_err(|e| format!("invalid or overly complex regex: {}", e)),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Self::Exact(name) => text == name,
+            Self::Contains(needle) => text.contains(needle.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// Whether `text` could contain a matching identifier somewhere. Cheap
+    /// enough to pick candidate files before parsing them; false positives
+    /// are fine, false negatives are not.
+    pub fn may_occur_in(&self, text: &str) -> bool {
+        match self {
+            Self::Exact(needle) | Self::Contains(needle) => text.contains(needle.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+pub fn find_references(tree: &Tree, source: &str, matcher: &IdentifierMatcher) -> Vec<Range> {
     let mut stack = vec![tree.root_node()];
     let mut ranges = Vec::new();
 
     while let Some(node) = stack.pop() {
         if is_identifier(&node) {
             if let Ok(text) = node.utf8_text(source.as_bytes()) {
-                if text.trim() == name {
+                if matcher.matches(text.trim()) {
                     ranges.push(to_range(node.range()));
                 }
             }
@@ -135,6 +484,10 @@ pub fn find_references(tree: &Tree, source: &str, name: &str) -> Vec<Range> {
     ranges
 }
 
+pub fn node_range(node: &Node) -> Range {
+    to_range(node.range())
+}
+
 fn to_range(range: tree_sitter::Range) -> Range {
     Range {
         start: Position {
@@ -163,9 +516,40 @@ pub struct GraphEdge {
     pub relation: String,
 }
 
-pub fn analyze_graph(tree: &Tree, source: &str, path: &str) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+pub const DEFAULT_GRAPH_MAX_VISITS_PER_DECLARATION: usize = 20_000;
+pub const DEFAULT_GRAPH_MAX_EDGES: usize = 5_000;
+
+/// Bounds on the call search in [`analyze_graph`], so one giant function
+/// cannot stall a request or produce an unbounded edge list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphLimits {
+    /// Syntax nodes visited while looking for calls inside one declaration.
+    pub max_visits_per_declaration: usize,
+    /// Edges returned for the whole file.
+    pub max_edges: usize,
+}
+
+impl Default for GraphLimits {
+    fn default() -> Self {
+        Self {
+            max_visits_per_declaration: DEFAULT_GRAPH_MAX_VISITS_PER_DECLARATION,
+            max_edges: DEFAULT_GRAPH_MAX_EDGES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// A limit stopped the call search early, so `edges` is incomplete.
+    pub truncated: bool,
+}
+
+pub fn analyze_graph(tree: &Tree, source: &str, path: &str, limits: GraphLimits) -> SymbolGraph {
     let mut nodes = Vec::new();
     let mut edges = Vec::new();
+    let mut truncated = false;
     let mut stack = vec![tree.root_node()];
 
     // Simple heuristic:
@@ -200,38 +584,270 @@ pub fn analyze_graph(tree: &Tree, source: &str, path: &str) -> (Vec<GraphNode>,
     }
 
     // Second pass: find usages within declarations
-    for (parent_id, parent_node) in declarations {
-        let mut stack = vec![parent_node];
-        while let Some(node) = stack.pop() {
-            // Don't recurse into nested declarations for this scope (simplified)
-            if is_declaration(&node) && node.id() != parent_node.id() {
-                continue;
+    'declarations: for (parent_id, parent_node) in declarations {
+        let (calls, exhausted) = calls_within(parent_node, source, limits);
+        truncated |= exhausted;
+        for (callee_name, _) in calls {
+            if edges.len() >= limits.max_edges {
+                truncated = true;
+                break 'declarations;
             }
+            // Create an edge to a potential node
+            // In a real system, we would resolve this name to a specific ID
+            // For now, we just assume it might be in the same file or external
+            let to_id = format!("{}::{}", path, callee_name); // Naive resolution
+            edges.push(GraphEdge {
+                from_id: parent_id.clone(),
+                to_id,
+                relation: "calls".to_string(),
+            });
+        }
+    }
 
-            if is_call_expression(&node) {
-                if let Some(callee_name) = get_callee_name(&node, source) {
-                    // Create an edge to a potential node
-                    // In a real system, we would resolve this name to a specific ID
-                    // For now, we just assume it might be in the same file or external
-                    let to_id = format!("{}::{}", path, callee_name); // Naive resolution
-                    edges.push(GraphEdge {
-                        from_id: parent_id.clone(),
-                        to_id,
-                        relation: "calls".to_string(),
-                    });
-                }
+    SymbolGraph {
+        nodes,
+        edges,
+        truncated,
+    }
+}
+
+/// Calls made directly by `decl`, with the callee name and the call node.
+/// Nested declarations are left to their own search. The flag is set when the
+/// visit budget ran out before the body was fully searched.
+fn calls_within<'a>(
+    decl: Node<'a>,
+    source: &str,
+    limits: GraphLimits,
+) -> (Vec<(String, Node<'a>)>, bool) {
+    let mut calls = Vec::new();
+    let mut stack = vec![decl];
+    let mut visits = 0;
+    while let Some(node) = stack.pop() {
+        visits += 1;
+        if visits > limits.max_visits_per_declaration {
+            return (calls, true);
+        }
+
+        // Don't recurse into nested declarations for this scope (simplified)
+        if is_declaration(&node) && node.id() != decl.id() {
+            continue;
+        }
+
+        if is_call_expression(&node) {
+            if let Some(callee_name) = get_callee_name(&node, source) {
+                calls.push((callee_name, node));
             }
+        }
 
-            let mut child_cursor = node.walk();
-            for child in node.children(&mut child_cursor) {
-                if child.is_named() {
-                    stack.push(child);
-                }
+        let mut child_cursor = node.walk();
+        for child in node.children(&mut child_cursor) {
+            if child.is_named() {
+                stack.push(child);
+            }
+        }
+    }
+    (calls, false)
+}
+
+/// Which side of a call to follow in [`call_hierarchy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallDirection {
+    /// Functions that call the symbol.
+    Incoming,
+    /// Functions the symbol calls.
+    Outgoing,
+}
+
+/// One caller (incoming) or callee (outgoing) and where the call is made.
+#[derive(Debug, Clone)]
+pub struct CallHierarchyItem {
+    pub node: GraphNode,
+    pub call_site: Range,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CallHierarchy {
+    pub items: Vec<CallHierarchyItem>,
+    /// A limit stopped the call search early, so `items` is incomplete.
+    pub truncated: bool,
+}
+
+/// Innermost declaration (function, method or class) enclosing `position`.
+pub fn enclosing_declaration(tree: &Tree, position: Position) -> Option<Node<'_>> {
+    let mut current = node_at_position(tree, position);
+    while let Some(node) = current {
+        if is_declaration(&node) {
+            return Some(node);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Every function, method or class declared in the file, outermost first.
+fn declarations<'a>(tree: &'a Tree) -> Vec<Node<'a>> {
+    let mut stack = vec![tree.root_node()];
+    let mut found = Vec::new();
+    while let Some(node) = stack.pop() {
+        if is_declaration(&node) {
+            found.push(node);
+        }
+        let mut child_cursor = node.walk();
+        for child in node.children(&mut child_cursor) {
+            if child.is_named() {
+                stack.push(child);
             }
         }
     }
+    found
+}
+
+fn graph_node(node: &Node, name: String, path: &str) -> GraphNode {
+    GraphNode {
+        id: format!("{}::{}", path, name),
+        name,
+        kind: node.kind().to_string(),
+    }
+}
 
-    (nodes, edges)
+/// Function a call hierarchy at `position` is built for, with its name.
+///
+/// That is the declaration in this file named by the identifier at the
+/// position, falling back to the declaration enclosing the position. A
+/// position outside any declaration has no target.
+pub fn call_target<'a>(
+    tree: &'a Tree,
+    source: &str,
+    position: Position,
+) -> Option<(String, Node<'a>)> {
+    let enclosing = enclosing_declaration(tree, position.clone())?;
+    let (name, _) = identifier_at_position(tree, source, position)?;
+    let target = declarations(tree)
+        .into_iter()
+        .find(|decl| get_name(decl, source).as_deref() == Some(name.as_str()))
+        .unwrap_or(enclosing);
+    Some((get_name(&target, source)?, target))
+}
+
+/// Calls into or out of the [`call_target`] at `position`; empty when there
+/// is none. Incoming calls are only those in `tree`, so callers elsewhere are
+/// found by running [`incoming_calls`] on each file.
+pub fn call_hierarchy(
+    tree: &Tree,
+    source: &str,
+    path: &str,
+    position: Position,
+    direction: CallDirection,
+    limits: GraphLimits,
+) -> CallHierarchy {
+    let Some((name, target)) = call_target(tree, source, position) else {
+        return CallHierarchy::default();
+    };
+
+    match direction {
+        CallDirection::Incoming => incoming_calls(tree, source, path, &name, limits),
+        CallDirection::Outgoing => outgoing_calls(tree, source, path, target, limits),
+    }
+}
+
+fn outgoing_calls(
+    tree: &Tree,
+    source: &str,
+    path: &str,
+    decl: Node,
+    limits: GraphLimits,
+) -> CallHierarchy {
+    let local: HashMap<String, Node> = declarations(tree)
+        .into_iter()
+        .filter_map(|node| get_name(&node, source).map(|name| (name, node)))
+        .collect();
+
+    let (calls, mut truncated) = calls_within(decl, source, limits);
+    if calls.len() > limits.max_edges {
+        truncated = true;
+    }
+    let items = calls
+        .into_iter()
+        .take(limits.max_edges)
+        .map(|(callee, call)| {
+            // Callees declared elsewhere keep the naive `path::name` id used by
+            // `analyze_graph`, with the kind left unknown.
+            let node = match local.get(&callee) {
+                Some(declared) => graph_node(declared, callee, path),
+                None => GraphNode {
+                    id: format!("{}::{}", path, callee),
+                    name: callee,
+                    kind: "unknown".to_string(),
+                },
+            };
+            CallHierarchyItem {
+                node,
+                call_site: to_range(call.range()),
+            }
+        })
+        .collect();
+
+    CallHierarchy { items, truncated }
+}
+
+/// Calls to `name` made by declarations in `tree`, each reported with its
+/// caller. Calls outside any declaration have no caller and are skipped.
+pub fn incoming_calls(
+    tree: &Tree,
+    source: &str,
+    path: &str,
+    name: &str,
+    limits: GraphLimits,
+) -> CallHierarchy {
+    let mut hierarchy = CallHierarchy::default();
+    for decl in declarations(tree) {
+        let Some(caller) = get_name(&decl, source) else {
+            continue;
+        };
+        let (calls, exhausted) = calls_within(decl, source, limits);
+        hierarchy.truncated |= exhausted;
+        for (_, call) in calls.into_iter().filter(|(callee, _)| callee == name) {
+            if hierarchy.items.len() >= limits.max_edges {
+                hierarchy.truncated = true;
+                return hierarchy;
+            }
+            hierarchy.items.push(CallHierarchyItem {
+                node: graph_node(&decl, caller.clone(), path),
+                call_site: to_range(call.range()),
+            });
+        }
+    }
+    hierarchy
+}
+
+/// Renders a symbol graph as GraphViz DOT. Nodes are labelled with their
+/// names and edges with their relation.
+pub fn to_dot(nodes: &[GraphNode], edges: &[GraphEdge]) -> String {
+    let mut dot = String::from("digraph symbols {\n");
+    for node in nodes {
+        dot.push_str(&format!(
+            "  \"{}\" [label=\"{}\"];\n",
+            escape_dot(&node.id),
+            escape_dot(&node.name)
+        ));
+    }
+    for edge in edges {
+        dot.push_str(&format!(
+            "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+            escape_dot(&edge.from_id),
+            escape_dot(&edge.to_id),
+            escape_dot(&edge.relation)
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn escape_dot(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
 
 fn is_declaration(node: &Node) -> bool {
@@ -241,11 +857,14 @@ fn is_declaration(node: &Node) -> bool {
             | "method_definition"
             | "class_declaration"
             | "interface_declaration"
+            | "function_definition"
+            | "class_definition"
+            | "method_declaration"
     )
 }
 
 fn is_call_expression(node: &Node) -> bool {
-    matches!(node.kind(), "call_expression" | "new_expression")
+    matches!(node.kind(), "call_expression" | "new_expression" | "call")
 }
 
 fn get_name(node: &Node, source: &str) -> Option<String> {
@@ -270,9 +889,16 @@ fn get_callee_name(node: &Node, source: &str) -> Option<String> {
             .ok()
             .map(|s| s.to_string());
     }
-    // Handle member expression (obj.method())
-    if child.kind() == "member_expression" {
-        let property = child.child_by_field_name("property")?;
+    // Handle member expression (obj.method()); Python calls it an attribute
+    // and Go a selector
+    if matches!(
+        child.kind(),
+        "member_expression" | "attribute" | "selector_expression"
+    ) {
+        let property = child
+            .child_by_field_name("property")
+            .or_else(|| child.child_by_field_name("attribute"))
+            .or_else(|| child.child_by_field_name("field"))?;
         return property
             .utf8_text(source.as_bytes())
             .ok()
@@ -280,3 +906,389 @@ fn get_callee_name(node: &Node, source: &str) -> Option<String> {
     }
     None
 }
+
+/// Lexical occurrences of `query` in `content`, literal or as a regex.
+/// Lines are shifted by `first_line` so a symbol's matches come back in file
+/// coordinates; columns are byte offsets, as tree-sitter reports them.
+/// Empty regex matches are skipped and at most [`MAX_TEXT_MATCHES`] returned.
+pub fn find_text_matches(
+    content: &str,
+    query: &str,
+    regex: bool,
+    first_line: u32,
+) -> Result<Vec<Range>, String> {
+    if query.is_empty() {
+        return Err("query cannot be empty".to_string());
+    }
+    if query.len() > MAX_MATCH_PATTERN_LENGTH {
+        return Err(format!(
+            "query exceeds maximum length of {} characters",
+            MAX_MATCH_PATTERN_LENGTH
+        ));
+    }
+
+    let spans: Vec<(usize, usize)> = if regex {
+        let pattern = RegexBuilder::new(query)
+            .size_limit(MAX_MATCH_REGEX_SIZE)
+            .dfa_size_limit(MAX_MATCH_REGEX_SIZE)
+            .build()
+            .map_err(|e| format!("invalid or overly complex regex: {}", e))?;
+        pattern
+            .find_iter(content)
+            .filter(|m| !m.is_empty())
+            .take(MAX_TEXT_MATCHES)
+            .map(|m| (m.start(), m.end()))
+            .collect()
+    } else {
+        content
+            .match_indices(query)
+            .take(MAX_TEXT_MATCHES)
+            .map(|(start, text)| (start, start + text.len()))
+            .collect()
+    };
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let position = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+        Position {
+            line: first_line + line as u32,
+            column: (offset - line_starts[line]) as u32,
+        }
+    };
+
+    Ok(spans
+        .into_iter()
+        .map(|(start, end)| Range {
+            start: position(start),
+            end: position(end),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast;
+
+    #[test]
+    fn one_megabyte_line_is_clamped_and_truncated() {
+        let source = "let value = 1;".repeat(75_000);
+        assert!(source.len() > 1_000_000);
+        let (tree, _) = ast::parse_tree("typescript", &source).expect("parse");
+        let lines = LineLengths::new(&source);
+
+        let middle = lines.clamp(0, 500_004);
+        let (name, _) = identifier_at_position(&tree, &source, middle).unwrap();
+        assert_eq!(name, "value");
+
+        let beyond = lines.clamp(7, u32::MAX);
+        assert_eq!(beyond.line, 0);
+        assert_eq!(beyond.column as usize, source.len());
+        assert!(node_at_position(&tree, beyond).is_some());
+
+        let snippet = truncate_lines(&source, MAX_SNIPPET_LINE_CHARS);
+        assert_eq!(snippet.chars().count(), MAX_SNIPPET_LINE_CHARS + 1);
+        assert!(snippet.ends_with('…'));
+        assert!(matches!(
+            truncate_lines("short\nlines", MAX_SNIPPET_LINE_CHARS),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn resolves_several_positions_from_one_tree() {
+        let source = "function greet() {}\nconst value = 1;\ngreet();\nconsole.log(value);\n";
+        let (tree, _) = ast::parse_tree("typescript", source).expect("parse");
+
+        let positions = [
+            Position { line: 2, column: 0 },
+            Position {
+                line: 3,
+                column: 12,
+            },
+            Position {
+                line: 1,
+                column: 14,
+            },
+        ];
+        let results = resolve_definitions(&tree, source, &positions);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].identifier.as_deref(), Some("greet"));
+        assert_eq!(
+            results[0].definition.as_ref().map(|r| r.start.line),
+            Some(0)
+        );
+        assert_eq!(results[1].identifier.as_deref(), Some("value"));
+        assert_eq!(
+            results[1].definition.as_ref().map(|r| r.start.line),
+            Some(1)
+        );
+        assert!(results[2].identifier.is_none());
+    }
+
+    #[test]
+    fn string_literal_position_has_no_identifier() {
+        let source = "const label = \"greet\";\ngreet(label);\n";
+        let (tree, _) = ast::parse_tree("typescript", source).expect("parse");
+
+        for column in [14, 15, 17, 20] {
+            assert!(
+                identifier_at_position(&tree, source, Position { line: 0, column }).is_none(),
+                "column {column} resolved an identifier"
+            );
+        }
+        let (name, _) =
+            identifier_at_position(&tree, source, Position { line: 1, column: 7 }).unwrap();
+        assert_eq!(name, "label");
+    }
+
+    #[test]
+    fn rejects_oversized_identifiers() {
+        assert!(is_valid_identifier("clickHandler"));
+        assert!(is_valid_identifier("$scope"));
+        assert!(!is_valid_identifier(""));
+        assert!(!is_valid_identifier("a b"));
+        assert!(!is_valid_identifier(&"x".repeat(MAX_IDENTIFIER_LENGTH + 1)));
+    }
+
+    fn scoped_lines(source: &str, name: &str, line: u32, column: u32) -> Vec<u32> {
+        let (tree, _) = ast::parse_tree("typescript", source).expect("parse");
+        let mut lines: Vec<u32> =
+            find_scoped_references(&tree, source, name, Position { line, column })
+                .into_iter()
+                .map(|range| range.start.line)
+                .collect();
+        lines.sort_unstable();
+        lines
+    }
+
+    #[test]
+    fn local_references_stay_in_their_function() {
+        let source = "function first() {\n  const x = 1;\n  return x;\n}\n\
+                      function second() {\n  let x = 2;\n  return x + 1;\n}\n";
+
+        assert_eq!(scoped_lines(source, "x", 2, 9), vec![1, 2]);
+        assert_eq!(scoped_lines(source, "x", 5, 6), vec![5, 6]);
+    }
+
+    #[test]
+    fn top_level_references_span_the_file_but_skip_shadowing() {
+        let source = "const id = 1;\nfunction use() {\n  return id;\n}\n\
+                      function other() {\n  const id = 2;\n  return id;\n}\n";
+        let (tree, _) = ast::parse_tree("typescript", source).expect("parse");
+        let decl = declaration_in_scope(&tree, source, "id", Position { line: 2, column: 9 })
+            .expect("declaration");
+        let scope = resolve_scope(&tree, decl);
+
+        assert_eq!(scope.start.line, 0);
+        assert_eq!(scoped_lines(source, "id", 2, 9), vec![0, 2]);
+        assert_eq!(scoped_lines(source, "id", 6, 9), vec![5, 6]);
+    }
+
+    #[test]
+    fn var_is_scoped_to_the_function_not_the_block() {
+        let source = "function run() {\n  if (ok) {\n    var x = 1;\n  }\n  return x;\n}\n\
+                      function other() {\n  return x;\n}\n";
+
+        assert_eq!(scoped_lines(source, "x", 4, 9), vec![2, 4]);
+    }
+
+    const HANDLERS: &str =
+        "function clickHandler() {}\nfunction keyHandler() {}\nclickHandler();\nrender();\n";
+
+    fn matched_lines(mode: &str, pattern: &str) -> Vec<u32> {
+        let (tree, _) = ast::parse_tree("typescript", HANDLERS).expect("parse");
+        let mode = MatchMode::parse(mode).expect("mode");
+        let matcher = IdentifierMatcher::new(mode, pattern).expect("matcher");
+        let mut lines: Vec<u32> = find_references(&tree, HANDLERS, &matcher)
+            .into_iter()
+            .map(|range| range.start.line)
+            .collect();
+        lines.sort_unstable();
+        lines
+    }
+
+    #[test]
+    fn exact_mode_matches_whole_identifier() {
+        assert_eq!(matched_lines("exact", "clickHandler"), vec![0, 2]);
+        assert!(matched_lines("", "Handler").is_empty());
+    }
+
+    #[test]
+    fn contains_mode_matches_substrings() {
+        assert_eq!(matched_lines("contains", "Handler"), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn regex_mode_matches_pattern() {
+        assert_eq!(matched_lines("regex", "^(key|render)"), vec![1, 3]);
+    }
+
+    #[test]
+    fn rejects_overly_complex_regex() {
+        let err = IdentifierMatcher::new(MatchMode::Regex, r"\w{1000}").unwrap_err();
+        assert!(err.contains("overly complex"));
+        let long = "a".repeat(MAX_MATCH_PATTERN_LENGTH + 1);
+        assert!(IdentifierMatcher::new(MatchMode::Regex, &long).is_err());
+    }
+
+    #[test]
+    fn dot_output_escapes_quotes() {
+        let nodes = vec![GraphNode {
+            id: "a.ts::say\"hi\"".to_string(),
+            name: "say\"hi\"".to_string(),
+            kind: "function_declaration".to_string(),
+        }];
+        let dot = to_dot(&nodes, &[]);
+        assert!(dot.contains(r#""a.ts::say\"hi\"" [label="say\"hi\""];"#));
+    }
+
+    #[test]
+    fn rejects_unknown_match_mode() {
+        assert!(MatchMode::parse("fuzzy").is_err());
+        assert_eq!(MatchMode::parse(" Contains "), Ok(MatchMode::Contains));
+    }
+
+    #[test]
+    fn text_matches_report_file_positions() {
+        let content = "fn retry() {\n    let n = retry_count();\n    retry();\n}";
+
+        let literal = find_text_matches(content, "retry", false, 10).unwrap();
+        let starts: Vec<(u32, u32)> = literal
+            .iter()
+            .map(|r| (r.start.line, r.start.column))
+            .collect();
+        assert_eq!(starts, vec![(10, 3), (11, 12), (12, 4)]);
+        assert_eq!(literal[0].end.column, 8);
+
+        let regex = find_text_matches(content, r"retry\(\)", true, 0).unwrap();
+        assert_eq!(regex.len(), 2);
+        assert!(find_text_matches(content, "x*", true, 0)
+            .unwrap()
+            .is_empty());
+        assert!(find_text_matches(content, "(", true, 0).is_err());
+    }
+
+    #[cfg(feature = "lang-go")]
+    #[test]
+    fn go_calls_become_graph_edges() {
+        let source =
+            "package main\n\nfunc (s *Server) Start() {\n\ts.listen()\n\tlog.Println(\"up\")\n}\n";
+        let (tree, _) = ast::parse_tree("go", source).expect("parse");
+
+        let graph = analyze_graph(&tree, source, "main.go", GraphLimits::default());
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].name, "Start");
+        let mut targets: Vec<&str> = graph.edges.iter().map(|e| e.to_id.as_str()).collect();
+        targets.sort();
+        assert_eq!(targets, vec!["main.go::Println", "main.go::listen"]);
+        assert!(!graph.truncated);
+    }
+
+    #[cfg(feature = "lang-typescript")]
+    #[test]
+    fn call_hierarchy_follows_both_directions() {
+        let source = "function load() {\n  parse();\n  fetch();\n}\n\
+                      function parse() {\n  return 1;\n}\n\
+                      function reload() {\n  parse();\n}\n\
+                      parse();\n";
+        let (tree, _) = ast::parse_tree("typescript", source).expect("parse");
+        let at = |line, column| Position { line, column };
+        let limits = GraphLimits::default();
+
+        let outgoing = call_hierarchy(
+            &tree,
+            source,
+            "lib.ts",
+            at(0, 10),
+            CallDirection::Outgoing,
+            limits,
+        );
+        let mut callees: Vec<(&str, &str, u32)> = outgoing
+            .items
+            .iter()
+            .map(|item| {
+                (
+                    item.node.name.as_str(),
+                    item.node.kind.as_str(),
+                    item.call_site.start.line,
+                )
+            })
+            .collect();
+        callees.sort();
+        assert_eq!(
+            callees,
+            vec![
+                ("fetch", "unknown", 2),
+                ("parse", "function_declaration", 1)
+            ]
+        );
+
+        // From a call site the target is the called function.
+        let incoming = call_hierarchy(
+            &tree,
+            source,
+            "lib.ts",
+            at(1, 3),
+            CallDirection::Incoming,
+            limits,
+        );
+        let mut callers: Vec<(&str, u32)> = incoming
+            .items
+            .iter()
+            .map(|item| (item.node.id.as_str(), item.call_site.start.line))
+            .collect();
+        callers.sort();
+        assert_eq!(callers, vec![("lib.ts::load", 1), ("lib.ts::reload", 8)]);
+        assert!(!incoming.truncated);
+
+        // The top-level call is not inside a function.
+        let outside = call_hierarchy(
+            &tree,
+            source,
+            "lib.ts",
+            at(10, 1),
+            CallDirection::Outgoing,
+            limits,
+        );
+        assert!(outside.items.is_empty());
+    }
+
+    #[cfg(feature = "lang-typescript")]
+    #[test]
+    fn graph_edges_are_capped_for_call_heavy_functions() {
+        let calls: String = (0..500).map(|i| format!("    step_{}();\n", i)).collect();
+        let source = format!(
+            "function small() {{ helper(); }}\nfunction huge() {{\n{}}}\n",
+            calls
+        );
+        let (tree, _) = ast::parse_tree("typescript", &source).expect("parse");
+
+        let full = analyze_graph(&tree, &source, "lib.ts", GraphLimits::default());
+        assert_eq!(full.edges.len(), 501);
+        assert!(!full.truncated);
+
+        let capped = GraphLimits {
+            max_edges: 50,
+            ..GraphLimits::default()
+        };
+        let graph = analyze_graph(&tree, &source, "lib.ts", capped);
+        assert_eq!(graph.edges.len(), 50);
+        assert_eq!(graph.nodes.len(), 2);
+        assert!(graph.truncated);
+
+        // A small visit budget stops the walk inside `huge` but still lets
+        // `small` be searched.
+        let budget = GraphLimits {
+            max_visits_per_declaration: 40,
+            ..GraphLimits::default()
+        };
+        let graph = analyze_graph(&tree, &source, "lib.ts", budget);
+        assert!(graph.truncated);
+        assert!(graph.edges.len() < 40);
+        assert!(graph.edges.iter().any(|e| e.to_id == "lib.ts::helper"));
+    }
+}
@@ -0,0 +1,563 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::StatusCode;
+use axum::{middleware, routing::post, Json, Router};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::info;
+
+use crate::ast;
+use crate::audit::{self, Outcome};
+use crate::auth::{self, ApiKeyAuth};
+use crate::security::SecurityConfig;
+use crate::storage::IndexStorage;
+use crate::validation::{self, validate_content, ControlCharPolicy};
+
+const DEFAULT_MAX_ARCHIVE_BYTES: usize = 50 * 1024 * 1024;
+const DEFAULT_MAX_ARCHIVE_ENTRIES: usize = 5_000;
+const DEFAULT_MAX_EXTRACTED_BYTES: u64 = 200 * 1024 * 1024;
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 512 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("archive exceeds maximum size of {0} bytes")]
+    TooLarge(usize),
+    #[error("archive exceeds maximum of {0} entries")]
+    TooManyEntries(usize),
+    #[error("archive expands beyond maximum of {0} bytes")]
+    ExtractedTooLarge(u64),
+    #[error("invalid archive: {0}")]
+    Invalid(String),
+}
+
+/// Bounds applied to uploaded archives before anything is indexed.
+#[derive(Clone, Debug)]
+pub struct ArchiveLimits {
+    /// Compressed upload size.
+    pub max_archive_bytes: usize,
+    pub max_entries: usize,
+    /// Total decompressed size across all entries, to stop gzip bombs.
+    pub max_extracted_bytes: u64,
+    /// Files larger than this are skipped rather than indexed.
+    pub max_file_bytes: u64,
+    /// Applied to each file as to single-document writes.
+    pub control_chars: ControlCharPolicy,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_archive_bytes: DEFAULT_MAX_ARCHIVE_BYTES,
+            max_entries: DEFAULT_MAX_ARCHIVE_ENTRIES,
+            max_extracted_bytes: DEFAULT_MAX_EXTRACTED_BYTES,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            control_chars: ControlCharPolicy::Allow,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_archive_bytes: env::var("INDEXER_ARCHIVE_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_archive_bytes),
+            max_entries: env::var("INDEXER_ARCHIVE_MAX_ENTRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_entries),
+            max_extracted_bytes: env::var("INDEXER_ARCHIVE_MAX_EXTRACTED_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_extracted_bytes),
+            max_file_bytes: env::var("INDEXER_MAX_CONTENT_LENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_file_bytes),
+            control_chars: ControlCharPolicy::from_env(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Indexed,
+    /// Blocked by ACL or DLP policy.
+    Denied,
+    /// Unsafe entry name (absolute or escaping the archive root), or content
+    /// single-document writes would refuse, such as a null byte.
+    Rejected,
+    /// Not indexable: non-regular file, too large, empty, or not UTF-8.
+    Skipped,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileResult {
+    pub path: String,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<&'static str>,
+    pub symbol_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl FileResult {
+    fn new(path: String, status: FileStatus) -> Self {
+        Self {
+            path,
+            status,
+            language: None,
+            symbol_count: 0,
+            reason: None,
+        }
+    }
+
+    fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ArchiveSummary {
+    pub indexed: usize,
+    pub denied: usize,
+    pub rejected: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub files: Vec<FileResult>,
+}
+
+impl ArchiveSummary {
+    fn push(&mut self, result: FileResult) {
+        match result.status {
+            FileStatus::Indexed => self.indexed += 1,
+            FileStatus::Denied => self.denied += 1,
+            FileStatus::Rejected => self.rejected += 1,
+            FileStatus::Skipped => self.skipped += 1,
+            FileStatus::Failed => self.failed += 1,
+        }
+        self.files.push(result);
+    }
+}
+
+enum Extracted {
+    File { path: String, content: String },
+    Outcome(FileResult),
+}
+
+/// Normalizes a tar entry name into a repository-relative path, refusing
+/// anything absolute or containing `..` so entries cannot escape the root.
+fn sanitize_entry_path(raw: &Path) -> Result<String, String> {
+    let mut segments = Vec::new();
+    for component in raw.components() {
+        match component {
+            Component::Normal(segment) => {
+                let segment = segment
+                    .to_str()
+                    .ok_or_else(|| "entry name is not valid UTF-8".to_string())?;
+                segments.push(segment);
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                return Err("entry name contains '..'".to_string());
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err("entry name is absolute".to_string());
+            }
+        }
+    }
+
+    if segments.is_empty() {
+        return Err("entry name is empty".to_string());
+    }
+
+    let path = segments.join("/");
+    if path.len() > validation::MAX_PATH_LENGTH {
+        return Err(format!(
+            "entry name exceeds maximum length of {} characters",
+            validation::MAX_PATH_LENGTH
+        ));
+    }
+    if path.chars().any(char::is_control) {
+        return Err("entry name contains invalid control characters".to_string());
+    }
+    Ok(path)
+}
+
+fn invalid(error: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::Invalid(error.to_string())
+}
+
+/// Walks a gzip-compressed tarball entry by entry, enforcing the entry and
+/// decompressed-size limits as it goes.
+fn extract(bytes: &[u8], limits: &ArchiveLimits) -> Result<Vec<Extracted>, ArchiveError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+    let mut extracted = Vec::new();
+    let mut extracted_bytes: u64 = 0;
+
+    for (index, entry) in archive.entries().map_err(invalid)?.enumerate() {
+        if index >= limits.max_entries {
+            return Err(ArchiveError::TooManyEntries(limits.max_entries));
+        }
+
+        let mut entry = entry.map_err(invalid)?;
+        let raw_path = entry.path().map_err(invalid)?.into_owned();
+        let path = match sanitize_entry_path(&raw_path) {
+            Ok(path) => path,
+            Err(reason) => {
+                let display = raw_path.to_string_lossy().to_string();
+                extracted.push(Extracted::Outcome(
+                    FileResult::new(display, FileStatus::Rejected).with_reason(reason),
+                ));
+                continue;
+            }
+        };
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            continue;
+        }
+        if !entry_type.is_file() {
+            extracted.push(Extracted::Outcome(
+                FileResult::new(path, FileStatus::Skipped).with_reason("not a regular file"),
+            ));
+            continue;
+        }
+
+        // Skipped entries are still decompressed when the reader moves past
+        // them, so they count towards the total as well.
+        let size = entry.size();
+        extracted_bytes = extracted_bytes.saturating_add(size);
+        if extracted_bytes > limits.max_extracted_bytes {
+            return Err(ArchiveError::ExtractedTooLarge(limits.max_extracted_bytes));
+        }
+        if size > limits.max_file_bytes {
+            extracted.push(Extracted::Outcome(
+                FileResult::new(path, FileStatus::Skipped).with_reason(format!(
+                    "file exceeds maximum size of {} bytes",
+                    limits.max_file_bytes
+                )),
+            ));
+            continue;
+        }
+
+        let mut buffer = Vec::with_capacity(size as usize);
+        entry
+            .by_ref()
+            .take(limits.max_file_bytes)
+            .read_to_end(&mut buffer)
+            .map_err(invalid)?;
+
+        match String::from_utf8(buffer) {
+            Ok(content) => extracted.push(Extracted::File { path, content }),
+            Err(_) => extracted.push(Extracted::Outcome(
+                FileResult::new(path, FileStatus::Skipped).with_reason("file is not valid UTF-8"),
+            )),
+        }
+    }
+
+    Ok(extracted)
+}
+
+async fn index_file(
+    path: String,
+    content: String,
+    storage: &Arc<dyn IndexStorage>,
+    security: &SecurityConfig,
+    control_chars: ControlCharPolicy,
+) -> FileResult {
+    if content.trim().is_empty() {
+        return FileResult::new(path, FileStatus::Skipped).with_reason("file is empty");
+    }
+    if let Err(e) = validate_content(&content, control_chars) {
+        audit::log_audit(
+            "index_archive",
+            Outcome::Rejected,
+            Some(&path),
+            Some(json!({ "error": e })),
+        );
+        return FileResult::new(path, FileStatus::Rejected).with_reason(e);
+    }
+
+    if let Err(e) = security.check_path(&path) {
+        audit::log_audit(
+            "index_archive",
+            Outcome::Denied,
+            Some(&path),
+            Some(e.audit_details()),
+        );
+        return FileResult::new(path, FileStatus::Denied).with_reason(e.to_string());
+    }
+    if let Err(e) = security.scan_content(&content) {
+        audit::log_audit(
+            "index_archive",
+            Outcome::Denied,
+            Some(&path),
+            Some(e.audit_details()),
+        );
+        return FileResult::new(path, FileStatus::Denied).with_reason(e.to_string());
+    }
+
+    let language = ast::language_for_path(&path);
+
+    if let Err(e) = storage
+        .index_document(path.clone(), content.clone(), None)
+        .await
+    {
+        return FileResult::new(path, FileStatus::Failed).with_reason(e.to_string());
+    }
+
+    let mut result = FileResult::new(path, FileStatus::Indexed);
+    result.language = language;
+
+    if let Some(language) = language {
+        match storage
+            .index_symbols(
+                result.path.clone(),
+                content,
+                language.to_string(),
+                None,
+                None,
+                false,
+            )
+            .await
+        {
+            Ok(ids) => result.symbol_count = ids.len(),
+            Err(e) => {
+                result.status = FileStatus::Failed;
+                result.reason = Some(format!(
+                    "document indexed but symbol extraction failed: {}",
+                    e
+                ));
+            }
+        }
+    }
+
+    result
+}
+
+/// Indexes every file in a `.tar.gz` upload, applying the same ACL and DLP
+/// checks as single-document writes, and reports an outcome per entry.
+pub async fn index_archive<B>(
+    bytes: B,
+    storage: Arc<dyn IndexStorage>,
+    security: &SecurityConfig,
+    limits: &ArchiveLimits,
+) -> Result<ArchiveSummary, ArchiveError>
+where
+    B: AsRef<[u8]> + Send + 'static,
+{
+    if bytes.as_ref().len() > limits.max_archive_bytes {
+        return Err(ArchiveError::TooLarge(limits.max_archive_bytes));
+    }
+
+    audit::log_audit(
+        "index_archive",
+        Outcome::Attempt,
+        None,
+        Some(json!({ "archive_bytes": bytes.as_ref().len() })),
+    );
+
+    let extract_limits = limits.clone();
+    let extracted = tokio::task::spawn_blocking(move || extract(bytes.as_ref(), &extract_limits))
+        .await
+        .map_err(invalid)?
+        .inspect_err(|e| {
+            audit::log_audit(
+                "index_archive",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e.to_string() })),
+            );
+        })?;
+
+    let mut summary = ArchiveSummary::default();
+    for item in extracted {
+        let result = match item {
+            Extracted::File { path, content } => {
+                index_file(path, content, &storage, security, limits.control_chars).await
+            }
+            Extracted::Outcome(result) => result,
+        };
+        summary.push(result);
+    }
+
+    audit::log_audit(
+        "index_archive",
+        Outcome::Success,
+        None,
+        Some(json!({
+            "indexed": summary.indexed,
+            "denied": summary.denied,
+            "rejected": summary.rejected,
+            "skipped": summary.skipped,
+            "failed": summary.failed
+        })),
+    );
+    info!(
+        indexed = summary.indexed,
+        denied = summary.denied,
+        rejected = summary.rejected,
+        "Archive indexed"
+    );
+
+    Ok(summary)
+}
+
+/// Shared state for the archive upload endpoint.
+#[derive(Clone)]
+struct ArchiveState {
+    storage: Arc<dyn IndexStorage>,
+    security: SecurityConfig,
+    limits: ArchiveLimits,
+}
+
+/// `POST /index/archive`, behind the same API keys as the gRPC service since
+/// it writes to the index.
+pub fn router(
+    storage: Arc<dyn IndexStorage>,
+    security: SecurityConfig,
+    limits: ArchiveLimits,
+    auth: ApiKeyAuth,
+) -> Router {
+    let body_limit = limits.max_archive_bytes;
+    Router::new()
+        .route(
+            "/index/archive",
+            post(index_archive_handler).layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_api_key))
+        .with_state(ArchiveState {
+            storage,
+            security,
+            limits,
+        })
+}
+
+/// `POST /index/archive` — indexes every file in a `.tar.gz` request body and
+/// returns a per-file summary.
+async fn index_archive_handler(
+    State(state): State<ArchiveState>,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    match index_archive(body, state.storage.clone(), &state.security, &state.limits).await {
+        Ok(summary) => (StatusCode::OK, Json(json!(summary))),
+        Err(error) => {
+            let status = match error {
+                ArchiveError::TooLarge(_)
+                | ArchiveError::TooManyEntries(_)
+                | ArchiveError::ExtractedTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                ArchiveError::Invalid(_) => StatusCode::BAD_REQUEST,
+            };
+            (status, Json(json!({ "error": error.to_string() })))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    /// Builds a tarball, writing entry names straight into the header so
+    /// unsafe names that `tar::Builder` would refuse can be tested.
+    fn tarball(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, content) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn statuses(extracted: &[Extracted]) -> Vec<(String, Option<FileStatus>)> {
+        extracted
+            .iter()
+            .map(|item| match item {
+                Extracted::File { path, .. } => (path.clone(), None),
+                Extracted::Outcome(result) => (result.path.clone(), Some(result.status)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sanitizes_entry_paths() {
+        assert_eq!(
+            sanitize_entry_path(Path::new("./src/lib.rs")),
+            Ok("src/lib.rs".to_string())
+        );
+        assert!(sanitize_entry_path(Path::new("../etc/passwd")).is_err());
+        assert!(sanitize_entry_path(Path::new("src/../../x")).is_err());
+        assert!(sanitize_entry_path(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn rejects_traversal_entries() {
+        let bytes = tarball(&[("src/ok.rs", "fn ok() {}"), ("../evil.rs", "fn evil() {}")]);
+        let extracted = extract(&bytes, &ArchiveLimits::default()).unwrap();
+
+        assert_eq!(
+            statuses(&extracted),
+            vec![
+                ("src/ok.rs".to_string(), None),
+                ("../evil.rs".to_string(), Some(FileStatus::Rejected)),
+            ]
+        );
+    }
+
+    #[test]
+    fn enforces_entry_limit() {
+        let bytes = tarball(&[("a.rs", "a"), ("b.rs", "b"), ("c.rs", "c")]);
+        let limits = ArchiveLimits {
+            max_entries: 2,
+            ..ArchiveLimits::default()
+        };
+        assert!(matches!(
+            extract(&bytes, &limits),
+            Err(ArchiveError::TooManyEntries(2))
+        ));
+    }
+
+    #[test]
+    fn enforces_extracted_size_limit() {
+        let big = "x".repeat(4096);
+        let bytes = tarball(&[("a.rs", &big), ("b.rs", &big)]);
+        let limits = ArchiveLimits {
+            max_extracted_bytes: 6000,
+            ..ArchiveLimits::default()
+        };
+        assert!(matches!(
+            extract(&bytes, &limits),
+            Err(ArchiveError::ExtractedTooLarge(6000))
+        ));
+    }
+
+    #[test]
+    fn rejects_non_gzip_input() {
+        assert!(matches!(
+            extract(b"not an archive", &ArchiveLimits::default()),
+            Err(ArchiveError::Invalid(_))
+        ));
+    }
+}
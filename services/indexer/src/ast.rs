@@ -11,6 +11,8 @@ const DEFAULT_MAX_NODES: usize = 2048;
 pub enum AstError {
     #[error("unsupported language: {0}")]
     UnsupportedLanguage(String),
+    #[error("language '{0}' is not enabled in this build (requires the `{1}` feature)")]
+    LanguageNotEnabled(String, &'static str),
     #[error("failed to configure parser for language: {0}")]
     LanguageUnavailable(String),
     #[error("failed to parse source")]
@@ -70,8 +72,7 @@ impl Default for AstOptions {
 
 pub fn parse_tree(language_id: &str, source: &str) -> Result<(Tree, Language), AstError> {
     let mut parser = Parser::new();
-    let language = language_for_id(language_id)
-        .ok_or_else(|| AstError::UnsupportedLanguage(language_id.to_string()))?;
+    let language = language_for_id(language_id)?;
     parser
         .set_language(language) // Fixed: removed borrow
         .map_err(|_| AstError::LanguageUnavailable(language_id.to_string()))?;
@@ -171,17 +172,125 @@ fn serialize_node(
     Some(ast_node)
 }
 
-fn language_for_id(id: &str) -> Option<Language> {
+/// Maps a file path to the language id used for symbol extraction and
+/// navigation, based on its extension.
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    if path.ends_with(".rs") {
+        Some("rust")
+    } else if path.ends_with(".ts") || path.ends_with(".tsx") {
+        Some("typescript")
+    } else if path.ends_with(".js") || path.ends_with(".jsx") {
+        Some("javascript")
+    } else if path.ends_with(".py") || path.ends_with(".pyi") {
+        Some("python")
+    } else if path.ends_with(".go") {
+        Some("go")
+    } else {
+        None
+    }
+}
+
+/// Maps a language id or alias to the name `language_for_path` reports.
+pub fn canonical_language(id: &str) -> Option<&'static str> {
+    match id.trim().to_ascii_lowercase().as_str() {
+        "typescript" | "ts" | "tsx" => Some("typescript"),
+        "javascript" | "js" | "jsx" => Some("javascript"),
+        "rust" | "rs" => Some("rust"),
+        "python" | "py" => Some("python"),
+        "go" | "golang" => Some("go"),
+        "json" => Some("json"),
+        _ => None,
+    }
+}
+
+/// Returns the language implied by `path` when it disagrees with `language`.
+/// Paths without a recognised extension never mismatch.
+pub fn language_mismatch(path: &str, language: &str) -> Option<&'static str> {
+    let expected = language_for_path(path)?;
+    (canonical_language(language) != Some(expected)).then_some(expected)
+}
+
+/// Zero-based start rows of the syntax errors in `tree`: nodes the parser
+/// could not place and tokens it had to invent. Errors nested inside an
+/// error are not counted again.
+pub fn syntax_error_lines(tree: &Tree) -> Vec<usize> {
+    let mut lines = Vec::new();
+    if !tree.root_node().has_error() {
+        return lines;
+    }
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if node.is_error() || node.is_missing() {
+            lines.push(node.start_position().row);
+        } else if node.has_error() {
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+    }
+    lines.sort_unstable();
+    lines
+}
+
+/// Languages whose grammars are compiled into this build, by canonical name.
+pub fn enabled_languages() -> Vec<&'static str> {
+    let mut languages = Vec::new();
+    if cfg!(feature = "lang-rust") {
+        languages.push("rust");
+    }
+    if cfg!(feature = "lang-typescript") {
+        languages.push("typescript");
+    }
+    if cfg!(feature = "lang-javascript") {
+        languages.push("javascript");
+    }
+    if cfg!(feature = "lang-json") {
+        languages.push("json");
+    }
+    if cfg!(feature = "lang-python") {
+        languages.push("python");
+    }
+    if cfg!(feature = "lang-go") {
+        languages.push("go");
+    }
+    languages
+}
+
+/// Cargo feature that compiles in the grammar for a known language id.
+fn feature_for_language(id: &str) -> Option<&'static str> {
     match id {
-        "typescript" | "ts" => Some(tree_sitter_typescript::language_typescript()),
-        "tsx" => Some(tree_sitter_typescript::language_tsx()),
-        "javascript" | "js" => Some(tree_sitter_javascript::language()),
-        "json" => Some(tree_sitter_json::language()),
-        "rust" | "rs" => Some(tree_sitter_rust::language()),
+        "typescript" | "ts" | "tsx" => Some("lang-typescript"),
+        "javascript" | "js" => Some("lang-javascript"),
+        "json" => Some("lang-json"),
+        "rust" | "rs" => Some("lang-rust"),
+        "python" | "py" => Some("lang-python"),
+        "go" | "golang" => Some("lang-go"),
         _ => None,
     }
 }
 
+fn language_for_id(id: &str) -> Result<Language, AstError> {
+    match id {
+        #[cfg(feature = "lang-typescript")]
+        "typescript" | "ts" => Ok(tree_sitter_typescript::language_typescript()),
+        #[cfg(feature = "lang-typescript")]
+        "tsx" => Ok(tree_sitter_typescript::language_tsx()),
+        #[cfg(feature = "lang-javascript")]
+        "javascript" | "js" => Ok(tree_sitter_javascript::language()),
+        #[cfg(feature = "lang-json")]
+        "json" => Ok(tree_sitter_json::language()),
+        #[cfg(feature = "lang-rust")]
+        "rust" | "rs" => Ok(tree_sitter_rust::language()),
+        #[cfg(feature = "lang-python")]
+        "python" | "py" => Ok(tree_sitter_python::language()),
+        #[cfg(feature = "lang-go")]
+        "go" | "golang" => Ok(tree_sitter_go::language()),
+        _ => Err(match feature_for_language(id) {
+            Some(feature) => AstError::LanguageNotEnabled(id.to_string(), feature),
+            None => AstError::UnsupportedLanguage(id.to_string()),
+        }),
+    }
+}
+
 fn to_position(point: tree_sitter::Point) -> Position {
     Position {
         line: point.row as u32,
@@ -193,6 +302,29 @@ fn to_position(point: tree_sitter::Point) -> Position {
 mod tests {
     use super::*;
 
+    #[test]
+    fn reports_lines_of_syntax_errors() {
+        let (tree, _) = parse_tree("rust", "fn ok() {}\n").unwrap();
+        assert!(syntax_error_lines(&tree).is_empty());
+
+        let (tree, _) = parse_tree("rust", "fn ok() {}\nfn broken( {\n}\n").unwrap();
+        let lines = syntax_error_lines(&tree);
+        assert!(!lines.is_empty());
+        assert!(lines.iter().all(|line| *line >= 1));
+    }
+
+    #[test]
+    fn flags_language_that_disagrees_with_extension() {
+        assert_eq!(language_mismatch("src/a.rs", "typescript"), Some("rust"));
+        assert_eq!(language_mismatch("src/a.rs", "rs"), None);
+        assert_eq!(language_mismatch("web/app.tsx", "TS"), None);
+        assert_eq!(language_mismatch("web/app.js", "rust"), Some("javascript"));
+        assert_eq!(language_mismatch("tools/build.py", "py"), None);
+        assert_eq!(language_mismatch("cmd/main.go", "golang"), None);
+        assert_eq!(language_mismatch("Makefile", "rust"), None);
+    }
+
+    #[cfg(feature = "lang-typescript")]
     #[test]
     fn parses_typescript_ast() {
         let source = "const answer = 42;";
@@ -222,4 +354,23 @@ mod tests {
         };
         assert!(matches!(err, AstError::UnsupportedLanguage(_)));
     }
+
+    #[cfg(not(feature = "lang-json"))]
+    #[test]
+    fn disabled_language_errors_distinctly_from_unknown() {
+        let err = match parse_tree("json", "{}") {
+            Ok(_) => panic!("expected json to be disabled"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err,
+            AstError::LanguageNotEnabled(ref language, "lang-json") if language == "json"
+        ));
+
+        let err = match parse_tree("cobol", "") {
+            Ok(_) => panic!("expected unsupported language error"),
+            Err(err) => err,
+        };
+        assert!(matches!(err, AstError::UnsupportedLanguage(_)));
+    }
 }
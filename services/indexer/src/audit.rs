@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 
 use chrono::Utc;
 use once_cell::sync::Lazy;
@@ -65,6 +67,43 @@ fn hash_identity(value: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// How file paths appear in the `target` field of audit records. Handlers
+/// always see the real path; only the logged form changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditPathMode {
+    #[default]
+    Full,
+    /// Salted SHA-256 of the path, so records for one file still correlate.
+    Hashed,
+    /// Final path segment only, dropping directory names.
+    Basename,
+}
+
+impl AuditPathMode {
+    pub fn from_env() -> Self {
+        match env::var("INDEXER_AUDIT_PATH_MODE") {
+            Ok(v) if v.eq_ignore_ascii_case("hashed") => AuditPathMode::Hashed,
+            Ok(v) if v.eq_ignore_ascii_case("basename") => AuditPathMode::Basename,
+            _ => AuditPathMode::Full,
+        }
+    }
+
+    fn render(self, path: &str) -> String {
+        match self {
+            AuditPathMode::Full => path.to_string(),
+            AuditPathMode::Hashed => format!("sha256:{}", hash_identity(path)),
+            AuditPathMode::Basename => path
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+}
+
+static PATH_MODE: Lazy<AuditPathMode> = Lazy::new(AuditPathMode::from_env);
+
 fn should_mask(key: Option<&str>) -> bool {
     if let Some(key) = key {
         SECRET_KEY_PATTERNS
@@ -142,15 +181,117 @@ fn extract_details(details: Option<Value>) -> (Option<String>, Option<Value>) {
     }
 }
 
-fn map_level(outcome: &str) -> Level {
-    match outcome {
-        "failure" => Level::ERROR,
-        "denied" | "rejected" => Level::WARN,
-        _ => Level::INFO,
+/// Every outcome an audit entry may carry. Handlers pass one of these rather
+/// than a free-form string so a new outcome cannot slip in without a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Attempt,
+    Success,
+    Failure,
+    Denied,
+    Rejected,
+    NotFound,
+    Timeout,
+    Throttled,
+    Skipped,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 9] = [
+        Outcome::Attempt,
+        Outcome::Success,
+        Outcome::Failure,
+        Outcome::Denied,
+        Outcome::Rejected,
+        Outcome::NotFound,
+        Outcome::Timeout,
+        Outcome::Throttled,
+        Outcome::Skipped,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Attempt => "attempt",
+            Outcome::Success => "success",
+            Outcome::Failure => "failure",
+            Outcome::Denied => "denied",
+            Outcome::Rejected => "rejected",
+            Outcome::NotFound => "not_found",
+            Outcome::Timeout => "timeout",
+            Outcome::Throttled => "throttled",
+            Outcome::Skipped => "skipped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == value)
+    }
+
+    /// Level used when `INDEXER_AUDIT_LEVELS` does not override it.
+    pub fn default_level(self) -> Level {
+        match self {
+            Outcome::Failure => Level::ERROR,
+            Outcome::Denied | Outcome::Rejected | Outcome::Timeout | Outcome::Throttled => {
+                Level::WARN
+            }
+            Outcome::Skipped => Level::DEBUG,
+            Outcome::Attempt | Outcome::Success | Outcome::NotFound => Level::INFO,
+        }
     }
 }
 
-pub fn log_audit(action: &str, outcome: &str, resource: Option<&str>, details: Option<Value>) {
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses `outcome=level` pairs separated by commas, e.g.
+/// `not_found=warn,skipped=info`. Unknown outcomes or levels are reported and
+/// the rest of the table still applies.
+fn parse_level_overrides(spec: &str) -> (HashMap<Outcome, Level>, Vec<String>) {
+    let mut overrides = HashMap::new();
+    let mut errors = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry.split_once('=').and_then(|(outcome, level)| {
+            let outcome = Outcome::parse(outcome.trim())?;
+            let level = level.trim().parse::<Level>().ok()?;
+            Some((outcome, level))
+        });
+        match parsed {
+            Some((outcome, level)) => {
+                overrides.insert(outcome, level);
+            }
+            None => errors.push(entry.to_string()),
+        }
+    }
+    (overrides, errors)
+}
+
+static LEVEL_OVERRIDES: Lazy<HashMap<Outcome, Level>> = Lazy::new(|| {
+    let spec = env::var("INDEXER_AUDIT_LEVELS").unwrap_or_default();
+    let (overrides, errors) = parse_level_overrides(&spec);
+    for entry in errors {
+        tracing::warn!(
+            target: "audit",
+            service = SERVICE_NAME,
+            entry = %entry,
+            "Ignoring invalid INDEXER_AUDIT_LEVELS entry"
+        );
+    }
+    overrides
+});
+
+fn map_level(outcome: Outcome) -> Level {
+    LEVEL_OVERRIDES
+        .get(&outcome)
+        .copied()
+        .unwrap_or_else(|| outcome.default_level())
+}
+
+pub fn log_audit(action: &str, outcome: Outcome, resource: Option<&str>, details: Option<Value>) {
     let (capability, redacted_details) = extract_details(details);
     let details_json = redacted_details
         .map(|value| serde_json::to_string(&value).unwrap_or_else(|_| "{}".to_string()))
@@ -165,58 +306,48 @@ pub fn log_audit(action: &str, outcome: &str, resource: Option<&str>, details: O
         .as_ref()
         .and_then(|ctx| ctx.client_ip().map(|ip| ip.to_string()))
         .unwrap_or_else(|| "anonymous".to_string());
-    let actor_id = hash_identity(&client_ip);
+    // An authenticated caller is named as such; anyone else is only known by
+    // a salted hash of their address.
+    let actor_id = match context.as_ref().and_then(|ctx| ctx.actor()) {
+        Some(actor) => actor.to_string(),
+        None => hash_identity(&client_ip),
+    };
 
     let level = map_level(outcome);
     let timestamp = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
-    let target_resource = resource.unwrap_or("unspecified");
+    let target_resource = resource
+        .map(|path| PATH_MODE.render(path))
+        .unwrap_or_else(|| "unspecified".to_string());
     let request_id_field = request_id.unwrap_or_default();
     let trace_id_field = trace_id.unwrap_or_default();
     let capability_field = capability.unwrap_or_default();
 
+    macro_rules! audit_event {
+        ($level:expr) => {
+            event!(
+                target: "audit",
+                $level,
+                ts = %timestamp,
+                service = SERVICE_NAME,
+                event = action,
+                outcome = outcome.as_str(),
+                target = %target_resource,
+                actor_id = %actor_id,
+                request_id = %request_id_field,
+                trace_id = %trace_id_field,
+                capability = %capability_field,
+                redacted_details = %details_json
+            )
+        };
+    }
+
+    // `event!` needs its level at compile time.
     match level {
-        Level::ERROR => event!(
-            target: "audit",
-            Level::ERROR,
-            ts = %timestamp,
-            service = SERVICE_NAME,
-            event = action,
-            outcome = outcome,
-            target = target_resource,
-            actor_id = %actor_id,
-            request_id = %request_id_field,
-            trace_id = %trace_id_field,
-            capability = %capability_field,
-            redacted_details = %details_json
-        ),
-        Level::WARN => event!(
-            target: "audit",
-            Level::WARN,
-            ts = %timestamp,
-            service = SERVICE_NAME,
-            event = action,
-            outcome = outcome,
-            target = target_resource,
-            actor_id = %actor_id,
-            request_id = %request_id_field,
-            trace_id = %trace_id_field,
-            capability = %capability_field,
-            redacted_details = %details_json
-        ),
-        _ => event!(
-            target: "audit",
-            Level::INFO,
-            ts = %timestamp,
-            service = SERVICE_NAME,
-            event = action,
-            outcome = outcome,
-            target = target_resource,
-            actor_id = %actor_id,
-            request_id = %request_id_field,
-            trace_id = %trace_id_field,
-            capability = %capability_field,
-            redacted_details = %details_json
-        ),
+        Level::ERROR => audit_event!(Level::ERROR),
+        Level::WARN => audit_event!(Level::WARN),
+        Level::INFO => audit_event!(Level::INFO),
+        Level::DEBUG => audit_event!(Level::DEBUG),
+        _ => audit_event!(Level::TRACE),
     }
 }
 
@@ -254,6 +385,53 @@ mod tests {
         assert_eq!(map.get("allowed"), Some(&json!(true)));
     }
 
+    #[test]
+    fn known_outcomes_map_to_expected_levels() {
+        let expected = [
+            (Outcome::Attempt, Level::INFO),
+            (Outcome::Success, Level::INFO),
+            (Outcome::Failure, Level::ERROR),
+            (Outcome::Denied, Level::WARN),
+            (Outcome::Rejected, Level::WARN),
+            (Outcome::NotFound, Level::INFO),
+            (Outcome::Timeout, Level::WARN),
+            (Outcome::Throttled, Level::WARN),
+            (Outcome::Skipped, Level::DEBUG),
+        ];
+        assert_eq!(expected.len(), Outcome::ALL.len());
+        for (outcome, level) in expected {
+            assert_eq!(outcome.default_level(), level, "{outcome}");
+            assert_eq!(Outcome::parse(outcome.as_str()), Some(outcome));
+        }
+    }
+
+    #[test]
+    fn level_overrides_replace_defaults_and_report_bad_entries() {
+        let (overrides, errors) =
+            parse_level_overrides("not_found=warn, skipped=INFO,bogus=error,timeout=loud,");
+
+        assert_eq!(overrides.get(&Outcome::NotFound), Some(&Level::WARN));
+        assert_eq!(overrides.get(&Outcome::Skipped), Some(&Level::INFO));
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(errors, vec!["bogus=error", "timeout=loud"]);
+    }
+
+    #[test]
+    fn path_modes_shape_logged_paths() {
+        let path = "projects/orion/src/lib.rs";
+
+        assert_eq!(AuditPathMode::Full.render(path), path);
+        assert_eq!(AuditPathMode::Basename.render(path), "lib.rs");
+        assert_eq!(AuditPathMode::Basename.render("lib.rs"), "lib.rs");
+
+        let hashed = AuditPathMode::Hashed.render(path);
+        let digest = hashed.strip_prefix("sha256:").unwrap();
+        assert_eq!(digest.len(), 64);
+        assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(!hashed.contains("orion"));
+        assert_eq!(hashed, AuditPathMode::Hashed.render(path));
+    }
+
     #[test]
     fn hash_identity_is_stable() {
         let first = hash_identity("example");
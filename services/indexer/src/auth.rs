@@ -0,0 +1,282 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+use axum::extract::{Request as HttpRequest, State};
+use axum::http::{header::AUTHORIZATION, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::request_context::{scope_request_context, RequestContext};
+
+/// API key authentication for the indexer's gRPC service and its HTTP write
+/// routes (see [`require_api_key`]).
+///
+/// `INDEXER_API_KEYS` lists the accepted keys, comma-separated. An entry is
+/// either `name:key`, which audits calls as `name`, or a bare key without a
+/// `:`, audited as `key-` and the first 12 hex digits of its SHA-256. Keys
+/// may contain `=`, so base64 keys work with their padding. Callers send the key
+/// in the `authorization` header, with or without a `Bearer ` prefix.
+///
+/// `INDEXER_AUTH_DISABLED=1` (or `true`), meant for local development, is the
+/// only way to let every call through; with neither it nor any key set the
+/// indexer refuses to start. Either way the interceptor stores a
+/// [`RequestContext`] in the request's extensions for
+/// [`crate::request_context::ScopedContext`].
+#[derive(Clone, Default)]
+pub struct ApiKeyAuth {
+    /// SHA-256 of each accepted key to the identity it authenticates as;
+    /// `None` when authentication is off.
+    keys: Option<Arc<HashMap<[u8; 32], String>>>,
+}
+
+impl ApiKeyAuth {
+    /// Lets every call through.
+    pub fn disabled() -> Self {
+        Self { keys: None }
+    }
+
+    /// Accepts the keys in a comma-separated `INDEXER_API_KEYS` value. Blank
+    /// entries are skipped; with no keys at all every call is rejected. An
+    /// entry with an empty name or key is an error rather than dropped.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut keys = HashMap::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (digest, identity) = match entry.split_once(':') {
+                Some((name, key)) => {
+                    let (name, key) = (name.trim(), key.trim());
+                    if name.is_empty() || key.is_empty() {
+                        return Err(format!(
+                            "INDEXER_API_KEYS entry '{}' needs both a name and a key",
+                            name
+                        ));
+                    }
+                    (key_digest(key), name.to_string())
+                }
+                None => {
+                    let digest = key_digest(entry);
+                    (digest, format!("key-{}", &hex::encode(digest)[..12]))
+                }
+            };
+            keys.insert(digest, identity);
+        }
+        Ok(Self {
+            keys: Some(Arc::new(keys)),
+        })
+    }
+
+    /// Reads `INDEXER_AUTH_DISABLED` and `INDEXER_API_KEYS`. Fails when
+    /// authentication is on but no key is configured, so a missing secret
+    /// cannot silently open the API.
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_settings(
+            env::var("INDEXER_AUTH_DISABLED").ok().as_deref(),
+            env::var("INDEXER_API_KEYS").ok().as_deref(),
+        )
+    }
+
+    fn from_settings(disabled: Option<&str>, keys: Option<&str>) -> Result<Self, String> {
+        let disabled = disabled
+            .map(|v| v.trim() == "1" || v.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if disabled {
+            warn!("INDEXER_AUTH_DISABLED is set; the API accepts unauthenticated calls");
+            return Ok(Self::disabled());
+        }
+
+        let auth = Self::parse(keys.unwrap_or_default())?;
+        if auth.key_count() == 0 {
+            return Err(
+                "INDEXER_API_KEYS lists no keys; configure at least one or set \
+                 INDEXER_AUTH_DISABLED=1 to run without authentication"
+                    .to_string(),
+            );
+        }
+        Ok(auth)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    fn key_count(&self) -> usize {
+        self.keys.as_ref().map_or(0, |keys| keys.len())
+    }
+
+    /// The identity of the key in `metadata`'s `authorization` header;
+    /// `None` when authentication is off. The error says why the call is
+    /// unauthenticated.
+    pub fn authenticate(&self, metadata: &MetadataMap) -> Result<Option<String>, &'static str> {
+        self.authenticate_header(metadata.get("authorization").map(|value| value.as_bytes()))
+    }
+
+    /// Like [`Self::authenticate`] for the raw `authorization` header value.
+    pub fn authenticate_header(
+        &self,
+        header: Option<&[u8]>,
+    ) -> Result<Option<String>, &'static str> {
+        let Some(keys) = &self.keys else {
+            return Ok(None);
+        };
+
+        let header = header.ok_or("missing authorization header")?;
+        let header = std::str::from_utf8(header)
+            .map_err(|_| "malformed authorization header")?
+            .trim();
+        let key = match header.split_once(' ') {
+            Some((scheme, key)) if scheme.eq_ignore_ascii_case("bearer") => key.trim(),
+            _ => header,
+        };
+
+        keys.get(&key_digest(key))
+            .cloned()
+            .map(Some)
+            .ok_or("invalid API key")
+    }
+}
+
+impl Interceptor for ApiKeyAuth {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let actor = self
+            .authenticate(request.metadata())
+            .map_err(Status::unauthenticated)?;
+
+        let mut context = RequestContext::new(Uuid::new_v4());
+        if let Some(actor) = actor {
+            context = context.with_actor(actor);
+        }
+        if let Some(addr) = request.remote_addr() {
+            context = context.with_client_ip(addr.ip());
+        }
+        request.extensions_mut().insert(context);
+        Ok(request)
+    }
+}
+
+/// Axum middleware applying [`ApiKeyAuth`] to HTTP routes. Rejected requests
+/// get `401` with a JSON error; accepted ones run in a [`RequestContext`]
+/// naming the caller, like gRPC calls.
+pub async fn require_api_key(
+    State(auth): State<ApiKeyAuth>,
+    request: HttpRequest,
+    next: Next,
+) -> Response {
+    let header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .map(|value| value.as_bytes());
+    match auth.authenticate_header(header) {
+        Ok(actor) => {
+            let mut context = RequestContext::new(Uuid::new_v4());
+            if let Some(actor) = actor {
+                context = context.with_actor(actor);
+            }
+            scope_request_context(context, next.run(request)).await
+        }
+        Err(error) => (StatusCode::UNAUTHORIZED, Json(json!({ "error": error }))).into_response(),
+    }
+}
+
+/// Keys are only compared by digest, so a lookup takes no longer for a key
+/// that shares a prefix with a valid one.
+fn key_digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(authorization: Option<&str>) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        if let Some(value) = authorization {
+            metadata.insert("authorization", value.parse().unwrap());
+        }
+        metadata
+    }
+
+    #[test]
+    fn resolves_named_and_bare_keys() {
+        let auth = ApiKeyAuth::parse(" ci:s3cret , , bare-key").unwrap();
+        assert!(auth.is_enabled());
+
+        let named = auth.authenticate(&metadata(Some("Bearer s3cret"))).unwrap();
+        assert_eq!(named.as_deref(), Some("ci"));
+        let raw = auth.authenticate(&metadata(Some("s3cret"))).unwrap();
+        assert_eq!(raw.as_deref(), Some("ci"));
+
+        let bare = auth
+            .authenticate(&metadata(Some("bearer bare-key")))
+            .unwrap()
+            .unwrap();
+        assert!(bare.starts_with("key-"));
+        assert_eq!(bare.len(), "key-".len() + 12);
+        assert!(!bare.contains("bare-key"));
+    }
+
+    #[test]
+    fn keeps_base64_padding_in_keys() {
+        let auth = ApiKeyAuth::parse("dGVzdA==,ci:c2VjcmV0Cg==").unwrap();
+
+        let bare = auth.authenticate(&metadata(Some("dGVzdA=="))).unwrap();
+        assert!(bare.unwrap().starts_with("key-"));
+        let named = auth.authenticate(&metadata(Some("c2VjcmV0Cg=="))).unwrap();
+        assert_eq!(named.as_deref(), Some("ci"));
+
+        for truncated in ["dGVzdA", "=", "c2VjcmV0Cg"] {
+            assert!(auth.authenticate(&metadata(Some(truncated))).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_entries_without_a_name_or_key() {
+        for list in ["ci:", ":s3cret", " : ", "ok:key, broken:"] {
+            assert!(ApiKeyAuth::parse(list).is_err(), "{list}");
+        }
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_keys() {
+        let auth = ApiKeyAuth::parse("ci:s3cret").unwrap();
+        for header in [None, Some("Bearer wrong"), Some("Bearer ")] {
+            assert!(auth.authenticate(&metadata(header)).is_err());
+        }
+    }
+
+    #[test]
+    fn no_keys_fails_closed() {
+        let auth = ApiKeyAuth::parse(" , ").unwrap();
+        assert!(auth.is_enabled());
+        for header in [None, Some(""), Some("Bearer anything")] {
+            assert!(auth.authenticate(&metadata(header)).is_err());
+        }
+
+        for keys in [None, Some(""), Some(" , ")] {
+            assert!(ApiKeyAuth::from_settings(None, keys).is_err());
+            assert!(ApiKeyAuth::from_settings(Some("0"), keys).is_err());
+            assert!(ApiKeyAuth::from_settings(Some("false"), keys).is_err());
+        }
+    }
+
+    #[test]
+    fn only_the_disable_flag_turns_authentication_off() {
+        for flag in ["1", "true", "TRUE"] {
+            let auth = ApiKeyAuth::from_settings(Some(flag), None).unwrap();
+            assert!(!auth.is_enabled());
+            assert_eq!(auth.authenticate(&metadata(None)).unwrap(), None);
+        }
+
+        let auth = ApiKeyAuth::from_settings(None, Some("ci:s3cret")).unwrap();
+        assert!(auth.authenticate(&metadata(None)).is_err());
+    }
+}
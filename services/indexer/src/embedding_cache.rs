@@ -0,0 +1,234 @@
+#![allow(dead_code)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::embeddings::EmbeddingError;
+
+pub const DEFAULT_EMBEDDING_CACHE_CAPACITY: usize = 2048;
+
+struct Entry {
+    embedding: Arc<[f32]>,
+    sequence: u64,
+}
+
+struct Entries {
+    by_key: HashMap<u64, Entry>,
+    next_sequence: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Embeddings of recently embedded texts, keyed by a 64-bit hash of the
+/// provider name and the text, so re-indexing an unchanged symbol body does
+/// not call the provider again. The least recently used entry is evicted
+/// once `capacity` is reached; only successful embeddings are kept.
+pub struct EmbeddingCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                by_key: HashMap::new(),
+                next_sequence: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads `EMBEDDING_CACHE_CAPACITY`; 0 disables the cache.
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("EMBEDDING_CACHE_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_EMBEDDING_CACHE_CAPACITY),
+        )
+    }
+
+    /// The cached embedding of `text` from `provider`, or the result of
+    /// `embed` on a miss. A zero-capacity cache always calls `embed`.
+    pub async fn get_or_embed<F, Fut>(
+        &self,
+        provider: &str,
+        text: &str,
+        embed: F,
+    ) -> Result<Vec<f32>, EmbeddingError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<f32>, EmbeddingError>>,
+    {
+        if self.capacity == 0 {
+            return embed().await;
+        }
+
+        let key = cache_key(provider, text);
+        if let Some(embedding) = self.lookup(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(embedding.to_vec());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = embed().await?;
+        self.insert(key, Arc::from(embedding.as_slice()));
+        Ok(embedding)
+    }
+
+    fn lookup(&self, key: u64) -> Option<Arc<[f32]>> {
+        let mut entries = self.entries.lock();
+        let sequence = entries.next_sequence;
+        let entry = entries.by_key.get_mut(&key)?;
+        entry.sequence = sequence;
+        let embedding = entry.embedding.clone();
+        entries.next_sequence += 1;
+        Some(embedding)
+    }
+
+    fn insert(&self, key: u64, embedding: Arc<[f32]>) {
+        let mut guard = self.entries.lock();
+        let sequence = guard.next_sequence;
+        guard.next_sequence += 1;
+
+        let entries = &mut guard.by_key;
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.sequence)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            key,
+            Entry {
+                embedding,
+                sequence,
+            },
+        );
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn cache_key(provider: &str, text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    provider.hash(&mut hasher);
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddingProvider;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for CountingProvider {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![text.len() as f32; 4])
+        }
+
+        async fn health_check(&self) -> Result<(), EmbeddingError> {
+            Ok(())
+        }
+    }
+
+    async fn embed(cache: &EmbeddingCache, provider: &CountingProvider, text: &str) -> Vec<f32> {
+        cache
+            .get_or_embed("counting", text, || provider.embed(text))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn repeated_text_calls_the_provider_once() {
+        let cache = EmbeddingCache::new(8);
+        let provider = CountingProvider::default();
+
+        let first = embed(&cache, &provider, "fn main() {}").await;
+        let second = embed(&cache, &provider, "fn main() {}").await;
+
+        assert_eq!(first, second);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats(), EmbeddingCacheStats { hits: 1, misses: 1 });
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used_and_keeps_failures_out() {
+        let cache = EmbeddingCache::new(2);
+        let provider = CountingProvider::default();
+
+        embed(&cache, &provider, "a").await;
+        embed(&cache, &provider, "bb").await;
+        embed(&cache, &provider, "a").await;
+        embed(&cache, &provider, "ccc").await;
+        assert_eq!(cache.len(), 2);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+
+        // "bb" was the least recently used, so it went.
+        embed(&cache, &provider, "bb").await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+
+        let failed = cache
+            .get_or_embed("counting", "down", || async {
+                Err(EmbeddingError::Unavailable("down".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+        embed(&cache, &provider, "down").await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn providers_do_not_share_entries() {
+        let cache = EmbeddingCache::new(8);
+        let provider = CountingProvider::default();
+
+        for name in ["local", "orchestrator"] {
+            cache
+                .get_or_embed(name, "same text", || provider.embed("same text"))
+                .await
+                .unwrap();
+        }
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,15 +1,22 @@
 #![allow(dead_code)]
 
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use candle_core::{Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config, DTYPE};
 use hf_hub::{api::sync::Api, Repo, RepoType};
+use once_cell::sync::OnceCell;
 use thiserror::Error;
-use tokenizers::Tokenizer;
+use tokenizers::{Tokenizer, TruncationParams};
 use tokio::task;
+use tracing::{info, warn};
+
+use crate::embedding_cache::EmbeddingCache;
 
 #[derive(Debug, Error)]
 pub enum EmbeddingError {
@@ -19,57 +26,394 @@ pub enum EmbeddingError {
     ModelLoad(String),
     #[error("HTTP client error: {0}")]
     HttpClient(String),
+    #[error("embedding provider unavailable: {0}")]
+    Unavailable(String),
+    /// The provider answered, but not with a usable vector.
+    #[error("malformed embedding: {0}")]
+    Malformed(String),
 }
 
-pub const EMBEDDING_DIM: usize = 384;
+/// Output dimension of [`DEFAULT_EMBEDDING_MODEL`]. The orchestrator is
+/// expected to produce vectors of this size; the local provider reports the
+/// `hidden_size` of whichever model it loaded.
+pub const DEFAULT_EMBEDDING_DIM: usize = 384;
+
+const DEFAULT_HEALTH_CACHE_SECS: u64 = 5;
+
+/// Hugging Face repo of the local model when `EMBEDDING_MODEL` is unset.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+const MODEL_CONFIG_FILE: &str = "config.json";
+const MODEL_TOKENIZER_FILE: &str = "tokenizer.json";
+const MODEL_WEIGHTS_FILE: &str = "model.safetensors";
+/// Tokens per window the local model embeds at once, special tokens
+/// included. all-MiniLM-L6-v2 was trained on sequences of at most 512.
+pub const DEFAULT_CHUNK_TOKENS: usize = 512;
+/// Rough resident size of one loaded all-MiniLM-L6-v2 instance: f32 weights
+/// plus activations for a full window. Used to cap `EMBEDDING_POOL_SIZE`.
+const MODEL_INSTANCE_BYTES: u64 = 256 * 1024 * 1024;
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_FAILOVER_CHECK_SECS: u64 = 30;
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com";
+/// Longest `Retry-After` an embedding server can make us wait.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug)]
 pub struct EmbeddingConfig {
     pub provider: String,
+    /// Hugging Face repo id of the local BERT model.
+    pub model: String,
     pub model_path: Option<String>,
+    /// Where the local BERT model runs.
+    pub device: EmbeddingDevice,
+    /// Window size of the local BERT model; see [`LocalModelOptions`].
+    pub chunk_tokens: usize,
+    /// Local BERT model instances; see [`LocalModelOptions`].
+    pub pool_size: usize,
+}
+
+/// How the local BERT model is loaded and fed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalModelOptions {
+    /// Hugging Face repo id, e.g. `BAAI/bge-small-en-v1.5`. With `MODEL_DIR`
+    /// set the files are read from there instead, and this only names them.
+    pub model: String,
+    pub device: EmbeddingDevice,
+    /// Text longer than this many tokens is embedded window by window and
+    /// the token embeddings of all windows mean-pooled into one vector.
+    /// Capped at the model's `max_position_embeddings`.
+    pub chunk_tokens: usize,
+    /// Model instances that may embed at the same time. Each is loaded the
+    /// first time concurrent demand reaches it. Capped by available memory.
+    pub pool_size: usize,
+}
+
+impl Default for LocalModelOptions {
+    fn default() -> Self {
+        Self {
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            device: EmbeddingDevice::default(),
+            chunk_tokens: DEFAULT_CHUNK_TOKENS,
+            pool_size: 1,
+        }
+    }
+}
+
+impl LocalModelOptions {
+    /// Reads `EMBEDDING_MODEL`, `EMBEDDING_DEVICE`, `EMBEDDING_CHUNK_TOKENS`
+    /// and `EMBEDDING_POOL_SIZE`.
+    pub fn from_env() -> Self {
+        Self {
+            model: std::env::var("EMBEDDING_MODEL")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string()),
+            device: EmbeddingDevice::from_env(),
+            chunk_tokens: std::env::var("EMBEDDING_CHUNK_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(DEFAULT_CHUNK_TOKENS),
+            pool_size: std::env::var("EMBEDDING_POOL_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(1),
+        }
+    }
+}
+
+/// `requested` instances, or as many as fit in half of `available_bytes`
+/// when that is fewer. Never less than one.
+fn bounded_pool_size(requested: usize, available_bytes: Option<u64>) -> usize {
+    let Some(available) = available_bytes else {
+        return requested.max(1);
+    };
+    let fits = (available / 2 / MODEL_INSTANCE_BYTES).max(1) as usize;
+    if requested > fits {
+        warn!(
+            requested,
+            allowed = fits,
+            available_mib = available / (1024 * 1024),
+            "EMBEDDING_POOL_SIZE exceeds what available memory can hold; capping"
+        );
+    }
+    requested.clamp(1, fits)
+}
+
+/// `MemAvailable` from `/proc/meminfo`; `None` where that is not readable.
+fn available_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Device the local BERT model is loaded onto, from `EMBEDDING_DEVICE`
+/// (`cpu`, `cuda`, `cuda:N` or `metal`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingDevice {
+    #[default]
+    Cpu,
+    /// CUDA device by ordinal.
+    Cuda(usize),
+    Metal,
+}
+
+impl EmbeddingDevice {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "" | "cpu" => Ok(Self::Cpu),
+            "cuda" => Ok(Self::Cuda(0)),
+            "metal" => Ok(Self::Metal),
+            other => match other.strip_prefix("cuda:").map(str::parse) {
+                Some(Ok(ordinal)) => Ok(Self::Cuda(ordinal)),
+                _ => Err(format!(
+                    "unknown embedding device '{}'; expected cpu, cuda:N or metal",
+                    other
+                )),
+            },
+        }
+    }
+
+    /// Reads `EMBEDDING_DEVICE`. An unknown value is logged and CPU used.
+    pub fn from_env() -> Self {
+        let Ok(value) = std::env::var("EMBEDDING_DEVICE") else {
+            return Self::default();
+        };
+        Self::parse(&value).unwrap_or_else(|reason| {
+            warn!(%reason, "ignoring EMBEDDING_DEVICE; using cpu");
+            Self::default()
+        })
+    }
+
+    /// The candle device to load onto. A GPU that this build was compiled
+    /// without, or that the host does not have, falls back to CPU.
+    fn select(&self) -> Device {
+        let device = match self {
+            Self::Cpu => return Device::Cpu,
+            Self::Cuda(ordinal) => Device::new_cuda(*ordinal),
+            Self::Metal => Device::new_metal(0),
+        };
+        device.unwrap_or_else(|e| {
+            warn!(requested = %self, error = %e, "embedding device unavailable; using cpu");
+            Device::Cpu
+        })
+    }
+}
+
+impl std::fmt::Display for EmbeddingDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cpu => f.write_str("cpu"),
+            Self::Cuda(ordinal) => write!(f, "cuda:{}", ordinal),
+            Self::Metal => f.write_str("metal"),
+        }
+    }
 }
 
 #[async_trait]
 pub trait EmbeddingProvider: Send + Sync {
     async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError>;
+
+    /// Reports whether the provider can currently serve embeddings.
+    async fn health_check(&self) -> Result<(), EmbeddingError>;
+
+    /// Name recorded alongside the embeddings this provider produces.
+    fn model_name(&self) -> &str {
+        recorded_model_name(DEFAULT_EMBEDDING_MODEL)
+    }
+
+    /// Length of the vectors this provider produces.
+    async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        Ok(DEFAULT_EMBEDDING_DIM)
+    }
+}
+
+/// `repo` without its owner, so `sentence-transformers/all-MiniLM-L6-v2` is
+/// recorded as `all-MiniLM-L6-v2`.
+pub fn recorded_model_name(repo: &str) -> &str {
+    repo.rsplit('/').next().unwrap_or(repo)
 }
 
 struct BertModelWrapper {
     model: BertModel,
+    /// Truncates to `chunk_tokens`, handing the rest back as overflow.
     tokenizer: Tokenizer,
     device: Device,
+    chunk_tokens: usize,
+    /// The model's `hidden_size`, which is the length of its embeddings.
+    dimension: usize,
 }
 
-impl BertModelWrapper {
-    fn new() -> Result<Self, EmbeddingError> {
-        let device = Device::Cpu; // Use CPU for now, can be configured for CUDA/Metal
+/// On-disk locations of the files that make up the local BERT model.
+#[derive(Debug, Clone, PartialEq)]
+struct ModelFiles {
+    config: PathBuf,
+    tokenizer: PathBuf,
+    weights: PathBuf,
+}
+
+impl ModelFiles {
+    /// Uses `MODEL_DIR` when set so air-gapped deployments never touch the
+    /// Hugging Face API; otherwise downloads (or reuses the hub cache).
+    /// `MODEL_TOKENIZER_PATH` replaces the tokenizer from either source.
+    fn resolve(repo: &str) -> Result<Self, EmbeddingError> {
+        let tokenizer = Self::tokenizer_override()?;
+        match std::env::var("MODEL_DIR")
+            .ok()
+            .filter(|dir| !dir.trim().is_empty())
+        {
+            Some(dir) => Self::from_dir(Path::new(&dir), tokenizer),
+            None => Self::download(repo, tokenizer),
+        }
+    }
+
+    fn tokenizer_override() -> Result<Option<PathBuf>, EmbeddingError> {
+        let Some(path) = std::env::var("MODEL_TOKENIZER_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let path = PathBuf::from(path);
+        if path.is_file() {
+            Ok(Some(path))
+        } else {
+            Err(EmbeddingError::ModelLoad(format!(
+                "MODEL_TOKENIZER_PATH {} is not a file",
+                path.display()
+            )))
+        }
+    }
+
+    fn from_dir(dir: &Path, tokenizer: Option<PathBuf>) -> Result<Self, EmbeddingError> {
+        let file = |name: &str| {
+            let path = dir.join(name);
+            if path.is_file() {
+                Ok(path)
+            } else {
+                Err(EmbeddingError::ModelLoad(format!(
+                    "MODEL_DIR {} is missing {name}",
+                    dir.display()
+                )))
+            }
+        };
 
+        Ok(Self {
+            config: file(MODEL_CONFIG_FILE)?,
+            tokenizer: match tokenizer {
+                Some(path) => path,
+                None => file(MODEL_TOKENIZER_FILE)?,
+            },
+            weights: file(MODEL_WEIGHTS_FILE)?,
+        })
+    }
+
+    fn download(repo: &str, tokenizer: Option<PathBuf>) -> Result<Self, EmbeddingError> {
         let api = Api::new().map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
-        let repo = api.repo(Repo::new(
-            "sentence-transformers/all-MiniLM-L6-v2".to_string(),
-            RepoType::Model,
-        ));
+        let hub = api.repo(Repo::new(repo.to_string(), RepoType::Model));
+        let file = |name: &str| {
+            hub.get(name).map_err(|e| {
+                EmbeddingError::ModelLoad(format!("failed to fetch {name} from {repo}: {e}"))
+            })
+        };
 
-        let config_filename = repo
-            .get("config.json")
-            .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
-        let tokenizer_filename = repo
-            .get("tokenizer.json")
-            .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
-        let weights_filename = repo
-            .get("model.safetensors")
-            .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
+        Ok(Self {
+            config: file(MODEL_CONFIG_FILE)?,
+            tokenizer: match tokenizer {
+                Some(path) => path,
+                None => file(MODEL_TOKENIZER_FILE)?,
+            },
+            weights: file(MODEL_WEIGHTS_FILE)?,
+        })
+    }
+
+    /// Fails when the tokenizer's vocabulary is not the one the model's
+    /// embedding table was trained on. Ids from a foreign tokenizer either
+    /// fall outside the table or land on unrelated rows, and the resulting
+    /// embeddings look valid while being meaningless.
+    fn check_vocab_size(
+        &self,
+        config: &serde_json::Value,
+        tokenizer: &Tokenizer,
+    ) -> Result<(), EmbeddingError> {
+        let model_vocab = config
+            .get("vocab_size")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| {
+                EmbeddingError::ModelLoad(format!(
+                    "{} does not declare vocab_size",
+                    self.config.display()
+                ))
+            })?;
+        let tokenizer_vocab = tokenizer.get_vocab_size(true) as u64;
 
-        let config = std::fs::read_to_string(config_filename)
+        if tokenizer_vocab == model_vocab {
+            Ok(())
+        } else {
+            Err(EmbeddingError::ModelLoad(format!(
+                "tokenizer {} has a vocabulary of {} tokens but model config {} declares vocab_size {}; \
+                 the tokenizer does not belong to this model",
+                self.tokenizer.display(),
+                tokenizer_vocab,
+                self.config.display(),
+                model_vocab
+            )))
+        }
+    }
+}
+
+impl BertModelWrapper {
+    fn new(options: &LocalModelOptions) -> Result<Self, EmbeddingError> {
+        Self::from_files(
+            &ModelFiles::resolve(&options.model)?,
+            options.device.select(),
+            options.chunk_tokens,
+        )
+    }
+
+    fn from_files(
+        files: &ModelFiles,
+        device: Device,
+        chunk_tokens: usize,
+    ) -> Result<Self, EmbeddingError> {
+        let config = std::fs::read_to_string(&files.config)
             .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
-        let config: Config =
+        let config: serde_json::Value =
             serde_json::from_str(&config).map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
 
-        let tokenizer = Tokenizer::from_file(tokenizer_filename)
+        let mut tokenizer = Tokenizer::from_file(&files.tokenizer)
+            .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
+        files.check_vocab_size(&config, &tokenizer)?;
+
+        // Positions past max_position_embeddings have no embedding to look up.
+        let chunk_tokens = config
+            .get("max_position_embeddings")
+            .and_then(|v| v.as_u64())
+            .map_or(chunk_tokens, |max| chunk_tokens.min(max as usize))
+            .max(1);
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: chunk_tokens,
+                ..TruncationParams::default()
+            }))
             .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
 
+        // `Config` keeps its fields private, so read the width beforehand.
+        let dimension = config
+            .get("hidden_size")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| {
+                EmbeddingError::ModelLoad("config.json has no hidden_size".to_string())
+            })? as usize;
+        let config: Config =
+            serde_json::from_value(config).map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?;
+
         let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&[weights_filename], DTYPE, &device)
+            VarBuilder::from_mmaped_safetensors(&[&files.weights], DTYPE, &device)
                 .map_err(|e| EmbeddingError::ModelLoad(e.to_string()))?
         };
 
@@ -80,16 +424,55 @@ impl BertModelWrapper {
             model,
             tokenizer,
             device,
+            chunk_tokens,
+            dimension,
         })
     }
 
+    /// Mean of the token embeddings of `text`, L2-normalized. Text over
+    /// `chunk_tokens` is split into consecutive windows, each with its own
+    /// special tokens, and the mean is taken over the tokens of all of them.
     fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
-        let tokens = self
+        let mut tokens = self
             .tokenizer
             .encode(text, true)
             .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
+        let overflow = tokens.take_overflowing();
+
+        let mut sum: Option<Tensor> = None;
+        let mut n_tokens = 0;
+        for window in std::iter::once(tokens).chain(overflow) {
+            let (window_sum, window_tokens) = self.sum_token_embeddings(window.get_ids())?;
+            sum = Some(match sum {
+                Some(sum) => {
+                    (sum + window_sum).map_err(|e| EmbeddingError::Generation(e.to_string()))?
+                }
+                None => window_sum,
+            });
+            n_tokens += window_tokens;
+        }
+        let sum = sum.ok_or_else(|| EmbeddingError::Generation("no tokens".to_string()))?;
+
+        // Mean pooling
+        let embeddings =
+            (sum / (n_tokens as f64)).map_err(|e| EmbeddingError::Generation(e.to_string()))?;
+
+        let embeddings =
+            normalize_l2(&embeddings).map_err(|e| EmbeddingError::Generation(e.to_string()))?;
+
+        let embedding_vec = embeddings
+            .squeeze(0)
+            .map_err(|e| EmbeddingError::Generation(e.to_string()))?
+            .to_vec1::<f32>()
+            .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
+
+        Ok(embedding_vec)
+    }
 
-        let token_ids = Tensor::new(tokens.get_ids(), &self.device)
+    /// Sum over tokens of the model's output for one window, shaped
+    /// `[1, hidden]`, and the number of tokens summed.
+    fn sum_token_embeddings(&self, ids: &[u32]) -> Result<(Tensor, usize), EmbeddingError> {
+        let token_ids = Tensor::new(ids, &self.device)
             .map_err(|e| EmbeddingError::Generation(e.to_string()))?
             .unsqueeze(0)
             .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
@@ -103,26 +486,12 @@ impl BertModelWrapper {
             .forward(&token_ids, &token_type_ids) // Fixed: removed None argument
             .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
 
-        // Mean pooling
-        let dims = embeddings.dims();
-        let n_tokens = dims[1];
-
-        let embeddings = (embeddings
+        let n_tokens = embeddings.dims()[1];
+        let sum = embeddings
             .sum(1)
-            .map_err(|e| EmbeddingError::Generation(e.to_string()))?
-            / (n_tokens as f64))
-            .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
-
-        let embeddings =
-            normalize_l2(&embeddings).map_err(|e| EmbeddingError::Generation(e.to_string()))?;
-
-        let embedding_vec = embeddings
-            .squeeze(0)
-            .map_err(|e| EmbeddingError::Generation(e.to_string()))?
-            .to_vec1::<f32>()
             .map_err(|e| EmbeddingError::Generation(e.to_string()))?;
 
-        Ok(embedding_vec)
+        Ok((sum, n_tokens))
     }
 }
 
@@ -132,43 +501,288 @@ fn normalize_l2(v: &Tensor) -> candle_core::Result<Tensor> {
     v.broadcast_div(&norm)
 }
 
-pub struct LocalBertProvider {
-    // Wrap in Arc<Mutex> because BertModel is not Sync/Send by default (due to internal caches/buffers potentially)
+type ModelLoader = fn(&LocalModelOptions) -> Result<BertModelWrapper, EmbeddingError>;
+
+/// Loaded model instances, each behind its own lock so that as many embeds
+/// run at once as there are instances.
+struct ModelPool {
+    // Wrap in Mutex because BertModel is not Sync/Send by default (due to internal caches/buffers potentially)
     // Actually candle models are usually stateless or immutable after load, but let's be safe.
-    // However, we need to share it across threads.
-    model: Arc<Mutex<BertModelWrapper>>,
+    slots: Vec<OnceCell<Mutex<BertModelWrapper>>>,
+    next: AtomicUsize,
+}
+
+impl ModelPool {
+    fn new(size: usize) -> Self {
+        Self {
+            slots: (0..size.max(1)).map(|_| OnceCell::new()).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs `f` on an idle loaded instance if there is one. Otherwise takes
+    /// the next slot round-robin, loading it if it is empty and waiting for
+    /// it if it is busy.
+    fn run<T>(
+        &self,
+        load: impl FnOnce() -> Result<BertModelWrapper, EmbeddingError>,
+        f: impl FnOnce(&BertModelWrapper) -> Result<T, EmbeddingError>,
+    ) -> Result<T, EmbeddingError> {
+        let poisoned = || EmbeddingError::Generation("mutex poisoned".to_string());
+
+        for model in self.slots.iter().filter_map(OnceCell::get) {
+            match model.try_lock() {
+                Ok(wrapper) => return f(&wrapper),
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Poisoned(_)) => return Err(poisoned()),
+            }
+        }
+
+        let slot = &self.slots[self.next.fetch_add(1, Ordering::Relaxed) % self.slots.len()];
+        let model = slot.get_or_try_init(|| load().map(Mutex::new))?;
+        let wrapper = model.lock().map_err(|_| poisoned())?;
+        f(&wrapper)
+    }
+
+    fn loaded(&self) -> impl Iterator<Item = &Mutex<BertModelWrapper>> {
+        self.slots.iter().filter_map(OnceCell::get)
+    }
+}
+
+/// Embeds with local BERT models that are loaded on first use, so deployments
+/// that never embed locally never pay for the download or the memory.
+pub struct LocalBertProvider {
+    models: Arc<ModelPool>,
+    loader: ModelLoader,
+    options: LocalModelOptions,
+    /// Read from the first instance loaded.
+    dimension: OnceCell<usize>,
+}
+
+impl Default for LocalBertProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LocalBertProvider {
-    pub fn new() -> Result<Self, EmbeddingError> {
-        // Load model in a blocking task
-        let wrapper = task::block_in_place(BertModelWrapper::new)?;
-        Ok(Self {
-            model: Arc::new(Mutex::new(wrapper)),
+    /// Configured by `EMBEDDING_DEVICE`, `EMBEDDING_CHUNK_TOKENS` and
+    /// `EMBEDDING_POOL_SIZE`.
+    pub fn new() -> Self {
+        Self::with_options(LocalModelOptions::from_env())
+    }
+
+    pub fn with_device(device: EmbeddingDevice) -> Self {
+        Self::with_options(LocalModelOptions {
+            device,
+            ..LocalModelOptions::default()
+        })
+    }
+
+    pub fn with_options(options: LocalModelOptions) -> Self {
+        Self::with_loader(BertModelWrapper::new, options)
+    }
+
+    pub fn from_config(config: &EmbeddingConfig) -> Self {
+        Self::with_options(LocalModelOptions {
+            model: config.model.clone(),
+            device: config.device,
+            chunk_tokens: config.chunk_tokens,
+            pool_size: config.pool_size,
         })
     }
+
+    fn with_loader(loader: ModelLoader, options: LocalModelOptions) -> Self {
+        let pool_size = bounded_pool_size(options.pool_size, available_memory_bytes());
+        Self {
+            models: Arc::new(ModelPool::new(pool_size)),
+            loader,
+            options,
+            dimension: OnceCell::new(),
+        }
+    }
+
+    pub fn device(&self) -> EmbeddingDevice {
+        self.options.device
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.models.loaded().next().is_some()
+    }
+
+    /// Model instances in the pool, after the memory cap.
+    pub fn pool_size(&self) -> usize {
+        self.models.slots.len()
+    }
+
+    /// Runs `f` against a model instance on a blocking thread, loading one
+    /// first if needed. Concurrent callers that land on the same empty slot
+    /// wait on one load; a failed load leaves the slot empty so the next call
+    /// retries.
+    async fn with_model<T, F>(&self, f: F) -> Result<T, EmbeddingError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&BertModelWrapper) -> Result<T, EmbeddingError> + Send + 'static,
+    {
+        let models = self.models.clone();
+        let loader = self.loader;
+        let options = self.options.clone();
+
+        task::spawn_blocking(move || models.run(|| loader(&options), f))
+            .await
+            .map_err(|e| EmbeddingError::Generation(format!("task join error: {e}")))?
+    }
 }
 
 #[async_trait]
 impl EmbeddingProvider for LocalBertProvider {
     async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
-        let model = self.model.clone();
         let text = text.to_string();
+        self.with_model(move |wrapper| wrapper.embed(&text)).await
+    }
 
-        task::spawn_blocking(move || {
-            let wrapper = model
-                .lock()
-                .map_err(|_| EmbeddingError::Generation("mutex poisoned".to_string()))?;
-            wrapper.embed(&text)
-        })
-        .await
-        .map_err(|e| EmbeddingError::Generation(format!("task join error: {e}")))?
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        // Readiness loads the model so a missing or corrupt model fails the
+        // probe instead of the first embed. A poisoned lock means a previous
+        // embed panicked and the provider can no longer be used.
+        self.with_model(|_| Ok(())).await.map_err(|e| match e {
+            EmbeddingError::Unavailable(_) => e,
+            other => EmbeddingError::Unavailable(other.to_string()),
+        })?;
+        if self.models.loaded().any(|model| model.is_poisoned()) {
+            return Err(EmbeddingError::Unavailable(
+                "local model mutex poisoned".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn model_name(&self) -> &str {
+        recorded_model_name(&self.options.model)
+    }
+
+    /// Loads the model the first time it is asked.
+    async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        if let Some(dimension) = self.dimension.get() {
+            return Ok(*dimension);
+        }
+        let dimension = self.with_model(|model| Ok(model.dimension)).await?;
+        Ok(*self.dimension.get_or_init(|| dimension))
+    }
+}
+
+/// How an HTTP embedding request is retried. Connection errors, 429 and 5xx
+/// answers are retried; other answers are returned as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Requests sent in total, the first one included. 1 disables retry.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled before each one after.
+    pub base_delay: Duration,
+    /// Wait a random 50-100% of each delay, so clients that failed together
+    /// do not retry together.
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Reads `{prefix}_RETRY_ATTEMPTS`, `{prefix}_RETRY_BASE_MS` and
+    /// `{prefix}_RETRY_JITTER`, falling back to `default` for each.
+    fn from_env(prefix: &str, default: Self) -> Self {
+        let var = |name: &str| std::env::var(format!("{prefix}_{name}")).ok();
+        Self {
+            max_attempts: var("RETRY_ATTEMPTS")
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &u32| *v > 0)
+                .unwrap_or(default.max_attempts),
+            base_delay: var("RETRY_BASE_MS")
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.base_delay),
+            jitter: var("RETRY_JITTER")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.jitter),
+        }
+    }
+
+    /// Wait before retry number `retry`, counting from 1.
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16));
+        if self.jitter {
+            use rand::Rng;
+            delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+/// A request that still failed when its [`RetryPolicy`] ran out.
+struct RetriesExhausted {
+    attempts: u32,
+    last_error: String,
+}
+
+/// Sends the request `build` makes, again after each retryable failure,
+/// until an answer that is not 429 or 5xx comes back or `policy` runs out.
+/// A server's `Retry-After`, in seconds, replaces the computed delay.
+async fn send_with_retry(
+    policy: RetryPolicy,
+    provider: &str,
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, RetriesExhausted> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let (last_error, retry_after) = match build().send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status != reqwest::StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                    return Ok(response);
+                }
+                (format!("HTTP error: {status}"), retry_after(&response))
+            }
+            Err(e) => (format!("HTTP request failed: {e}"), None),
+        };
+
+        if attempts >= policy.max_attempts.max(1) {
+            return Err(RetriesExhausted {
+                attempts,
+                last_error,
+            });
+        }
+        let delay = retry_after.unwrap_or_else(|| policy.delay(attempts));
+        warn!(
+            provider,
+            attempt = attempts,
+            error = %last_error,
+            delay_ms = delay.as_millis() as u64,
+            "Retrying embedding request"
+        );
+        tokio::time::sleep(delay).await;
     }
 }
 
+/// Delay the server asked for in `Retry-After`, when given in seconds.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
 pub struct OrchestratorProvider {
     client: reqwest::Client,
     base_url: String,
+    retry: RetryPolicy,
+    health_cache_ttl: Duration,
+    last_health: parking_lot::Mutex<Option<(Instant, Result<(), String>)>>,
 }
 
 impl OrchestratorProvider {
@@ -185,7 +799,53 @@ impl OrchestratorProvider {
                 EmbeddingError::HttpClient(format!("failed to create HTTP client: {e}"))
             })?;
 
-        Ok(Self { client, base_url })
+        let health_cache_ttl = std::env::var("EMBEDDING_HEALTH_CACHE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_HEALTH_CACHE_SECS));
+
+        Ok(Self {
+            client,
+            base_url,
+            retry: RetryPolicy::from_env(
+                "ORCHESTRATOR",
+                RetryPolicy {
+                    max_attempts: 3,
+                    base_delay: Duration::from_millis(200),
+                    jitter: true,
+                },
+            ),
+            health_cache_ttl,
+            last_health: parking_lot::Mutex::new(None),
+        })
+    }
+
+    /// How long a health check result is reused before the orchestrator is probed again.
+    pub fn with_health_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.health_cache_ttl = ttl;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    async fn probe_health(&self) -> Result<(), String> {
+        let url = format!("{}/healthz", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("health request failed: {e}"))?;
+
+        if !response.status().is_success() {
+            return Err(format!("health endpoint returned {}", response.status()));
+        }
+        Ok(())
     }
 }
 
@@ -193,13 +853,17 @@ impl OrchestratorProvider {
 impl EmbeddingProvider for OrchestratorProvider {
     async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
         let url = format!("{}/api/v1/embeddings", self.base_url);
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({ "text": text }))
-            .send()
-            .await
-            .map_err(|e| EmbeddingError::Generation(format!("HTTP request failed: {e}")))?;
+        let body = serde_json::json!({ "text": text });
+        let response = send_with_retry(self.retry, "orchestrator", || {
+            self.client.post(&url).json(&body)
+        })
+        .await
+        .map_err(|e| {
+            EmbeddingError::Generation(format!(
+                "{} (gave up after {} attempts)",
+                e.last_error, e.attempts
+            ))
+        })?;
 
         if !response.status().is_success() {
             return Err(EmbeddingError::Generation(format!(
@@ -216,53 +880,871 @@ impl EmbeddingProvider for OrchestratorProvider {
         let embedding = json
             .get("embedding")
             .and_then(|v| v.as_array())
-            .ok_or_else(|| EmbeddingError::Generation("invalid response format".to_string()))?
+            .ok_or_else(|| EmbeddingError::Malformed("invalid response format".to_string()))?
             .iter()
             .filter_map(|v| v.as_f64().map(|f| f as f32))
             .collect::<Vec<f32>>();
 
-        if embedding.len() != EMBEDDING_DIM {
-            return Err(EmbeddingError::Generation(format!(
-                "expected embedding dimension {}, got {}",
-                EMBEDDING_DIM,
-                embedding.len()
-            )));
+        check_embedding(&embedding, DEFAULT_EMBEDDING_DIM)?;
+        Ok(embedding)
+    }
+
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        if let Some((checked_at, result)) = self.last_health.lock().as_ref() {
+            if checked_at.elapsed() < self.health_cache_ttl {
+                return result.clone().map_err(EmbeddingError::Unavailable);
+            }
+        }
+
+        let result = self.probe_health().await;
+        *self.last_health.lock() = Some((Instant::now(), result.clone()));
+        result.map_err(EmbeddingError::Unavailable)
+    }
+}
+
+/// Embeds through an OpenAI-compatible `/v1/embeddings` endpoint, such as
+/// OpenAI itself, vLLM or text-embeddings-inference. Rate limiting (429) and
+/// server errors are retried with exponential backoff.
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dimension: usize,
+    retry: RetryPolicy,
+    health_cache_ttl: Duration,
+    last_health: parking_lot::Mutex<Option<(Instant, Result<(), String>)>>,
+}
+
+impl OpenAiProvider {
+    /// Reads `OPENAI_BASE_URL` (when `base_url` is `None`), `OPENAI_API_KEY`,
+    /// `OPENAI_EMBED_MODEL` and `OPENAI_EMBED_DIMENSIONS`. The model defaults
+    /// to [`DEFAULT_EMBEDDING_MODEL`] and the dimension to
+    /// [`DEFAULT_EMBEDDING_DIM`], matching the default schema.
+    pub fn new(base_url: Option<String>) -> Result<Self, EmbeddingError> {
+        let non_empty = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let base_url = base_url
+            .or_else(|| non_empty("OPENAI_BASE_URL"))
+            .unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string());
+        // Accept both `https://host` and the SDK-style `https://host/v1`.
+        let base_url = base_url.trim_end_matches('/');
+        let base_url = base_url.strip_suffix("/v1").unwrap_or(base_url).to_string();
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .map_err(|e| {
+                EmbeddingError::HttpClient(format!("failed to create HTTP client: {e}"))
+            })?;
+
+        let health_cache_ttl = std::env::var("EMBEDDING_HEALTH_CACHE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_HEALTH_CACHE_SECS));
+
+        Ok(Self {
+            client,
+            base_url,
+            api_key: non_empty("OPENAI_API_KEY"),
+            model: non_empty("OPENAI_EMBED_MODEL")
+                .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string()),
+            dimension: non_empty("OPENAI_EMBED_DIMENSIONS")
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(DEFAULT_EMBEDDING_DIM),
+            retry: RetryPolicy {
+                max_attempts: 4,
+                base_delay: Duration::from_millis(500),
+                jitter: false,
+            },
+            health_cache_ttl,
+            last_health: parking_lot::Mutex::new(None),
+        })
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// The model to request and the dimension its embeddings must have.
+    pub fn with_model(mut self, model: impl Into<String>, dimension: usize) -> Self {
+        self.model = model.into();
+        self.dimension = dimension;
+        self
+    }
+
+    /// Retries up to `max_retries` times after a connection error or a 429
+    /// or 5xx answer, waiting `backoff` before the first and twice as long
+    /// before each one after, unless the server sends `Retry-After`.
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: max_retries + 1,
+            base_delay: backoff,
+            jitter: false,
+        };
+        self
+    }
+
+    /// How long a health check result is reused before the server is probed again.
+    pub fn with_health_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.health_cache_ttl = ttl;
+        self
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
         }
+    }
+
+    async fn request_embedding(&self, text: &str) -> Result<serde_json::Value, EmbeddingError> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let body = serde_json::json!({ "model": self.model, "input": text });
 
+        let response = send_with_retry(self.retry, "openai", || {
+            self.authorized(self.client.post(&url)).json(&body)
+        })
+        .await
+        .map_err(|e| {
+            EmbeddingError::Unavailable(format!(
+                "{} (gave up after {} attempts)",
+                e.last_error, e.attempts
+            ))
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(EmbeddingError::Generation(format!("HTTP error: {status}")));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| EmbeddingError::Generation(format!("failed to parse response: {e}")))
+    }
+
+    async fn probe_health(&self) -> Result<(), String> {
+        let url = format!("{}/v1/models", self.base_url);
+        let response = self
+            .authorized(self.client.get(&url))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("health request failed: {e}"))?;
+
+        if !response.status().is_success() {
+            return Err(format!("models endpoint returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let json = self.request_embedding(text).await?;
+
+        let embedding = json
+            .pointer("/data/0/embedding")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| EmbeddingError::Malformed("invalid response format".to_string()))?
+            .iter()
+            .filter_map(|v| v.as_f64().map(|f| f as f32))
+            .collect::<Vec<f32>>();
+
+        check_embedding(&embedding, self.dimension)?;
         Ok(embedding)
     }
+
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        if let Some((checked_at, result)) = self.last_health.lock().as_ref() {
+            if checked_at.elapsed() < self.health_cache_ttl {
+                return result.clone().map_err(EmbeddingError::Unavailable);
+            }
+        }
+
+        let result = self.probe_health().await;
+        *self.last_health.lock() = Some((Instant::now(), result.clone()));
+        result.map_err(EmbeddingError::Unavailable)
+    }
+
+    fn model_name(&self) -> &str {
+        recorded_model_name(&self.model)
+    }
+
+    async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        Ok(self.dimension)
+    }
+}
+
+/// Rejects vectors that cannot be stored or searched: empty, of the wrong
+/// dimension, or holding NaN or infinite values.
+fn check_embedding(embedding: &[f32], dim: usize) -> Result<(), EmbeddingError> {
+    if embedding.len() != dim {
+        return Err(EmbeddingError::Malformed(format!(
+            "expected embedding dimension {}, got {}",
+            dim,
+            embedding.len()
+        )));
+    }
+    if embedding.iter().any(|v| !v.is_finite()) {
+        return Err(EmbeddingError::Malformed(
+            "embedding contains non-finite values".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Embeds `text` and checks the result is a `dim`-dimensional vector. A
+/// malformed answer is asked for once more when `retry` is set; other errors
+/// are returned as they are.
+pub async fn embed_checked(
+    provider: &dyn EmbeddingProvider,
+    text: &str,
+    dim: usize,
+    retry: bool,
+) -> Result<Vec<f32>, EmbeddingError> {
+    let attempt = || async {
+        let embedding = provider.embed(text).await?;
+        check_embedding(&embedding, dim)?;
+        Ok(embedding)
+    };
+
+    match attempt().await {
+        Err(EmbeddingError::Malformed(reason)) if retry => {
+            warn!(%reason, "retrying malformed embedding");
+            attempt().await
+        }
+        result => result,
+    }
+}
+
+/// Whether a malformed embedding is retried once, from
+/// `EMBEDDING_RETRY_MALFORMED` (default `true`). Read on first use.
+fn retry_malformed() -> bool {
+    static RETRY: OnceCell<bool> = OnceCell::new();
+    *RETRY.get_or_init(|| {
+        std::env::var("EMBEDDING_RETRY_MALFORMED")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true)
+    })
+}
+
+/// Embeddings shared by every manager in the process, sized by
+/// `EMBEDDING_CACHE_CAPACITY`. Created on first use.
+fn embedding_cache() -> &'static EmbeddingCache {
+    static CACHE: OnceCell<EmbeddingCache> = OnceCell::new();
+    CACHE.get_or_init(EmbeddingCache::from_env)
+}
+
+/// A provider together with the name reported for it in health output.
+pub type NamedProvider = (&'static str, Arc<dyn EmbeddingProvider>);
+
+/// Serves embeddings from a preferred provider while it is healthy and from a
+/// fallback while it is not. [`FailoverProvider::check`] re-evaluates the
+/// choice; [`EmbeddingManager::spawn_failover_supervisor`] runs it on an
+/// interval so the service moves back once the preferred provider recovers.
+pub struct FailoverProvider {
+    preferred: NamedProvider,
+    fallback: NamedProvider,
+    using_fallback: AtomicBool,
+    check_interval: Duration,
+}
+
+impl FailoverProvider {
+    pub fn new(preferred: NamedProvider, fallback: NamedProvider) -> Self {
+        Self {
+            preferred,
+            fallback,
+            using_fallback: AtomicBool::new(false),
+            check_interval: Duration::from_secs(DEFAULT_FAILOVER_CHECK_SECS),
+        }
+    }
+
+    /// How often the supervisor re-checks the preferred provider.
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    fn active(&self) -> &NamedProvider {
+        if self.using_fallback.load(Ordering::SeqCst) {
+            &self.fallback
+        } else {
+            &self.preferred
+        }
+    }
+
+    pub fn active_provider(&self) -> &'static str {
+        self.active().0
+    }
+
+    /// Health-checks the preferred provider, switches to or from the fallback
+    /// as needed, and returns the provider now serving requests.
+    pub async fn check(&self) -> &'static str {
+        let (preferred, provider) = &self.preferred;
+        let result = provider.health_check().await;
+        let use_fallback = result.is_err();
+
+        if self.using_fallback.swap(use_fallback, Ordering::SeqCst) != use_fallback {
+            match result {
+                Err(e) => warn!(
+                    preferred,
+                    fallback = self.fallback.0,
+                    error = %e,
+                    "Preferred embedding provider unhealthy; switching to fallback"
+                ),
+                Ok(()) => info!(
+                    preferred,
+                    "Preferred embedding provider recovered; switching back"
+                ),
+            }
+        }
+
+        self.active_provider()
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for FailoverProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.active().1.embed(text).await
+    }
+
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        self.active().1.health_check().await
+    }
+
+    fn model_name(&self) -> &str {
+        self.active().1.model_name()
+    }
+
+    /// The preferred provider's dimension, or the fallback's while the
+    /// preferred one cannot say.
+    async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        match self.preferred.1.dimension().await {
+            Ok(dimension) => Ok(dimension),
+            Err(_) => self.fallback.1.dimension().await,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingManager {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        EmbeddingManager::embed(self, text).await
+    }
+
+    async fn health_check(&self) -> Result<(), EmbeddingError> {
+        EmbeddingManager::health_check(self).await
+    }
+
+    fn model_name(&self) -> &str {
+        EmbeddingManager::model_name(self)
+    }
+
+    async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        EmbeddingManager::dimension(self).await
+    }
 }
 
 pub enum EmbeddingManager {
     Local(LocalBertProvider),
     Orchestrator(OrchestratorProvider),
+    OpenAi(OpenAiProvider),
+    Failover(FailoverProvider),
 }
 
 impl EmbeddingManager {
+    /// Builds the provider selected by [`EmbeddingManager::single`]. When
+    /// `EMBEDDING_FALLBACK_PROVIDER` names a different provider, the two are
+    /// combined into a [`FailoverProvider`] re-checked every
+    /// `EMBEDDING_FAILOVER_CHECK_SECS`.
     pub fn new(provider_type: Option<&str>) -> Result<Self, EmbeddingError> {
+        let primary = Self::single(provider_type)?;
+
+        let fallback = std::env::var("EMBEDDING_FALLBACK_PROVIDER")
+            .ok()
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| !v.is_empty() && v != primary.provider_name());
+        let Some(fallback) = fallback else {
+            return Ok(primary);
+        };
+
+        let check_interval = std::env::var("EMBEDDING_FAILOVER_CHECK_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs: &u64| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_FAILOVER_CHECK_SECS));
+
+        let fallback = Self::single(Some(&fallback))?;
+        Ok(EmbeddingManager::Failover(
+            FailoverProvider::new(primary.into_named(), fallback.into_named())
+                .with_check_interval(check_interval),
+        ))
+    }
+
+    /// Builds the provider named by `provider_type`, falling back to the
+    /// `EMBEDDING_PROVIDER` env var. With neither set, the orchestrator is used
+    /// when `ORCHESTRATOR_URL` is present and the local model otherwise.
+    fn single(provider_type: Option<&str>) -> Result<Self, EmbeddingError> {
+        let configured = std::env::var("EMBEDDING_PROVIDER")
+            .ok()
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| !v.is_empty());
+        let provider_type = provider_type.or(configured.as_deref());
+
         match provider_type {
-            Some("orchestrator") | None => {
-                // Default to orchestrator if ORCHESTRATOR_URL is set, otherwise local
-                let orchestrator_url = std::env::var("ORCHESTRATOR_URL").ok();
-                if orchestrator_url.is_some() || provider_type == Some("orchestrator") {
-                    Ok(EmbeddingManager::Orchestrator(OrchestratorProvider::new(
-                        orchestrator_url,
-                    )?))
-                } else {
-                    Ok(EmbeddingManager::Local(LocalBertProvider::new()?))
-                }
-            }
-            Some("local") => Ok(EmbeddingManager::Local(LocalBertProvider::new()?)),
+            Some("orchestrator") => Ok(EmbeddingManager::Orchestrator(OrchestratorProvider::new(
+                None,
+            )?)),
+            Some("local") => Ok(EmbeddingManager::Local(LocalBertProvider::new())),
+            Some("openai") => Ok(EmbeddingManager::OpenAi(OpenAiProvider::new(None)?)),
             Some(other) => Err(EmbeddingError::Generation(format!(
                 "unknown provider type: {other}"
             ))),
+            None => match std::env::var("ORCHESTRATOR_URL") {
+                Ok(url) => Ok(EmbeddingManager::Orchestrator(OrchestratorProvider::new(
+                    Some(url),
+                )?)),
+                Err(_) => Ok(EmbeddingManager::Local(LocalBertProvider::new())),
+            },
+        }
+    }
+
+    fn into_named(self) -> NamedProvider {
+        let name = self.provider_name();
+        let provider: Arc<dyn EmbeddingProvider> = match self {
+            EmbeddingManager::Local(provider) => Arc::new(provider),
+            EmbeddingManager::Orchestrator(provider) => Arc::new(provider),
+            EmbeddingManager::OpenAi(provider) => Arc::new(provider),
+            EmbeddingManager::Failover(provider) => Arc::new(provider),
+        };
+        (name, provider)
+    }
+
+    fn provider(&self) -> &dyn EmbeddingProvider {
+        match self {
+            EmbeddingManager::Local(provider) => provider,
+            EmbeddingManager::Orchestrator(provider) => provider,
+            EmbeddingManager::OpenAi(provider) => provider,
+            EmbeddingManager::Failover(provider) => provider,
         }
     }
 
+    /// Embeds `text`, rejecting anything but a finite vector of
+    /// [`Self::dimension`]. Text the serving provider has embedded recently
+    /// is answered from the embedding cache.
     pub async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let provider = self.provider();
+        embedding_cache()
+            .get_or_embed(self.provider_name(), text, || async {
+                let dimension = provider.dimension().await?;
+                embed_checked(provider, text, dimension, retry_malformed()).await
+            })
+            .await
+    }
+
+    pub async fn health_check(&self) -> Result<(), EmbeddingError> {
+        self.provider().health_check().await
+    }
+
+    /// Name recorded alongside stored embeddings.
+    pub fn model_name(&self) -> &str {
+        self.provider().model_name()
+    }
+
+    /// Length of the embeddings this manager returns. For the local
+    /// provider this loads the model.
+    pub async fn dimension(&self) -> Result<usize, EmbeddingError> {
+        self.provider().dimension().await
+    }
+
+    /// The provider currently serving embeddings.
+    pub fn provider_name(&self) -> &'static str {
         match self {
-            EmbeddingManager::Local(provider) => provider.embed(text).await,
-            EmbeddingManager::Orchestrator(provider) => provider.embed(text).await,
+            EmbeddingManager::Local(_) => "local",
+            EmbeddingManager::Orchestrator(_) => "orchestrator",
+            EmbeddingManager::OpenAi(_) => "openai",
+            EmbeddingManager::Failover(provider) => provider.active_provider(),
+        }
+    }
+
+    /// Starts the background health check for a failover manager. Returns
+    /// `None` for single-provider managers, which have nothing to supervise.
+    pub fn spawn_failover_supervisor(self: &Arc<Self>) -> Option<task::JoinHandle<()>> {
+        let EmbeddingManager::Failover(failover) = self.as_ref() else {
+            return None;
+        };
+        let interval = failover.check_interval;
+        let manager = Arc::clone(self);
+
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let EmbeddingManager::Failover(failover) = manager.as_ref() {
+                    failover.check().await;
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+
+    const TINY_CONFIG: &str = r#"{
+        "vocab_size": 4,
+        "hidden_size": 8,
+        "num_hidden_layers": 1,
+        "num_attention_heads": 2,
+        "intermediate_size": 16,
+        "hidden_act": "gelu",
+        "hidden_dropout_prob": 0.0,
+        "max_position_embeddings": 16,
+        "type_vocab_size": 2,
+        "initializer_range": 0.02,
+        "layer_norm_eps": 1e-12,
+        "pad_token_id": 0
+    }"#;
+
+    const TINY_TOKENIZER: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": { "[UNK]": 0, "fn": 1, "main": 2, "()": 3 },
+            "unk_token": "[UNK]"
+        }
+    }"#;
+
+    /// Writes a randomly initialised, 8-dimensional BERT into `dir`.
+    fn write_tiny_model(dir: &Path) {
+        std::fs::write(dir.join(MODEL_CONFIG_FILE), TINY_CONFIG).unwrap();
+        std::fs::write(dir.join(MODEL_TOKENIZER_FILE), TINY_TOKENIZER).unwrap();
+
+        let config: Config = serde_json::from_str(TINY_CONFIG).unwrap();
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DTYPE, &Device::Cpu);
+        BertModel::load(vb, &config).unwrap();
+        varmap.save(dir.join(MODEL_WEIGHTS_FILE)).unwrap();
+    }
+
+    #[test]
+    fn loads_model_from_local_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tiny_model(dir.path());
+
+        let files = ModelFiles::from_dir(dir.path(), None).unwrap();
+        assert_eq!(files.weights, dir.path().join(MODEL_WEIGHTS_FILE));
+
+        let model =
+            BertModelWrapper::from_files(&files, Device::Cpu, DEFAULT_CHUNK_TOKENS).unwrap();
+        let embedding = model.embed("fn main ()").unwrap();
+        assert_eq!(embedding.len(), 8);
+    }
+
+    #[test]
+    fn long_text_is_embedded_in_windows() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tiny_model(dir.path());
+        let files = ModelFiles::from_dir(dir.path(), None).unwrap();
+
+        // The tiny model has 16 positions, so this runs in 16-token windows.
+        let model =
+            BertModelWrapper::from_files(&files, Device::Cpu, DEFAULT_CHUNK_TOKENS).unwrap();
+        assert_eq!(model.chunk_tokens, 16);
+
+        let text = ["fn", "main", "()", "main", "fn"].repeat(1000).join(" ");
+        let embedding = model.embed(&text).unwrap();
+        assert_eq!(embedding.len(), 8);
+        assert!(embedding.iter().all(|v| v.is_finite()));
+        let norm: f32 = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+
+        // Small windows still pool to one vector, which is the same for a
+        // text made of whole repeats of one window.
+        let model = BertModelWrapper::from_files(&files, Device::Cpu, 4).unwrap();
+        let once = model.embed("fn main () main").unwrap();
+        let repeated = model.embed(&"fn main () main ".repeat(50)).unwrap();
+        for (a, b) in once.iter().zip(&repeated) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn parses_devices_and_falls_back_to_cpu() {
+        assert_eq!(
+            EmbeddingDevice::parse(" CPU ").unwrap(),
+            EmbeddingDevice::Cpu
+        );
+        assert_eq!(
+            EmbeddingDevice::parse("cuda").unwrap(),
+            EmbeddingDevice::Cuda(0)
+        );
+        assert_eq!(
+            EmbeddingDevice::parse("cuda:1").unwrap(),
+            EmbeddingDevice::Cuda(1)
+        );
+        assert_eq!(
+            EmbeddingDevice::parse("metal").unwrap(),
+            EmbeddingDevice::Metal
+        );
+        assert!(EmbeddingDevice::parse("cuda:x").is_err());
+        assert!(EmbeddingDevice::parse("tpu").is_err());
+        assert_eq!(EmbeddingDevice::Cuda(1).to_string(), "cuda:1");
+
+        // This build has neither CUDA nor Metal support.
+        assert!(EmbeddingDevice::Cuda(0).select().is_cpu());
+        assert!(EmbeddingDevice::Metal.select().is_cpu());
+    }
+
+    static LOADS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    static TINY_MODEL_DIR: OnceCell<tempfile::TempDir> = OnceCell::new();
+
+    fn counting_loader(options: &LocalModelOptions) -> Result<BertModelWrapper, EmbeddingError> {
+        LOADS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(50));
+        tiny_loader(options)
+    }
+
+    fn tiny_loader(options: &LocalModelOptions) -> Result<BertModelWrapper, EmbeddingError> {
+        let dir = TINY_MODEL_DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            write_tiny_model(dir.path());
+            dir
+        });
+        BertModelWrapper::from_files(
+            &ModelFiles::from_dir(dir.path(), None)?,
+            options.device.select(),
+            options.chunk_tokens,
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_first_use_loads_model_once() {
+        let provider = Arc::new(LocalBertProvider::with_loader(
+            counting_loader,
+            LocalModelOptions::default(),
+        ));
+        assert!(!provider.is_loaded());
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let provider = provider.clone();
+                tokio::spawn(async move { provider.embed("fn main").await })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().len(), 8);
+        }
+
+        assert!(provider.is_loaded());
+        assert_eq!(LOADS.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn pooled_instances_embed_in_parallel() {
+        let provider = Arc::new(LocalBertProvider::with_loader(
+            tiny_loader,
+            LocalModelOptions {
+                pool_size: 2,
+                ..LocalModelOptions::default()
+            },
+        ));
+        assert_eq!(provider.pool_size(), 2);
+
+        // Each call holds its instance until it has seen the other one inside
+        // too, which can only happen if they do not share a lock.
+        let inside = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let provider = provider.clone();
+                let inside = inside.clone();
+                tokio::spawn(async move {
+                    provider
+                        .with_model(move |model| {
+                            inside.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+                            while inside.load(std::sync::atomic::Ordering::SeqCst) < 2
+                                && std::time::Instant::now() < deadline
+                            {
+                                std::thread::yield_now();
+                            }
+                            let overlapped = inside.load(std::sync::atomic::Ordering::SeqCst) == 2;
+                            model.embed("fn main").map(|_| overlapped)
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().unwrap(), "embeds were serialized");
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_the_configured_model_and_its_dimension() {
+        let provider = LocalBertProvider::with_loader(
+            tiny_loader,
+            LocalModelOptions {
+                model: "BAAI/bge-small-en-v1.5".to_string(),
+                ..LocalModelOptions::default()
+            },
+        );
+        assert_eq!(provider.model_name(), "bge-small-en-v1.5");
+        assert!(!provider.is_loaded());
+
+        // The tiny model's hidden_size, not DEFAULT_EMBEDDING_DIM.
+        assert_eq!(provider.dimension().await.unwrap(), 8);
+        assert!(provider.is_loaded());
+        assert_eq!(
+            OrchestratorProvider::new(None).unwrap().model_name(),
+            "all-MiniLM-L6-v2"
+        );
+    }
+
+    #[test]
+    fn pool_size_is_capped_by_available_memory() {
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(bounded_pool_size(4, None), 4);
+        assert_eq!(bounded_pool_size(4, Some(16 * gib)), 4);
+        assert_eq!(bounded_pool_size(16, Some(gib)), 2);
+        assert_eq!(bounded_pool_size(3, Some(0)), 1);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn explicit_orchestrator_provider_never_builds_local_model() {
+        std::env::remove_var("ORCHESTRATOR_URL");
+        std::env::set_var("EMBEDDING_PROVIDER", "orchestrator");
+        let manager = EmbeddingManager::new(None);
+        std::env::remove_var("EMBEDDING_PROVIDER");
+
+        let manager = manager.unwrap();
+        assert!(matches!(manager, EmbeddingManager::Orchestrator(_)));
+        assert_eq!(manager.provider_name(), "orchestrator");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn local_provider_defers_model_load() {
+        std::env::remove_var("ORCHESTRATOR_URL");
+        std::env::set_var("EMBEDDING_PROVIDER", "local");
+        let manager = EmbeddingManager::new(None);
+        std::env::remove_var("EMBEDDING_PROVIDER");
+
+        match manager.unwrap() {
+            EmbeddingManager::Local(provider) => assert!(!provider.is_loaded()),
+            _ => panic!("expected the local provider"),
+        }
+    }
+
+    /// Answers with each queued embedding in turn.
+    struct ScriptedProvider {
+        answers: Mutex<Vec<Vec<f32>>>,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ScriptedProvider {
+        fn new(answers: Vec<Vec<f32>>) -> Self {
+            Self {
+                answers: Mutex::new(answers),
+                calls: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for ScriptedProvider {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.answers.lock().unwrap().remove(0))
+        }
+
+        async fn health_check(&self) -> Result<(), EmbeddingError> {
+            Ok(())
         }
     }
+
+    #[tokio::test]
+    async fn empty_embedding_is_retried_once() {
+        let provider = ScriptedProvider::new(vec![vec![], vec![0.5; 4]]);
+        let embedding = embed_checked(&provider, "fn main", 4, true).await.unwrap();
+        assert_eq!(embedding, vec![0.5; 4]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+        let provider = ScriptedProvider::new(vec![vec![], vec![], vec![0.5; 4]]);
+        let err = embed_checked(&provider, "fn main", 4, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EmbeddingError::Malformed(_)), "{err}");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+        let provider = ScriptedProvider::new(vec![vec![0.5; 3], vec![0.5; 4]]);
+        assert!(embed_checked(&provider, "fn main", 4, false).await.is_err());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn missing_local_file_names_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tiny_model(dir.path());
+        std::fs::remove_file(dir.path().join(MODEL_TOKENIZER_FILE)).unwrap();
+
+        let err = ModelFiles::from_dir(dir.path(), None).unwrap_err();
+        assert!(matches!(err, EmbeddingError::ModelLoad(_)));
+        assert!(err.to_string().contains(MODEL_TOKENIZER_FILE), "{err}");
+    }
+
+    #[test]
+    fn explicit_tokenizer_replaces_the_bundled_one() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tiny_model(dir.path());
+        let tokenizer = dir.path().join("custom-tokenizer.json");
+        std::fs::rename(dir.path().join(MODEL_TOKENIZER_FILE), &tokenizer).unwrap();
+
+        let files = ModelFiles::from_dir(dir.path(), Some(tokenizer.clone())).unwrap();
+        assert_eq!(files.tokenizer, tokenizer);
+        assert!(BertModelWrapper::from_files(&files, Device::Cpu, DEFAULT_CHUNK_TOKENS).is_ok());
+    }
+
+    #[test]
+    fn mismatched_tokenizer_fails_to_load() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tiny_model(dir.path());
+        let tokenizer = dir.path().join("other-tokenizer.json");
+        std::fs::write(
+            &tokenizer,
+            TINY_TOKENIZER.replace(r#""()": 3"#, r#""()": 3, "let": 4"#),
+        )
+        .unwrap();
+
+        let files = ModelFiles::from_dir(dir.path(), Some(tokenizer)).unwrap();
+        let err = BertModelWrapper::from_files(&files, Device::Cpu, DEFAULT_CHUNK_TOKENS)
+            .err()
+            .unwrap();
+        assert!(matches!(err, EmbeddingError::ModelLoad(_)));
+        let message = err.to_string();
+        assert!(message.contains("vocabulary of 5 tokens"), "{message}");
+        assert!(message.contains("vocab_size 4"), "{message}");
+    }
 }
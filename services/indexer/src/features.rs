@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::sync::Arc;
+
+use tracing::warn;
+
+use crate::storage::IndexStorage;
+use crate::temporal::{TemporalConfig, TemporalError, TemporalIndex};
+
+/// Optional subsystems the server wires up at startup.
+///
+/// `INDEXER_FEATURES` is an allowlist: unset enables everything, otherwise
+/// only the listed features are built. An empty value leaves pure vector
+/// search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    /// Git history, commit-scoped reads and CI failure correlation. When off
+    /// the repository is never opened.
+    pub temporal: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self { temporal: true }
+    }
+}
+
+impl Features {
+    pub const NAMES: &'static [&'static str] = &["temporal"];
+
+    pub fn none() -> Self {
+        Self { temporal: false }
+    }
+
+    /// Parses a comma-separated allowlist. Unknown names are logged and
+    /// ignored, so a typo can only leave a feature off.
+    pub fn parse(list: &str) -> Self {
+        let mut features = Self::none();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match name.to_ascii_lowercase().as_str() {
+                "temporal" => features.temporal = true,
+                _ => warn!(
+                    feature = name,
+                    known = ?Self::NAMES,
+                    "ignoring unknown INDEXER_FEATURES entry"
+                ),
+            }
+        }
+        features
+    }
+
+    pub fn from_env() -> Self {
+        match env::var("INDEXER_FEATURES") {
+            Ok(list) => Self::parse(&list),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Builds the temporal index when the feature is on. With it off this returns
+/// `None` without touching `config.repo_path`, so a missing or untrusted
+/// repository cannot fail startup.
+pub fn temporal_index(
+    features: &Features,
+    config: TemporalConfig,
+    storage: Arc<dyn IndexStorage>,
+) -> Result<Option<TemporalIndex>, TemporalError> {
+    if !features.temporal {
+        return Ok(None);
+    }
+    TemporalIndex::new(config, storage).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowlist_enables_only_listed_features() {
+        assert!(Features::parse("temporal").temporal);
+        assert!(Features::parse(" Temporal , ").temporal);
+        assert!(!Features::parse("").temporal);
+        assert!(!Features::parse("temproal").temporal);
+        assert!(Features::default().temporal);
+    }
+}
@@ -1,344 +1,626 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeSet, HashSet};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
+use prost::Message;
 use serde_json::json;
-use tonic::{Request, Response, Status};
-use tracing::{info, instrument};
-
-use crate::analysis;
+use tokio::sync::mpsc;
+use tonic::metadata::MetadataValue;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, instrument, warn};
+use tree_sitter::Tree;
+
+use crate::analysis::{self, GraphLimits};
+use crate::archive;
 use crate::ast;
-use crate::audit;
+use crate::audit::{self, Outcome};
+use crate::clock::{self, SharedClock};
+use crate::embeddings::{
+    recorded_model_name, EmbeddingError, EmbeddingManager, DEFAULT_EMBEDDING_DIM,
+    DEFAULT_EMBEDDING_MODEL,
+};
+use crate::idempotency::{self, IdempotencyCache, Lookup};
+use crate::page_token::{PageTokenSigner, MAX_PAGE_DEPTH};
+use crate::request_context::{current_request_context, scope_request_context};
 use crate::security::SecurityConfig;
-use crate::storage::{IndexStorage, StorageError};
-use crate::temporal::TemporalIndex;
-use crate::validation;
+use crate::similarity::{ScoreRange, SearchMode};
+use crate::storage::{
+    CommitFilter, DedupBy, IndexStorage, MetadataFilter, NewDocument, PathDeletion, StorageError,
+    StoredDocument, StoredSymbol, SymbolMetric,
+};
+use crate::symbol_extractor::LineRange;
+use crate::symbol_metadata::SymbolMetadata;
+use crate::symbol_registry::{SymbolKind, SymbolRegistry};
+use crate::temporal::{ChangeType, TemporalError, TemporalIndex};
+use crate::tree_cache::{TreeCache, TreeCacheStats, DEFAULT_TREE_CACHE_ENTRIES};
+use crate::validation::{self, validate_content, ControlCharPolicy};
 
 pub mod proto {
     tonic::include_proto!("indexer");
+
+    /// Encoded descriptors of `indexer.proto`, served by gRPC reflection.
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("indexer_descriptor");
 }
 
 use proto::{
-    indexer_service_server::IndexerService, CorrelateFailureRequest, CorrelateFailureResponse,
-    GetDefinitionsRequest, GetDefinitionsResponse, GetReferencesRequest, GetReferencesResponse,
-    GetSymbolAtCommitRequest, GetSymbolAtCommitResponse, GetSymbolGraphRequest,
-    GetSymbolGraphResponse, GetSymbolHistoryRequest, GetSymbolHistoryResponse, GraphEdge,
-    GraphNode, IndexDocumentRequest, IndexDocumentResponse, IndexSymbolsRequest,
-    IndexSymbolsResponse, Location, Position, Range, SearchDocumentsRequest,
+    indexer_service_server::IndexerService, BatchGetDefinitionsRequest,
+    BatchGetDefinitionsResponse, BatchIndexDocumentsRequest, CallHierarchyItem, ComponentHealth,
+    CorrelateFailureRequest, CorrelateFailureResponse, DeleteByCommitRequest,
+    DeleteByCommitResponse, DeleteSymbolsRequest, DeleteSymbolsResponse, EmbedRequest,
+    EmbedResponse, FindSymbolsByNameRequest, FindSymbolsByNameResponse, GetBlameRequest,
+    GetBlameResponse, GetCallHierarchyRequest, GetCallHierarchyResponse, GetCapabilitiesRequest,
+    GetCapabilitiesResponse, GetCoChangesRequest, GetCoChangesResponse, GetDefinitionsRequest,
+    GetDefinitionsResponse, GetHealthRequest, GetHealthResponse, GetReferencesRequest,
+    GetReferencesResponse, GetSymbolAtCommitRequest, GetSymbolAtCommitResponse,
+    GetSymbolByIdRequest, GetSymbolByIdResponse, GetSymbolGraphRequest, GetSymbolGraphResponse,
+    GetSymbolHistoryRequest, GetSymbolHistoryResponse, GetTopSymbolsRequest, GetTopSymbolsResponse,
+    GraphEdge, GraphNode, IndexDocumentRequest, IndexDocumentResponse, IndexDocumentsBatchResponse,
+    IndexDocumentsBatchResult, IndexSymbolsRequest, IndexSymbolsResponse, Location, Position,
+    PositionDefinitions, Range, RankedSymbol, RepairIndexRequest, RepairIndexResponse,
+    ScoredSymbol, SearchByVectorRequest, SearchByVectorResponse, SearchDocumentsRequest,
     SearchDocumentsResponse, SearchResult, SearchSymbolsRequest, SearchSymbolsResponse,
-    SuspectChange, Symbol, SymbolVersion,
+    SearchWithinSymbolRequest, SearchWithinSymbolResponse, SuspectChange, Symbol, SymbolVersion,
+    TouchDocumentRequest, TouchDocumentResponse, Warning,
 };
 
+/// Default ceiling for a single encoded gRPC response (4 MiB, matching tonic's
+/// default decode limit on the client side).
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// `Warning.code` values; see the proto for what each means.
+pub const WARNING_PARSE_ERRORS: &str = "PARSE_ERRORS";
+pub const WARNING_LANGUAGE_MISMATCH: &str = "LANGUAGE_MISMATCH";
+pub const WARNING_KEYWORD_FALLBACK: &str = "KEYWORD_FALLBACK";
+
+fn warning(code: &str, message: impl Into<String>) -> Warning {
+    Warning {
+        code: code.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Default number of documents from one `IndexDocumentsBatch` stream that may
+/// be validated and embedded at the same time.
+pub const DEFAULT_BATCH_MAX_IN_FLIGHT: usize = 8;
+
+/// Most files, besides the requested one, a workspace `GetReferences` or an
+/// incoming `GetCallHierarchy` parses.
+pub const MAX_WORKSPACE_REFERENCE_FILES: usize = 200;
+
+/// Most related files a `GetCoChanges` response lists.
+pub const MAX_CO_CHANGES: usize = 50;
+
+/// Longest `FindSymbolsByName` query accepted.
+pub const MAX_NAME_QUERY_LENGTH: usize = 256;
+
+/// Most results a unary search returns.
+pub const MAX_TOP_K: usize = 100;
+
+/// Most results a streaming search yields, matching the deepest page a page
+/// token reaches.
+pub const MAX_STREAM_RESULTS: usize = MAX_PAGE_DEPTH;
+
+/// Results a streaming search fetches per storage query, and builds ahead of
+/// a slow client.
+const STREAM_BUFFER: usize = 25;
+
+/// Binary metadata key carrying each [`Warning`] of a streaming search,
+/// protobuf-encoded, in the response headers.
+pub const WARNING_METADATA_KEY: &str = "indexer-warning-bin";
+
+/// Rows per storage page `GetTopSymbols` reads for each requested result;
+/// symbols under denied paths are dropped from every page, and paging stops
+/// once `limit` symbols remain or storage runs out.
+const TOP_SYMBOLS_OVERFETCH_FACTOR: usize = 4;
+const MAX_TOP_SYMBOLS_PAGE: usize = 400;
+
+/// How much `FindSymbolsByName` widens its storage query each time denied
+/// paths leave fewer than `limit` matches, up to [`MAX_NAME_MATCH_FETCH`].
+const NAME_MATCH_WIDEN_FACTOR: usize = 4;
+const MAX_NAME_MATCH_FETCH: usize = 400;
+
+/// Characters of a result's content looked at when building its snippet.
+const SNIPPET_SCAN_CHARS: usize = 4 * 1024;
+
+/// Tunables for the gRPC handlers that are not security policy.
+#[derive(Clone, Debug)]
+pub struct ServiceConfig {
+    /// Responses whose encoded size exceeds this are rejected with `out_of_range`
+    /// before serialization. The tonic encode limit is set to the same value.
+    pub max_response_bytes: usize,
+    /// How long results of writes carrying an `idempotency_key` are remembered.
+    /// Zero disables replay.
+    pub idempotency_ttl: Duration,
+    /// Whether searches with no matches fail with `not_found` rather than
+    /// returning an empty list, when the request does not say.
+    pub not_found_on_empty: bool,
+    /// Whether a `path_prefix` without a trailing `/` is read as a directory,
+    /// when the request does not say.
+    pub path_prefix_directory_boundary: bool,
+    /// Whether vector and hybrid searches whose query cannot be embedded are
+    /// answered by keyword search rather than failing.
+    pub keyword_fallback: bool,
+    /// What `IndexSymbols` does when `language` disagrees with the path's extension.
+    pub language_mismatch: LanguageMismatchPolicy,
+    /// Parsed trees kept for navigation requests; 0 disables the cache.
+    pub tree_cache_entries: usize,
+    /// Documents from one `IndexDocumentsBatch` stream indexed concurrently.
+    /// The next message is not read until one of them finishes.
+    pub batch_max_in_flight: usize,
+    /// What indexing does with control characters other than tab, newline,
+    /// carriage return and form feed. Null bytes are always rejected.
+    pub control_chars: ControlCharPolicy,
+    /// Bounds on the call search behind `GetSymbolGraph`.
+    pub graph_limits: GraphLimits,
+    /// Signs the `page_token`s handed out by paginated searches.
+    pub page_tokens: PageTokenSigner,
+    /// Largest working-tree file navigation requests read; larger ones fail
+    /// with `out_of_range`.
+    pub max_file_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LanguageMismatchPolicy {
+    /// Index anyway and record the mismatch in the audit log.
+    #[default]
+    Warn,
+    /// Fail the request with `invalid_argument`.
+    Reject,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            idempotency_ttl: idempotency::DEFAULT_IDEMPOTENCY_TTL,
+            not_found_on_empty: false,
+            path_prefix_directory_boundary: false,
+            keyword_fallback: false,
+            language_mismatch: LanguageMismatchPolicy::Warn,
+            tree_cache_entries: DEFAULT_TREE_CACHE_ENTRIES,
+            batch_max_in_flight: DEFAULT_BATCH_MAX_IN_FLIGHT,
+            control_chars: ControlCharPolicy::Allow,
+            graph_limits: GraphLimits::default(),
+            page_tokens: PageTokenSigner::default(),
+            max_file_bytes: archive::DEFAULT_MAX_FILE_BYTES,
+        }
+    }
+}
+
+impl ServiceConfig {
+    pub fn from_env() -> Self {
+        let max_response_bytes = std::env::var("INDEXER_MAX_RESPONSE_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &usize| *v > 0)
+            .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+
+        let idempotency_ttl = std::env::var("INDEXER_IDEMPOTENCY_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(idempotency::DEFAULT_IDEMPOTENCY_TTL);
+
+        let not_found_on_empty = std::env::var("INDEXER_NOT_FOUND_ON_EMPTY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false);
+
+        let path_prefix_directory_boundary =
+            std::env::var("INDEXER_PATH_PREFIX_DIRECTORY_BOUNDARY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false);
+
+        let keyword_fallback = std::env::var("INDEXER_KEYWORD_FALLBACK")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false);
+
+        let language_mismatch = match std::env::var("INDEXER_LANGUAGE_MISMATCH") {
+            Ok(v) if v.eq_ignore_ascii_case("reject") => LanguageMismatchPolicy::Reject,
+            _ => LanguageMismatchPolicy::Warn,
+        };
+
+        let tree_cache_entries = std::env::var("INDEXER_TREE_CACHE_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TREE_CACHE_ENTRIES);
+
+        let batch_max_in_flight = std::env::var("INDEXER_BATCH_MAX_IN_FLIGHT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &usize| *v > 0)
+            .unwrap_or(DEFAULT_BATCH_MAX_IN_FLIGHT);
+
+        let control_chars = ControlCharPolicy::from_env();
+
+        let graph_limit = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(default)
+        };
+        let graph_limits = GraphLimits {
+            max_visits_per_declaration: graph_limit(
+                "INDEXER_GRAPH_MAX_VISITS",
+                analysis::DEFAULT_GRAPH_MAX_VISITS_PER_DECLARATION,
+            ),
+            max_edges: graph_limit("INDEXER_GRAPH_MAX_EDGES", analysis::DEFAULT_GRAPH_MAX_EDGES),
+        };
+
+        Self {
+            max_response_bytes,
+            idempotency_ttl,
+            not_found_on_empty,
+            path_prefix_directory_boundary,
+            keyword_fallback,
+            language_mismatch,
+            tree_cache_entries,
+            batch_max_in_flight,
+            control_chars,
+            graph_limits,
+            page_tokens: PageTokenSigner::from_env(),
+            max_file_bytes: std::env::var("INDEXER_MAX_CONTENT_LENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(archive::DEFAULT_MAX_FILE_BYTES),
+        }
+    }
+}
+
 pub struct IndexerServiceImpl {
     storage: Arc<dyn IndexStorage>,
-    temporal: Arc<TemporalIndex>,
+    /// `None` when temporal features are disabled; temporal RPCs then return
+    /// `unimplemented` and no git operation is reachable.
+    temporal: Option<Arc<TemporalIndex>>,
     security_config: SecurityConfig,
+    config: ServiceConfig,
+    document_writes: IdempotencyCache<IndexDocumentResponse>,
+    symbol_writes: IdempotencyCache<IndexSymbolsResponse>,
+    embedding_manager: Option<Arc<EmbeddingManager>>,
+    /// In-memory symbol mappings kept in step with deletes, when one is in use.
+    symbol_registry: Option<Arc<SymbolRegistry>>,
+    trees: TreeCache,
+    clock: SharedClock,
 }
 
 impl IndexerServiceImpl {
     pub fn new(storage: Arc<dyn IndexStorage>, temporal: Arc<TemporalIndex>) -> Self {
+        Self::from_storage(storage, Some(temporal))
+    }
+
+    /// Builds a service with temporal features off.
+    pub fn without_temporal(storage: Arc<dyn IndexStorage>) -> Self {
+        Self::from_storage(storage, None)
+    }
+
+    pub fn from_storage(
+        storage: Arc<dyn IndexStorage>,
+        temporal: Option<Arc<TemporalIndex>>,
+    ) -> Self {
+        let config = ServiceConfig::from_env();
         Self {
             storage,
             temporal,
             security_config: SecurityConfig::from_env(),
+            document_writes: idempotency_cache(&config),
+            symbol_writes: idempotency_cache(&config),
+            trees: TreeCache::new(config.tree_cache_entries),
+            config,
+            embedding_manager: None,
+            symbol_registry: None,
+            clock: clock::system_clock(),
         }
     }
 
-    async fn get_file_content(
-        &self,
-        path: &str,
-        commit_id: Option<&str>,
-    ) -> Result<String, Status> {
-        // If commit_id is provided, use temporal index
-        if let Some(commit) = commit_id {
-            let symbol = self
-                .temporal
-                .get_symbol_at_commit(path, commit)
-                .await
-                .map_err(|e| Status::internal(e.to_string()))?;
-
-            return match symbol {
-                Some(s) => Ok(s.content),
-                None => Err(Status::not_found(format!(
-                    "File not found at commit {}",
-                    commit
-                ))),
-            };
-        }
+    /// Enables embedding provider checks in `GetHealth`.
+    pub fn with_embedding_manager(mut self, embedding_manager: Arc<EmbeddingManager>) -> Self {
+        self.embedding_manager = Some(embedding_manager);
+        self
+    }
 
-        // Check ACL before returning any content
-        if let Err(e) = self.security_config.check_path(path) {
-            return Err(Status::permission_denied(e.to_string()));
-        }
+    /// Registry whose entries `DeleteSymbols` drops along with the stored rows.
+    pub fn with_symbol_registry(mut self, symbol_registry: Arc<SymbolRegistry>) -> Self {
+        self.symbol_registry = Some(symbol_registry);
+        self
+    }
 
-        Err(Status::unimplemented(
-            "Must provide commit_id for code navigation currently",
-        ))
+    /// Replace the time source page tokens are issued and checked against
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
-}
 
-fn validate_path(path: &str) -> Result<(), String> {
-    if path.trim().is_empty() {
-        return Err("path cannot be blank".to_string());
+    pub fn with_security_config(mut self, security_config: SecurityConfig) -> Self {
+        self.security_config = security_config;
+        self
     }
-    if path.len() > validation::MAX_PATH_LENGTH {
-        return Err(format!(
-            "path exceeds maximum length of {} characters",
-            validation::MAX_PATH_LENGTH
-        ));
+
+    pub fn with_config(mut self, config: ServiceConfig) -> Self {
+        self.document_writes = idempotency_cache(&config);
+        self.symbol_writes = idempotency_cache(&config);
+        self.trees = TreeCache::new(config.tree_cache_entries);
+        self.config = config;
+        self
     }
-    if path.contains(['\0', '\r', '\n']) {
-        return Err("path contains invalid control characters".to_string());
+
+    pub fn config(&self) -> &ServiceConfig {
+        &self.config
     }
-    Ok(())
-}
 
-fn validate_content(content: &str) -> Result<(), String> {
-    if content.trim().is_empty() {
-        return Err("content cannot be blank".to_string());
+    /// Hit and miss counts for the parsed-tree cache behind code navigation.
+    pub fn tree_cache_stats(&self) -> TreeCacheStats {
+        self.trees.stats()
     }
-    Ok(())
-}
 
-fn validate_commit_id(commit_id: Option<&String>) -> Result<(), String> {
-    if let Some(commit) = commit_id {
-        if !commit.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err("commit id must be hexadecimal".to_string());
+    /// Name of the embedding model; the default one without a manager.
+    fn embedding_model_name(&self) -> String {
+        match &self.embedding_manager {
+            Some(manager) => manager.model_name().to_string(),
+            None => recorded_model_name(DEFAULT_EMBEDDING_MODEL).to_string(),
         }
     }
-    Ok(())
-}
 
-fn validate_query(query: &str) -> Result<(), String> {
-    if query.trim().is_empty() {
-        return Err("query cannot be blank".to_string());
-    }
-    if query.len() > validation::MAX_QUERY_LENGTH {
-        return Err(format!(
-            "query exceeds maximum length of {} characters",
-            validation::MAX_QUERY_LENGTH
-        ));
+    /// Length of stored embeddings. Storage checked the model against its
+    /// columns at startup, so this is also what a query embedding must have.
+    async fn embedding_dimension(&self) -> usize {
+        match &self.embedding_manager {
+            Some(manager) => manager.dimension().await.unwrap_or(DEFAULT_EMBEDDING_DIM),
+            None => DEFAULT_EMBEDDING_DIM,
+        }
     }
-    Ok(())
-}
 
-#[tonic::async_trait]
-impl IndexerService for IndexerServiceImpl {
-    #[instrument(skip(self, request))]
-    async fn index_document(
+    /// `prefix` as storage matches it. With directory boundaries on, per
+    /// request or by default, `src` means the directory `src/` and no longer
+    /// matches `srcutils/x.rs`.
+    /// The warning to return when a search that failed to embed its query
+    /// should be retried in keyword mode, which needs no embedding. Logs the
+    /// fallback; the success audit record then carries the mode actually used.
+    fn keyword_fallback<T>(
         &self,
-        request: Request<IndexDocumentRequest>,
-    ) -> Result<Response<IndexDocumentResponse>, Status> {
-        let req = request.into_inner();
+        result: &Result<T, StorageError>,
+        mode: SearchMode,
+        operation: &str,
+    ) -> Option<Warning> {
+        let Err(StorageError::Embedding(e)) = result else {
+            return None;
+        };
+        if !self.config.keyword_fallback || mode == SearchMode::Keyword {
+            return None;
+        }
+        warn!(error = %e, operation, "query embedding failed; falling back to keyword search");
+        Some(warning(
+            WARNING_KEYWORD_FALLBACK,
+            format!(
+                "the query could not be embedded ({}); results are from keyword search",
+                e
+            ),
+        ))
+    }
 
-        audit::log_audit(
-            "index_document",
-            "attempt",
-            Some(&req.path),
-            Some(json!({ "commit_id": req.commit_id })),
-        );
+    fn path_prefix(
+        &self,
+        prefix: Option<String>,
+        directory_boundary: Option<bool>,
+    ) -> Option<String> {
+        let directory_boundary =
+            directory_boundary.unwrap_or(self.config.path_prefix_directory_boundary);
+        prefix.map(|prefix| {
+            if directory_boundary && !prefix.is_empty() && !prefix.ends_with('/') {
+                format!("{}/", prefix)
+            } else {
+                prefix
+            }
+        })
+    }
 
+    /// Why a document write fails its validation, ACL or DLP checks, if it
+    /// does. Failures are audited under `index_document`, whichever RPC
+    /// carried the document.
+    fn document_rejection(
+        &self,
+        path: &str,
+        content: &str,
+        commit_id: Option<&String>,
+    ) -> Option<Status> {
         // Input validation
-        if let Err(e) = validate_path(&req.path) {
+        if let Err(e) = validate_path(path) {
             audit::log_audit(
                 "index_document",
-                "failure",
-                Some(&req.path),
+                Outcome::Failure,
+                Some(path),
                 Some(json!({ "error": e })),
             );
-            return Err(Status::invalid_argument(e));
+            return Some(Status::invalid_argument(e));
         }
-        if let Err(e) = validate_content(&req.content) {
+        if let Err(e) = validate_content(content, self.config.control_chars) {
             audit::log_audit(
                 "index_document",
-                "failure",
-                Some(&req.path),
+                Outcome::Failure,
+                Some(path),
                 Some(json!({ "error": e })),
             );
-            return Err(Status::invalid_argument(e));
+            return Some(Status::invalid_argument(e));
         }
-        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+        if let Err(e) = validate_commit_id(commit_id) {
             audit::log_audit(
                 "index_document",
-                "failure",
-                Some(&req.path),
+                Outcome::Failure,
+                Some(path),
                 Some(json!({ "error": e })),
             );
-            return Err(Status::invalid_argument(e));
+            return Some(Status::invalid_argument(e));
         }
 
         // Security checks
-        if let Err(e) = self.security_config.check_path(&req.path) {
+        if let Err(e) = self.security_config.check_path(path) {
             audit::log_audit(
                 "index_document",
-                "denied",
-                Some(&req.path),
-                Some(json!({ "error": e.to_string() })),
+                Outcome::Denied,
+                Some(path),
+                Some(e.audit_details()),
             );
-            return Err(Status::permission_denied(e.to_string()));
+            return Some(Status::permission_denied(e.to_string()));
         }
 
-        if let Err(e) = self.security_config.scan_content(&req.content) {
+        if let Err(e) = self.security_config.scan_content(content) {
             audit::log_audit(
                 "index_document",
-                "denied",
-                Some(&req.path),
-                Some(json!({ "error": e.to_string() })),
+                Outcome::Denied,
+                Some(path),
+                Some(e.audit_details()),
             );
-            return Err(Status::permission_denied(e.to_string()));
+            return Some(Status::permission_denied(e.to_string()));
         }
 
-        let document_id = self
-            .storage
-            .index_document(req.path.clone(), req.content, req.commit_id)
-            .await
-            .map_err(|e: StorageError| {
-                audit::log_audit(
-                    "index_document",
-                    "failure",
-                    Some(&req.path),
-                    Some(json!({ "error": e.to_string() })),
-                );
-                Status::from(e)
-            })?;
-
-        audit::log_audit(
-            "index_document",
-            "success",
-            Some(&req.path),
-            Some(json!({ "document_id": document_id.to_string() })),
-        );
-        info!(path = %req.path, "Document indexed successfully");
+        None
+    }
 
-        Ok(Response::new(IndexDocumentResponse {
-            document_id: document_id.to_string(),
-            embedding_dim: crate::embeddings::EMBEDDING_DIM as i32,
-        }))
+    /// Resolves the per-request `not_found_on_empty` override against the service default.
+    fn not_found_on_empty(&self, requested: Option<bool>) -> bool {
+        requested.unwrap_or(self.config.not_found_on_empty)
     }
 
-    #[instrument(skip(self, request))]
-    async fn index_symbols(
+    /// Turns a search's `commit_id`/`commit_ancestry` pair into a storage filter,
+    /// resolving the ancestor set through the temporal index when asked.
+    async fn commit_filter(
         &self,
-        request: Request<IndexSymbolsRequest>,
-    ) -> Result<Response<IndexSymbolsResponse>, Status> {
-        let req = request.into_inner();
-
-        audit::log_audit(
-            "index_symbols",
-            "attempt",
-            Some(&req.path),
-            Some(json!({ "commit_id": req.commit_id, "language": req.language })),
-        );
-
-        // Input validation
-        if let Err(e) = validate_path(&req.path) {
-            audit::log_audit(
-                "index_symbols",
-                "failure",
-                Some(&req.path),
-                Some(json!({ "error": e })),
-            );
-            return Err(Status::invalid_argument(e));
-        }
-        if let Err(e) = validate_content(&req.content) {
-            audit::log_audit(
-                "index_symbols",
-                "failure",
-                Some(&req.path),
-                Some(json!({ "error": e })),
-            );
-            return Err(Status::invalid_argument(e));
-        }
-        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
-            audit::log_audit(
-                "index_symbols",
-                "failure",
-                Some(&req.path),
-                Some(json!({ "error": e })),
-            );
-            return Err(Status::invalid_argument(e));
+        commit_id: Option<String>,
+        ancestry: bool,
+    ) -> Result<Option<CommitFilter>, Status> {
+        match commit_id {
+            None if ancestry => Err(Status::invalid_argument(
+                "commit_ancestry requires commit_id",
+            )),
+            None => Ok(None),
+            Some(commit) if ancestry => {
+                let ancestors = self
+                    .temporal
+                    .as_deref()
+                    .ok_or_else(temporal_disabled)?
+                    .ancestor_commits(&commit)
+                    .await
+                    .map_err(temporal_status)?;
+                Ok(Some(CommitFilter::AnyOf(ancestors)))
+            }
+            Some(commit) => Ok(Some(CommitFilter::Exact(commit))),
         }
+    }
 
-        if req.language.trim().is_empty() {
-            let e = Status::invalid_argument("language cannot be blank");
-            audit::log_audit(
-                "index_symbols",
-                "failure",
-                Some(&req.path),
-                Some(json!({ "error": e.to_string() })),
-            );
-            return Err(e);
-        }
+    /// References matching `matcher` in indexed files other than `origin`,
+    /// read at `commit`. Candidates are the paths of stored symbols whose text
+    /// could contain a match, so usages outside any extracted symbol in a file
+    /// with no such symbol are not found. Files outside the ACL or in an
+    /// unsupported language are never read; files missing at the commit or
+    /// failing to parse are skipped with a warning.
+    async fn find_references_workspace(
+        &self,
+        origin: &str,
+        commit: &str,
+        matcher: &analysis::IdentifierMatcher,
+    ) -> Result<Vec<Location>, Status> {
+        let (files, _) = self.workspace_files(origin, commit, matcher).await?;
+        Ok(files
+            .into_iter()
+            .flat_map(|(path, content, tree)| {
+                analysis::find_references(&tree, &content, matcher)
+                    .into_iter()
+                    .map(move |range| to_location(&path, &range))
+            })
+            .collect())
+    }
 
-        // Security checks
-        if let Err(e) = self.security_config.check_path(&req.path) {
-            audit::log_audit(
-                "index_symbols",
-                "denied",
-                Some(&req.path),
-                Some(json!({ "error": e.to_string() })),
-            );
-            return Err(Status::permission_denied(e.to_string()));
-        }
+    /// Parsed candidate files for a workspace search: indexed files other than
+    /// `origin` that could mention `matcher`, read at `commit`, at most
+    /// [`MAX_WORKSPACE_REFERENCE_FILES`] of them. The flag reports candidates
+    /// left out by that cap.
+    async fn workspace_files(
+        &self,
+        origin: &str,
+        commit: &str,
+        matcher: &analysis::IdentifierMatcher,
+    ) -> Result<(Vec<(String, String, Tree)>, bool), Status> {
+        let temporal = self.temporal.as_deref().ok_or_else(temporal_disabled)?;
+        let symbols = self
+            .storage
+            .query_all_symbols()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
 
-        if let Err(e) = self.security_config.scan_content(&req.content) {
-            audit::log_audit(
-                "index_symbols",
-                "denied",
-                Some(&req.path),
-                Some(json!({ "error": e.to_string() })),
+        let paths: BTreeSet<String> = symbols
+            .into_iter()
+            .filter(|symbol| symbol.path != origin && matcher.may_occur_in(&symbol.content))
+            .map(|symbol| symbol.path)
+            .filter(|path| self.security_config.check_path(path).is_ok())
+            .filter(|path| ast::language_for_path(path).is_some())
+            .collect();
+        let truncated = paths.len() > MAX_WORKSPACE_REFERENCE_FILES;
+        if truncated {
+            warn!(
+                candidates = paths.len(),
+                limit = MAX_WORKSPACE_REFERENCE_FILES,
+                "workspace reference search truncated"
             );
-            return Err(Status::permission_denied(e.to_string()));
         }
 
-        let symbol_count = self
-            .storage
-            .index_symbols(req.path.clone(), req.content, req.language, req.commit_id)
-            .await
-            .map_err(|e: StorageError| {
-                audit::log_audit(
-                    "index_symbols",
-                    "failure",
-                    Some(&req.path),
-                    Some(json!({ "error": e.to_string() })),
-                );
-                Status::from(e)
-            })?;
-
-        audit::log_audit(
-            "index_symbols",
-            "success",
-            Some(&req.path),
-            Some(json!({ "symbol_count": symbol_count })),
-        );
-        info!(path = %req.path, count = symbol_count, "Symbols indexed successfully");
+        let mut files = Vec::new();
+        for path in paths.into_iter().take(MAX_WORKSPACE_REFERENCE_FILES) {
+            let content = match temporal.get_symbol_at_commit(&path, commit).await {
+                Ok(Some(file)) => file.content,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(path = %path, error = %e, "skipping unreadable reference candidate");
+                    continue;
+                }
+            };
+            let language = ast::language_for_path(&path).unwrap_or_default();
+            let tree = match self.trees.parse(language, &content) {
+                Ok(tree) => tree,
+                Err(e) => {
+                    warn!(path = %path, error = %e, "skipping unparsable reference candidate");
+                    continue;
+                }
+            };
+            files.push((path, content, tree));
+        }
 
-        Ok(Response::new(IndexSymbolsResponse {
-            symbol_count: symbol_count as i32,
-        }))
+        Ok((files, truncated))
     }
 
-    #[instrument(skip(self, request))]
-    async fn search_documents(
+    /// Validates a `SearchDocuments` request, checks its prefix against the
+    /// ACL and resolves its commit filter. `top_k` is clamped to `max_top_k`.
+    async fn document_search(
         &self,
-        request: Request<SearchDocumentsRequest>,
-    ) -> Result<Response<SearchDocumentsResponse>, Status> {
-        let req = request.into_inner();
+        mut req: SearchDocumentsRequest,
+        max_top_k: usize,
+        operation: &str,
+    ) -> Result<SearchPlan, Status> {
+        req.path_prefix = self.path_prefix(req.path_prefix.take(), req.directory_boundary);
 
         audit::log_audit(
-            "search_documents",
-            "attempt",
+            operation,
+            Outcome::Attempt,
             None,
             Some(json!({
                 "query": req.query,
                 "path_prefix": req.path_prefix,
-                "commit_id": req.commit_id
+                "commit_id": req.commit_id,
+                "mode": req.mode,
             })),
         );
 
         // Input validation
         if let Err(e) = validate_query(&req.query) {
             audit::log_audit(
-                "search_documents",
-                "failure",
+                operation,
+                Outcome::Failure,
                 None,
                 Some(json!({ "error": e })),
             );
@@ -348,8 +630,8 @@ impl IndexerService for IndexerServiceImpl {
         if let Some(ref prefix) = req.path_prefix {
             if let Err(e) = validate_path(prefix) {
                 audit::log_audit(
-                    "search_documents",
-                    "failure",
+                    operation,
+                    Outcome::Failure,
                     None,
                     Some(json!({ "error": e })),
                 );
@@ -357,181 +639,2092 @@ impl IndexerService for IndexerServiceImpl {
             }
             // Security check for path prefix
             if let Err(e) = self.security_config.check_path(prefix) {
-                audit::log_audit(
-                    "search_documents",
-                    "denied",
-                    None,
-                    Some(json!({ "error": e.to_string() })),
-                );
+                audit::log_audit(operation, Outcome::Denied, None, Some(e.audit_details()));
                 return Err(Status::permission_denied(e.to_string()));
             }
         }
 
         if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
             audit::log_audit(
-                "search_documents",
-                "failure",
+                operation,
+                Outcome::Failure,
                 None,
                 Some(json!({ "error": e })),
             );
             return Err(Status::invalid_argument(e));
         }
 
-        let top_k = if req.top_k <= 0 {
-            5
-        } else if req.top_k > 100 {
-            100
-        } else {
-            req.top_k as usize
+        let parsed = DedupBy::parse(&req.dedup_by).and_then(|dedup_by| {
+            Ok((
+                dedup_by,
+                validate_min_score(req.min_score)?,
+                search_mode(req.mode)?,
+            ))
+        });
+        let (dedup_by, min_score, mode) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                audit::log_audit(
+                    operation,
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+        };
+
+        let commit = match self
+            .commit_filter(req.commit_id, req.commit_ancestry.unwrap_or(false))
+            .await
+        {
+            Ok(filter) => filter,
+            Err(status) => {
+                audit::log_audit(
+                    operation,
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": status.message() })),
+                );
+                return Err(status);
+            }
+        };
+
+        Ok(SearchPlan {
+            target: SearchTarget::Documents,
+            query: req.query,
+            top_k: clamp_top_k(req.top_k, max_top_k),
+            offset: 0,
+            path_prefix: req.path_prefix,
+            commit,
+            min_score,
+            mode,
+            dedup_by,
+            not_found_on_empty: req.not_found_on_empty,
+        })
+    }
+
+    /// Like [`Self::document_search`] for `SearchSymbols`, which also takes
+    /// metadata filters and a page token. Returns the search's fingerprint
+    /// alongside, for issuing the next page token.
+    async fn symbol_search(
+        &self,
+        mut req: SearchSymbolsRequest,
+        max_top_k: usize,
+        operation: &str,
+    ) -> Result<(SearchPlan, String), Status> {
+        req.path_prefix = self.path_prefix(req.path_prefix.take(), req.directory_boundary);
+
+        audit::log_audit(
+            operation,
+            Outcome::Attempt,
+            None,
+            Some(json!({
+                "query": req.query,
+                "path_prefix": req.path_prefix,
+                "commit_id": req.commit_id,
+                "mode": req.mode,
+            })),
+        );
+
+        // Input validation
+        if let Err(e) = validate_query(&req.query) {
+            audit::log_audit(
+                operation,
+                Outcome::Failure,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        if let Some(ref prefix) = req.path_prefix {
+            if let Err(e) = validate_path(prefix) {
+                audit::log_audit(
+                    operation,
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+            // Security check for path prefix
+            if let Err(e) = self.security_config.check_path(prefix) {
+                audit::log_audit(operation, Outcome::Denied, None, Some(e.audit_details()));
+                return Err(Status::permission_denied(e.to_string()));
+            }
+        }
+
+        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+            audit::log_audit(
+                operation,
+                Outcome::Failure,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        let top_k = clamp_top_k(req.top_k, max_top_k);
+
+        // Every parameter that shapes the result list, so a page token only
+        // continues the search it was issued for.
+        let mut filters: Vec<String> = req
+            .metadata_filters
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        filters.sort();
+        let search_fingerprint = idempotency::fingerprint(&[
+            Some(&req.query),
+            Some(&top_k.to_string()),
+            req.path_prefix.as_deref(),
+            req.commit_id.as_deref(),
+            Some(if req.commit_ancestry.unwrap_or(false) {
+                "ancestry"
+            } else {
+                "exact"
+            }),
+            Some(req.dedup_by.trim().to_ascii_lowercase().as_str()),
+            Some(&filters.join("\n")),
+            req.min_score.map(|score| score.to_string()).as_deref(),
+            Some(&req.mode.to_string()),
+        ]);
+        let offset = if req.page_token.is_empty() {
+            0
+        } else {
+            match self.config.page_tokens.verify(
+                &req.page_token,
+                &search_fingerprint,
+                self.clock.now(),
+            ) {
+                Ok(offset) => offset,
+                Err(e) => {
+                    audit::log_audit(
+                        operation,
+                        Outcome::Failure,
+                        None,
+                        Some(json!({ "error": e.to_string() })),
+                    );
+                    return Err(Status::invalid_argument(e.to_string()));
+                }
+            }
+        };
+
+        let parsed = MetadataFilter::parse(req.metadata_filters).and_then(|metadata| {
+            Ok((
+                metadata,
+                DedupBy::parse(&req.dedup_by)?,
+                validate_min_score(req.min_score)?,
+                search_mode(req.mode)?,
+            ))
+        });
+        let (metadata, dedup_by, min_score, mode) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                audit::log_audit(
+                    operation,
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+        };
+
+        let commit = match self
+            .commit_filter(req.commit_id, req.commit_ancestry.unwrap_or(false))
+            .await
+        {
+            Ok(filter) => filter,
+            Err(status) => {
+                audit::log_audit(
+                    operation,
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": status.message() })),
+                );
+                return Err(status);
+            }
+        };
+
+        let search = SearchPlan {
+            target: SearchTarget::Symbols(metadata),
+            query: req.query,
+            top_k,
+            offset,
+            path_prefix: req.path_prefix,
+            commit,
+            min_score,
+            mode,
+            dedup_by,
+            not_found_on_empty: req.not_found_on_empty,
+        };
+        Ok((search, search_fingerprint))
+    }
+
+    /// The rows of `search`, retried in keyword mode (and `search` switched
+    /// to it) when the query cannot be embedded and the fallback is enabled.
+    async fn search_hits(
+        &self,
+        search: &mut SearchPlan,
+        operation: &str,
+    ) -> Result<(Vec<Hit>, Vec<Warning>), Status> {
+        let mut hits = search.hits(self.storage.as_ref()).await;
+        let mut warnings = Vec::new();
+        if let Some(fallback) = self.keyword_fallback(&hits, search.mode, operation) {
+            warnings.push(fallback);
+            search.mode = SearchMode::Keyword;
+            hits = search.hits(self.storage.as_ref()).await;
+        }
+        let hits = hits.map_err(|e| search_failed(operation, e))?;
+        Ok((hits, warnings))
+    }
+
+    /// The results of `search`, for the unary search RPCs.
+    async fn search_results(
+        &self,
+        search: &mut SearchPlan,
+        operation: &str,
+    ) -> Result<(Vec<SearchResult>, Vec<Warning>), Status> {
+        let (hits, warnings) = self.search_hits(search, operation).await?;
+        let mut results = Vec::with_capacity(hits.len());
+        for hit in hits {
+            results.push(
+                search
+                    .result(hit, self.storage.as_ref(), &self.security_config)
+                    .await,
+            );
+        }
+        Ok((results, warnings))
+    }
+
+    /// Streams the results of `search` a page of [`STREAM_BUFFER`] at a
+    /// time. The first page is queried here, so a failing query or an empty
+    /// result that should be `not_found` fails the call itself, and its
+    /// warnings go out as [`WARNING_METADATA_KEY`] headers. Later pages are
+    /// queried as the client reads, in the mode the first one settled on; the
+    /// query's embedding comes from the embedding cache after the first.
+    async fn stream_search(
+        &self,
+        mut search: SearchPlan,
+        operation: &'static str,
+    ) -> Result<Response<SearchResultStream>, Status> {
+        let total = search.top_k;
+        search.top_k = total.min(STREAM_BUFFER);
+        let (hits, warnings) = self.search_hits(&mut search, operation).await?;
+        if hits.is_empty()
+            && search.offset == 0
+            && self.not_found_on_empty(search.not_found_on_empty)
+        {
+            audit::log_audit(
+                operation,
+                Outcome::NotFound,
+                None,
+                Some(json!({ "result_count": 0 })),
+            );
+            return Err(Status::not_found("no results matched the query"));
+        }
+
+        let storage = self.storage.clone();
+        let security_config = self.security_config.clone();
+        let (tx, mut rx) = mpsc::channel(STREAM_BUFFER);
+        let results = async move {
+            let mut page = hits;
+            let mut count = 0;
+            loop {
+                let fetched = page.len();
+                for hit in page {
+                    let result = search.result(hit, storage.as_ref(), &security_config).await;
+                    if tx.send(Ok(result)).await.is_err() {
+                        // The client went away; stop working on its behalf.
+                        return;
+                    }
+                }
+                count += fetched;
+                if fetched < search.top_k || count >= total {
+                    break;
+                }
+
+                search.offset += fetched;
+                search.top_k = (total - count).min(STREAM_BUFFER);
+                page = match search.hits(storage.as_ref()).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let _ = tx.send(Err(search_failed(operation, e))).await;
+                        return;
+                    }
+                };
+            }
+
+            audit::log_audit(
+                operation,
+                Outcome::Success,
+                None,
+                Some(json!({ "result_count": count, "mode": search.mode.as_str() })),
+            );
+            info!(count, operation, "Streamed search completed");
+        };
+        // The results are audited as the caller of this one.
+        match current_request_context() {
+            Some(context) => tokio::spawn(scope_request_context(context, results)),
+            None => tokio::spawn(results),
+        };
+
+        let stream: SearchResultStream =
+            Box::pin(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)));
+        let mut response = Response::new(stream);
+        for warning in warnings {
+            response.metadata_mut().append_bin(
+                WARNING_METADATA_KEY,
+                MetadataValue::from_bytes(&warning.encode_to_vec()),
+            );
+        }
+        Ok(response)
+    }
+
+    /// Rejects responses that would exceed `max_response_bytes` once encoded,
+    /// so callers get actionable guidance instead of an opaque transport error.
+    fn check_response_size<M: Message>(&self, message: &M, hint: &str) -> Result<(), String> {
+        let size = message.encoded_len();
+        if size > self.config.max_response_bytes {
+            return Err(format!(
+                "response of {} bytes exceeds maximum of {} bytes; {}",
+                size, self.config.max_response_bytes, hint
+            ));
+        }
+        Ok(())
+    }
+
+    async fn get_file_content(
+        &self,
+        path: &str,
+        commit_id: Option<&str>,
+    ) -> Result<String, Status> {
+        let temporal = self.temporal.as_deref().ok_or_else(temporal_disabled)?;
+
+        // Check ACL before returning any content, committed or not
+        if let Err(e) = self.security_config.check_path(path) {
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        // If commit_id is provided, use temporal index
+        if let Some(commit) = commit_id {
+            let symbol = temporal
+                .get_symbol_at_commit(path, commit)
+                .await
+                .map_err(temporal_status)?
+                .ok_or_else(|| file_not_found(path, commit))?;
+
+            return Ok(symbol.content);
+        }
+
+        // Otherwise read the working tree, uncommitted edits included
+        temporal
+            .read_working_tree(path, &self.security_config, self.config.max_file_bytes)
+            .await
+            .map_err(temporal_status)?
+            .ok_or_else(|| {
+                Status::not_found(format!("File {} not found in the working tree", path))
+            })
+    }
+}
+
+fn temporal_disabled() -> Status {
+    Status::unimplemented("temporal features are disabled by INDEXER_FEATURES")
+}
+
+/// Temporal reads share one contract: a missing commit or a path absent from
+/// the commit's tree is `not_found`, while a file that exists but is empty is
+/// returned as an empty result.
+fn temporal_status(error: TemporalError) -> Status {
+    match error {
+        TemporalError::CommitNotFound(_) => Status::not_found(error.to_string()),
+        TemporalError::TooManyAncestors(..) | TemporalError::EmbeddingUnavailable => {
+            Status::failed_precondition(error.to_string())
+        }
+        TemporalError::Embedding(_) => Status::unavailable(error.to_string()),
+        TemporalError::InvalidPath(_) => Status::invalid_argument(error.to_string()),
+        TemporalError::AccessDenied(_) => Status::permission_denied(error.to_string()),
+        TemporalError::FileTooLarge(..) => Status::out_of_range(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+fn record_batch_result(
+    response: &mut IndexDocumentsBatchResponse,
+    path: String,
+    result: Result<Response<IndexDocumentResponse>, Status>,
+) {
+    let result = match result {
+        Ok(indexed) => {
+            response.indexed += 1;
+            IndexDocumentsBatchResult {
+                path,
+                document_id: indexed.into_inner().document_id,
+                error: String::new(),
+            }
+        }
+        Err(status) => {
+            response.failed += 1;
+            IndexDocumentsBatchResult {
+                path,
+                document_id: String::new(),
+                error: status.message().to_string(),
+            }
+        }
+    };
+    response.results.push(result);
+}
+
+fn file_not_found(path: &str, commit_id: &str) -> Status {
+    Status::not_found(format!("File {} not found at commit {}", path, commit_id))
+}
+
+/// Results a `SearchDocumentsStream`/`SearchSymbolsStream` call yields one by one.
+pub type SearchResultStream = Pin<Box<dyn Stream<Item = Result<SearchResult, Status>> + Send>>;
+
+enum SearchTarget {
+    Documents,
+    Symbols(MetadataFilter),
+}
+
+/// A validated search, shared by the unary search RPCs and their streaming
+/// variants.
+struct SearchPlan {
+    target: SearchTarget,
+    query: String,
+    top_k: usize,
+    /// Results skipped before the first one returned, from a page token.
+    offset: usize,
+    path_prefix: Option<String>,
+    commit: Option<CommitFilter>,
+    min_score: f32,
+    mode: SearchMode,
+    dedup_by: DedupBy,
+    not_found_on_empty: Option<bool>,
+}
+
+/// A ranked row of a search, before it is turned into a [`SearchResult`].
+enum Hit {
+    Document(StoredDocument, f32),
+    Symbol(StoredSymbol, f32),
+}
+
+impl SearchPlan {
+    /// Up to `top_k` ranked rows of the search, starting at `offset`, from a
+    /// single storage query so the query is embedded once.
+    async fn hits(&self, storage: &dyn IndexStorage) -> Result<Vec<Hit>, StorageError> {
+        let end = self.offset + self.top_k;
+        match &self.target {
+            SearchTarget::Documents => {
+                let documents = storage
+                    .search_documents(
+                        self.query.clone(),
+                        self.dedup_by.candidates(end),
+                        self.path_prefix.clone(),
+                        self.commit.clone(),
+                        self.min_score,
+                        self.mode,
+                    )
+                    .await?;
+                Ok(self
+                    .dedup_by
+                    .apply(documents, end, |doc| {
+                        (doc.path.as_str(), doc.commit_id.as_deref())
+                    })
+                    .into_iter()
+                    .skip(self.offset)
+                    .map(|(doc, score)| Hit::Document(doc, score))
+                    .collect())
+            }
+            SearchTarget::Symbols(metadata) => {
+                let symbols = storage
+                    .search_symbols(
+                        self.query.clone(),
+                        self.dedup_by.candidates(end),
+                        self.path_prefix.clone(),
+                        self.commit.clone(),
+                        metadata.clone(),
+                        self.min_score,
+                        self.mode,
+                    )
+                    .await?;
+                Ok(self
+                    .dedup_by
+                    .apply(symbols, end, |symbol| {
+                        (symbol.path.as_str(), symbol.commit_id.as_deref())
+                    })
+                    .into_iter()
+                    .skip(self.offset)
+                    .map(|(symbol, score)| Hit::Symbol(symbol, score))
+                    .collect())
+            }
+        }
+    }
+
+    /// The result returned for `hit`: its score clamped to the mode's range,
+    /// a redacted snippet and, for documents, the enclosing symbol.
+    async fn result(
+        &self,
+        hit: Hit,
+        storage: &dyn IndexStorage,
+        security_config: &SecurityConfig,
+    ) -> SearchResult {
+        let (_, range) = self.mode.scoring(storage.similarity_metric());
+        match hit {
+            Hit::Document(doc, score) => {
+                let container = container(
+                    storage,
+                    &doc.path,
+                    &doc.content,
+                    &self.query,
+                    doc.commit_id.clone(),
+                )
+                .await;
+                SearchResult {
+                    id: doc.id.to_string(),
+                    path: doc.path,
+                    score: score.clamp(range.min, range.max),
+                    snippet: snippet(security_config, doc.content),
+                    commit_id: doc.commit_id,
+                    container,
+                }
+            }
+            Hit::Symbol(symbol, score) => SearchResult {
+                id: symbol.id.to_string(),
+                path: symbol.path,
+                score: score.clamp(range.min, range.max),
+                snippet: snippet(security_config, symbol.content),
+                commit_id: symbol.commit_id,
+                container: String::new(),
+            },
+        }
+    }
+}
+
+/// `top_k` as requested, defaulting to 5 and capped at `max`.
+fn clamp_top_k(top_k: i32, max: usize) -> usize {
+    if top_k <= 0 {
+        5
+    } else {
+        (top_k as usize).min(max)
+    }
+}
+
+fn search_failed(operation: &str, error: StorageError) -> Status {
+    audit::log_audit(
+        operation,
+        Outcome::Failure,
+        None,
+        Some(json!({ "error": error.to_string() })),
+    );
+    Status::from(error)
+}
+
+/// Builds the preview returned with search results, masking DLP matches first
+/// when snippet redaction is enabled so a secret cut by truncation is still caught.
+fn snippet(security_config: &SecurityConfig, content: String) -> String {
+    // Only the head is shown, so neither redaction nor truncation needs
+    // to look at the rest of a very large symbol.
+    let content = match content.char_indices().nth(SNIPPET_SCAN_CHARS) {
+        Some((end, _)) => content[..end].to_string(),
+        None => content,
+    };
+    let content = if security_config.redact_snippets() {
+        security_config.redact_content(&content).into_owned()
+    } else {
+        content
+    };
+    if content.len() > 160 {
+        format!("{}…", content.chars().take(157).collect::<String>())
+    } else {
+        content
+    }
+}
+
+/// Qualified name of the stored symbol enclosing the line of `content`
+/// that best matches `query`, or empty. Lookup failures only cost the
+/// context, not the search.
+async fn container(
+    storage: &dyn IndexStorage,
+    path: &str,
+    content: &str,
+    query: &str,
+    commit_id: Option<String>,
+) -> String {
+    let Some(line) = match_line(content, query) else {
+        return String::new();
+    };
+    match storage
+        .symbols_enclosing(path.to_string(), line as i32, commit_id)
+        .await
+    {
+        Ok(enclosing) => qualified_name(&enclosing),
+        Err(e) => {
+            warn!(path, line, error = %e, "Failed to look up enclosing symbol");
+            String::new()
+        }
+    }
+}
+
+fn idempotency_cache<V: Clone>(config: &ServiceConfig) -> IdempotencyCache<V> {
+    IdempotencyCache::new(
+        config.idempotency_ttl,
+        idempotency::DEFAULT_IDEMPOTENCY_MAX_KEYS,
+    )
+}
+
+fn validate_idempotency_key(key: Option<&String>) -> Result<(), String> {
+    match key {
+        Some(key) => idempotency::validate_key(key),
+        None => Ok(()),
+    }
+}
+
+impl From<ChangeType> for proto::ChangeType {
+    fn from(change: ChangeType) -> Self {
+        match change {
+            ChangeType::Added => proto::ChangeType::Added,
+            ChangeType::Modified => proto::ChangeType::Modified,
+            ChangeType::Deleted => proto::ChangeType::Deleted,
+            ChangeType::Renamed => proto::ChangeType::Renamed,
+        }
+    }
+}
+
+impl From<SymbolKind> for proto::SymbolKind {
+    fn from(kind: SymbolKind) -> Self {
+        match kind {
+            SymbolKind::Function => proto::SymbolKind::Function,
+            SymbolKind::Class => proto::SymbolKind::Class,
+            SymbolKind::Interface => proto::SymbolKind::Interface,
+            SymbolKind::Enum => proto::SymbolKind::Enum,
+            SymbolKind::Constant => proto::SymbolKind::Constant,
+            SymbolKind::Variable => proto::SymbolKind::Variable,
+            SymbolKind::Type => proto::SymbolKind::Type,
+            SymbolKind::Module => proto::SymbolKind::Module,
+            SymbolKind::Method => proto::SymbolKind::Method,
+            SymbolKind::Property => proto::SymbolKind::Property,
+            SymbolKind::Namespace => proto::SymbolKind::Namespace,
+            SymbolKind::Trait => proto::SymbolKind::Trait,
+            SymbolKind::Impl => proto::SymbolKind::Impl,
+            SymbolKind::Struct => proto::SymbolKind::Struct,
+        }
+    }
+}
+
+/// Maps the stored `kind` string to the proto enum. Stored kinds come from
+/// `SymbolKind`'s Display form, except temporal snapshots which use "File".
+fn symbol_kind_to_proto(kind: &str) -> proto::SymbolKind {
+    if kind.eq_ignore_ascii_case("file") {
+        return proto::SymbolKind::File;
+    }
+    kind.parse::<SymbolKind>()
+        .map(proto::SymbolKind::from)
+        .unwrap_or(proto::SymbolKind::Unspecified)
+}
+
+fn version_to_proto(version: crate::temporal::SymbolVersion) -> SymbolVersion {
+    SymbolVersion {
+        symbol_id: version.symbol_id.to_string(),
+        commit_id: version.commit_id,
+        timestamp: version.timestamp.to_rfc3339(),
+        change_type: version.change_type.as_str().to_string(),
+        change: proto::ChangeType::from(version.change_type) as i32,
+        author: version.author,
+        commit_message: version.commit_message,
+        previous_path: version.previous_path,
+    }
+}
+
+fn symbol_to_proto(symbol: StoredSymbol) -> Symbol {
+    let language = symbol
+        .metadata
+        .as_ref()
+        .map(SymbolMetadata::from_value)
+        .and_then(|m| m.language)
+        .unwrap_or_else(|| "unknown".to_string());
+
+    Symbol {
+        id: symbol.id.to_string(),
+        path: symbol.path,
+        name: symbol.name,
+        symbol_kind: symbol_kind_to_proto(&symbol.kind) as i32,
+        kind: symbol.kind,
+        content: symbol.content,
+        commit_id: symbol.commit_id.unwrap_or_default(),
+        start_line: symbol.start_line,
+        end_line: symbol.end_line,
+        language,
+    }
+}
+
+/// Zero-based line of `content` containing the most distinct words of
+/// `query`, ignoring case; the earliest wins a tie. Words are runs of
+/// letters, digits and `_`, and words shorter than 3 characters are ignored
+/// so filler like `a` or `of` does not match every line.
+fn match_line(content: &str, query: &str) -> Option<usize> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|term| term.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect();
+    terms.sort();
+    terms.dedup();
+
+    let mut best = None;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.to_lowercase();
+        let hits = terms.iter().filter(|term| line.contains(*term)).count();
+        if hits > 0 && best.is_none_or(|(_, most)| hits > most) {
+            best = Some((index, hits));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+/// `enclosing` names joined outermost first with `::`. An impl block
+/// contributes the type it implements, so a method reads `Parser::parse`.
+fn qualified_name(enclosing: &[StoredSymbol]) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for symbol in enclosing {
+        let name = symbol
+            .name
+            .strip_prefix("impl ")
+            .unwrap_or(&symbol.name)
+            .trim();
+        if parts.last() != Some(&name) {
+            parts.push(name);
+        }
+    }
+    parts.join("::")
+}
+
+/// Drops repeated path and range pairs, such as a declaration that is also
+/// reported as a reference, keeping first occurrences in order.
+fn score_range(range: ScoreRange) -> proto::ScoreRange {
+    proto::ScoreRange {
+        min: range.min,
+        max: range.max,
+        higher_is_better: range.higher_is_better,
+    }
+}
+
+fn dedup_locations(locations: &mut Vec<Location>) {
+    let mut seen = HashSet::new();
+    let point = |position: Option<&Position>| position.map(|p| (p.line, p.character));
+    locations.retain(|location| {
+        let range = location.range.as_ref();
+        seen.insert((
+            location.path.clone(),
+            point(range.and_then(|r| r.start.as_ref())),
+            point(range.and_then(|r| r.end.as_ref())),
+        ))
+    });
+}
+
+fn call_hierarchy_item(path: &str, item: analysis::CallHierarchyItem) -> CallHierarchyItem {
+    CallHierarchyItem {
+        location: Some(to_location(path, &item.call_site)),
+        node: Some(GraphNode {
+            id: item.node.id,
+            name: item.node.name,
+            kind: item.node.kind,
+            path: path.to_string(),
+        }),
+    }
+}
+
+fn to_location(path: &str, range: &analysis::Range) -> Location {
+    Location {
+        path: path.to_string(),
+        range: Some(Range {
+            start: Some(Position {
+                line: range.start.line,
+                character: range.start.column,
+            }),
+            end: Some(Position {
+                line: range.end.line,
+                character: range.end.column,
+            }),
+        }),
+    }
+}
+
+fn validate_path(path: &str) -> Result<(), String> {
+    if path.trim().is_empty() {
+        return Err("path cannot be blank".to_string());
+    }
+    if path.len() > validation::MAX_PATH_LENGTH {
+        return Err(format!(
+            "path exceeds maximum length of {} characters",
+            validation::MAX_PATH_LENGTH
+        ));
+    }
+    if path.contains(['\0', '\r', '\n']) {
+        return Err("path contains invalid control characters".to_string());
+    }
+    Ok(())
+}
+
+fn validate_commit_id(commit_id: Option<&String>) -> Result<(), String> {
+    if let Some(commit) = commit_id {
+        if !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("commit id must be hexadecimal".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// A client-supplied query embedding must match the stored dimension and be
+/// finite throughout.
+fn validate_embedding(embedding: &[f32], dimension: usize) -> Result<(), String> {
+    if embedding.len() != dimension {
+        return Err(format!(
+            "embedding has {} dimensions; expected {}",
+            embedding.len(),
+            dimension
+        ));
+    }
+    if embedding.iter().any(|v| !v.is_finite()) {
+        return Err("embedding contains NaN or infinite values".to_string());
+    }
+    Ok(())
+}
+
+/// The score threshold to search with; unset is 0, keeping every result.
+fn validate_min_score(min_score: Option<f32>) -> Result<f32, String> {
+    match min_score {
+        None => Ok(0.0),
+        Some(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        Some(score) => Err(format!("min_score must be within [0, 1], got {}", score)),
+    }
+}
+
+fn search_mode(mode: i32) -> Result<SearchMode, String> {
+    match proto::SearchMode::try_from(mode) {
+        Ok(proto::SearchMode::Unspecified | proto::SearchMode::Vector) => Ok(SearchMode::Vector),
+        Ok(proto::SearchMode::Keyword) => Ok(SearchMode::Keyword),
+        Ok(proto::SearchMode::Hybrid) => Ok(SearchMode::Hybrid),
+        Err(_) => Err(format!("unknown search mode {}", mode)),
+    }
+}
+
+fn validate_query(query: &str) -> Result<(), String> {
+    if query.trim().is_empty() {
+        return Err("query cannot be blank".to_string());
+    }
+    if query.len() > validation::MAX_QUERY_LENGTH {
+        return Err(format!(
+            "query exceeds maximum length of {} characters",
+            validation::MAX_QUERY_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl IndexerService for IndexerServiceImpl {
+    #[instrument(skip(self, request))]
+    async fn index_documents_batch(
+        &self,
+        request: Request<Streaming<IndexDocumentRequest>>,
+    ) -> Result<Response<IndexDocumentsBatchResponse>, Status> {
+        let mut stream = request.into_inner();
+        let limit = self.config.batch_max_in_flight.max(1);
+        let mut in_flight = FuturesUnordered::new();
+        let mut response = IndexDocumentsBatchResponse::default();
+        let mut exhausted = false;
+
+        // Each document goes through the single-document handler, so
+        // validation, ACL, DLP, idempotency and audit behave identically.
+        let index_one = |req: IndexDocumentRequest| async move {
+            let path = req.path.clone();
+            let result = self.index_document(Request::new(req)).await;
+            (path, result)
+        };
+
+        loop {
+            tokio::select! {
+                Some((path, result)) = in_flight.next(), if !in_flight.is_empty() => {
+                    record_batch_result(&mut response, path, result);
+                }
+                // Only pull another message when there is room; until then the
+                // transport's flow control holds the client back.
+                message = stream.message(), if !exhausted && in_flight.len() < limit => {
+                    match message? {
+                        Some(req) => in_flight.push(index_one(req)),
+                        None => exhausted = true,
+                    }
+                }
+                else => break,
+            }
+        }
+
+        self.check_response_size(&response, "send fewer documents per stream")
+            .map_err(Status::out_of_range)?;
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn batch_index_documents(
+        &self,
+        request: Request<BatchIndexDocumentsRequest>,
+    ) -> Result<Response<IndexDocumentsBatchResponse>, Status> {
+        let req = request.into_inner();
+        if req.documents.len() > validation::MAX_BATCH_DOCUMENTS {
+            return Err(Status::invalid_argument(format!(
+                "batch has {} documents; at most {} are allowed",
+                req.documents.len(),
+                validation::MAX_BATCH_DOCUMENTS
+            )));
+        }
+
+        // Each path in request order, with why it was turned away; admitted
+        // documents are resolved once storage has written them.
+        let mut checked: Vec<(String, Option<Status>)> = Vec::new();
+        let mut admitted = Vec::new();
+        let mut seen = HashSet::new();
+        for document in req.documents {
+            audit::log_audit(
+                "index_document",
+                Outcome::Attempt,
+                Some(&document.path),
+                Some(json!({ "commit_id": document.commit_id, "batch": true })),
+            );
+            let mut rejection = self.document_rejection(
+                &document.path,
+                &document.content,
+                document.commit_id.as_ref(),
+            );
+            if rejection.is_none() && !seen.insert(document.path.clone()) {
+                let e = "path appears more than once in the batch";
+                audit::log_audit(
+                    "index_document",
+                    Outcome::Failure,
+                    Some(&document.path),
+                    Some(json!({ "error": e })),
+                );
+                rejection = Some(Status::invalid_argument(e));
+            }
+            if rejection.is_none() {
+                admitted.push(NewDocument {
+                    path: document.path.clone(),
+                    content: document.content,
+                    commit_id: document.commit_id,
+                });
+            }
+            checked.push((document.path, rejection));
+        }
+
+        let mut stored: Vec<Result<uuid::Uuid, Status>> = Vec::new();
+        if !admitted.is_empty() {
+            let count = admitted.len();
+            match self.storage.batch_index_documents(admitted).await {
+                Ok(results) => {
+                    for result in results {
+                        stored.push(match result {
+                            Ok(id) => Ok(id),
+                            Err(e) => Err(Status::from(e)),
+                        });
+                    }
+                }
+                Err(e) => {
+                    let status = Status::from(e);
+                    for _ in 0..count {
+                        stored.push(Err(Status::new(status.code(), status.message())));
+                    }
+                }
+            }
+        }
+        let mut stored = stored.into_iter();
+        let embedding_dim = self.embedding_dimension().await as i32;
+
+        let mut response = IndexDocumentsBatchResponse::default();
+        for (path, rejection) in checked {
+            if let Some(status) = rejection {
+                record_batch_result(&mut response, path, Err(status));
+                continue;
+            }
+            let outcome = match stored.next() {
+                Some(Ok(id)) => {
+                    audit::log_audit(
+                        "index_document",
+                        Outcome::Success,
+                        Some(&path),
+                        Some(json!({ "document_id": id.to_string(), "batch": true })),
+                    );
+                    info!(path = %path, "Document indexed successfully");
+                    Ok(Response::new(IndexDocumentResponse {
+                        document_id: id.to_string(),
+                        embedding_dim,
+                    }))
+                }
+                Some(Err(status)) => {
+                    audit::log_audit(
+                        "index_document",
+                        Outcome::Failure,
+                        Some(&path),
+                        Some(json!({ "error": status.message() })),
+                    );
+                    Err(status)
+                }
+                None => Err(Status::internal("storage returned too few batch results")),
+            };
+            record_batch_result(&mut response, path, outcome);
+        }
+
+        self.check_response_size(&response, "send fewer documents per batch")
+            .map_err(Status::out_of_range)?;
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn index_document(
+        &self,
+        request: Request<IndexDocumentRequest>,
+    ) -> Result<Response<IndexDocumentResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "index_document",
+            Outcome::Attempt,
+            Some(&req.path),
+            Some(json!({ "commit_id": req.commit_id })),
+        );
+
+        if let Err(e) = validate_idempotency_key(req.idempotency_key.as_ref()) {
+            audit::log_audit(
+                "index_document",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+        if let Some(status) =
+            self.document_rejection(&req.path, &req.content, req.commit_id.as_ref())
+        {
+            return Err(status);
+        }
+
+        // Replay only after the ACL/DLP checks so policy changes still apply to retries.
+        let fingerprint = idempotency::fingerprint(&[
+            Some(&req.path),
+            Some(&req.content),
+            req.commit_id.as_deref(),
+        ]);
+        if let Some(key) = req.idempotency_key.as_deref() {
+            match self.document_writes.lookup(key, &fingerprint) {
+                Lookup::Hit(response) => {
+                    audit::log_audit(
+                        "index_document",
+                        Outcome::Success,
+                        Some(&req.path),
+                        Some(json!({
+                            "document_id": response.document_id,
+                            "idempotent_replay": true
+                        })),
+                    );
+                    return Ok(Response::new(response));
+                }
+                Lookup::Conflict => {
+                    let e = "idempotency_key was already used for a different request";
+                    audit::log_audit(
+                        "index_document",
+                        Outcome::Rejected,
+                        Some(&req.path),
+                        Some(json!({ "error": e })),
+                    );
+                    return Err(Status::invalid_argument(e));
+                }
+                Lookup::Miss => {}
+            }
+        }
+
+        let document_id = self
+            .storage
+            .index_document(req.path.clone(), req.content, req.commit_id)
+            .await
+            .map_err(|e: StorageError| {
+                audit::log_audit(
+                    "index_document",
+                    Outcome::Failure,
+                    Some(&req.path),
+                    Some(json!({ "error": e.to_string() })),
+                );
+                Status::from(e)
+            })?;
+
+        audit::log_audit(
+            "index_document",
+            Outcome::Success,
+            Some(&req.path),
+            Some(json!({ "document_id": document_id.to_string() })),
+        );
+        info!(path = %req.path, "Document indexed successfully");
+
+        let response = IndexDocumentResponse {
+            document_id: document_id.to_string(),
+            embedding_dim: self.embedding_dimension().await as i32,
+        };
+        if let Some(key) = req.idempotency_key.as_deref() {
+            self.document_writes
+                .record(key, &fingerprint, response.clone());
+        }
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn index_symbols(
+        &self,
+        request: Request<IndexSymbolsRequest>,
+    ) -> Result<Response<IndexSymbolsResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "index_symbols",
+            Outcome::Attempt,
+            Some(&req.path),
+            Some(json!({
+                "commit_id": req.commit_id,
+                "language": req.language,
+                "declarations_only": req.declarations_only,
+            })),
+        );
+
+        // Input validation
+        if let Err(e) = validate_path(&req.path) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = validate_content(&req.content, self.config.control_chars) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = validate_idempotency_key(req.idempotency_key.as_ref()) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        if req.language.trim().is_empty() {
+            let e = Status::invalid_argument("language cannot be blank");
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e.to_string() })),
+            );
+            return Err(e);
+        }
+
+        let line_range = match (req.start_line, req.end_line) {
+            (None, None) => None,
+            (start, end) => Some(LineRange {
+                start: start.unwrap_or(0) as usize,
+                end: end.map_or_else(
+                    || req.content.split_inclusive('\n').count().saturating_sub(1),
+                    |end| end as usize,
+                ),
+            }),
+        };
+        if let Some(Err(e)) = line_range.map(|range| range.slice(&req.content)) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        let mut warnings = Vec::new();
+        let language_mismatch = ast::language_mismatch(&req.path, &req.language);
+        if let Some(expected) = language_mismatch {
+            let e = format!(
+                "language '{}' does not match '{}' implied by the path",
+                req.language, expected
+            );
+            if self.config.language_mismatch == LanguageMismatchPolicy::Reject {
+                audit::log_audit(
+                    "index_symbols",
+                    Outcome::Rejected,
+                    Some(&req.path),
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+            warn!(path = %req.path, language = %req.language, expected, "{e}");
+            warnings.push(warning(WARNING_LANGUAGE_MISMATCH, e));
+        }
+
+        // Security checks
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Denied,
+                Some(&req.path),
+                Some(e.audit_details()),
+            );
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        if let Err(e) = self.security_config.scan_content(&req.content) {
+            audit::log_audit(
+                "index_symbols",
+                Outcome::Denied,
+                Some(&req.path),
+                Some(e.audit_details()),
+            );
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let fingerprint = idempotency::fingerprint(&[
+            Some(&req.path),
+            Some(&req.content),
+            Some(&req.language),
+            req.commit_id.as_deref(),
+            line_range
+                .map(|range| format!("{}-{}", range.start, range.end))
+                .as_deref(),
+            req.declarations_only.then_some("declarations_only"),
+        ]);
+        if let Some(key) = req.idempotency_key.as_deref() {
+            match self.symbol_writes.lookup(key, &fingerprint) {
+                Lookup::Hit(response) => {
+                    audit::log_audit(
+                        "index_symbols",
+                        Outcome::Success,
+                        Some(&req.path),
+                        Some(json!({
+                            "symbol_count": response.symbol_count,
+                            "idempotent_replay": true
+                        })),
+                    );
+                    return Ok(Response::new(response));
+                }
+                Lookup::Conflict => {
+                    let e = "idempotency_key was already used for a different request";
+                    audit::log_audit(
+                        "index_symbols",
+                        Outcome::Rejected,
+                        Some(&req.path),
+                        Some(json!({ "error": e })),
+                    );
+                    return Err(Status::invalid_argument(e));
+                }
+                Lookup::Miss => {}
+            }
+        }
+
+        // Extraction skips what tree-sitter could not parse; say so rather
+        // than let the missing symbols go unnoticed. The range was checked above.
+        let (source, first_line) = match line_range {
+            Some(range) => (range.slice(&req.content).unwrap_or_default(), range.start),
+            None => (req.content.as_str(), 0),
+        };
+        if let Ok(tree) = self.trees.parse(&req.language, source) {
+            let errors = ast::syntax_error_lines(&tree);
+            if let Some(first) = errors.first() {
+                warnings.push(warning(
+                    WARNING_PARSE_ERRORS,
+                    format!(
+                        "{} syntax error(s), the first on line {}; symbols were extracted from the parts that parsed",
+                        errors.len(),
+                        first_line + first + 1
+                    ),
+                ));
+            }
+        }
+
+        let symbol_ids = self
+            .storage
+            .index_symbols(
+                req.path.clone(),
+                req.content,
+                req.language,
+                req.commit_id,
+                line_range,
+                req.declarations_only,
+            )
+            .await
+            .map_err(|e: StorageError| {
+                audit::log_audit(
+                    "index_symbols",
+                    Outcome::Failure,
+                    Some(&req.path),
+                    Some(json!({ "error": e.to_string() })),
+                );
+                Status::from(e)
+            })?;
+
+        let symbol_count = symbol_ids.len();
+        audit::log_audit(
+            "index_symbols",
+            Outcome::Success,
+            Some(&req.path),
+            Some(json!({
+                "symbol_count": symbol_count,
+                "language_mismatch": language_mismatch,
+                "warnings": warnings.iter().map(|w| w.code.as_str()).collect::<Vec<_>>(),
+            })),
+        );
+        info!(path = %req.path, count = symbol_count, "Symbols indexed successfully");
+
+        let response = IndexSymbolsResponse {
+            symbol_count: symbol_count as i32,
+            symbol_ids: symbol_ids.iter().map(uuid::Uuid::to_string).collect(),
+            warnings,
+        };
+        if let Some(key) = req.idempotency_key.as_deref() {
+            self.symbol_writes
+                .record(key, &fingerprint, response.clone());
+        }
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn touch_document(
+        &self,
+        request: Request<TouchDocumentRequest>,
+    ) -> Result<Response<TouchDocumentResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit("touch_document", Outcome::Attempt, Some(&req.path), None);
+
+        if let Err(e) = validate_path(&req.path) {
+            audit::log_audit(
+                "touch_document",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            audit::log_audit(
+                "touch_document",
+                Outcome::Denied,
+                Some(&req.path),
+                Some(e.audit_details()),
+            );
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let touched = self
+            .storage
+            .touch(req.path.clone())
+            .await
+            .map_err(|e: StorageError| {
+                audit::log_audit(
+                    "touch_document",
+                    Outcome::Failure,
+                    Some(&req.path),
+                    Some(json!({ "error": e.to_string() })),
+                );
+                Status::from(e)
+            })?;
+
+        if touched == 0 {
+            audit::log_audit("touch_document", Outcome::NotFound, Some(&req.path), None);
+            return Err(Status::not_found(format!(
+                "no document indexed at '{}'",
+                req.path
+            )));
+        }
+
+        audit::log_audit("touch_document", Outcome::Success, Some(&req.path), None);
+
+        Ok(Response::new(TouchDocumentResponse {
+            document_count: touched as i32,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_documents(
+        &self,
+        request: Request<SearchDocumentsRequest>,
+    ) -> Result<Response<SearchDocumentsResponse>, Status> {
+        let mut search = self
+            .document_search(request.into_inner(), MAX_TOP_K, "search_documents")
+            .await?;
+        let (results, warnings) = self.search_results(&mut search, "search_documents").await?;
+
+        let result_count = results.len();
+        if result_count == 0 && self.not_found_on_empty(search.not_found_on_empty) {
+            audit::log_audit(
+                "search_documents",
+                Outcome::NotFound,
+                None,
+                Some(json!({ "result_count": 0 })),
+            );
+            return Err(Status::not_found("no results matched the query"));
+        }
+
+        let (scoring, range) = search.mode.scoring(self.storage.similarity_metric());
+        let response = SearchDocumentsResponse {
+            results,
+            metric: scoring.to_string(),
+            score_range: Some(score_range(range)),
+            warnings,
+        };
+        if let Err(e) = self.check_response_size(&response, "reduce top_k or narrow path_prefix") {
+            audit::log_audit(
+                "search_documents",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::out_of_range(e));
+        }
+
+        audit::log_audit(
+            "search_documents",
+            Outcome::Success,
+            None,
+            Some(json!({ "result_count": result_count, "mode": search.mode.as_str() })),
+        );
+        info!(count = result_count, "Document search completed");
+
+        Ok(Response::new(response))
+    }
+
+    type SearchDocumentsStreamStream = SearchResultStream;
+
+    #[instrument(skip(self, request))]
+    async fn search_documents_stream(
+        &self,
+        request: Request<SearchDocumentsRequest>,
+    ) -> Result<Response<Self::SearchDocumentsStreamStream>, Status> {
+        let search = self
+            .document_search(
+                request.into_inner(),
+                MAX_STREAM_RESULTS,
+                "search_documents_stream",
+            )
+            .await?;
+        self.stream_search(search, "search_documents_stream").await
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_symbols(
+        &self,
+        request: Request<SearchSymbolsRequest>,
+    ) -> Result<Response<SearchSymbolsResponse>, Status> {
+        let (mut search, search_fingerprint) = self
+            .symbol_search(request.into_inner(), MAX_TOP_K, "search_symbols")
+            .await?;
+        let top_k = search.top_k;
+        let (results, warnings) = self.search_results(&mut search, "search_symbols").await?;
+
+        let result_count = results.len();
+        // A later page can legitimately come back empty when the previous one
+        // ended exactly on the last match.
+        if search.offset == 0
+            && result_count == 0
+            && self.not_found_on_empty(search.not_found_on_empty)
+        {
+            audit::log_audit(
+                "search_symbols",
+                Outcome::NotFound,
+                None,
+                Some(json!({ "result_count": 0 })),
+            );
+            return Err(Status::not_found("no results matched the query"));
+        }
+
+        let next = search.offset + top_k;
+        let next_page_token = if result_count == top_k && next < MAX_PAGE_DEPTH {
+            self.config
+                .page_tokens
+                .issue(next, &search_fingerprint, self.clock.now())
+        } else {
+            String::new()
+        };
+
+        let (scoring, range) = search.mode.scoring(self.storage.similarity_metric());
+        let response = SearchSymbolsResponse {
+            results,
+            next_page_token,
+            metric: scoring.to_string(),
+            score_range: Some(score_range(range)),
+            warnings,
+        };
+        if let Err(e) = self.check_response_size(&response, "reduce top_k or narrow path_prefix") {
+            audit::log_audit(
+                "search_symbols",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::out_of_range(e));
+        }
+
+        audit::log_audit(
+            "search_symbols",
+            Outcome::Success,
+            None,
+            Some(json!({ "result_count": result_count, "mode": search.mode.as_str() })),
+        );
+        info!(count = result_count, "Symbol search completed");
+
+        Ok(Response::new(response))
+    }
+
+    type SearchSymbolsStreamStream = SearchResultStream;
+
+    #[instrument(skip(self, request))]
+    async fn search_symbols_stream(
+        &self,
+        request: Request<SearchSymbolsRequest>,
+    ) -> Result<Response<Self::SearchSymbolsStreamStream>, Status> {
+        let (search, _) = self
+            .symbol_search(
+                request.into_inner(),
+                MAX_STREAM_RESULTS,
+                "search_symbols_stream",
+            )
+            .await?;
+        self.stream_search(search, "search_symbols_stream").await
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_by_vector(
+        &self,
+        request: Request<SearchByVectorRequest>,
+    ) -> Result<Response<SearchByVectorResponse>, Status> {
+        let mut req = request.into_inner();
+        req.path_prefix = self.path_prefix(req.path_prefix.take(), req.directory_boundary);
+
+        audit::log_audit(
+            "search_by_vector",
+            Outcome::Attempt,
+            None,
+            Some(json!({
+                "dimensions": req.embedding.len(),
+                "path_prefix": req.path_prefix,
+                "commit_id": req.commit_id
+            })),
+        );
+
+        let validation = validate_embedding(&req.embedding, self.embedding_dimension().await)
+            .and_then(|()| validate_commit_id(req.commit_id.as_ref()))
+            .and_then(|()| req.path_prefix.as_deref().map_or(Ok(()), validate_path));
+        if let Err(e) = validation {
+            audit::log_audit(
+                "search_by_vector",
+                Outcome::Failure,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        if let Some(ref prefix) = req.path_prefix {
+            if let Err(e) = self.security_config.check_path(prefix) {
+                audit::log_audit(
+                    "search_by_vector",
+                    Outcome::Denied,
+                    None,
+                    Some(e.audit_details()),
+                );
+                return Err(Status::permission_denied(e.to_string()));
+            }
+        }
+
+        let commit_filter = match self.commit_filter(req.commit_id, false).await {
+            Ok(filter) => filter,
+            Err(status) => {
+                audit::log_audit(
+                    "search_by_vector",
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": status.message() })),
+                );
+                return Err(status);
+            }
+        };
+
+        let top_k = if req.top_k <= 0 {
+            5
+        } else if req.top_k > 100 {
+            100
+        } else {
+            req.top_k as usize
         };
 
-        let documents = self
+        let symbols = self
             .storage
-            .search_documents(req.query, top_k, req.path_prefix, req.commit_id)
+            .search_symbols_by_vector(req.embedding, top_k, req.path_prefix, commit_filter)
             .await
             .map_err(|e: StorageError| {
                 audit::log_audit(
-                    "search_documents",
-                    "failure",
+                    "search_by_vector",
+                    Outcome::Failure,
                     None,
                     Some(json!({ "error": e.to_string() })),
                 );
                 Status::from(e)
             })?;
 
-        let results: Vec<SearchResult> = documents
+        let metric = self.storage.similarity_metric();
+        let results: Vec<SearchResult> = symbols
             .into_iter()
-            .map(|(doc, score)| SearchResult {
-                id: doc.id.to_string(),
-                path: doc.path,
-                score,
-                snippet: if doc.content.len() > 160 {
-                    format!("{}…", doc.content.chars().take(157).collect::<String>())
-                } else {
-                    doc.content
-                },
-                commit_id: doc.commit_id,
+            .filter(|(symbol, _)| self.security_config.check_path(&symbol.path).is_ok())
+            .map(|(symbol, score)| SearchResult {
+                id: symbol.id.to_string(),
+                path: symbol.path,
+                score: metric.report(score),
+                snippet: snippet(&self.security_config, symbol.content),
+                commit_id: symbol.commit_id,
+                container: String::new(),
             })
             .collect();
 
+        let result_count = results.len();
+        let response = SearchByVectorResponse {
+            results,
+            metric: metric.as_str().to_string(),
+            score_range: Some(score_range(metric.score_range())),
+        };
+        if let Err(e) = self.check_response_size(&response, "reduce top_k or narrow path_prefix") {
+            audit::log_audit(
+                "search_by_vector",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::out_of_range(e));
+        }
+
         audit::log_audit(
-            "search_documents",
-            "success",
+            "search_by_vector",
+            Outcome::Success,
             None,
-            Some(json!({ "result_count": results.len() })),
+            Some(json!({ "result_count": result_count })),
         );
-        info!(count = results.len(), "Document search completed");
+        info!(count = result_count, "Vector search completed");
 
-        Ok(Response::new(SearchDocumentsResponse { results }))
+        Ok(Response::new(response))
     }
 
     #[instrument(skip(self, request))]
-    async fn search_symbols(
+    async fn embed(
         &self,
-        request: Request<SearchSymbolsRequest>,
-    ) -> Result<Response<SearchSymbolsResponse>, Status> {
+        request: Request<EmbedRequest>,
+    ) -> Result<Response<EmbedResponse>, Status> {
         let req = request.into_inner();
 
         audit::log_audit(
-            "search_symbols",
-            "attempt",
+            "embed",
+            Outcome::Attempt,
             None,
-            Some(json!({
-                "query": req.query,
-                "path_prefix": req.path_prefix,
-                "commit_id": req.commit_id
-            })),
+            Some(json!({ "text_length": req.text.len() })),
         );
 
-        // Input validation
-        if let Err(e) = validate_query(&req.query) {
+        if let Err(e) = validate_content(&req.text, self.config.control_chars) {
+            audit::log_audit("embed", Outcome::Failure, None, Some(json!({ "error": e })));
+            return Err(Status::invalid_argument(e));
+        }
+
+        if let Err(e) = self.security_config.scan_content(&req.text) {
+            audit::log_audit("embed", Outcome::Denied, None, Some(e.audit_details()));
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let Some(manager) = &self.embedding_manager else {
+            let e = "no embedding provider is configured";
+            audit::log_audit("embed", Outcome::Failure, None, Some(json!({ "error": e })));
+            return Err(Status::failed_precondition(e));
+        };
+
+        let embedding = manager.embed(&req.text).await.map_err(|e| {
             audit::log_audit(
-                "search_symbols",
-                "failure",
+                "embed",
+                Outcome::Failure,
                 None,
-                Some(json!({ "error": e })),
+                Some(json!({ "error": e.to_string() })),
             );
-            return Err(Status::invalid_argument(e));
+            match e {
+                EmbeddingError::Unavailable(_) => Status::unavailable(e.to_string()),
+                _ => Status::internal(e.to_string()),
+            }
+        })?;
+
+        audit::log_audit(
+            "embed",
+            Outcome::Success,
+            None,
+            Some(json!({ "dim": embedding.len(), "provider": manager.provider_name() })),
+        );
+
+        Ok(Response::new(EmbedResponse {
+            dim: embedding.len() as u32,
+            embedding,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_within_symbol(
+        &self,
+        request: Request<SearchWithinSymbolRequest>,
+    ) -> Result<Response<SearchWithinSymbolResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "search_within_symbol",
+            Outcome::Attempt,
+            None,
+            Some(json!({ "symbol_id": req.symbol_id, "regex": req.regex })),
+        );
+
+        let id = match uuid::Uuid::parse_str(req.symbol_id.trim()) {
+            Ok(id) => id,
+            Err(_) => {
+                let e = "symbol_id must be a UUID";
+                audit::log_audit(
+                    "search_within_symbol",
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+        };
+
+        let symbol = match self.storage.get_symbol(id).await {
+            Ok(Some(symbol)) => symbol,
+            Ok(None) => {
+                audit::log_audit(
+                    "search_within_symbol",
+                    Outcome::NotFound,
+                    None,
+                    Some(json!({ "symbol_id": id.to_string() })),
+                );
+                return Err(Status::not_found(format!("Symbol {} not found", id)));
+            }
+            Err(e) => {
+                audit::log_audit(
+                    "search_within_symbol",
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e.to_string() })),
+                );
+                return Err(Status::from(e));
+            }
+        };
+
+        if let Err(e) = self.security_config.check_path(&symbol.path) {
+            audit::log_audit(
+                "search_within_symbol",
+                Outcome::Denied,
+                Some(&symbol.path),
+                Some(e.audit_details()),
+            );
+            return Err(Status::permission_denied(e.to_string()));
         }
 
+        let first_line = u32::try_from(symbol.start_line).unwrap_or(0);
+        let ranges =
+            match analysis::find_text_matches(&symbol.content, &req.query, req.regex, first_line) {
+                Ok(ranges) => ranges,
+                Err(e) => {
+                    audit::log_audit(
+                        "search_within_symbol",
+                        Outcome::Failure,
+                        Some(&symbol.path),
+                        Some(json!({ "error": e })),
+                    );
+                    return Err(Status::invalid_argument(e));
+                }
+            };
+
+        let matches: Vec<Location> = ranges
+            .iter()
+            .map(|range| to_location(&symbol.path, range))
+            .collect();
+
+        audit::log_audit(
+            "search_within_symbol",
+            Outcome::Success,
+            Some(&symbol.path),
+            Some(json!({ "match_count": matches.len() })),
+        );
+
+        Ok(Response::new(SearchWithinSymbolResponse { matches }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_top_symbols(
+        &self,
+        request: Request<GetTopSymbolsRequest>,
+    ) -> Result<Response<GetTopSymbolsResponse>, Status> {
+        let mut req = request.into_inner();
+        req.path_prefix = self.path_prefix(req.path_prefix.take(), req.directory_boundary);
+
+        audit::log_audit(
+            "get_top_symbols",
+            Outcome::Attempt,
+            None,
+            Some(json!({
+                "metric": req.metric,
+                "limit": req.limit,
+                "path_prefix": req.path_prefix,
+            })),
+        );
+
+        let metric = match SymbolMetric::parse(&req.metric) {
+            Ok(metric) => metric,
+            Err(e) => {
+                audit::log_audit(
+                    "get_top_symbols",
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "error": e })),
+                );
+                return Err(Status::invalid_argument(e));
+            }
+        };
+
         if let Some(ref prefix) = req.path_prefix {
             if let Err(e) = validate_path(prefix) {
                 audit::log_audit(
-                    "search_symbols",
-                    "failure",
+                    "get_top_symbols",
+                    Outcome::Failure,
                     None,
                     Some(json!({ "error": e })),
                 );
                 return Err(Status::invalid_argument(e));
             }
-            // Security check for path prefix
             if let Err(e) = self.security_config.check_path(prefix) {
                 audit::log_audit(
-                    "search_symbols",
-                    "denied",
+                    "get_top_symbols",
+                    Outcome::Denied,
                     None,
-                    Some(json!({ "error": e.to_string() })),
+                    Some(e.audit_details()),
                 );
                 return Err(Status::permission_denied(e.to_string()));
             }
         }
 
-        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+        let limit = if req.limit <= 0 {
+            20
+        } else if req.limit > 100 {
+            100
+        } else {
+            req.limit as usize
+        };
+
+        // Denied paths are dropped after each query, so keep reading pages
+        // until `limit` symbols are left or storage has no more.
+        let page_size = (limit * TOP_SYMBOLS_OVERFETCH_FACTOR).min(MAX_TOP_SYMBOLS_PAGE);
+        let mut symbols: Vec<RankedSymbol> = Vec::with_capacity(limit);
+        let mut offset = 0;
+        while symbols.len() < limit {
+            let page = match self
+                .storage
+                .top_symbols(metric, page_size, offset, req.path_prefix.clone())
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => {
+                    audit::log_audit(
+                        "get_top_symbols",
+                        Outcome::Failure,
+                        None,
+                        Some(json!({ "error": e.to_string() })),
+                    );
+                    return Err(Status::from(e));
+                }
+            };
+            let exhausted = page.len() < page_size;
+            offset += page.len();
+            symbols.extend(
+                page.into_iter()
+                    .filter(|(symbol, _)| self.security_config.check_path(&symbol.path).is_ok())
+                    .map(|(symbol, value)| RankedSymbol {
+                        symbol: Some(symbol_to_proto(symbol)),
+                        value,
+                    }),
+            );
+            if exhausted {
+                break;
+            }
+        }
+        symbols.truncate(limit);
+        let result_count = symbols.len();
+
+        let response = GetTopSymbolsResponse { symbols };
+        if let Err(e) = self.check_response_size(&response, "reduce limit or narrow path_prefix") {
             audit::log_audit(
-                "search_symbols",
-                "failure",
+                "get_top_symbols",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::out_of_range(e));
+        }
+
+        audit::log_audit(
+            "get_top_symbols",
+            Outcome::Success,
+            None,
+            Some(json!({ "metric": metric.metadata_key(), "result_count": result_count })),
+        );
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn find_symbols_by_name(
+        &self,
+        request: Request<FindSymbolsByNameRequest>,
+    ) -> Result<Response<FindSymbolsByNameResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "find_symbols_by_name",
+            Outcome::Attempt,
+            None,
+            Some(json!({ "kind": req.kind, "limit": req.limit })),
+        );
+
+        let query = req.query.trim();
+        let invalid = if query.is_empty() {
+            Some("query cannot be blank".to_string())
+        } else if query.len() > MAX_NAME_QUERY_LENGTH {
+            Some(format!(
+                "query exceeds maximum length of {} characters",
+                MAX_NAME_QUERY_LENGTH
+            ))
+        } else {
+            None
+        };
+        if let Some(e) = invalid {
+            audit::log_audit(
+                "find_symbols_by_name",
+                Outcome::Failure,
                 None,
                 Some(json!({ "error": e })),
             );
             return Err(Status::invalid_argument(e));
         }
+        let kind = match req.kind.as_deref() {
+            None => None,
+            Some(kind) => match kind.trim().to_ascii_lowercase().parse::<SymbolKind>() {
+                Ok(kind) => Some(kind),
+                Err(_) => {
+                    let e = format!(
+                        "unsupported kind '{}'; see GetCapabilities.symbol_kinds",
+                        kind
+                    );
+                    audit::log_audit(
+                        "find_symbols_by_name",
+                        Outcome::Failure,
+                        None,
+                        Some(json!({ "error": e })),
+                    );
+                    return Err(Status::invalid_argument(e));
+                }
+            },
+        };
 
-        let top_k = if req.top_k <= 0 {
-            5
-        } else if req.top_k > 100 {
+        let limit = if req.limit <= 0 {
+            20
+        } else if req.limit > 100 {
             100
         } else {
-            req.top_k as usize
+            req.limit as usize
         };
 
-        let symbols = self
-            .storage
-            .search_symbols(req.query, top_k, req.path_prefix, req.commit_id)
-            .await
-            .map_err(|e: StorageError| {
-                audit::log_audit(
-                    "search_symbols",
-                    "failure",
-                    None,
-                    Some(json!({ "error": e.to_string() })),
-                );
-                Status::from(e)
-            })?;
+        // Storage ranks and truncates before denied paths are dropped, so ask
+        // for more matches until `limit` are left or storage has no more.
+        let mut fetch = limit;
+        let symbols = loop {
+            let matches = match self
+                .storage
+                .find_symbols_by_name(query.to_string(), kind, fetch)
+                .await
+            {
+                Ok(matches) => matches,
+                Err(e) => {
+                    audit::log_audit(
+                        "find_symbols_by_name",
+                        Outcome::Failure,
+                        None,
+                        Some(json!({ "error": e.to_string() })),
+                    );
+                    return Err(Status::from(e));
+                }
+            };
+            let exhausted = matches.len() < fetch;
+            let mut symbols: Vec<ScoredSymbol> = matches
+                .into_iter()
+                .filter(|(symbol, _)| self.security_config.check_path(&symbol.path).is_ok())
+                .map(|(symbol, score)| ScoredSymbol {
+                    symbol: Some(symbol_to_proto(symbol)),
+                    score,
+                })
+                .collect();
+            if symbols.len() >= limit || exhausted || fetch >= MAX_NAME_MATCH_FETCH {
+                symbols.truncate(limit);
+                break symbols;
+            }
+            fetch = (fetch * NAME_MATCH_WIDEN_FACTOR).min(MAX_NAME_MATCH_FETCH);
+        };
+        let result_count = symbols.len();
 
-        let results: Vec<SearchResult> = symbols
-            .into_iter()
-            .map(|(symbol, score)| SearchResult {
-                id: symbol.id.to_string(),
-                path: symbol.path,
-                score,
-                snippet: if symbol.content.len() > 160 {
-                    format!("{}…", symbol.content.chars().take(157).collect::<String>())
-                } else {
-                    symbol.content
-                },
-                commit_id: symbol.commit_id,
-            })
-            .collect();
+        let response = FindSymbolsByNameResponse { symbols };
+        if let Err(e) =
+            self.check_response_size(&response, "reduce limit or use a more specific query")
+        {
+            audit::log_audit(
+                "find_symbols_by_name",
+                Outcome::Rejected,
+                None,
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::out_of_range(e));
+        }
 
         audit::log_audit(
-            "search_symbols",
-            "success",
+            "find_symbols_by_name",
+            Outcome::Success,
             None,
-            Some(json!({ "result_count": results.len() })),
+            Some(json!({ "result_count": result_count })),
         );
-        info!(count = results.len(), "Symbol search completed");
 
-        Ok(Response::new(SearchSymbolsResponse { results }))
+        Ok(Response::new(response))
     }
 
     #[instrument(skip(self, request))]
@@ -545,54 +2738,264 @@ impl IndexerService for IndexerServiceImpl {
         if let Err(e) = validate_path(&req.path) {
             return Err(Status::invalid_argument(e));
         }
+        let render_dot = match req.output_format.trim().to_ascii_lowercase().as_str() {
+            "" | "proto" => false,
+            "dot" => true,
+            other => {
+                return Err(Status::invalid_argument(format!(
+                    "unsupported output_format '{}'; expected proto or dot",
+                    other
+                )))
+            }
+        };
 
         // Note: get_file_content handles ACL check internally
         let content = self
             .get_file_content(&req.path, req.commit_id.as_deref())
             .await?;
 
-        // Determine language from path
-        let language = if req.path.ends_with(".rs") {
-            "rust"
-        } else if req.path.ends_with(".ts") || req.path.ends_with(".tsx") {
-            "typescript"
-        } else if req.path.ends_with(".js") || req.path.ends_with(".jsx") {
-            "javascript"
-        } else {
-            return Err(Status::invalid_argument("Unsupported language"));
+        // Determine language from path
+        let language = ast::language_for_path(&req.path)
+            .ok_or_else(|| Status::invalid_argument("Unsupported language"))?;
+
+        let tree = self
+            .trees
+            .parse(language, &content)
+            .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
+
+        let graph = analysis::analyze_graph(&tree, &content, &req.path, self.config.graph_limits);
+        if graph.truncated {
+            warn!(path = %req.path, edges = graph.edges.len(), "symbol graph truncated");
+        }
+
+        let response = if render_dot {
+            GetSymbolGraphResponse {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+                dot: Some(analysis::to_dot(&graph.nodes, &graph.edges)),
+                truncated: graph.truncated,
+            }
+        } else {
+            GetSymbolGraphResponse {
+                nodes: graph
+                    .nodes
+                    .into_iter()
+                    .map(|n| GraphNode {
+                        id: n.id,
+                        name: n.name,
+                        kind: n.kind,
+                        path: req.path.clone(),
+                    })
+                    .collect(),
+                edges: graph
+                    .edges
+                    .into_iter()
+                    .map(|e| GraphEdge {
+                        from_id: e.from_id,
+                        to_id: e.to_id,
+                        relation: e.relation,
+                    })
+                    .collect(),
+                dot: None,
+                truncated: graph.truncated,
+            }
+        };
+        self.check_response_size(&response, "request the graph for a smaller file")
+            .map_err(Status::out_of_range)?;
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_references(
+        &self,
+        request: Request<GetReferencesRequest>,
+    ) -> Result<Response<GetReferencesResponse>, Status> {
+        let req = request.into_inner();
+
+        // Validate path
+        if let Err(e) = validate_path(&req.path) {
+            return Err(Status::invalid_argument(e));
+        }
+        let match_mode =
+            analysis::MatchMode::parse(&req.match_mode).map_err(Status::invalid_argument)?;
+        // Compile an explicit pattern up front so a bad regex fails before any git work.
+        let explicit_matcher = req
+            .pattern
+            .as_deref()
+            .map(|pattern| analysis::IdentifierMatcher::new(match_mode, pattern))
+            .transpose()
+            .map_err(Status::invalid_argument)?;
+
+        let content = self
+            .get_file_content(&req.path, req.commit_id.as_deref())
+            .await?;
+
+        let language = ast::language_for_path(&req.path)
+            .ok_or_else(|| Status::invalid_argument("Unsupported language"))?;
+
+        let tree = self
+            .trees
+            .parse(language, &content)
+            .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
+
+        let position = analysis::LineLengths::new(&content).clamp(req.line, req.character);
+        // Set when the name came from the cursor, so references can follow
+        // the declaration visible there instead of every same-named identifier.
+        let mut cursor = None;
+        let matcher = match explicit_matcher {
+            Some(matcher) => matcher,
+            None => {
+                let (name, _) = analysis::identifier_at_position(&tree, &content, position.clone())
+                    .ok_or_else(|| Status::not_found("No identifier at position"))?;
+                cursor = Some(position);
+                analysis::IdentifierMatcher::new(match_mode, &name)
+                    .map_err(Status::invalid_argument)?
+            }
+        };
+
+        let mut locations = Vec::new();
+
+        // Declarations are only meaningful for a single exact name.
+        if req.include_declaration {
+            if let analysis::IdentifierMatcher::Exact(name) = &matcher {
+                let declaration = match &cursor {
+                    Some(position) => {
+                        analysis::declaration_in_scope(&tree, &content, name, position.clone())
+                            .map(|node| analysis::node_range(&node))
+                    }
+                    None => analysis::find_declaration(&tree, &content, name),
+                };
+                if let Some(range) = declaration {
+                    locations.push(to_location(&req.path, &range));
+                }
+            }
+        }
+
+        let refs = match (&matcher, cursor) {
+            (analysis::IdentifierMatcher::Exact(name), Some(position)) => {
+                analysis::find_scoped_references(&tree, &content, name, position)
+            }
+            _ => analysis::find_references(&tree, &content, &matcher),
+        };
+        locations.extend(refs.iter().map(|r| to_location(&req.path, r)));
+
+        if req.workspace {
+            // The origin file was read at this commit, so it is set.
+            let commit = req.commit_id.as_deref().unwrap_or_default();
+            locations.extend(
+                self.find_references_workspace(&req.path, commit, &matcher)
+                    .await?,
+            );
+        }
+        dedup_locations(&mut locations);
+
+        let response = GetReferencesResponse { locations };
+        self.check_response_size(&response, "narrow the lookup to a less common identifier")
+            .map_err(Status::out_of_range)?;
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_call_hierarchy(
+        &self,
+        request: Request<GetCallHierarchyRequest>,
+    ) -> Result<Response<GetCallHierarchyResponse>, Status> {
+        let req = request.into_inner();
+
+        if let Err(e) = validate_path(&req.path) {
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            return Err(Status::permission_denied(e.to_string()));
+        }
+        let direction = match proto::CallDirection::try_from(req.direction) {
+            Ok(proto::CallDirection::Incoming) => analysis::CallDirection::Incoming,
+            Ok(proto::CallDirection::Outgoing) => analysis::CallDirection::Outgoing,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "direction must be CALL_DIRECTION_INCOMING or CALL_DIRECTION_OUTGOING",
+                ))
+            }
+        };
+
+        let content = self
+            .get_file_content(&req.path, Some(&req.commit_id))
+            .await?;
+        let language = ast::language_for_path(&req.path)
+            .ok_or_else(|| Status::invalid_argument("Unsupported language"))?;
+        let tree = self
+            .trees
+            .parse(language, &content)
+            .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
+
+        let position = analysis::LineLengths::new(&content).clamp(req.line, req.character);
+        let limits = self.config.graph_limits;
+        let mut hierarchy = analysis::call_hierarchy(
+            &tree,
+            &content,
+            &req.path,
+            position.clone(),
+            direction,
+            limits,
+        );
+        let mut items: Vec<CallHierarchyItem> = hierarchy
+            .items
+            .drain(..)
+            .map(|item| call_hierarchy_item(&req.path, item))
+            .collect();
+
+        // Callers can live in any indexed file; callees are all in this one.
+        let incoming_target = match direction {
+            analysis::CallDirection::Incoming => {
+                analysis::call_target(&tree, &content, position).map(|(name, _)| name)
+            }
+            analysis::CallDirection::Outgoing => None,
+        };
+        if let Some(name) = incoming_target {
+            let matcher = analysis::IdentifierMatcher::Exact(name.clone());
+            let (files, capped) = self
+                .workspace_files(&req.path, &req.commit_id, &matcher)
+                .await?;
+            hierarchy.truncated |= capped;
+            for (path, content, tree) in files {
+                let found = analysis::incoming_calls(&tree, &content, &path, &name, limits);
+                hierarchy.truncated |= found.truncated;
+                items.extend(
+                    found
+                        .items
+                        .into_iter()
+                        .map(|item| call_hierarchy_item(&path, item)),
+                );
+            }
+        }
+        if items.len() > limits.max_edges {
+            items.truncate(limits.max_edges);
+            hierarchy.truncated = true;
+        }
+        if hierarchy.truncated {
+            warn!(path = %req.path, items = items.len(), "call hierarchy truncated");
+        }
+
+        let response = GetCallHierarchyResponse {
+            items,
+            truncated: hierarchy.truncated,
         };
+        self.check_response_size(
+            &response,
+            "request the hierarchy for a less common function",
+        )
+        .map_err(Status::out_of_range)?;
 
-        let (tree, _) = ast::parse_tree(language, &content)
-            .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
-
-        let (nodes, edges) = analysis::analyze_graph(&tree, &content, &req.path);
-
-        Ok(Response::new(GetSymbolGraphResponse {
-            nodes: nodes
-                .into_iter()
-                .map(|n| GraphNode {
-                    id: n.id,
-                    name: n.name,
-                    kind: n.kind,
-                    path: req.path.clone(),
-                })
-                .collect(),
-            edges: edges
-                .into_iter()
-                .map(|e| GraphEdge {
-                    from_id: e.from_id,
-                    to_id: e.to_id,
-                    relation: e.relation,
-                })
-                .collect(),
-        }))
+        Ok(Response::new(response))
     }
 
     #[instrument(skip(self, request))]
-    async fn get_references(
+    async fn get_definitions(
         &self,
-        request: Request<GetReferencesRequest>,
-    ) -> Result<Response<GetReferencesResponse>, Status> {
+        request: Request<GetDefinitionsRequest>,
+    ) -> Result<Response<GetDefinitionsResponse>, Status> {
         let req = request.into_inner();
 
         // Validate path
@@ -604,123 +3007,89 @@ impl IndexerService for IndexerServiceImpl {
             .get_file_content(&req.path, req.commit_id.as_deref())
             .await?;
 
-        let language = if req.path.ends_with(".rs") {
-            "rust"
-        } else if req.path.ends_with(".ts") || req.path.ends_with(".tsx") {
-            "typescript"
-        } else if req.path.ends_with(".js") || req.path.ends_with(".jsx") {
-            "javascript"
-        } else {
-            return Err(Status::invalid_argument("Unsupported language"));
-        };
+        let language = ast::language_for_path(&req.path)
+            .ok_or_else(|| Status::invalid_argument("Unsupported language"))?;
 
-        let (tree, _) = ast::parse_tree(language, &content)
+        let tree = self
+            .trees
+            .parse(language, &content)
             .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
 
-        let position = ast::Position {
-            line: req.line,
-            column: req.character,
-        };
+        let position = analysis::LineLengths::new(&content).clamp(req.line, req.character);
 
         let (name, _) = analysis::identifier_at_position(&tree, &content, position)
             .ok_or_else(|| Status::not_found("No identifier at position"))?;
 
-        let mut locations = Vec::new();
-
-        if req.include_declaration {
-            if let Some(range) = analysis::find_declaration(&tree, &content, &name) {
-                locations.push(Location {
-                    path: req.path.clone(),
-                    range: Some(Range {
-                        start: Some(Position {
-                            line: range.start.line,
-                            character: range.start.column,
-                        }),
-                        end: Some(Position {
-                            line: range.end.line,
-                            character: range.end.column,
-                        }),
-                    }),
-                });
-            }
-        }
-
-        let refs = analysis::find_references(&tree, &content, &name);
-        for r in refs {
-            locations.push(Location {
-                path: req.path.clone(),
-                range: Some(Range {
-                    start: Some(Position {
-                        line: r.start.line,
-                        character: r.start.column,
-                    }),
-                    end: Some(Position {
-                        line: r.end.line,
-                        character: r.end.column,
-                    }),
-                }),
-            });
-        }
+        let locations = analysis::find_declaration(&tree, &content, &name)
+            .map(|range| vec![to_location(&req.path, &range)])
+            .unwrap_or_default();
 
-        Ok(Response::new(GetReferencesResponse { locations }))
+        Ok(Response::new(GetDefinitionsResponse { locations }))
     }
 
     #[instrument(skip(self, request))]
-    async fn get_definitions(
+    async fn batch_get_definitions(
         &self,
-        request: Request<GetDefinitionsRequest>,
-    ) -> Result<Response<GetDefinitionsResponse>, Status> {
+        request: Request<BatchGetDefinitionsRequest>,
+    ) -> Result<Response<BatchGetDefinitionsResponse>, Status> {
         let req = request.into_inner();
 
-        // Validate path
         if let Err(e) = validate_path(&req.path) {
             return Err(Status::invalid_argument(e));
         }
+        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+            return Err(Status::invalid_argument(e));
+        }
+        if req.positions.is_empty() {
+            return Err(Status::invalid_argument("positions cannot be empty"));
+        }
+        if req.positions.len() > validation::MAX_BATCH_POSITIONS {
+            return Err(Status::invalid_argument(format!(
+                "positions exceeds maximum of {} entries",
+                validation::MAX_BATCH_POSITIONS
+            )));
+        }
 
         let content = self
             .get_file_content(&req.path, req.commit_id.as_deref())
             .await?;
 
-        let language = if req.path.ends_with(".rs") {
-            "rust"
-        } else if req.path.ends_with(".ts") || req.path.ends_with(".tsx") {
-            "typescript"
-        } else if req.path.ends_with(".js") || req.path.ends_with(".jsx") {
-            "javascript"
-        } else {
-            return Err(Status::invalid_argument("Unsupported language"));
-        };
+        let language = ast::language_for_path(&req.path)
+            .ok_or_else(|| Status::invalid_argument("Unsupported language"))?;
 
-        let (tree, _) = ast::parse_tree(language, &content)
+        // Parse once and resolve every position against the same tree.
+        let tree = self
+            .trees
+            .parse(language, &content)
             .map_err(|e| Status::internal(format!("Failed to parse AST: {}", e)))?;
 
-        let position = ast::Position {
-            line: req.line,
-            column: req.character,
-        };
-
-        let (name, _) = analysis::identifier_at_position(&tree, &content, position)
-            .ok_or_else(|| Status::not_found("No identifier at position"))?;
+        let lines = analysis::LineLengths::new(&content);
+        let positions: Vec<ast::Position> = req
+            .positions
+            .iter()
+            .map(|p| lines.clamp(p.line, p.character))
+            .collect();
+        let lookups = analysis::resolve_definitions(&tree, &content, &positions);
 
-        let mut locations = Vec::new();
+        let results = req
+            .positions
+            .into_iter()
+            .zip(lookups)
+            .map(|(position, lookup)| PositionDefinitions {
+                position: Some(position),
+                identifier: lookup.identifier,
+                locations: lookup
+                    .definition
+                    .map(|range| vec![to_location(&req.path, &range)])
+                    .unwrap_or_default(),
+            })
+            .collect();
 
-        if let Some(range) = analysis::find_declaration(&tree, &content, &name) {
-            locations.push(Location {
-                path: req.path.clone(),
-                range: Some(Range {
-                    start: Some(Position {
-                        line: range.start.line,
-                        character: range.start.column,
-                    }),
-                    end: Some(Position {
-                        line: range.end.line,
-                        character: range.end.column,
-                    }),
-                }),
-            });
-        }
+        let response = BatchGetDefinitionsResponse { results };
+        self.check_response_size(&response, "split the positions across several requests")
+            .map_err(Status::out_of_range)?;
 
-        Ok(Response::new(GetDefinitionsResponse { locations }))
+        Ok(Response::new(response))
     }
 
     #[instrument(skip(self, request))]
@@ -740,20 +3109,15 @@ impl IndexerService for IndexerServiceImpl {
             return Err(Status::permission_denied(e.to_string()));
         }
 
-        let history = self.temporal.get_symbol_history(&req.path);
+        let history = self
+            .temporal
+            .as_deref()
+            .ok_or_else(temporal_disabled)?
+            .get_symbol_history(&req.path)
+            .await
+            .map_err(temporal_status)?;
 
-        let versions = history
-            .into_iter()
-            .map(|v| SymbolVersion {
-                symbol_id: v.symbol_id.to_string(),
-                commit_id: v.commit_id,
-                timestamp: v.timestamp.to_rfc3339(),
-                change_type: format!("{:?}", v.change_type),
-                author: v.author,
-                commit_message: v.commit_message,
-                previous_path: v.previous_path,
-            })
-            .collect();
+        let versions = history.into_iter().map(version_to_proto).collect();
 
         Ok(Response::new(GetSymbolHistoryResponse { versions }))
     }
@@ -781,30 +3145,144 @@ impl IndexerService for IndexerServiceImpl {
 
         let symbol = self
             .temporal
+            .as_deref()
+            .ok_or_else(temporal_disabled)?
             .get_symbol_at_commit(&req.path, &req.commit_id)
             .await
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        let symbol_proto = symbol.map(|s| Symbol {
-            id: s.id.to_string(),
-            path: s.path,
-            name: s.name,
-            kind: s.kind,
-            content: s.content,
-            commit_id: s.commit_id.unwrap_or_default(),
-            start_line: s.start_line,
-            end_line: s.end_line,
-            language: s
-                .metadata
-                .as_ref()
-                .and_then(|m| m.get("language"))
-                .and_then(|l| l.as_str())
-                .unwrap_or("unknown")
-                .to_string(),
-        });
+            .map_err(temporal_status)?
+            .ok_or_else(|| file_not_found(&req.path, &req.commit_id))?;
 
         Ok(Response::new(GetSymbolAtCommitResponse {
-            symbol: symbol_proto,
+            symbol: Some(symbol_to_proto(symbol)),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_symbol_by_id(
+        &self,
+        request: Request<GetSymbolByIdRequest>,
+    ) -> Result<Response<GetSymbolByIdResponse>, Status> {
+        let req = request.into_inner();
+
+        let id = uuid::Uuid::parse_str(req.id.trim())
+            .map_err(|_| Status::invalid_argument("id must be a UUID"))?;
+
+        let symbol = self
+            .storage
+            .get_symbol(id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Symbol {} not found", id)))?;
+
+        if let Err(e) = self.security_config.check_path(&symbol.path) {
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        // Without temporal features the symbol is still served, just with no history.
+        let history = match &self.temporal {
+            Some(temporal) => temporal
+                .get_symbol_history(&symbol.path)
+                .await
+                .map_err(temporal_status)?,
+            None => Vec::new(),
+        };
+        let history = history.into_iter().map(version_to_proto).collect();
+
+        Ok(Response::new(GetSymbolByIdResponse {
+            symbol: Some(symbol_to_proto(symbol)),
+            history,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_blame(
+        &self,
+        request: Request<GetBlameRequest>,
+    ) -> Result<Response<GetBlameResponse>, Status> {
+        let req = request.into_inner();
+
+        if let Err(e) = validate_path(&req.path) {
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = validate_commit_id(req.commit_id.as_ref()) {
+            return Err(Status::invalid_argument(e));
+        }
+        let lines = match (req.start_line, req.end_line) {
+            (None, None) => None,
+            (start, end) => Some(LineRange {
+                start: start.unwrap_or(0) as usize,
+                end: end.map_or(usize::MAX, |end| end as usize),
+            }),
+        };
+        if let Some(range) = lines.filter(|range| range.start > range.end) {
+            return Err(Status::invalid_argument(format!(
+                "start_line {} is after end_line {}",
+                range.start, range.end
+            )));
+        }
+
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let commit = req.commit_id.as_deref();
+        let blamed = self
+            .temporal
+            .as_deref()
+            .ok_or_else(temporal_disabled)?
+            .blame(&req.path, commit, lines)
+            .await
+            .map_err(temporal_status)?
+            .ok_or_else(|| file_not_found(&req.path, commit.unwrap_or("HEAD")))?;
+
+        Ok(Response::new(GetBlameResponse {
+            lines: blamed
+                .into_iter()
+                .map(|blamed| proto::BlameLine {
+                    line: blamed.line as u32,
+                    commit_id: blamed.commit_id,
+                    author: blamed.author,
+                    author_email: blamed.author_email,
+                    timestamp: blamed.timestamp.to_rfc3339(),
+                })
+                .collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_co_changes(
+        &self,
+        request: Request<GetCoChangesRequest>,
+    ) -> Result<Response<GetCoChangesResponse>, Status> {
+        let req = request.into_inner();
+
+        if let Err(e) = validate_path(&req.path) {
+            return Err(Status::invalid_argument(e));
+        }
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let (commits, related) = self
+            .temporal
+            .as_deref()
+            .ok_or_else(temporal_disabled)?
+            .co_changes(&req.path, req.min_support as usize)
+            .await
+            .map_err(temporal_status)?;
+
+        Ok(Response::new(GetCoChangesResponse {
+            related: related
+                .into_iter()
+                .filter(|related| self.security_config.check_path(&related.path).is_ok())
+                .take(MAX_CO_CHANGES)
+                .map(|related| proto::CoChange {
+                    path: related.path,
+                    support: related.support as u32,
+                    confidence: related.confidence,
+                })
+                .collect(),
+            commits: commits as u32,
         }))
     }
 
@@ -824,32 +3302,26 @@ impl IndexerService for IndexerServiceImpl {
 
         let suspects = self
             .temporal
+            .as_deref()
+            .ok_or_else(temporal_disabled)?
             .correlate_ci_failure(
                 &req.test_name,
                 &req.failure_message,
                 &req.commit_id,
                 req.previous_commit_id.as_deref(),
+                req.semantic.unwrap_or(false),
             )
             .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .map_err(temporal_status)?;
 
         let suspects_proto = suspects
             .into_iter()
             .map(|s| SuspectChange {
-                symbol: Some(Symbol {
-                    id: s.symbol.id.to_string(),
-                    path: s.symbol.path,
-                    name: s.symbol.name,
-                    kind: s.symbol.kind,
-                    content: s.symbol.content,
-                    commit_id: s.symbol.commit_id.unwrap_or_default(),
-                    start_line: s.symbol.start_line,
-                    end_line: s.symbol.end_line,
-                    language: "unknown".to_string(), // Simplified
-                }),
+                symbol: Some(symbol_to_proto(s.symbol)),
                 relevance_score: s.relevance_score,
                 reason: s.reason,
-                change_type: format!("{:?}", s.change_type),
+                change_type: s.change_type.as_str().to_string(),
+                change: proto::ChangeType::from(s.change_type) as i32,
             })
             .collect();
 
@@ -857,4 +3329,312 @@ impl IndexerService for IndexerServiceImpl {
             suspects: suspects_proto,
         }))
     }
+
+    #[instrument(skip(self, _request))]
+    async fn get_health(
+        &self,
+        _request: Request<GetHealthRequest>,
+    ) -> Result<Response<GetHealthResponse>, Status> {
+        let mut components = Vec::new();
+        if let Some(manager) = &self.embedding_manager {
+            let result = manager.health_check().await;
+            components.push(ComponentHealth {
+                name: "embedding".to_string(),
+                healthy: result.is_ok(),
+                provider: manager.provider_name().to_string(),
+                detail: result.err().map(|e| e.to_string()).unwrap_or_default(),
+            });
+        }
+
+        Ok(Response::new(GetHealthResponse {
+            ready: components.iter().all(|component| component.healthy),
+            components,
+        }))
+    }
+
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<GetCapabilitiesResponse>, Status> {
+        let features = [
+            ("embeddings", self.embedding_manager.is_some()),
+            ("temporal", self.temporal.is_some()),
+            (
+                "semantic_correlation",
+                self.temporal
+                    .as_ref()
+                    .is_some_and(|temporal| temporal.has_embedder()),
+            ),
+            ("metadata_filters", true),
+            ("dedup", true),
+            ("hybrid_search", true),
+            ("batch_indexing", true),
+            ("tree_cache", self.config.tree_cache_entries > 0),
+            ("snippet_redaction", self.security_config.redact_snippets()),
+        ]
+        .into_iter()
+        .map(|(name, enabled)| (name.to_string(), enabled))
+        .collect();
+
+        Ok(Response::new(GetCapabilitiesResponse {
+            languages: ast::enabled_languages()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            symbol_kinds: SymbolKind::ALL.iter().map(|k| k.to_string()).collect(),
+            embedding_model: self.embedding_model_name(),
+            embedding_dimension: self.embedding_dimension().await as u32,
+            features,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_by_commit(
+        &self,
+        request: Request<DeleteByCommitRequest>,
+    ) -> Result<Response<DeleteByCommitResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "delete_by_commit",
+            Outcome::Attempt,
+            None,
+            Some(json!({ "commit_id": req.commit_id })),
+        );
+
+        let validation = if req.commit_id.trim().is_empty() {
+            Err("commit_id cannot be blank".to_string())
+        } else {
+            validate_commit_id(Some(&req.commit_id))
+        };
+        if let Err(e) = validation {
+            audit::log_audit(
+                "delete_by_commit",
+                Outcome::Failure,
+                None,
+                Some(json!({ "commit_id": req.commit_id, "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        let deleted = self
+            .storage
+            .delete_by_commit(req.commit_id.clone())
+            .await
+            .map_err(|e: StorageError| {
+                audit::log_audit(
+                    "delete_by_commit",
+                    Outcome::Failure,
+                    None,
+                    Some(json!({ "commit_id": req.commit_id, "error": e.to_string() })),
+                );
+                Status::from(e)
+            })?;
+        if let Some(temporal) = &self.temporal {
+            temporal.invalidate_history();
+        }
+
+        audit::log_audit(
+            "delete_by_commit",
+            Outcome::Success,
+            None,
+            Some(json!({
+                "commit_id": req.commit_id,
+                "documents": deleted.documents,
+                "symbols": deleted.symbols,
+                "symbol_versions": deleted.symbol_versions,
+            })),
+        );
+        info!(commit_id = %req.commit_id, ?deleted, "Deleted index entries for commit");
+
+        Ok(Response::new(DeleteByCommitResponse {
+            documents_deleted: deleted.documents as i32,
+            symbols_deleted: deleted.symbols as i32,
+            symbol_versions_deleted: deleted.symbol_versions as i32,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_symbols(
+        &self,
+        request: Request<DeleteSymbolsRequest>,
+    ) -> Result<Response<DeleteSymbolsResponse>, Status> {
+        let req = request.into_inner();
+
+        audit::log_audit(
+            "delete_symbols",
+            Outcome::Attempt,
+            Some(&req.path),
+            Some(json!({ "commit_id": req.commit_id })),
+        );
+
+        let validation =
+            validate_path(&req.path).and_then(|()| validate_commit_id(req.commit_id.as_ref()));
+        if let Err(e) = validation {
+            audit::log_audit(
+                "delete_symbols",
+                Outcome::Failure,
+                Some(&req.path),
+                Some(json!({ "error": e })),
+            );
+            return Err(Status::invalid_argument(e));
+        }
+
+        if let Err(e) = self.security_config.check_path(&req.path) {
+            audit::log_audit(
+                "delete_symbols",
+                Outcome::Denied,
+                Some(&req.path),
+                Some(e.audit_details()),
+            );
+            return Err(Status::permission_denied(e.to_string()));
+        }
+
+        let PathDeletion { documents, symbols } = match self
+            .storage
+            .delete_path(req.path.clone(), req.commit_id.clone())
+            .await
+        {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                audit::log_audit(
+                    "delete_symbols",
+                    Outcome::Failure,
+                    Some(&req.path),
+                    Some(json!({ "error": e.to_string() })),
+                );
+                return Err(Status::from(e));
+            }
+        };
+        if let Some(registry) = &self.symbol_registry {
+            registry
+                .remove_path(&req.path, req.commit_id.as_deref())
+                .await;
+        }
+
+        audit::log_audit(
+            "delete_symbols",
+            Outcome::Success,
+            Some(&req.path),
+            Some(json!({
+                "commit_id": req.commit_id,
+                "symbols": symbols,
+                "documents": documents,
+            })),
+        );
+        info!(path = %req.path, symbols, documents, "Deleted index entries for path");
+
+        Ok(Response::new(DeleteSymbolsResponse {
+            symbols_deleted: symbols as i32,
+            documents_deleted: documents as i32,
+        }))
+    }
+
+    async fn repair_index(
+        &self,
+        request: Request<RepairIndexRequest>,
+    ) -> Result<Response<RepairIndexResponse>, Status> {
+        let delete = request.into_inner().delete;
+
+        audit::log_audit(
+            "repair_index",
+            Outcome::Attempt,
+            None,
+            Some(json!({ "delete": delete })),
+        );
+
+        let result = if delete {
+            self.storage.delete_orphans().await
+        } else {
+            self.storage.find_orphans().await
+        };
+        let report = result.map_err(|e: StorageError| {
+            audit::log_audit(
+                "repair_index",
+                Outcome::Failure,
+                None,
+                Some(json!({ "delete": delete, "error": e.to_string() })),
+            );
+            Status::from(e)
+        })?;
+
+        audit::log_audit(
+            "repair_index",
+            Outcome::Success,
+            None,
+            Some(json!({
+                "delete": delete,
+                "symbols_without_document": report.symbols_without_document,
+                "dangling_edges": report.dangling_edges,
+                "invalid_embeddings": report.invalid_embeddings,
+            })),
+        );
+        if report.total() > 0 {
+            info!(delete, ?report, "Found orphaned index rows");
+        }
+
+        Ok(Response::new(RepairIndexResponse {
+            symbols_without_document: report.symbols_without_document as i32,
+            dangling_edges: report.dangling_edges as i32,
+            invalid_embeddings: report.invalid_embeddings as i32,
+            deleted: delete,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_types_map_to_proto_values() {
+        let cases = [
+            (ChangeType::Added, proto::ChangeType::Added, "Added"),
+            (
+                ChangeType::Modified,
+                proto::ChangeType::Modified,
+                "Modified",
+            ),
+            (ChangeType::Deleted, proto::ChangeType::Deleted, "Deleted"),
+            (ChangeType::Renamed, proto::ChangeType::Renamed, "Renamed"),
+        ];
+        for (change, expected, label) in cases {
+            assert_eq!(proto::ChangeType::from(change), expected);
+            assert_eq!(change.as_str(), label);
+        }
+    }
+
+    #[test]
+    fn symbol_kinds_map_to_proto_values() {
+        let cases = [
+            (SymbolKind::Function, proto::SymbolKind::Function),
+            (SymbolKind::Class, proto::SymbolKind::Class),
+            (SymbolKind::Interface, proto::SymbolKind::Interface),
+            (SymbolKind::Enum, proto::SymbolKind::Enum),
+            (SymbolKind::Constant, proto::SymbolKind::Constant),
+            (SymbolKind::Variable, proto::SymbolKind::Variable),
+            (SymbolKind::Type, proto::SymbolKind::Type),
+            (SymbolKind::Module, proto::SymbolKind::Module),
+            (SymbolKind::Method, proto::SymbolKind::Method),
+            (SymbolKind::Property, proto::SymbolKind::Property),
+            (SymbolKind::Namespace, proto::SymbolKind::Namespace),
+            (SymbolKind::Trait, proto::SymbolKind::Trait),
+            (SymbolKind::Impl, proto::SymbolKind::Impl),
+            (SymbolKind::Struct, proto::SymbolKind::Struct),
+        ];
+        for (kind, expected) in cases {
+            assert_eq!(proto::SymbolKind::from(kind), expected);
+            // Stored symbols carry the Display form; it must round-trip too.
+            assert_eq!(symbol_kind_to_proto(&kind.to_string()), expected);
+        }
+    }
+
+    #[test]
+    fn stored_kind_strings_without_a_variant() {
+        assert_eq!(symbol_kind_to_proto("File"), proto::SymbolKind::File);
+        assert_eq!(
+            symbol_kind_to_proto("function_declaration"),
+            proto::SymbolKind::Unspecified
+        );
+    }
 }
@@ -0,0 +1,222 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(600);
+pub const DEFAULT_IDEMPOTENCY_MAX_KEYS: usize = 10_000;
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 256;
+
+/// Outcome of looking up an idempotency key.
+#[derive(Debug, PartialEq)]
+pub enum Lookup<V> {
+    /// Key not seen (or expired); the write should proceed.
+    Miss,
+    /// Key seen with the same request; replay the stored result.
+    Hit(V),
+    /// Key seen with a different request payload.
+    Conflict,
+}
+
+struct Entry<V> {
+    fingerprint: String,
+    value: V,
+    expires_at: Instant,
+    sequence: u64,
+}
+
+struct Entries<V> {
+    by_key: HashMap<String, Entry<V>>,
+    next_sequence: u64,
+}
+
+/// Remembers the results of recent writes so retried requests carrying the same
+/// idempotency key return the original result instead of re-indexing.
+///
+/// Only successful results are recorded. Two requests racing with the same key
+/// can both miss; the cache makes retries safe, not concurrent duplicates.
+pub struct IdempotencyCache<V> {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Entries<V>>,
+}
+
+impl<V: Clone> IdempotencyCache<V> {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(Entries {
+                by_key: HashMap::new(),
+                next_sequence: 0,
+            }),
+        }
+    }
+
+    /// A zero TTL disables the cache entirely.
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    pub fn lookup(&self, key: &str, fingerprint: &str) -> Lookup<V> {
+        if !self.is_enabled() {
+            return Lookup::Miss;
+        }
+
+        let entries = &mut self.entries.lock().by_key;
+        let lookup = match entries.get(key) {
+            Some(entry) if entry.expires_at <= Instant::now() => None,
+            Some(entry) if entry.fingerprint == fingerprint => {
+                Some(Lookup::Hit(entry.value.clone()))
+            }
+            Some(_) => Some(Lookup::Conflict),
+            None => Some(Lookup::Miss),
+        };
+
+        lookup.unwrap_or_else(|| {
+            entries.remove(key);
+            Lookup::Miss
+        })
+    }
+
+    pub fn record(&self, key: &str, fingerprint: &str, value: V) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut guard = self.entries.lock();
+        let sequence = guard.next_sequence;
+        guard.next_sequence += 1;
+
+        let entries = &mut guard.by_key;
+        if entries.len() >= self.max_entries && !entries.contains_key(key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.sequence)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            key.to_string(),
+            Entry {
+                fingerprint: fingerprint.to_string(),
+                value,
+                expires_at: now + self.ttl,
+                sequence,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Hashes the request fields that define "the same write", so a key reused for
+/// a different payload can be detected.
+pub fn fingerprint(parts: &[Option<&str>]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        match part {
+            Some(value) => {
+                hasher.update([1u8]);
+                hasher.update((value.len() as u64).to_le_bytes());
+                hasher.update(value.as_bytes());
+            }
+            None => hasher.update([0u8]),
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("idempotency_key cannot be blank".to_string());
+    }
+    if key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(format!(
+            "idempotency_key exceeds maximum length of {} characters",
+            MAX_IDEMPOTENCY_KEY_LENGTH
+        ));
+    }
+    if key.chars().any(char::is_control) {
+        return Err("idempotency_key contains invalid control characters".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_matching_request() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 8);
+        let fp = fingerprint(&[Some("src/lib.rs"), Some("fn main() {}"), None]);
+
+        assert_eq!(cache.lookup("key-1", &fp), Lookup::Miss);
+        cache.record("key-1", &fp, 42u32);
+        assert_eq!(cache.lookup("key-1", &fp), Lookup::Hit(42));
+    }
+
+    #[test]
+    fn flags_key_reuse_with_different_payload() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 8);
+        let first = fingerprint(&[Some("src/lib.rs"), Some("a")]);
+        let second = fingerprint(&[Some("src/lib.rs"), Some("b")]);
+
+        cache.record("key-1", &first, 1u32);
+        assert_eq!(cache.lookup("key-1", &second), Lookup::Conflict);
+    }
+
+    #[test]
+    fn fingerprint_distinguishes_missing_from_empty() {
+        assert_ne!(fingerprint(&[None]), fingerprint(&[Some("")]));
+        assert_ne!(
+            fingerprint(&[Some("ab"), Some("c")]),
+            fingerprint(&[Some("a"), Some("bc")])
+        );
+    }
+
+    #[test]
+    fn evicts_oldest_entry_when_full() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 2);
+        cache.record("a", "fp", 1u32);
+        cache.record("b", "fp", 2u32);
+        cache.record("c", "fp", 3u32);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.lookup("a", "fp"), Lookup::Miss);
+        assert_eq!(cache.lookup("c", "fp"), Lookup::Hit(3));
+    }
+
+    #[test]
+    fn zero_ttl_disables_cache() {
+        let cache = IdempotencyCache::new(Duration::ZERO, 8);
+        cache.record("a", "fp", 1u32);
+        assert!(cache.is_empty());
+        assert_eq!(cache.lookup("a", "fp"), Lookup::Miss);
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(validate_key("retry-7f3a").is_ok());
+        assert!(validate_key("  ").is_err());
+        assert!(validate_key("bad\nkey").is_err());
+        assert!(validate_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1)).is_err());
+    }
+}
@@ -1,22 +1,36 @@
 // Library exports for the indexer service
 
 pub mod analysis; // Added
+pub mod archive;
 pub mod ast;
 pub mod audit;
+pub mod auth;
+pub mod clock;
+pub mod embedding_cache;
 pub mod embeddings;
+pub mod features;
+pub mod idempotency;
 pub mod lsp;
+pub mod normalize;
+pub mod page_token;
+pub mod precision;
 pub mod request_context;
 pub mod security;
+pub mod similarity;
 // pub mod semantic;
 pub mod grpc_service;
 pub mod storage;
 pub mod symbol_extractor;
+pub mod symbol_metadata;
 pub mod symbol_registry;
 pub mod telemetry;
 pub mod temporal;
+pub mod tree_cache;
 pub mod validation;
 
 // Re-export commonly used types
-pub use embeddings::{EmbeddingConfig, EmbeddingManager, EmbeddingProvider};
+pub use embeddings::{
+    EmbeddingConfig, EmbeddingDevice, EmbeddingManager, EmbeddingProvider, LocalModelOptions,
+};
 // pub use semantic::{SemanticConfig, SemanticStore};
 pub use storage::{IndexStorage, StorageConfig, StorageError, StoredDocument, StoredSymbol};
@@ -18,7 +18,7 @@ use tower_lsp::{lsp_types, Client, LanguageServer, LspService, Server};
 
 use tracing::{error, info, warn};
 
-use crate::ast;
+use crate::{analysis, ast};
 
 const LSP_DEFAULT_ADDR: &str = "127.0.0.1:9257";
 const LSP_ACCEPT_TIMEOUT_MS: u64 = 1000;
@@ -183,11 +183,9 @@ impl LanguageServer for Backend {
 
         if let Some(node) = node_at_position(&document, position) {
             let range = to_lsp_range(node.range());
-            let snippet = node
-                .utf8_text(document.text.as_bytes())
-                .unwrap_or_default()
-                .trim()
-                .to_string();
+            let text = node.utf8_text(document.text.as_bytes()).unwrap_or_default();
+            let snippet = analysis::truncate_lines(text.trim(), analysis::MAX_SNIPPET_LINE_CHARS)
+                .into_owned();
             let contents = if snippet.is_empty() {
                 format!("Node kind: {}", node.kind())
             } else {
@@ -272,9 +270,11 @@ fn parse_document(language_id: &str, text: &str) -> Result<tree_sitter::Tree, as
 }
 
 fn node_at_position(document: &Document, position: Position) -> Option<tree_sitter::Node<'_>> {
+    let position =
+        analysis::LineLengths::new(&document.text).clamp(position.line, position.character);
     let point = tree_sitter::Point {
         row: position.line as usize,
-        column: position.character as usize,
+        column: position.column as usize,
     };
     document
         .tree
@@ -287,6 +287,9 @@ fn identifier_at_position(
     position: Position,
 ) -> Option<(String, tree_sitter::Node<'_>)> {
     let node = node_at_position(document, position)?;
+    if analysis::is_within_literal(&node) {
+        return None;
+    }
     let identifier_node = if is_identifier(&node) {
         node
     } else {
@@ -307,7 +310,7 @@ fn identifier_at_position(
         .trim()
         .to_string();
 
-    if text.is_empty() {
+    if !analysis::is_valid_identifier(&text) {
         return None;
     }
 
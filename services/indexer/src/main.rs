@@ -1,18 +1,30 @@
 mod analysis;
+mod archive;
 mod ast;
 mod audit;
+mod auth;
+mod clock;
+mod embedding_cache;
 mod embeddings;
+mod features;
 mod grpc_service;
+mod idempotency;
 mod lsp;
+mod normalize;
+mod page_token;
+mod precision;
 mod request_context;
 mod security;
+mod similarity;
 // mod semantic;
 mod server;
 mod storage;
 mod symbol_extractor;
+mod symbol_metadata;
 mod symbol_registry;
 mod telemetry;
 mod temporal;
+mod tree_cache;
 mod validation;
 
 #[tokio::main]
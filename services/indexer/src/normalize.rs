@@ -0,0 +1,291 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use std::env;
+
+use sha2::{Digest, Sha256};
+
+use crate::ast;
+
+/// Optional clean-up applied to file content before it is hashed, parsed or
+/// embedded, so checkouts that differ only in line endings or trailing
+/// whitespace index identically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentNormalization {
+    /// Rewrite `\r\n` and lone `\r` as `\n`.
+    pub line_endings: bool,
+    /// Drop spaces and tabs at the end of each line.
+    pub trailing_whitespace: bool,
+}
+
+impl ContentNormalization {
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false)
+        };
+
+        Self {
+            line_endings: flag("INDEXER_NORMALIZE_LINE_ENDINGS"),
+            trailing_whitespace: flag("INDEXER_STRIP_TRAILING_WHITESPACE"),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.line_endings || self.trailing_whitespace
+    }
+
+    pub fn apply<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        let mut content = Cow::Borrowed(content);
+
+        if self.line_endings && content.contains('\r') {
+            content = Cow::Owned(content.replace("\r\n", "\n").replace('\r', "\n"));
+        }
+
+        if self.trailing_whitespace {
+            let needs_trim = content.split('\n').any(|line| line.ends_with([' ', '\t']));
+            if needs_trim {
+                let trimmed = content
+                    .split('\n')
+                    .map(|line| line.trim_end_matches([' ', '\t']))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                content = Cow::Owned(trimmed);
+            }
+        }
+
+        content
+    }
+}
+
+/// Optional clean-up applied to search queries before they are embedded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryPreprocessing {
+    /// Drop markdown code fences with their language tags, inline backticks
+    /// and a leading language-name line, as left by queries pasted from chat.
+    pub strip_markdown: bool,
+}
+
+impl QueryPreprocessing {
+    pub fn from_env() -> Self {
+        Self {
+            strip_markdown: env::var("INDEXER_QUERY_STRIP_MARKDOWN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn apply<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        if self.strip_markdown {
+            Cow::Owned(strip_markdown(query))
+        } else {
+            Cow::Borrowed(query)
+        }
+    }
+}
+
+/// Text a symbol is embedded as, in place of its raw content.
+///
+/// Placeholders `{kind}`, `{name}`, `{signature}`, `{doc}` and `{body}` are
+/// substituted; missing values become empty strings. With no template the
+/// body is embedded unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddingTemplate {
+    template: Option<String>,
+}
+
+/// Parts of a symbol an [`EmbeddingTemplate`] can refer to.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemplateFields<'a> {
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub signature: Option<&'a str>,
+    pub doc: Option<&'a str>,
+    pub body: &'a str,
+}
+
+impl EmbeddingTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        let template = template.into();
+        Self {
+            template: (!template.trim().is_empty()).then_some(template),
+        }
+    }
+
+    /// Reads `INDEXER_SYMBOL_EMBED_TEMPLATE`. A literal `\n` in the value is a
+    /// newline, since most deployment tooling cannot put one in an env var.
+    pub fn from_env() -> Self {
+        env::var("INDEXER_SYMBOL_EMBED_TEMPLATE")
+            .map(|v| Self::new(v.replace("\\n", "\n")))
+            .unwrap_or_default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.template.is_some()
+    }
+
+    pub fn render<'a>(&self, fields: TemplateFields<'a>) -> Cow<'a, str> {
+        let Some(template) = &self.template else {
+            return Cow::Borrowed(fields.body);
+        };
+        // One pass, so placeholder text inside a doc comment or body is not
+        // itself substituted.
+        let mut rendered = String::with_capacity(template.len() + fields.body.len());
+        let mut rest = template.as_str();
+        while let Some(open) = rest.find('{') {
+            rendered.push_str(&rest[..open]);
+            let after = &rest[open..];
+            let value = [
+                ("{kind}", fields.kind),
+                ("{name}", fields.name),
+                ("{signature}", fields.signature.unwrap_or_default()),
+                ("{doc}", fields.doc.unwrap_or_default()),
+                ("{body}", fields.body),
+            ]
+            .into_iter()
+            .find(|(placeholder, _)| after.starts_with(placeholder));
+            match value {
+                Some((placeholder, value)) => {
+                    rendered.push_str(value);
+                    rest = &after[placeholder.len()..];
+                }
+                None => {
+                    rendered.push('{');
+                    rest = &after[1..];
+                }
+            }
+        }
+        rendered.push_str(rest);
+        Cow::Owned(rendered.trim().to_string())
+    }
+}
+
+/// Removes markdown wrapping from `query`, keeping fenced code verbatim.
+/// Outside fences every backtick is dropped.
+pub fn strip_markdown(query: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in query.lines() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                // A fence opened and closed on one line is inline code.
+                let rest = &trimmed[marker.len()..];
+                match rest.trim_end().strip_suffix(marker) {
+                    Some(inner) if !inner.trim().is_empty() => lines.push(inner.trim().to_string()),
+                    _ => fence = Some(marker),
+                }
+            }
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            (Some(_), _) => lines.push(line.to_string()),
+            (None, None) => lines.push(line.replace('`', "")),
+        }
+    }
+
+    // A first line that only names a language is a hint, unless it is all
+    // there is.
+    let start = lines
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .unwrap_or(lines.len());
+    let is_hint = lines
+        .get(start)
+        .is_some_and(|line| ast::canonical_language(line).is_some())
+        && lines[start + 1..]
+            .iter()
+            .any(|line| !line.trim().is_empty());
+    let start = start + is_hint as usize;
+
+    lines[start..].join("\n").trim().to_string()
+}
+
+/// Hex SHA-256 of `content`, used to tell whether a file really changed.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: ContentNormalization = ContentNormalization {
+        line_endings: true,
+        trailing_whitespace: true,
+    };
+
+    #[test]
+    fn crlf_and_lf_hash_the_same_when_normalized() {
+        let lf = "fn main() {\n    run();\n}\n";
+        let crlf = "fn main() {\r\n    run();  \r\n}\r\n";
+
+        assert_eq!(content_hash(&ALL.apply(lf)), content_hash(&ALL.apply(crlf)));
+
+        let off = ContentNormalization::default();
+        assert_ne!(content_hash(&off.apply(lf)), content_hash(&off.apply(crlf)));
+    }
+
+    #[test]
+    fn leaves_clean_content_borrowed() {
+        let content = "line one\nline two\n";
+        assert!(matches!(ALL.apply(content), Cow::Borrowed(_)));
+        assert_eq!(ALL.apply("a\rb"), "a\nb");
+        assert_eq!(ALL.apply("keep\t\n  indent"), "keep\n  indent");
+    }
+
+    #[test]
+    fn strips_fences_inline_backticks_and_language_hints() {
+        let code = "fn foo() -> u32 {\n    1\n}";
+        assert_eq!(strip_markdown(&format!("```rust\n{}\n```", code)), code);
+        assert_eq!(strip_markdown(&format!("~~~\n{}\n~~~\n", code)), code);
+        assert_eq!(strip_markdown(&format!("rust\n{}", code)), code);
+        assert_eq!(
+            strip_markdown("where is `parse_config` called"),
+            "where is parse_config called"
+        );
+        assert_eq!(strip_markdown("```fn foo()```"), "fn foo()");
+        // Backticks inside a fence are code.
+        assert_eq!(
+            strip_markdown("```js\nconst s = `x`;\n```"),
+            "const s = `x`;"
+        );
+        // A lone language name is the query, not a hint.
+        assert_eq!(strip_markdown("rust"), "rust");
+    }
+
+    #[test]
+    fn template_substitutes_each_placeholder_once() {
+        let fields = TemplateFields {
+            kind: "function",
+            name: "run",
+            signature: Some("fn run()"),
+            doc: None,
+            body: "let s = \"{name}\";",
+        };
+        assert_eq!(
+            EmbeddingTemplate::new("{kind} {signature} {unknown} {doc}\n{body}").render(fields),
+            "function fn run() {unknown} \nlet s = \"{name}\";"
+        );
+        assert!(matches!(
+            EmbeddingTemplate::default().render(fields),
+            Cow::Borrowed("let s = \"{name}\";")
+        ));
+    }
+
+    #[test]
+    fn query_preprocessing_is_off_by_default() {
+        let query = "```rust\nfn foo()\n```";
+        assert_eq!(QueryPreprocessing::default().apply(query), query);
+        let on = QueryPreprocessing {
+            strip_markdown: true,
+        };
+        assert_eq!(on.apply(query), "fn foo()");
+    }
+}
@@ -0,0 +1,168 @@
+#![allow(dead_code)]
+
+use std::env;
+
+use half::f16;
+use pgvector::{HalfVector, Vector};
+
+/// How embeddings are narrowed before they are written to Postgres.
+///
+/// `F32` keeps the full `vector` column. `F16` writes to the `halfvec`
+/// column added in migration 004, which halves the on-disk size and the HNSW
+/// index. pgvector has no 8-bit vector type, so there is no int8 option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingPrecision {
+    #[default]
+    F32,
+    F16,
+}
+
+/// Embedding written in one of the two storage columns; the other is NULL.
+#[derive(Debug, Clone, Default)]
+pub struct StoredEmbedding {
+    pub full: Option<Vector>,
+    pub half: Option<HalfVector>,
+}
+
+impl EmbeddingPrecision {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "f32" | "float32" => Ok(Self::F32),
+            "f16" | "float16" | "half" => Ok(Self::F16),
+            other => Err(format!(
+                "unknown EMBEDDING_STORAGE_PRECISION '{}'; expected f32 or f16",
+                other
+            )),
+        }
+    }
+
+    /// Reads `EMBEDDING_STORAGE_PRECISION`, `F32` when unset. An unrecognised
+    /// value is an error rather than silently storing at another precision.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("EMBEDDING_STORAGE_PRECISION") {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::F32 => "f32",
+            Self::F16 => "f16",
+        }
+    }
+
+    /// Column the embedding is written to and searched against.
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::F32 => "embedding_vector",
+            Self::F16 => "embedding_half",
+        }
+    }
+
+    pub fn is_reduced(&self) -> bool {
+        !matches!(self, Self::F32)
+    }
+
+    /// Values as they will be stored in `embedding_half`. Only meaningful for
+    /// `F16`; `F32` embeddings go to `embedding_vector` as-is.
+    pub fn encode(&self, embedding: &[f32]) -> Vec<f16> {
+        embedding.iter().copied().map(f16::from_f32).collect()
+    }
+
+    /// Splits an embedding across the two storage columns. An empty embedding
+    /// (a symbol skipped as trivial) is NULL in both.
+    pub fn store(&self, embedding: &[f32]) -> StoredEmbedding {
+        if embedding.is_empty() {
+            return StoredEmbedding::default();
+        }
+        match self {
+            Self::F32 => StoredEmbedding {
+                full: Some(Vector::from(embedding.to_vec())),
+                half: None,
+            },
+            Self::F16 => StoredEmbedding {
+                full: None,
+                half: Some(HalfVector::from(self.encode(embedding))),
+            },
+        }
+    }
+
+    /// What a stored embedding reads back as, in the original scale.
+    pub fn round_trip(&self, embedding: &[f32]) -> Vec<f32> {
+        match self {
+            Self::F32 => embedding.to_vec(),
+            Self::F16 => embedding
+                .iter()
+                .map(|&v| f16::from_f32(v).to_f32())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (na * nb)
+    }
+
+    /// Deterministic unit vector with some spread, shaped like a sentence
+    /// embedding.
+    fn sample(seed: u32, dim: usize) -> Vec<f32> {
+        let v: Vec<f32> = (0..dim)
+            .map(|i| (i as f32 * 0.37 + seed as f32 * 1.13).sin() * 0.5)
+            .collect();
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        v.into_iter().map(|x| x / norm).collect()
+    }
+
+    #[test]
+    fn parses_precision_names() {
+        assert_eq!(
+            EmbeddingPrecision::parse("").unwrap(),
+            EmbeddingPrecision::F32
+        );
+        assert_eq!(
+            EmbeddingPrecision::parse("F16").unwrap(),
+            EmbeddingPrecision::F16
+        );
+        assert!(EmbeddingPrecision::parse("int8").is_err());
+        assert!(EmbeddingPrecision::parse("f8").is_err());
+    }
+
+    #[test]
+    fn f16_round_trip_stays_within_epsilon() {
+        let original = sample(3, 384);
+        let restored = EmbeddingPrecision::F16.round_trip(&original);
+
+        for (a, b) in original.iter().zip(&restored) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+        assert!(cosine(&original, &restored) > 0.9999);
+    }
+
+    #[test]
+    fn reduced_precision_preserves_search_order() {
+        let query = sample(1, 384);
+        let candidates: Vec<Vec<f32>> = (2..12).map(|seed| sample(seed, 384)).collect();
+
+        let rank = |precision: EmbeddingPrecision| {
+            let query = precision.round_trip(&query);
+            let mut scored: Vec<(usize, f32)> = candidates
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i, cosine(&query, &precision.round_trip(c))))
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored.into_iter().map(|(i, _)| i).collect::<Vec<_>>()
+        };
+
+        let expected = rank(EmbeddingPrecision::F32);
+        assert_eq!(rank(EmbeddingPrecision::F16), expected);
+    }
+}
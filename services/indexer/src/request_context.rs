@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 
 use std::cell::RefCell;
+use std::future::Future;
 use std::net::IpAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
+use tonic::codegen::http;
+use tonic::server::NamedService;
+use tower::Service;
 use uuid::Uuid;
 
 std::thread_local! {
@@ -10,11 +16,17 @@ std::thread_local! {
     static CONTEXT: RefCell<Option<RequestContext>> = RefCell::new(None);
 }
 
+tokio::task_local! {
+    static TASK_CONTEXT: RequestContext;
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RequestContext {
     request_id: Uuid,
     trace_id: Option<String>,
     client_ip: Option<IpAddr>,
+    /// Authenticated caller, e.g. the name of its API key.
+    actor: Option<String>,
 }
 
 impl RequestContext {
@@ -23,6 +35,7 @@ impl RequestContext {
             request_id,
             trace_id: None,
             client_ip: None,
+            actor: None,
         }
     }
 
@@ -36,6 +49,11 @@ impl RequestContext {
         self
     }
 
+    pub fn with_actor<T: Into<String>>(mut self, actor: T) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
     pub fn request_id(&self) -> Uuid {
         self.request_id
     }
@@ -47,6 +65,10 @@ impl RequestContext {
     pub fn client_ip(&self) -> Option<IpAddr> {
         self.client_ip
     }
+
+    pub fn actor(&self) -> Option<&str> {
+        self.actor.as_deref()
+    }
 }
 
 pub fn set_request_context(context: RequestContext) {
@@ -61,8 +83,63 @@ pub fn clear_request_context() {
     });
 }
 
+/// The context of the task running `future`, or of the calling thread as
+/// set by [`set_request_context`].
 pub fn current_request_context() -> Option<RequestContext> {
-    CONTEXT.with(|cell| cell.borrow().clone())
+    TASK_CONTEXT
+        .try_with(RequestContext::clone)
+        .ok()
+        .or_else(|| CONTEXT.with(|cell| cell.borrow().clone()))
+}
+
+/// Runs `future` with `context` as its request context, wherever the
+/// runtime polls it.
+pub fn scope_request_context<F: Future>(
+    context: RequestContext,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    TASK_CONTEXT.scope(context, future)
+}
+
+/// Wraps a gRPC service so each call runs in the [`RequestContext`] an
+/// interceptor left in its extensions, e.g. [`crate::auth::ApiKeyAuth`].
+/// Calls without one run as before.
+#[derive(Clone, Debug)]
+pub struct ScopedContext<S> {
+    inner: S,
+}
+
+impl<S> ScopedContext<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for ScopedContext<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let context = request.extensions().get::<RequestContext>().cloned();
+        let future = self.inner.call(request);
+        match context {
+            Some(context) => Box::pin(scope_request_context(context, future)),
+            None => Box::pin(future),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for ScopedContext<S> {
+    const NAME: &'static str = S::NAME;
 }
 
 #[cfg(test)]
@@ -85,6 +162,20 @@ mod tests {
         assert_eq!(retrieved, ctx);
     }
 
+    #[tokio::test]
+    async fn scoped_context_follows_the_task() {
+        clear_request_context();
+        let ctx = RequestContext::new(Uuid::nil()).with_actor("ci");
+
+        let seen = scope_request_context(ctx.clone(), async {
+            tokio::task::yield_now().await;
+            current_request_context()
+        })
+        .await;
+        assert_eq!(seen, Some(ctx));
+        assert!(current_request_context().is_none());
+    }
+
     #[test]
     fn clearing_context_removes_value() {
         clear_request_context();
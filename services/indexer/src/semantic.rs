@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
 use std::mem::size_of;
 use std::sync::Arc;
@@ -12,28 +12,157 @@ use tracing::warn;
 use twox_hash::xxh3::hash64_with_seed;
 use uuid::Uuid;
 
+use crate::clock::{self, SharedClock};
+
 const EMBEDDING_DIM: usize = 256;
 const HASH_SEED: u64 = 0x000A_11CE_D00D_F005_u64;
 const DEFAULT_MAX_DOCUMENTS: usize = 10_000;
+const DEFAULT_KEYWORD_WEIGHT: f32 = 0.2;
+
+/// Keywords common to the indexed languages. They carry little meaning on their
+/// own, so left at full weight they make unrelated functions look alike.
+const DEFAULT_DOWNWEIGHT_KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "fn",
+    "for",
+    "function",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "null",
+    "pub",
+    "return",
+    "self",
+    "static",
+    "struct",
+    "super",
+    "this",
+    "trait",
+    "true",
+    "type",
+    "use",
+    "var",
+    "where",
+    "while",
+];
+
+/// Per-token weights applied by the hashing embedder.
+#[derive(Clone, Debug)]
+pub struct TokenWeighting {
+    /// Lowercased tokens whose contribution is scaled by `keyword_weight`.
+    pub keywords: HashSet<String>,
+    pub keyword_weight: f32,
+    /// Count repeated tokens as `1 + ln(count)` instead of `count`.
+    pub sublinear_tf: bool,
+}
+
+impl Default for TokenWeighting {
+    fn default() -> Self {
+        Self {
+            keywords: DEFAULT_DOWNWEIGHT_KEYWORDS
+                .iter()
+                .map(|keyword| keyword.to_string())
+                .collect(),
+            keyword_weight: DEFAULT_KEYWORD_WEIGHT,
+            sublinear_tf: false,
+        }
+    }
+}
+
+impl TokenWeighting {
+    /// Equal weight for every occurrence of every token.
+    pub fn uniform() -> Self {
+        Self {
+            keywords: HashSet::new(),
+            keyword_weight: 1.0,
+            sublinear_tf: false,
+        }
+    }
+
+    fn weight(&self, token: &str, count: usize) -> f32 {
+        let frequency = if self.sublinear_tf {
+            1.0 + (count as f32).ln()
+        } else {
+            count as f32
+        };
+        if self.keywords.contains(token) {
+            frequency * self.keyword_weight
+        } else {
+            frequency
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct SemanticConfig {
     pub max_documents: Option<usize>,
+    pub weighting: TokenWeighting,
 }
 
 impl Default for SemanticConfig {
     fn default() -> Self {
         Self {
             max_documents: Some(DEFAULT_MAX_DOCUMENTS),
+            weighting: TokenWeighting::default(),
         }
     }
 }
 
 impl SemanticConfig {
     const MAX_DOCS_ENV: &'static str = "SEMANTIC_STORE_MAX_DOCUMENTS";
+    const KEYWORDS_ENV: &'static str = "SEMANTIC_DOWNWEIGHT_KEYWORDS";
+    const KEYWORD_WEIGHT_ENV: &'static str = "SEMANTIC_KEYWORD_WEIGHT";
+    const SUBLINEAR_TF_ENV: &'static str = "SEMANTIC_SUBLINEAR_TF";
 
     pub fn from_env() -> Self {
         let mut config = Self::default();
+
+        // An empty list is honoured and disables keyword down-weighting.
+        if let Ok(value) = env::var(Self::KEYWORDS_ENV) {
+            config.weighting.keywords = value
+                .split(',')
+                .map(|keyword| keyword.trim().to_lowercase())
+                .filter(|keyword| !keyword.is_empty())
+                .collect();
+        }
+        if let Ok(value) = env::var(Self::KEYWORD_WEIGHT_ENV) {
+            match value.trim().parse::<f32>() {
+                Ok(weight) if (0.0..=1.0).contains(&weight) => {
+                    config.weighting.keyword_weight = weight
+                }
+                _ => warn!(
+                    "invalid {}='{}' (expected 0.0-1.0) — using default",
+                    Self::KEYWORD_WEIGHT_ENV,
+                    value
+                ),
+            }
+        }
+        if let Ok(value) = env::var(Self::SUBLINEAR_TF_ENV) {
+            config.weighting.sublinear_tf = value.trim().eq_ignore_ascii_case("true");
+        }
+
         if let Ok(value) = env::var(Self::MAX_DOCS_ENV) {
             let trimmed = value.trim();
             if trimmed.is_empty() {
@@ -57,10 +186,17 @@ impl SemanticConfig {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct SemanticStore {
     inner: Arc<RwLock<SemanticIndex>>,
     config: Arc<SemanticConfig>,
+    clock: SharedClock,
+}
+
+impl Default for SemanticStore {
+    fn default() -> Self {
+        Self::from_config(SemanticConfig::default())
+    }
 }
 
 #[derive(Default)]
@@ -136,18 +272,24 @@ impl SemanticStore {
         Self {
             inner: Arc::new(RwLock::new(SemanticIndex::default())),
             config: Arc::new(config),
+            clock: clock::system_clock(),
         }
     }
 
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn add_document(&self, request: AddDocumentRequest) -> AddDocumentResponse {
-        let embedding = embed_text(&request.content);
+        let embedding = embed_text(&request.content, &self.config.weighting);
         let record = DocumentRecord {
             id: Uuid::new_v4(),
             path: request.path.clone(),
             content: request.content,
             embedding,
             commit_id: request.commit_id,
-            timestamp: request.timestamp.unwrap_or_else(Utc::now),
+            timestamp: request.timestamp.unwrap_or_else(|| self.clock.now()),
         };
 
         let mut guard = self.inner.write();
@@ -165,7 +307,7 @@ impl SemanticStore {
     }
 
     pub fn search(&self, request: SearchRequest) -> Vec<SearchResult> {
-        let query_embedding = embed_text(&request.query);
+        let query_embedding = embed_text(&request.query, &self.config.weighting);
         let guard = self.inner.read();
         let mut results = guard
             .records
@@ -268,18 +410,23 @@ impl SemanticIndex {
     }
 }
 
-fn embed_text(text: &str) -> Vec<f32> {
+fn embed_text(text: &str, weighting: &TokenWeighting) -> Vec<f32> {
     let mut vector = vec![0.0f32; EMBEDDING_DIM];
     if text.trim().is_empty() {
         return vector;
     }
 
-    let tokens = tokenize(text);
-    for token in tokens {
+    // Ordered so the floating point sums, and therefore embeddings, are reproducible.
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for token in tokenize(text) {
+        *counts.entry(token).or_default() += 1;
+    }
+
+    for (token, count) in counts {
         let hash = hash64_with_seed(token.as_bytes(), HASH_SEED);
         let bucket = (hash as usize) % EMBEDDING_DIM;
         let magnitude = (hash as f32 % 997.0) / 997.0;
-        vector[bucket] += magnitude;
+        vector[bucket] += magnitude * weighting.weight(&token, count);
     }
 
     normalize(&mut vector);
@@ -325,10 +472,56 @@ fn default_top_k() -> usize {
 mod tests {
     use super::*;
 
+    #[test]
+    fn keyword_changes_matter_less_than_identifier_changes() {
+        let weighting = TokenWeighting::default();
+        let base = embed_text(
+            "pub fn parse_config(path) { return read_file(path) }",
+            &weighting,
+        );
+        let keyword_changed = embed_text(
+            "async fn parse_config(path) { return read_file(path) }",
+            &weighting,
+        );
+        let identifier_changed = embed_text(
+            "pub fn render_widget(path) { return read_file(path) }",
+            &weighting,
+        );
+
+        assert!(
+            cosine_similarity(&base, &keyword_changed)
+                > cosine_similarity(&base, &identifier_changed)
+        );
+    }
+
+    #[test]
+    fn weighted_embeddings_stay_normalized() {
+        let weighting = TokenWeighting {
+            sublinear_tf: true,
+            ..TokenWeighting::default()
+        };
+        let embedding = embed_text("fn fn fn handler handler request", &weighting);
+        let norm: f32 = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sublinear_tf_damps_repeated_tokens() {
+        let linear = TokenWeighting::uniform();
+        let sublinear = TokenWeighting {
+            sublinear_tf: true,
+            ..TokenWeighting::uniform()
+        };
+        assert_eq!(linear.weight("token", 8), 8.0);
+        assert!(sublinear.weight("token", 8) < 4.0);
+        assert_eq!(sublinear.weight("token", 1), 1.0);
+    }
+
     #[test]
     fn evicts_oldest_documents_when_over_capacity() {
         let store = SemanticStore::from_config(SemanticConfig {
             max_documents: Some(2),
+            ..SemanticConfig::default()
         });
 
         store.add_document(AddDocumentRequest {
@@ -370,6 +563,7 @@ mod tests {
     fn memory_usage_remains_stable_under_load() {
         let store = SemanticStore::from_config(SemanticConfig {
             max_documents: Some(3),
+            ..SemanticConfig::default()
         });
 
         for i in 0..3 {
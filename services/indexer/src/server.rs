@@ -1,18 +1,34 @@
 use std::net::{AddrParseError, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
+use axum::extract::State;
+use axum::http::StatusCode;
 use axum::{routing::get, Json, Router};
 use serde_json::{json, Value};
 use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tonic::server::NamedService;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Server;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
 use tracing::{info, warn};
 
+use crate::archive::{self, ArchiveLimits};
+use crate::auth::ApiKeyAuth;
+use crate::embeddings::EmbeddingManager;
+use crate::features::{self, Features};
 use crate::grpc_service::{
-    proto::indexer_service_server::IndexerServiceServer, IndexerServiceImpl,
+    proto::{self, indexer_service_server::IndexerServiceServer},
+    IndexerServiceImpl,
 };
-use crate::storage::{create_storage, StorageConfig};
+use crate::request_context::ScopedContext;
+use crate::security::SecurityConfig;
+use crate::storage::{create_storage, IndexStorage, StorageConfig};
 use crate::telemetry;
-use crate::temporal::{TemporalConfig, TemporalIndex};
+use crate::temporal::TemporalConfig;
 
 /// Guard that ensures tracing is shut down when dropped.
 /// This guarantees pending traces are flushed even on early returns.
@@ -28,6 +44,8 @@ const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:9200";
 const LISTEN_ADDR_ENV: &str = "INDEXER_LISTEN_ADDR";
 const DEFAULT_GRPC_ADDR: &str = "0.0.0.0:9201";
 const GRPC_ADDR_ENV: &str = "INDEXER_GRPC_ADDR";
+const DEFAULT_HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
+const HEALTH_CHECK_INTERVAL_ENV: &str = "INDEXER_HEALTH_CHECK_INTERVAL_SECS";
 
 #[derive(Debug, Error)]
 pub enum IndexerError {
@@ -45,6 +63,42 @@ pub enum IndexerError {
     Temporal(#[from] crate::temporal::TemporalError),
     #[error("gRPC server error: {0}")]
     GrpcServer(#[from] tonic::transport::Error),
+    #[error("authentication misconfigured: {0}")]
+    Auth(String),
+    #[error("gRPC reflection error: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
+}
+
+/// Status reported over `grpc.health.v1.Health`, for the server as a whole
+/// (the empty service name) and for `indexer.IndexerService` alike.
+#[derive(Clone)]
+pub struct GrpcHealth {
+    reporter: HealthReporter,
+}
+
+impl GrpcHealth {
+    pub fn new(reporter: HealthReporter) -> Self {
+        Self { reporter }
+    }
+
+    pub async fn set_serving(&self) {
+        self.set(ServingStatus::Serving).await;
+    }
+
+    pub async fn set_not_serving(&self) {
+        self.set(ServingStatus::NotServing).await;
+    }
+
+    async fn set(&self, status: ServingStatus) {
+        let mut reporter = self.reporter.clone();
+        reporter.set_service_status("", status).await;
+        reporter
+            .set_service_status(
+                <IndexerServiceServer<IndexerServiceImpl> as NamedService>::NAME,
+                status,
+            )
+            .await;
+    }
 }
 
 pub async fn run() -> Result<(), IndexerError> {
@@ -55,6 +109,12 @@ pub async fn run() -> Result<(), IndexerError> {
 
     let http_addr = resolve_listen_addr()?;
     let grpc_addr = resolve_grpc_addr()?;
+    let auth = ApiKeyAuth::from_env().map_err(IndexerError::Auth)?;
+
+    // Probes see NOT_SERVING until storage and the temporal index are up.
+    let (health_reporter, health_server) = tonic_health::server::health_reporter();
+    let health = GrpcHealth::new(health_reporter);
+    health.set_not_serving().await;
 
     // Initialize storage
     let storage_config =
@@ -65,18 +125,53 @@ pub async fn run() -> Result<(), IndexerError> {
 
     info!("Storage initialized successfully");
 
-    // Initialize temporal index
-    let temporal_config = TemporalConfig::from_env();
-    let temporal_index = Arc::new(TemporalIndex::new(temporal_config, storage.clone())?);
+    let embedding_manager = storage.embedding_manager();
+    if embedding_manager.spawn_failover_supervisor().is_some() {
+        info!("Embedding provider failover supervisor started");
+    }
+
+    // Initialize temporal index unless INDEXER_FEATURES leaves it out
+    let features = Features::from_env();
+    let temporal_index =
+        features::temporal_index(&features, TemporalConfig::from_env(), storage.clone())?
+            .map(|index| Arc::new(index.with_embedder(embedding_manager.clone())));
+
+    if temporal_index.is_some() {
+        info!("Temporal index initialized successfully");
+    } else {
+        info!("Temporal features disabled; the git repository will not be opened");
+    }
 
-    info!("Temporal index initialized successfully");
+    health.set_serving().await;
+    let health_monitor = resolve_health_check_interval()
+        .map(|interval| spawn_storage_health_monitor(storage.clone(), health.clone(), interval));
 
     // Create gRPC service
-    let grpc_service = IndexerServiceImpl::new(storage.clone(), temporal_index);
-    let grpc_server = IndexerServiceServer::new(grpc_service);
+    let grpc_service = IndexerServiceImpl::from_storage(storage.clone(), temporal_index)
+        .with_embedding_manager(embedding_manager.clone());
+    // Keep tonic's encode limit in line with the handler-level size guard.
+    let max_response_bytes = grpc_service.config().max_response_bytes;
+    let grpc_server =
+        IndexerServiceServer::new(grpc_service).max_encoding_message_size(max_response_bytes);
+    // Health and reflection stay open to probes; only the indexer API needs a key.
+    let grpc_server = InterceptedService::new(ScopedContext::new(grpc_server), auth.clone());
+    let reflection_server = reflection_service()?;
 
-    // Create HTTP service (legacy support / health check)
-    let app = Router::new().route("/healthz", get(health_check));
+    // Create HTTP service (legacy support / health check / archive upload)
+    // The archive upload writes to the index, so it takes the same API keys.
+    let app = Router::new()
+        .route("/healthz", get(health_check))
+        .merge(archive::router(
+            storage.clone(),
+            SecurityConfig::from_env(),
+            ArchiveLimits::from_env(),
+            auth,
+        ))
+        .merge(
+            Router::new()
+                .route("/readyz", get(readiness_check))
+                .with_state(embedding_manager),
+        );
 
     // Spawn HTTP server
     let http_handle = {
@@ -96,6 +191,8 @@ pub async fn run() -> Result<(), IndexerError> {
 
         tokio::spawn(async move {
             Server::builder()
+                .add_service(health_server)
+                .add_service(reflection_server)
                 .add_service(grpc_server)
                 .serve_with_shutdown(grpc_addr, shutdown_signal())
                 .await
@@ -114,6 +211,10 @@ pub async fn run() -> Result<(), IndexerError> {
         warn!("gRPC server task failed: {}", e);
     }
 
+    if let Some(monitor) = health_monitor {
+        monitor.abort();
+    }
+
     // TracingGuard Drop handles shutdown_tracing()
     Ok(())
 }
@@ -130,12 +231,93 @@ fn resolve_grpc_addr() -> Result<SocketAddr, IndexerError> {
         .map_err(|error| IndexerError::InvalidListenAddr(raw, error))
 }
 
+/// How often storage is probed for the gRPC health status; `None` when
+/// `INDEXER_HEALTH_CHECK_INTERVAL_SECS` is 0.
+fn resolve_health_check_interval() -> Option<Duration> {
+    let secs = std::env::var(HEALTH_CHECK_INTERVAL_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Reflection over the indexer and health services, for `grpcurl` and the like.
+fn reflection_service() -> Result<
+    tonic_reflection::server::ServerReflectionServer<
+        impl tonic_reflection::server::ServerReflection,
+    >,
+    IndexerError,
+> {
+    Ok(tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()?)
+}
+
+/// Probes storage every `interval`, reporting NOT_SERVING while the database
+/// fails to answer and SERVING again once it recovers.
+fn spawn_storage_health_monitor(
+    storage: Arc<dyn IndexStorage>,
+    health: GrpcHealth,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut serving = true;
+        loop {
+            ticker.tick().await;
+            match storage.health_check().await {
+                Ok(()) if !serving => {
+                    info!("Storage health check recovered; reporting SERVING");
+                    health.set_serving().await;
+                    serving = true;
+                }
+                Err(error) if serving => {
+                    warn!("Storage health check failed; reporting NOT_SERVING: {error}");
+                    health.set_not_serving().await;
+                    serving = false;
+                }
+                _ => {}
+            }
+        }
+    })
+}
+
 async fn health_check() -> Json<Value> {
     Json(json!({
         "status": "ok"
     }))
 }
 
+/// `GET /readyz` — 503 while the embedding provider cannot serve requests, so
+/// traffic is held back instead of failing at index time.
+async fn readiness_check(
+    State(embedding_manager): State<Arc<EmbeddingManager>>,
+) -> (StatusCode, Json<Value>) {
+    let provider = embedding_manager.provider_name();
+    match embedding_manager.health_check().await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ready",
+                "embedding": { "healthy": true, "provider": provider }
+            })),
+        ),
+        Err(error) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": "unavailable",
+                "embedding": {
+                    "healthy": false,
+                    "provider": provider,
+                    "detail": error.to_string()
+                }
+            })),
+        ),
+    }
+}
+
 async fn shutdown_signal() {
     if let Err(error) = tokio::signal::ctrl_c().await {
         warn!("failed to listen for shutdown signal: {error}");
@@ -184,4 +366,62 @@ mod tests {
             assert!(matches!(err, IndexerError::InvalidListenAddr(_, _)));
         });
     }
+
+    #[tokio::test]
+    async fn health_service_follows_the_serving_hooks() {
+        use tonic_health::pb::health_check_response::ServingStatus as Status;
+        use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
+
+        let (reporter, health_server) = tonic_health::server::health_reporter();
+        let health = GrpcHealth::new(reporter);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = futures::stream::unfold(listener, |listener| async move {
+            Some((listener.accept().await.map(|(stream, _)| stream), listener))
+        });
+        tokio::spawn(
+            Server::builder()
+                .add_service(health_server)
+                .add_service(reflection_service().unwrap())
+                .serve_with_incoming(incoming),
+        );
+
+        let mut client = HealthClient::new(
+            tonic::transport::Endpoint::from_shared(format!("http://{addr}"))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap(),
+        );
+        let statuses = || {
+            let mut client = client.clone();
+            async move {
+                let mut statuses = Vec::new();
+                for service in ["", "indexer.IndexerService"] {
+                    let response = client
+                        .check(HealthCheckRequest {
+                            service: service.to_string(),
+                        })
+                        .await
+                        .unwrap();
+                    statuses.push(response.into_inner().status());
+                }
+                statuses
+            }
+        };
+
+        health.set_not_serving().await;
+        assert_eq!(statuses().await, [Status::NotServing, Status::NotServing]);
+
+        health.set_serving().await;
+        assert_eq!(statuses().await, [Status::Serving, Status::Serving]);
+
+        let unknown = client
+            .check(HealthCheckRequest {
+                service: "indexer.Unknown".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+    }
 }
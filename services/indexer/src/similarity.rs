@@ -0,0 +1,155 @@
+#![allow(dead_code)]
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Smoothing constant of reciprocal rank fusion, from the original paper. It
+/// damps the lead of the very first ranks over the rest of each list.
+pub const RRF_K: f32 = 60.0;
+
+/// Measure vector search ranks by, and so what a result's `score` means.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimilarityMetric {
+    /// `1 - cosine distance`. Negative similarities are reported as 0.
+    #[default]
+    Cosine,
+}
+
+/// Bounds of the scores a metric produces, and which end is the better match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: f32,
+    pub max: f32,
+    pub higher_is_better: bool,
+}
+
+impl SimilarityMetric {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "cosine" => Ok(Self::Cosine),
+            other => Err(format!(
+                "unknown similarity metric '{}'; expected cosine",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cosine => "cosine",
+        }
+    }
+
+    pub fn score_range(&self) -> ScoreRange {
+        match self {
+            Self::Cosine => ScoreRange {
+                min: 0.0,
+                max: 1.0,
+                higher_is_better: true,
+            },
+        }
+    }
+
+    /// `score` as reported to clients: clamped into [`Self::score_range`].
+    pub fn report(&self, score: f32) -> f32 {
+        let range = self.score_range();
+        score.clamp(range.min, range.max)
+    }
+}
+
+/// How a symbol search ranks its candidates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Nearest embeddings under the storage's [`SimilarityMetric`].
+    #[default]
+    Vector,
+    /// Postgres full-text match of the query against names and content.
+    Keyword,
+    /// Both of the above, fused with [`reciprocal_rank_fusion`].
+    Hybrid,
+}
+
+impl SearchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Vector => "vector",
+            Self::Keyword => "keyword",
+            Self::Hybrid => "hybrid",
+        }
+    }
+
+    /// Name and bounds of the scores this mode reports, where `metric` is
+    /// the one vector search uses.
+    pub fn scoring(&self, metric: SimilarityMetric) -> (&'static str, ScoreRange) {
+        let unit = ScoreRange {
+            min: 0.0,
+            max: 1.0,
+            higher_is_better: true,
+        };
+        match self {
+            Self::Vector => (metric.as_str(), metric.score_range()),
+            Self::Keyword => ("ts_rank", unit),
+            Self::Hybrid => ("rrf", unit),
+        }
+    }
+}
+
+/// Merges ranked lists into one, scoring each item by the sum of
+/// `1 / (RRF_K + rank)` over the lists it appears in. Items are matched by
+/// `key`, and the first copy seen is kept. Scores are scaled so an item
+/// ranked first in every list scores 1.
+pub fn reciprocal_rank_fusion<T, K: Eq + Hash>(
+    lists: Vec<Vec<T>>,
+    key: impl Fn(&T) -> K,
+) -> Vec<(T, f32)> {
+    let best = lists.len().max(1) as f32 / (RRF_K + 1.0);
+    let mut slots: HashMap<K, usize> = HashMap::new();
+    let mut fused: Vec<(T, f32)> = Vec::new();
+    for list in lists {
+        for (rank, item) in list.into_iter().enumerate() {
+            let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match slots.entry(key(&item)) {
+                Entry::Occupied(slot) => fused[*slot.get()].1 += contribution,
+                Entry::Vacant(slot) => {
+                    slot.insert(fused.len());
+                    fused.push((item, contribution));
+                }
+            }
+        }
+    }
+    for (_, score) in &mut fused {
+        *score /= best;
+    }
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_scores_are_bounded_and_higher_is_better() {
+        let cosine = SimilarityMetric::parse("Cosine").unwrap();
+        assert_eq!(cosine.as_str(), "cosine");
+        assert!(cosine.score_range().higher_is_better);
+        assert_eq!(cosine.report(-0.3), 0.0);
+        assert_eq!(cosine.report(0.8), 0.8);
+        assert!(SimilarityMetric::parse("hamming").is_err());
+    }
+
+    #[test]
+    fn fusion_favours_items_ranked_in_both_lists() {
+        let vector = vec!["load_settings", "parse_config", "render"];
+        let keyword = vec!["parse_config"];
+        let fused = reciprocal_rank_fusion(vec![vector, keyword], |name| *name);
+
+        let names: Vec<_> = fused.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["parse_config", "load_settings", "render"]);
+        assert!(fused.iter().all(|(_, score)| (0.0..=1.0).contains(score)));
+
+        let alone = reciprocal_rank_fusion(vec![vec!["a"], vec!["a"]], |name| *name);
+        assert!((alone[0].1 - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,15 +1,28 @@
+use std::collections::HashMap;
 use std::env;
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
-use pgvector::Vector;
+use pgvector::{HalfVector, Vector};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
 use sqlx::{FromRow, Row};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::clock::{self, SharedClock};
+use crate::embeddings::EmbeddingProvider;
+use crate::normalize::{
+    self, ContentNormalization, EmbeddingTemplate, QueryPreprocessing, TemplateFields,
+};
+use crate::precision::EmbeddingPrecision;
+use crate::similarity::{reciprocal_rank_fusion, SearchMode, SimilarityMetric};
+use crate::symbol_extractor::{self, LineRange};
+use crate::symbol_metadata::SymbolMetadata;
+use crate::symbol_registry::SymbolKind;
+use crate::temporal::{ChangeType, CiEvent, CiStatus, SymbolVersion};
+
 #[derive(Debug, Error)]
 pub enum StorageError {
     #[error("document not found: {0}")]
@@ -43,6 +56,17 @@ impl From<StorageError> for tonic::Status {
 pub struct StorageConfig {
     pub database_url: String,
     pub max_connections: u32,
+    pub normalization: ContentNormalization,
+    pub query_preprocessing: QueryPreprocessing,
+    /// Symbols whose trimmed content is shorter than this many characters are
+    /// stored without an embedding. Zero embeds everything.
+    pub min_embed_chars: usize,
+    /// Precision embeddings are stored and searched at.
+    pub embedding_precision: EmbeddingPrecision,
+    /// Text each symbol is embedded as; unset embeds the raw content.
+    pub embedding_template: EmbeddingTemplate,
+    /// Columns a re-indexed document is matched on.
+    pub document_conflict_key: DocumentConflictKey,
 }
 
 impl Default for StorageConfig {
@@ -60,10 +84,33 @@ impl StorageConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(5),
+            normalization: ContentNormalization::from_env(),
+            query_preprocessing: QueryPreprocessing::from_env(),
+            min_embed_chars: env::var("INDEXER_MIN_EMBED_CHARS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            embedding_precision: EmbeddingPrecision::from_env()
+                .map_err(StorageError::Configuration)?,
+            embedding_template: EmbeddingTemplate::from_env(),
+            document_conflict_key: match env::var("INDEXER_DOCUMENT_CONFLICT_KEY") {
+                Ok(value) => {
+                    DocumentConflictKey::parse(&value).map_err(StorageError::Configuration)?
+                }
+                Err(_) => DocumentConflictKey::default(),
+            },
         })
     }
 }
 
+/// One entry of [`IndexStorage::batch_index_documents`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewDocument {
+    pub path: String,
+    pub content: String,
+    pub commit_id: Option<String>,
+}
+
 #[derive(Clone, Debug, FromRow)]
 pub struct StoredDocument {
     pub id: Uuid,
@@ -97,6 +144,621 @@ pub struct StoredSymbol {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Relation of a [`SymbolEdge`] from a symbol to one declared inside it.
+pub const CONTAINS_RELATION: &str = "contains";
+
+/// A row of `symbol_edges` (migration `009_symbol_edges.sql`).
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct SymbolEdge {
+    pub parent_id: Uuid,
+    pub child_id: Uuid,
+    pub relation: String,
+}
+
+impl StoredSymbol {
+    /// Canonical language written to `symbols.language`: `metadata.language`
+    /// when set, otherwise whatever the path's extension implies.
+    pub fn language(&self) -> Option<String> {
+        let declared = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("language"))
+            .and_then(|language| language.as_str());
+        match declared {
+            Some(language) => Some(canonical_language_name(language)),
+            None => crate::ast::language_for_path(&self.path).map(str::to_string),
+        }
+    }
+}
+
+/// `language` under the name `ast::canonical_language` gives it, or
+/// lowercased when it is not one the indexer parses.
+fn canonical_language_name(language: &str) -> String {
+    crate::ast::canonical_language(language)
+        .map(str::to_string)
+        .unwrap_or_else(|| language.trim().to_ascii_lowercase())
+}
+
+/// Restricts search results by the commit rows were indexed at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommitFilter {
+    /// Rows indexed at exactly this commit.
+    Exact(String),
+    /// Rows indexed at any of these commits, e.g. a commit and its ancestors.
+    AnyOf(Vec<String>),
+}
+
+impl CommitFilter {
+    #[allow(dead_code)]
+    pub fn matches(&self, commit_id: Option<&str>) -> bool {
+        match (self, commit_id) {
+            (Self::Exact(wanted), Some(commit)) => wanted == commit,
+            (Self::AnyOf(wanted), Some(commit)) => wanted.iter().any(|c| c == commit),
+            (_, None) => false,
+        }
+    }
+
+    /// Appends the matching `WHERE` clause and binds its parameter.
+    fn push_sql(
+        self,
+        sql: &mut String,
+        args: &mut sqlx::postgres::PgArguments,
+        param_idx: &mut usize,
+    ) -> Result<(), StorageError> {
+        use sqlx::Arguments;
+        match self {
+            Self::Exact(commit) => {
+                sql.push_str(&format!(" AND commit_id = ${}", param_idx));
+                args.add(commit)
+            }
+            Self::AnyOf(commits) => {
+                sql.push_str(&format!(" AND commit_id = ANY(${})", param_idx));
+                args.add(commits)
+            }
+        }
+        .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        *param_idx += 1;
+        Ok(())
+    }
+}
+
+/// Columns `INSERT ... ON CONFLICT` matches an existing document on, and so
+/// which document a re-index replaces. The table must carry a unique index on
+/// exactly these columns; [`DocumentConflictKey::verify`] checks that at
+/// startup instead of letting the first insert fail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocumentConflictKey {
+    /// One row per path; indexing a path again overwrites it whatever the
+    /// commit. Matches the `UNIQUE (path)` of migration 001.
+    #[default]
+    Path,
+    /// One row per path and commit. Postgres treats NULLs as distinct, so
+    /// documents indexed without a commit are never replaced.
+    PathAndCommit,
+}
+
+impl DocumentConflictKey {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().replace(' ', "").as_str() {
+            "" | "path" => Ok(Self::Path),
+            "path,commit_id" => Ok(Self::PathAndCommit),
+            other => Err(format!(
+                "unknown document conflict key '{}'; expected path or path,commit_id",
+                other
+            )),
+        }
+    }
+
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            Self::Path => &["path"],
+            Self::PathAndCommit => &["path", "commit_id"],
+        }
+    }
+
+    /// Conflict target for `ON CONFLICT (...)`.
+    fn sql(&self) -> String {
+        self.columns().join(", ")
+    }
+
+    /// Fails unless one of `unique_indexes`, each given by its columns,
+    /// covers exactly [`Self::columns`] — the index Postgres needs to infer
+    /// the conflict target.
+    pub fn check(&self, unique_indexes: &[Vec<String>]) -> Result<(), StorageError> {
+        let mut wanted: Vec<&str> = self.columns().to_vec();
+        wanted.sort_unstable();
+        let matches = unique_indexes.iter().any(|columns| {
+            let mut columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+            columns.sort_unstable();
+            columns == wanted
+        });
+        if matches {
+            return Ok(());
+        }
+
+        let found: Vec<String> = unique_indexes
+            .iter()
+            .map(|columns| format!("({})", columns.join(", ")))
+            .collect();
+        Err(StorageError::Configuration(format!(
+            "documents conflict key ({}) has no matching unique index; the table has {}. \
+             Set INDEXER_DOCUMENT_CONFLICT_KEY to match the schema or migrate the table",
+            self.sql(),
+            if found.is_empty() {
+                "none".to_string()
+            } else {
+                found.join(", ")
+            }
+        )))
+    }
+
+    /// Reads the unique indexes of `documents` and [`Self::check`]s them.
+    /// Partial and expression indexes are skipped, since `ON CONFLICT` with a
+    /// plain column list cannot use them.
+    pub async fn verify(&self, pool: &PgPool) -> Result<(), StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT array_agg(a.attname::text ORDER BY k.ord) AS columns
+            FROM pg_index i
+            CROSS JOIN LATERAL unnest(i.indkey) WITH ORDINALITY AS k(attnum, ord)
+            JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = k.attnum
+            WHERE i.indrelid = 'documents'::regclass
+              AND i.indisunique
+              AND i.indpred IS NULL
+              AND i.indexprs IS NULL
+            GROUP BY i.indexrelid
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let unique_indexes = rows
+            .iter()
+            .map(|row| row.try_get::<Vec<String>, _>("columns"))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.check(&unique_indexes)
+    }
+}
+
+/// Vector columns holding stored embeddings, as `(table, column)`.
+const EMBEDDING_COLUMNS: [(&str, &str); 4] = [
+    ("documents", "embedding_vector"),
+    ("documents", "embedding_half"),
+    ("symbols", "embedding_vector"),
+    ("symbols", "embedding_half"),
+];
+
+/// Fails unless every column in `columns`, given as `(table, column,
+/// declared width)`, is as wide as the vectors `model` produces. A column
+/// declared without a width accepts any.
+pub fn check_embedding_dimension(
+    columns: &[(String, String, Option<usize>)],
+    model: &str,
+    dimension: usize,
+) -> Result<(), StorageError> {
+    let mismatched: Vec<String> = columns
+        .iter()
+        .filter_map(|(table, column, width)| match width {
+            Some(width) if *width != dimension => Some(format!("{table}.{column} ({width})")),
+            _ => None,
+        })
+        .collect();
+    if mismatched.is_empty() {
+        return Ok(());
+    }
+
+    Err(StorageError::Configuration(format!(
+        "embedding model {} produces {}-dimensional vectors but {} declared otherwise. \
+         Set EMBEDDING_MODEL to a model of the stored dimension, or alter the columns \
+         to vector({}) / halfvec({}) and re-index",
+        model,
+        dimension,
+        mismatched.join(", "),
+        dimension,
+        dimension
+    )))
+}
+
+/// Reads the declared widths of the embedding columns and checks them with
+/// [`check_embedding_dimension`]. Columns a migration has not added yet are
+/// not checked.
+pub async fn verify_embedding_dimension(
+    pool: &PgPool,
+    model: &str,
+    dimension: usize,
+) -> Result<(), StorageError> {
+    let rows = sqlx::query(
+        r#"
+        SELECT c.relname::text AS table_name, a.attname::text AS column_name, a.atttypmod
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        WHERE a.attrelid IN ('documents'::regclass, 'symbols'::regclass)
+          AND a.attname = ANY($1)
+          AND NOT a.attisdropped
+        "#,
+    )
+    .bind(
+        EMBEDDING_COLUMNS
+            .iter()
+            .map(|(_, column)| column.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let columns = rows
+        .iter()
+        .map(|row| {
+            // pgvector stores the declared width as the typmod; -1 means none.
+            let typmod: i32 = row.try_get("atttypmod")?;
+            Ok((
+                row.try_get("table_name")?,
+                row.try_get("column_name")?,
+                usize::try_from(typmod).ok(),
+            ))
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+    check_embedding_dimension(&columns, model, dimension)
+}
+
+/// Symbol metadata keys that may be filtered on. Keys are matched against this
+/// list before they reach SQL, and are bound as parameters regardless.
+pub const FILTERABLE_METADATA_KEYS: [&str; 8] = [
+    "language",
+    "visibility",
+    "signature",
+    "complexity",
+    "line_count",
+    "truncated",
+    "normalized",
+    "embedding_skipped",
+];
+
+/// Equality filters on `symbols.metadata` fields, compared as text (the
+/// `->>` form), so `complexity=3` and `normalized=true` work as expected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataFilter {
+    fields: std::collections::BTreeMap<String, String>,
+}
+
+impl MetadataFilter {
+    pub fn parse<I>(filters: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut fields = std::collections::BTreeMap::new();
+        for (key, value) in filters {
+            if !FILTERABLE_METADATA_KEYS.contains(&key.as_str()) {
+                return Err(format!(
+                    "unsupported metadata filter '{}'; expected one of {}",
+                    key,
+                    FILTERABLE_METADATA_KEYS.join(", ")
+                ));
+            }
+            let value = if key == "language" {
+                canonical_language_name(&value)
+            } else {
+                value
+            };
+            fields.insert(key, value);
+        }
+        Ok(Self { fields })
+    }
+
+    /// Whether `symbol` passes the filter, decided as the SQL of
+    /// [`Self::push_sql`] would.
+    #[allow(dead_code)]
+    pub fn matches(&self, symbol: &StoredSymbol) -> bool {
+        let metadata = symbol.metadata.as_ref();
+        self.fields.iter().all(|(key, wanted)| {
+            if key == "language" {
+                return symbol.language().as_ref() == Some(wanted);
+            }
+            match metadata.and_then(|metadata| metadata.get(key)) {
+                Some(serde_json::Value::String(value)) => value == wanted,
+                Some(serde_json::Value::Null) | None => false,
+                Some(value) => serde_json::from_str::<serde_json::Value>(wanted)
+                    .is_ok_and(|wanted| wanted == *value),
+            }
+        })
+    }
+
+    /// Appends one `metadata->>key = value` clause per field. `language` is
+    /// matched against the indexed `symbols.language` column instead.
+    fn push_sql(
+        self,
+        sql: &mut String,
+        args: &mut sqlx::postgres::PgArguments,
+        param_idx: &mut usize,
+    ) -> Result<(), StorageError> {
+        use sqlx::Arguments;
+        for (key, value) in self.fields {
+            if key == "language" {
+                sql.push_str(&format!(" AND language = ${}", param_idx));
+                args.add(value)
+                    .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+                *param_idx += 1;
+                continue;
+            }
+            sql.push_str(&format!(
+                " AND metadata->>${} = ${}",
+                param_idx,
+                *param_idx + 1
+            ));
+            args.add(key)
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            args.add(value)
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            *param_idx += 2;
+        }
+        Ok(())
+    }
+}
+
+/// Numeric symbol metadata fields that symbols can be ranked by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolMetric {
+    Complexity,
+    LineCount,
+}
+
+impl SymbolMetric {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "complexity" => Ok(Self::Complexity),
+            "line_count" => Ok(Self::LineCount),
+            other => Err(format!(
+                "unsupported metric '{}'; expected complexity or line_count",
+                other
+            )),
+        }
+    }
+
+    /// Key under `symbols.metadata` holding the value.
+    pub fn metadata_key(self) -> &'static str {
+        match self {
+            Self::Complexity => "complexity",
+            Self::LineCount => "line_count",
+        }
+    }
+
+    /// Reads the metric from a symbol's metadata, if present and integral.
+    #[allow(dead_code)]
+    pub fn value_of(self, symbol: &StoredSymbol) -> Option<i64> {
+        let metadata = SymbolMetadata::from_value(symbol.metadata.as_ref()?);
+        match self {
+            Self::Complexity => metadata.complexity,
+            Self::LineCount => metadata.line_count,
+        }
+    }
+}
+
+/// How search results that point at the same file are collapsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DedupBy {
+    #[default]
+    None,
+    /// One result per path, whatever the chunk or commit.
+    Path,
+    /// One result per (path, commit) pair.
+    PathCommit,
+}
+
+impl DedupBy {
+    /// Candidates fetched per requested result when deduplicating, so `top_k`
+    /// can still be filled after duplicates are dropped.
+    pub const OVERFETCH_FACTOR: usize = 4;
+    pub const MAX_CANDIDATES: usize = 400;
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "path" => Ok(Self::Path),
+            "path_commit" => Ok(Self::PathCommit),
+            other => Err(format!(
+                "unsupported dedup_by '{}'; expected none, path or path_commit",
+                other
+            )),
+        }
+    }
+
+    /// Number of rows to ask storage for to end up with `top_k` results.
+    pub fn candidates(self, top_k: usize) -> usize {
+        match self {
+            Self::None => top_k,
+            _ => (top_k * Self::OVERFETCH_FACTOR)
+                .min(Self::MAX_CANDIDATES)
+                .max(top_k),
+        }
+    }
+
+    /// Keeps the best-scoring row of each group, ordered by score, and
+    /// truncates to `top_k`. `key` yields the row's path and commit.
+    pub fn apply<T>(
+        self,
+        rows: Vec<(T, f32)>,
+        top_k: usize,
+        key: impl Fn(&T) -> (&str, Option<&str>),
+    ) -> Vec<(T, f32)> {
+        if self == Self::None {
+            let mut rows = rows;
+            rows.truncate(top_k);
+            return rows;
+        }
+
+        let mut groups: HashMap<(String, Option<String>), usize> = HashMap::new();
+        let mut kept: Vec<(T, f32)> = Vec::new();
+        for (row, score) in rows {
+            let (path, commit) = key(&row);
+            let group = match self {
+                Self::PathCommit => (path.to_string(), commit.map(str::to_string)),
+                _ => (path.to_string(), None),
+            };
+            match groups.get(&group) {
+                Some(&idx) => {
+                    if score > kept[idx].1 {
+                        kept[idx] = (row, score);
+                    }
+                }
+                None => {
+                    groups.insert(group, kept.len());
+                    kept.push((row, score));
+                }
+            }
+        }
+
+        kept.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        kept.truncate(top_k);
+        kept
+    }
+}
+
+/// Rows fetched per requested result by
+/// [`IndexStorage::find_symbols_by_name`] before they are re-ranked.
+pub const NAME_MATCH_OVERFETCH_FACTOR: usize = 4;
+pub const MAX_NAME_MATCH_CANDIDATES: usize = 400;
+
+/// How well a symbol name matches a name query, case-insensitively, or `None`
+/// when it does not. An exact match scores 1.0, then prefixes, then
+/// substrings, each closer to 1.0 the less of the name is left over; names
+/// within a few edits of the query score below all of those.
+pub fn name_match_score(name: &str, query: &str) -> Option<f32> {
+    let name = name.to_lowercase();
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    let name_len = name.chars().count() as f32;
+    let coverage = query.chars().count() as f32 / name_len.max(1.0);
+
+    if name == query {
+        Some(1.0)
+    } else if name.starts_with(&query) {
+        Some(0.7 + 0.2 * coverage)
+    } else if name.contains(&query) {
+        Some(0.5 + 0.2 * coverage)
+    } else {
+        let distance = levenshtein(&name, &query) as f32;
+        let longest = name_len.max(query.chars().count() as f32);
+        let similarity = 1.0 - distance / longest;
+        (similarity >= 0.5).then_some(0.5 * similarity)
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Escapes `%`, `_` and `\` so `value` matches literally in a `LIKE` with
+/// `ESCAPE '\'`.
+pub(crate) fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Restricts a vector search to rows whose similarity to the query embedding,
+/// bound as `$1`, is at least `min_score`. Zero adds nothing.
+fn push_min_score(
+    sql: &mut String,
+    args: &mut sqlx::postgres::PgArguments,
+    param_idx: &mut usize,
+    column: &str,
+    min_score: f32,
+) -> Result<(), StorageError> {
+    use sqlx::Arguments;
+
+    if min_score <= 0.0 {
+        return Ok(());
+    }
+    sql.push_str(&format!(" AND 1 - ({} <=> $1) >= ${}", column, param_idx));
+    args.add(min_score as f64)
+        .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+    *param_idx += 1;
+    Ok(())
+}
+
+/// `LIKE` pattern matching paths that start with `prefix`, taken literally.
+pub(crate) fn prefix_pattern(prefix: &str) -> String {
+    format!("{}%", escape_like(prefix))
+}
+
+/// Sorts name matches best first; equal scores go to the shorter name, then
+/// by path.
+pub fn rank_name_matches(rows: &mut [(StoredSymbol, f32)]) {
+    rows.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| a.0.name.len().cmp(&b.0.name.len()))
+            .then_with(|| a.0.path.cmp(&b.0.path))
+    });
+}
+
+/// Full-text document of a symbol: its name, weighted above its content.
+/// Indexed by migration 006, which must use the same expression.
+const SYMBOL_TSVECTOR: &str = "(setweight(to_tsvector('simple', name), 'A') || \
+     setweight(to_tsvector('simple', content), 'D'))";
+
+/// Full-text document of a file: its path, weighted above its content.
+/// Indexed by migration 007, which must use the same expression.
+const DOCUMENT_TSVECTOR: &str = "(setweight(to_tsvector('simple', path), 'A') || \
+     setweight(to_tsvector('simple', content), 'D'))";
+
+/// Symbols, aliased `s`, whose file was never indexed or has been removed.
+const ORPHAN_SYMBOL: &str = "NOT EXISTS (SELECT 1 FROM documents d WHERE d.path = s.path)";
+/// Relationships, aliased `s`, with an end that is no longer a symbol.
+const DANGLING_EDGE: &str = "NOT EXISTS (SELECT 1 FROM symbols f WHERE f.id = s.from_symbol_id) \
+     OR NOT EXISTS (SELECT 1 FROM symbols t WHERE t.id = s.to_symbol_id)";
+
+/// Rows removed by [`IndexStorage::delete_by_commit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitDeletion {
+    pub documents: u64,
+    pub symbols: u64,
+    pub symbol_versions: u64,
+}
+
+/// Rows removed by [`IndexStorage::delete_path`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathDeletion {
+    pub documents: u64,
+    pub symbols: u64,
+}
+
+/// Rows found, or removed, by [`IndexStorage::find_orphans`] and
+/// [`IndexStorage::delete_orphans`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrphanReport {
+    /// Symbols whose path has no row in `documents`.
+    pub symbols_without_document: u64,
+    /// `symbol_relationships` rows with either end missing from `symbols`.
+    pub dangling_edges: u64,
+    /// Symbols with no vector in the searched column that were not skipped
+    /// as trivial, so vector search can never return them.
+    pub invalid_embeddings: u64,
+}
+
+impl OrphanReport {
+    pub fn total(&self) -> u64 {
+        self.symbols_without_document + self.dangling_edges + self.invalid_embeddings
+    }
+}
+
 #[async_trait::async_trait]
 pub trait IndexStorage: Send + Sync {
     async fn index_document(
@@ -106,38 +768,164 @@ pub trait IndexStorage: Send + Sync {
         commit_id: Option<String>,
     ) -> Result<Uuid, StorageError>;
 
+    /// Indexes `documents`, whose paths must be distinct, in one round trip.
+    /// The outer error fails the whole batch; an inner one, such as an
+    /// embedding failure, fails only that document. Results are in input
+    /// order.
+    async fn batch_index_documents(
+        &self,
+        documents: Vec<NewDocument>,
+    ) -> Result<Vec<Result<Uuid, StorageError>>, StorageError>;
+
     async fn index_symbols(
         &self,
         path: String,
         content: String,
         language: String,
         commit_id: Option<String>,
-    ) -> Result<usize, StorageError>;
+        range: Option<LineRange>,
+        declarations_only: bool,
+    ) -> Result<Vec<Uuid>, StorageError>;
 
+    /// Up to `top_k` documents scoring at least `min_score`, best first,
+    /// ranked as `mode` says. Keyword mode never embeds the query.
     async fn search_documents(
         &self,
         query: String,
         top_k: usize,
         path_prefix: Option<String>,
-        commit_id: Option<String>,
+        commit: Option<CommitFilter>,
+        min_score: f32,
+        mode: SearchMode,
     ) -> Result<Vec<(StoredDocument, f32)>, StorageError>;
 
+    /// Up to `top_k` symbols scoring at least `min_score`, best first, ranked
+    /// as `mode` says.
+    #[allow(clippy::too_many_arguments)]
     async fn search_symbols(
         &self,
         query: String,
         top_k: usize,
         path_prefix: Option<String>,
-        commit_id: Option<String>,
+        commit: Option<CommitFilter>,
+        metadata: MetadataFilter,
+        min_score: f32,
+        mode: SearchMode,
     ) -> Result<Vec<(StoredSymbol, f32)>, StorageError>;
 
+    /// Like [`IndexStorage::search_symbols`] without metadata filters or a
+    /// score threshold, ranked against a caller-supplied query embedding.
+    async fn search_symbols_by_vector(
+        &self,
+        embedding: Vec<f32>,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError>;
+
+    /// Symbols with the highest value for `metric`, descending, skipping the
+    /// first `offset`. Symbols without the metric in their metadata are
+    /// skipped. The order is total, so consecutive offsets page through it.
+    async fn top_symbols(
+        &self,
+        metric: SymbolMetric,
+        limit: usize,
+        offset: usize,
+        path_prefix: Option<String>,
+    ) -> Result<Vec<(StoredSymbol, i64)>, StorageError>;
+
+    /// Symbols whose name matches `query` as a substring or fuzzily, ranked by
+    /// [`name_match_score`] and optionally limited to one kind. Never embeds,
+    /// so it works without an embedding provider.
+    async fn find_symbols_by_name(
+        &self,
+        query: String,
+        kind: Option<SymbolKind>,
+        limit: usize,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError>;
+
+    /// Symbols stored for `path` whose lines cover the zero-based `line`,
+    /// outermost first. With `commit_id`, only symbols recorded at that commit.
+    async fn symbols_enclosing(
+        &self,
+        path: String,
+        line: i32,
+        commit_id: Option<String>,
+    ) -> Result<Vec<StoredSymbol>, StorageError>;
+
+    /// Removes the symbols and document stored for `path`, only those
+    /// recorded at `commit_id` when given, all or nothing.
+    async fn delete_path(
+        &self,
+        path: String,
+        commit_id: Option<String>,
+    ) -> Result<PathDeletion, StorageError>;
+
+    /// Bumps `updated_at` on the document stored at `path` without touching its
+    /// content or embedding. Returns the number of documents updated.
+    async fn touch(&self, path: String) -> Result<u64, StorageError>;
+
+    /// Removes every document, symbol and symbol version recorded for
+    /// `commit_id`, all or nothing.
+    async fn delete_by_commit(&self, commit_id: String) -> Result<CommitDeletion, StorageError>;
+
+    /// Metric the search methods score results with.
+    fn similarity_metric(&self) -> SimilarityMetric {
+        SimilarityMetric::Cosine
+    }
+
+    /// Whether the backing store answers queries.
+    async fn health_check(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Counts rows left behind by deletes and partial failures.
+    async fn find_orphans(&self) -> Result<OrphanReport, StorageError>;
+    /// Deletes what [`IndexStorage::find_orphans`] reports, returning how
+    /// many rows of each kind went.
+    async fn delete_orphans(&self) -> Result<OrphanReport, StorageError>;
+
+    /// The symbol stored under `id`, whatever its current name.
+    async fn get_symbol(&self, id: Uuid) -> Result<Option<StoredSymbol>, StorageError>;
+
     async fn query_all_symbols(&self) -> Result<Vec<StoredSymbol>, StorageError>;
+    /// Every recorded edge between symbols, such as a class containing its
+    /// methods.
+    async fn query_symbol_edges(&self) -> Result<Vec<SymbolEdge>, StorageError>;
     async fn store_symbol(&self, symbol: &StoredSymbol) -> Result<(), StorageError>;
+
+    async fn store_ci_event(&self, event: &CiEvent) -> Result<(), StorageError>;
+
+    /// Persisted CI events for `commit_id`, oldest first.
+    async fn ci_events_for_commit(&self, commit_id: String) -> Result<Vec<CiEvent>, StorageError>;
+
+    /// Replaces the history recorded for `path` at `commit_id` with
+    /// `versions`, so walking a commit twice does not duplicate it.
+    async fn store_symbol_versions(
+        &self,
+        path: &str,
+        commit_id: &str,
+        versions: &[SymbolVersion],
+    ) -> Result<(), StorageError>;
+
+    /// Persisted history of `path`, newest first.
+    async fn symbol_versions_for_path(
+        &self,
+        path: String,
+    ) -> Result<Vec<SymbolVersion>, StorageError>;
 }
 
 #[derive(Clone)]
 pub struct Storage {
     pool: PgPool,
     embedding_manager: Arc<crate::embeddings::EmbeddingManager>,
+    clock: SharedClock,
+    normalization: ContentNormalization,
+    query_preprocessing: QueryPreprocessing,
+    min_embed_chars: usize,
+    precision: EmbeddingPrecision,
+    embedding_template: EmbeddingTemplate,
+    document_conflict_key: DocumentConflictKey,
 }
 
 impl Storage {
@@ -146,218 +934,185 @@ impl Storage {
             .max_connections(config.max_connections)
             .connect(&config.database_url)
             .await?;
+        config.document_conflict_key.verify(&pool).await?;
 
         // Initialize embedding manager
         let embedding_manager = Arc::new(
             crate::embeddings::EmbeddingManager::new(None)
                 .map_err(|e| StorageError::Embedding(e.to_string()))?,
         );
+        // Loads a local model now rather than on the first embed, so a model
+        // that does not fit the schema stops startup. One that cannot be
+        // loaded at all is left to the readiness check.
+        match embedding_manager.dimension().await {
+            Ok(dimension) => {
+                verify_embedding_dimension(&pool, embedding_manager.model_name(), dimension).await?
+            }
+            Err(e) => tracing::warn!(
+                error = %e,
+                "Embedding model unavailable; its dimension was not checked against the schema"
+            ),
+        }
 
         Ok(Self {
             pool,
             embedding_manager,
+            clock: clock::system_clock(),
+            normalization: config.normalization,
+            query_preprocessing: config.query_preprocessing,
+            min_embed_chars: config.min_embed_chars,
+            precision: config.embedding_precision,
+            embedding_template: config.embedding_template,
+            document_conflict_key: config.document_conflict_key,
         })
     }
-}
-
-#[async_trait::async_trait]
-impl IndexStorage for Storage {
-    async fn index_document(
-        &self,
-        path: String,
-        content: String,
-        commit_id: Option<String>,
-    ) -> Result<Uuid, StorageError> {
-        let embedding = self
-            .embedding_manager
-            .embed(&content)
-            .await
-            .map_err(|e| StorageError::Embedding(e.to_string()))?;
-
-        let embedding_vector = Vector::from(embedding);
-        let id = Uuid::new_v4();
-        let now = Utc::now();
 
-        sqlx::query(
-            r#"
-            INSERT INTO documents (id, path, content, embedding_vector, commit_id, created_at, updated_at, embedding_model, embedding_generated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $6)
-            ON CONFLICT (path) DO UPDATE
-            SET content = $3,
-            embedding_vector = $4,
-            commit_id = $5,
-            updated_at = $6,
-            embedding_generated_at = $6
-            RETURNING id
-            "#
+    /// Matches symbols, aliased `s`, that vector search can never return.
+    fn missing_embedding(&self) -> String {
+        format!(
+            "s.{} IS NULL AND COALESCE((s.metadata->>'embedding_skipped')::boolean, false) = false",
+            self.precision.column()
         )
-        .bind(id)
-        .bind(path)
-        .bind(content)
-        .bind(embedding_vector)
-        .bind(commit_id)
-        .bind(now)
-        .bind("all-MiniLM-L6-v2")
-        .fetch_one(&self.pool)
-        .await?;
+    }
 
-        Ok(id)
+    /// Binds the query embedding as `$1` in the type of the searched column.
+    fn push_query_embedding(
+        &self,
+        args: &mut sqlx::postgres::PgArguments,
+        embedding: &[f32],
+    ) -> Result<(), StorageError> {
+        use sqlx::Arguments;
+        let added = if self.precision.is_reduced() {
+            args.add(HalfVector::from_f32_slice(embedding))
+        } else {
+            args.add(Vector::from(embedding.to_vec()))
+        };
+        added.map_err(|e| StorageError::InvalidInput(e.to_string()))
     }
 
-    async fn index_symbols(
+    /// Symbols closest to `query_embedding`, with the filters of
+    /// [`IndexStorage::search_symbols`].
+    async fn search_symbols_near(
         &self,
-        path: String,
-        content: String,
-        language: String,
-        commit_id: Option<String>,
-    ) -> Result<usize, StorageError> {
-        let extracted_symbols = crate::symbol_extractor::extract_symbols(&content, &language)
-            .map_err(|e| StorageError::InvalidInput(format!("failed to extract symbols: {e}")))?;
+        query_embedding: &[f32],
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        metadata: MetadataFilter,
+        min_score: f32,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        let column = self.precision.column();
+        let limit = top_k as i64;
 
-        let mut symbol_count = 0;
-        let mut symbols_to_store = Vec::new();
+        let mut sql = format!(
+            r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at,
+                   1 - ({column} <=> $1) as score
+            FROM symbols
+            WHERE {column} IS NOT NULL
+            "#,
+        );
 
-        fn flatten_symbols(
-            extracted_symbols: Vec<crate::symbol_extractor::ExtractedSymbol>,
-            path: &str,
-            commit_id: Option<&String>,
-            symbols_out: &mut Vec<StoredSymbol>,
-        ) {
-            for extracted in extracted_symbols {
-                let symbol = StoredSymbol {
-                    id: Uuid::new_v4(),
-                    path: path.to_string(),
-                    name: extracted.name,
-                    kind: extracted.kind.to_string(),
-                    content: extracted.content,
-                    embedding: vec![], // Will be computed
-                    commit_id: commit_id.cloned(),
-                    start_line: extracted.range.start.line as i32,
-                    end_line: extracted.range.end.line as i32,
-                    metadata: extracted
-                        .doc_comment
-                        .map(|doc| serde_json::json!({"doc": doc})),
-                    created_at: Utc::now(),
-                    updated_at: Utc::now(),
-                };
-                symbols_out.push(symbol);
-                flatten_symbols(extracted.children, path, commit_id, symbols_out);
-            }
+        let mut args = sqlx::postgres::PgArguments::default();
+        use sqlx::Arguments;
+        self.push_query_embedding(&mut args, query_embedding)?;
+
+        let mut param_idx = 2;
+
+        if let Some(prefix) = path_prefix {
+            sql.push_str(&format!(" AND path LIKE ${} ESCAPE '\\'", param_idx));
+            args.add(prefix_pattern(&prefix))
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            param_idx += 1;
         }
 
-        flatten_symbols(
-            extracted_symbols,
-            &path,
-            commit_id.as_ref(),
-            &mut symbols_to_store,
-        );
+        if let Some(commit) = commit {
+            commit.push_sql(&mut sql, &mut args, &mut param_idx)?;
+        }
 
-        // Now process symbols: generate embeddings and store
-        // Use parallel processing for embeddings
-        let concurrency = 4; // Adjust based on needs
-
-        let results = futures::stream::iter(symbols_to_store)
-            .map(|mut symbol| {
-                let storage = self.clone();
-                async move {
-                    let embedding = storage
-                        .embedding_manager
-                        .embed(&symbol.content)
-                        .await
-                        .map_err(|e| StorageError::Embedding(e.to_string()))?;
-
-                    symbol.embedding = embedding;
-                    Ok::<_, StorageError>(symbol)
-                }
-            })
-            .buffer_unordered(concurrency)
-            .collect::<Vec<_>>()
-            .await;
+        push_min_score(&mut sql, &mut args, &mut param_idx, column, min_score)?;
 
-        for result in results {
-            let symbol = result?;
-            let embedding_vector = Vector::from(symbol.embedding.clone());
+        metadata.push_sql(&mut sql, &mut args, &mut param_idx)?;
 
-            sqlx::query(
-                r#"
-                INSERT INTO symbols (id, path, name, kind, content, embedding_vector, commit_id, start_line, end_line, metadata, created_at, updated_at, embedding_model, embedding_generated_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11, $12, $11)
-                "#
-            )
-            .bind(symbol.id)
-            .bind(symbol.path)
-            .bind(symbol.name)
-            .bind(symbol.kind)
-            .bind(symbol.content)
-            .bind(embedding_vector)
-            .bind(symbol.commit_id)
-            .bind(symbol.start_line)
-            .bind(symbol.end_line)
-            .bind(symbol.metadata)
-            .bind(symbol.created_at)
-            .bind("all-MiniLM-L6-v2")
-            .execute(&self.pool)
-            .await?;
+        sql.push_str(&format!(
+            " ORDER BY {} <=> $1 ASC LIMIT ${}",
+            column, param_idx
+        ));
+        args.add(limit)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+
+        let rows: Vec<PgRow> = sqlx::query_with(&sql, args).fetch_all(&self.pool).await?;
 
-            symbol_count += 1;
+        let mut results = Vec::new();
+        for row in rows {
+            let symbol = StoredSymbol {
+                id: row.try_get("id")?,
+                path: row.try_get("path")?,
+                name: row.try_get("name")?,
+                kind: row.try_get("kind")?,
+                content: row.try_get("content")?,
+                embedding: vec![],
+                commit_id: row.try_get("commit_id")?,
+                start_line: row.try_get("start_line")?,
+                end_line: row.try_get("end_line")?,
+                metadata: row.try_get("metadata")?,
+                created_at: row.try_get("created_at")?,
+                updated_at: row.try_get("updated_at")?,
+            };
+            let score: f64 = row.try_get("score")?;
+            results.push((symbol, score as f32));
         }
 
-        Ok(symbol_count)
+        Ok(results)
     }
 
-    async fn search_documents(
+    /// Documents closest to `query_embedding`, best first.
+    async fn search_documents_near(
         &self,
-        query: String,
+        query_embedding: &[f32],
         top_k: usize,
         path_prefix: Option<String>,
-        commit_id: Option<String>,
+        commit: Option<CommitFilter>,
+        min_score: f32,
     ) -> Result<Vec<(StoredDocument, f32)>, StorageError> {
-        let query_embedding = self
-            .embedding_manager
-            .embed(&query)
-            .await
-            .map_err(|e| StorageError::Embedding(e.to_string()))?;
-
-        let embedding_vector = Vector::from(query_embedding);
+        let column = self.precision.column();
         let limit = top_k as i64;
 
         // Dynamic query construction is hard with sqlx macros, so we use query_as
         // Note: <=> is cosine distance, so we sort by ASC.
         // Similarity = 1 - distance.
 
-        let mut sql = String::from(
+        let mut sql = format!(
             r#"
             SELECT id, path, content, commit_id, created_at, updated_at, 
-                   1 - (embedding_vector <=> $1) as score
+                   1 - ({column} <=> $1) as score
             FROM documents
-            WHERE 1=1
+            WHERE {column} IS NOT NULL
             "#,
         );
 
         let mut args = sqlx::postgres::PgArguments::default();
         use sqlx::Arguments;
-        args.add(embedding_vector)
-            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        self.push_query_embedding(&mut args, query_embedding)?;
 
         let mut param_idx = 2;
 
         if let Some(prefix) = path_prefix {
-            sql.push_str(&format!(" AND path LIKE ${}", param_idx));
-            args.add(format!("{}%", prefix))
+            sql.push_str(&format!(" AND path LIKE ${} ESCAPE '\\'", param_idx));
+            args.add(prefix_pattern(&prefix))
                 .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
             param_idx += 1;
         }
 
-        if let Some(commit) = commit_id {
-            sql.push_str(&format!(" AND commit_id = ${}", param_idx));
-            args.add(commit)
-                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
-            param_idx += 1;
+        if let Some(commit) = commit {
+            commit.push_sql(&mut sql, &mut args, &mut param_idx)?;
         }
 
+        push_min_score(&mut sql, &mut args, &mut param_idx, column, min_score)?;
+
         sql.push_str(&format!(
-            " ORDER BY embedding_vector <=> $1 ASC LIMIT ${}",
-            param_idx
+            " ORDER BY {} <=> $1 ASC LIMIT ${}",
+            column, param_idx
         ));
         args.add(limit)
             .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
@@ -382,150 +1137,1799 @@ impl IndexStorage for Storage {
         Ok(results)
     }
 
-    async fn search_symbols(
+    /// Documents whose path or content contains every word of `query`,
+    /// ranked by `ts_rank` with path matches weighted highest.
+    async fn search_documents_by_keyword(
         &self,
-        query: String,
+        query: &str,
         top_k: usize,
         path_prefix: Option<String>,
-        commit_id: Option<String>,
-    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
-        let query_embedding = self
-            .embedding_manager
-            .embed(&query)
-            .await
-            .map_err(|e| StorageError::Embedding(e.to_string()))?;
-
-        let embedding_vector = Vector::from(query_embedding);
-        let limit = top_k as i64;
-
-        let mut sql = String::from(
+        commit: Option<CommitFilter>,
+    ) -> Result<Vec<(StoredDocument, f32)>, StorageError> {
+        let mut sql = format!(
             r#"
-            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at,
-                   1 - (embedding_vector <=> $1) as score
-            FROM symbols
-            WHERE 1=1
+            SELECT id, path, content, commit_id, created_at, updated_at,
+                   ts_rank({DOCUMENT_TSVECTOR}, plainto_tsquery('simple', $1), 32) as score
+            FROM documents
+            WHERE {DOCUMENT_TSVECTOR} @@ plainto_tsquery('simple', $1)
             "#,
         );
 
         let mut args = sqlx::postgres::PgArguments::default();
         use sqlx::Arguments;
-        args.add(embedding_vector)
+        args.add(query)
             .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
 
         let mut param_idx = 2;
 
         if let Some(prefix) = path_prefix {
-            sql.push_str(&format!(" AND path LIKE ${}", param_idx));
-            args.add(format!("{}%", prefix))
+            sql.push_str(&format!(" AND path LIKE ${} ESCAPE '\\'", param_idx));
+            args.add(prefix_pattern(&prefix))
                 .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
             param_idx += 1;
         }
 
-        if let Some(commit) = commit_id {
-            sql.push_str(&format!(" AND commit_id = ${}", param_idx));
-            args.add(commit)
-                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
-            param_idx += 1;
+        if let Some(commit) = commit {
+            commit.push_sql(&mut sql, &mut args, &mut param_idx)?;
         }
 
-        sql.push_str(&format!(
-            " ORDER BY embedding_vector <=> $1 ASC LIMIT ${}",
-            param_idx
-        ));
-        args.add(limit)
+        sql.push_str(&format!(" ORDER BY score DESC LIMIT ${}", param_idx));
+        args.add(top_k as i64)
             .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
 
         let rows: Vec<PgRow> = sqlx::query_with(&sql, args).fetch_all(&self.pool).await?;
 
         let mut results = Vec::new();
         for row in rows {
-            let symbol = StoredSymbol {
+            let doc = StoredDocument {
                 id: row.try_get("id")?,
                 path: row.try_get("path")?,
-                name: row.try_get("name")?,
-                kind: row.try_get("kind")?,
                 content: row.try_get("content")?,
                 embedding: vec![],
                 commit_id: row.try_get("commit_id")?,
-                start_line: row.try_get("start_line")?,
-                end_line: row.try_get("end_line")?,
-                metadata: row.try_get("metadata")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             };
-            let score: f64 = row.try_get("score")?;
-            results.push((symbol, score as f32));
+            let score: f32 = row.try_get("score")?;
+            results.push((doc, score));
         }
 
         Ok(results)
     }
 
-    async fn query_all_symbols(&self) -> Result<Vec<StoredSymbol>, StorageError> {
-        let symbols = sqlx::query_as::<_, StoredSymbol>(
+    /// Symbols whose name or content contains every word of `query`, ranked
+    /// by `ts_rank` with name matches weighted highest.
+    async fn search_symbols_by_keyword(
+        &self,
+        query: &str,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        metadata: MetadataFilter,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        // Normalization 32 maps the rank into [0, 1).
+        let mut sql = format!(
             r#"
-            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at,
+                   ts_rank({SYMBOL_TSVECTOR}, plainto_tsquery('simple', $1), 32) as score
             FROM symbols
-            "#
-        )
-        .fetch_all(&self.pool)
+            WHERE {SYMBOL_TSVECTOR} @@ plainto_tsquery('simple', $1)
+            "#,
+        );
+
+        let mut args = sqlx::postgres::PgArguments::default();
+        use sqlx::Arguments;
+        args.add(query)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+
+        let mut param_idx = 2;
+
+        if let Some(prefix) = path_prefix {
+            sql.push_str(&format!(" AND path LIKE ${} ESCAPE '\\'", param_idx));
+            args.add(prefix_pattern(&prefix))
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            param_idx += 1;
+        }
+
+        if let Some(commit) = commit {
+            commit.push_sql(&mut sql, &mut args, &mut param_idx)?;
+        }
+
+        metadata.push_sql(&mut sql, &mut args, &mut param_idx)?;
+
+        sql.push_str(&format!(" ORDER BY score DESC LIMIT ${}", param_idx));
+        args.add(top_k as i64)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+
+        let rows: Vec<PgRow> = sqlx::query_with(&sql, args).fetch_all(&self.pool).await?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let symbol = StoredSymbol::from_row(&row)?;
+            let score: f32 = row.try_get("score")?;
+            results.push((symbol, score));
+        }
+
+        Ok(results)
+    }
+
+    #[allow(dead_code)]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn embedding_manager(&self) -> Arc<crate::embeddings::EmbeddingManager> {
+        self.embedding_manager.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IndexStorage for Storage {
+    async fn index_document(
+        &self,
+        path: String,
+        content: String,
+        commit_id: Option<String>,
+    ) -> Result<Uuid, StorageError> {
+        let content = self.normalization.apply(&content).into_owned();
+        let metadata = serde_json::json!({
+            "content_hash": normalize::content_hash(&content),
+            "normalized": self.normalization.is_enabled(),
+        });
+
+        let embedding = self
+            .embedding_manager
+            .embed(&content)
+            .await
+            .map_err(|e| StorageError::Embedding(e.to_string()))?;
+
+        let stored = self.precision.store(&embedding);
+        let now = self.clock.now();
+
+        // On conflict the existing row keeps its id, which RETURNING reports.
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO documents (id, path, content, embedding_vector, commit_id, created_at, updated_at, embedding_model, embedding_generated_at, metadata, embedding_half)
+            VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $6, $8, $9)
+            ON CONFLICT ({}) DO UPDATE
+            SET content = $3,
+            embedding_vector = $4,
+            embedding_half = $9,
+            commit_id = $5,
+            updated_at = $6,
+            embedding_generated_at = $6,
+            metadata = COALESCE(documents.metadata, '{{}}'::jsonb) || $8
+            RETURNING id
+            "#,
+            self.document_conflict_key.sql()
+        ))
+        .bind(Uuid::new_v4())
+        .bind(path)
+        .bind(content)
+        .bind(stored.full)
+        .bind(commit_id)
+        .bind(now)
+        .bind(self.embedding_manager.model_name().to_string())
+        .bind(metadata)
+        .bind(stored.half)
+        .fetch_one(&self.pool)
         .await?;
 
-        Ok(symbols)
+        Ok(row.try_get("id")?)
     }
 
-    async fn store_symbol(&self, symbol: &StoredSymbol) -> Result<(), StorageError> {
-        // Note: This method is used by SymbolRegistry to update symbols.
-        // It assumes the symbol already has an embedding if it was fetched from DB,
-        // but if it's new or updated, we might need to re-embed.
-        // For now, we'll assume the embedding is handled by the caller or we re-embed if empty.
+    async fn batch_index_documents(
+        &self,
+        documents: Vec<NewDocument>,
+    ) -> Result<Vec<Result<Uuid, StorageError>>, StorageError> {
+        // One upsert cannot touch the same row twice.
+        let by_commit = self.document_conflict_key == DocumentConflictKey::PathAndCommit;
+        let mut seen = std::collections::HashSet::new();
+        if let Some(duplicate) = documents.iter().find(|doc| {
+            let commit = by_commit.then_some(&doc.commit_id);
+            !seen.insert((&doc.path, commit))
+        }) {
+            return Err(StorageError::InvalidInput(format!(
+                "path '{}' appears more than once in the batch",
+                duplicate.path
+            )));
+        }
 
-        let embedding_vector = if symbol.embedding.is_empty() {
-            let embedding = self
-                .embedding_manager
-                .embed(&symbol.content)
-                .await
-                .map_err(|e| StorageError::Embedding(e.to_string()))?;
-            Vector::from(embedding)
-        } else {
-            Vector::from(symbol.embedding.clone())
-        };
+        let mut embedded: Vec<_> = futures::stream::iter(documents.into_iter().enumerate())
+            .map(|(position, document)| async move {
+                let content = self.normalization.apply(&document.content).into_owned();
+                let embedding = self
+                    .embedding_manager
+                    .embed(&content)
+                    .await
+                    .map_err(|e| StorageError::Embedding(e.to_string()));
+                (position, document, content, embedding)
+            })
+            .buffer_unordered(EMBED_CONCURRENCY)
+            .collect()
+            .await;
+        embedded.sort_by_key(|(position, ..)| *position);
 
-        sqlx::query(
-                r#"
-                INSERT INTO symbols (id, path, name, kind, content, embedding_vector, commit_id, start_line, end_line, metadata, created_at, updated_at, embedding_model, embedding_generated_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $12)
-                ON CONFLICT (id) DO UPDATE
-                SET content = $5,
-                embedding_vector = $6,
-                commit_id = $7,
-                start_line = $8,
-                end_line = $9,
-                metadata = $10,
-                updated_at = $12,
-                embedding_generated_at = $12
-                "#
-            )
-            .bind(symbol.id)
-            .bind(symbol.path.clone())
-            .bind(symbol.name.clone())
-            .bind(symbol.kind.clone())
-            .bind(symbol.content.clone())
-            .bind(embedding_vector)
-            .bind(symbol.commit_id.clone())
-            .bind(symbol.start_line)
-            .bind(symbol.end_line)
-            .bind(symbol.metadata.clone())
-            .bind(symbol.created_at)
-            .bind(symbol.updated_at)
-            .bind("all-MiniLM-L6-v2")
-            .execute(&self.pool)
-            .await?;
+        // Each document's path, or why it is not written.
+        let mut outcomes = Vec::with_capacity(embedded.len());
+        let mut rows = Vec::new();
+        for (_, document, content, embedding) in embedded {
+            match embedding {
+                Ok(embedding) => {
+                    outcomes.push(Ok((document.path.clone(), document.commit_id.clone())));
+                    rows.push((document, content, self.precision.store(&embedding)));
+                }
+                Err(e) => outcomes.push(Err(e)),
+            }
+        }
+        if rows.is_empty() {
+            return Ok(outcomes
+                .into_iter()
+                .map(|outcome| outcome.map(|_| Uuid::nil()))
+                .collect());
+        }
 
-        Ok(())
+        let now = self.clock.now();
+        let normalized = self.normalization.is_enabled();
+        let mut insert = sqlx::QueryBuilder::new(
+            "INSERT INTO documents (id, path, content, embedding_vector, commit_id, created_at, updated_at, embedding_model, embedding_generated_at, metadata, embedding_half) ",
+        );
+        insert.push_values(rows, |mut row, (document, content, stored)| {
+            let metadata = serde_json::json!({
+                "content_hash": normalize::content_hash(&content),
+                "normalized": normalized,
+            });
+            row.push_bind(Uuid::new_v4())
+                .push_bind(document.path)
+                .push_bind(content)
+                .push_bind(stored.full)
+                .push_bind(document.commit_id)
+                .push_bind(now)
+                .push_bind(now)
+                .push_bind(self.embedding_manager.model_name().to_string())
+                .push_bind(now)
+                .push_bind(metadata)
+                .push_bind(stored.half);
+        });
+        // The same upsert as index_document; an existing row keeps its id.
+        insert.push(format!(
+            r#"
+            ON CONFLICT ({}) DO UPDATE
+            SET content = EXCLUDED.content,
+            embedding_vector = EXCLUDED.embedding_vector,
+            embedding_half = EXCLUDED.embedding_half,
+            commit_id = EXCLUDED.commit_id,
+            updated_at = EXCLUDED.updated_at,
+            embedding_generated_at = EXCLUDED.embedding_generated_at,
+            metadata = COALESCE(documents.metadata, '{{}}'::jsonb) || EXCLUDED.metadata
+            RETURNING id, path, commit_id
+            "#,
+            self.document_conflict_key.sql()
+        ));
+        let mut ids = HashMap::new();
+        for row in insert.build().fetch_all(&self.pool).await? {
+            let path: String = row.try_get("path")?;
+            let commit_id: Option<String> = row.try_get("commit_id")?;
+            let id: Uuid = row.try_get("id")?;
+            ids.insert((path, commit_id), id);
+        }
+
+        Ok(outcomes
+            .into_iter()
+            .map(|outcome| outcome.map(|key| ids.get(&key).copied().unwrap_or_default()))
+            .collect())
     }
-}
 
-pub async fn create_storage(config: StorageConfig) -> Result<Arc<Storage>, StorageError> {
-    let storage = Storage::new(config).await?;
-    Ok(Arc::new(storage))
+    async fn index_symbols(
+        &self,
+        path: String,
+        content: String,
+        language: String,
+        commit_id: Option<String>,
+        range: Option<LineRange>,
+        declarations_only: bool,
+    ) -> Result<Vec<Uuid>, StorageError> {
+        let content = self.normalization.apply(&content);
+        let source = match range {
+            Some(range) => range.slice(&content).map_err(StorageError::InvalidInput)?,
+            None => &content,
+        };
+        let mut extracted_symbols = symbol_extractor::extract_symbols(source, &language)
+            .map_err(|e| StorageError::InvalidInput(format!("failed to extract symbols: {e}")))?;
+        symbol_extractor::offset_lines(&mut extracted_symbols, range.map_or(0, |r| r.start));
+        if declarations_only {
+            symbol_extractor::declarations_only(&mut extracted_symbols);
+        }
+
+        let mut symbols_to_store = Vec::new();
+
+        let mut edges = Vec::new();
+
+        #[allow(clippy::too_many_arguments)]
+        fn flatten_symbols(
+            extracted_symbols: Vec<crate::symbol_extractor::ExtractedSymbol>,
+            parent: Option<Uuid>,
+            path: &str,
+            commit_id: Option<&String>,
+            metadata: &dyn Fn(&crate::symbol_extractor::ExtractedSymbol) -> serde_json::Value,
+            now: DateTime<Utc>,
+            symbols_out: &mut Vec<StoredSymbol>,
+            edges_out: &mut Vec<(Uuid, Uuid)>,
+        ) {
+            for extracted in extracted_symbols {
+                let metadata_value = metadata(&extracted);
+                let symbol = StoredSymbol {
+                    id: Uuid::new_v4(),
+                    path: path.to_string(),
+                    name: extracted.name,
+                    kind: extracted.kind.to_string(),
+                    content: extracted.content,
+                    embedding: vec![], // Will be computed
+                    commit_id: commit_id.cloned(),
+                    start_line: extracted.range.start.line as i32,
+                    end_line: extracted.range.end.line as i32,
+                    metadata: Some(metadata_value),
+                    created_at: now,
+                    updated_at: now,
+                };
+                let id = symbol.id;
+                if let Some(parent) = parent {
+                    edges_out.push((parent, id));
+                }
+                symbols_out.push(symbol);
+                flatten_symbols(
+                    extracted.children,
+                    Some(id),
+                    path,
+                    commit_id,
+                    metadata,
+                    now,
+                    symbols_out,
+                    edges_out,
+                );
+            }
+        }
+
+        let language = crate::ast::canonical_language(&language).unwrap_or(&language);
+        let normalized = self.normalization.is_enabled();
+        flatten_symbols(
+            extracted_symbols,
+            None,
+            &path,
+            commit_id.as_ref(),
+            &|extracted| {
+                symbol_metadata(
+                    extracted.doc_comment.clone(),
+                    language,
+                    extracted.range.start.line,
+                    extracted.range.end.line,
+                    extracted.complexity,
+                    normalized,
+                    declarations_only,
+                )
+            },
+            self.clock.now(),
+            &mut symbols_to_store,
+            &mut edges,
+        );
+
+        let mut symbols = embed_symbols(
+            self.embedding_manager.as_ref(),
+            symbols_to_store,
+            self.min_embed_chars,
+            &self.embedding_template,
+        )
+        .await?;
+
+        // Re-indexing replaces what the previous extraction stored for this
+        // path and commit (or, for a line range, within those lines). The
+        // delete and inserts share a transaction, rolled back when it is
+        // dropped on an early return, so a failed insert leaves the old rows in
+        // place and none of the new ones. The deleted rows hand their ids on
+        // to the symbols replacing them, so ids are stable across re-indexing.
+        let mut tx = self.pool.begin().await?;
+        let mut delete = sqlx::QueryBuilder::new("DELETE FROM symbols WHERE path = ");
+        delete
+            .push_bind(&path)
+            .push(" AND commit_id IS NOT DISTINCT FROM ")
+            .push_bind(&commit_id);
+        if let Some(range) = range {
+            delete
+                .push(" AND start_line >= ")
+                .push_bind(range.start as i32)
+                .push(" AND end_line <= ")
+                .push_bind(range.end as i32);
+        }
+        delete.push(
+            " RETURNING id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at",
+        );
+        let previous: Vec<StoredSymbol> = delete.build_query_as().fetch_all(&mut *tx).await?;
+        let reused = reuse_symbol_ids(&previous, &mut symbols);
+        let id_of = |id: Uuid| reused.get(&id).copied().unwrap_or(id);
+
+        let mut ids = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            // Symbols skipped as trivial are stored with a NULL vector.
+            let stored = self.precision.store(&symbol.embedding);
+
+            sqlx::query(
+                r#"
+                INSERT INTO symbols (id, path, name, kind, content, embedding_vector, commit_id, start_line, end_line, metadata, created_at, updated_at, embedding_model, embedding_generated_at, embedding_half, language)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $12, $14, $15)
+                "#
+            )
+            .bind(symbol.id)
+            .bind(symbol.path)
+            .bind(symbol.name)
+            .bind(symbol.kind)
+            .bind(symbol.content)
+            .bind(stored.full)
+            .bind(symbol.commit_id)
+            .bind(symbol.start_line)
+            .bind(symbol.end_line)
+            .bind(symbol.metadata)
+            .bind(symbol.created_at)
+            .bind(symbol.updated_at)
+            .bind(self.embedding_manager.model_name().to_string())
+            .bind(stored.half)
+            .bind(language)
+            .execute(&mut *tx)
+            .await?;
+
+            ids.push(symbol.id);
+        }
+
+        // Edges of the replaced symbols went with them (ON DELETE CASCADE).
+        for (parent_id, child_id) in edges {
+            sqlx::query(
+                "INSERT INTO symbol_edges (parent_id, child_id, relation) VALUES ($1, $2, $3)",
+            )
+            .bind(id_of(parent_id))
+            .bind(id_of(child_id))
+            .bind(CONTAINS_RELATION)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    async fn search_documents(
+        &self,
+        query: String,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        min_score: f32,
+        mode: SearchMode,
+    ) -> Result<Vec<(StoredDocument, f32)>, StorageError> {
+        if mode == SearchMode::Keyword {
+            let mut results = self
+                .search_documents_by_keyword(&query, top_k, path_prefix, commit)
+                .await?;
+            results.retain(|(_, score)| *score >= min_score);
+            return Ok(results);
+        }
+
+        let query_embedding = embed_query(
+            self.embedding_manager.as_ref(),
+            self.query_preprocessing,
+            &query,
+        )
+        .await?;
+
+        if mode == SearchMode::Vector {
+            return self
+                .search_documents_near(&query_embedding, top_k, path_prefix, commit, min_score)
+                .await;
+        }
+
+        // As for symbols, the threshold applies to the fused score.
+        let by_vector = self
+            .search_documents_near(
+                &query_embedding,
+                top_k,
+                path_prefix.clone(),
+                commit.clone(),
+                0.0,
+            )
+            .await?;
+        let by_keyword = self
+            .search_documents_by_keyword(&query, top_k, path_prefix, commit)
+            .await?;
+        let mut fused = reciprocal_rank_fusion(
+            vec![
+                by_vector.into_iter().map(|(doc, _)| doc).collect(),
+                by_keyword.into_iter().map(|(doc, _)| doc).collect(),
+            ],
+            |doc: &StoredDocument| doc.id,
+        );
+        fused.retain(|(_, score)| *score >= min_score);
+        fused.truncate(top_k);
+        Ok(fused)
+    }
+
+    async fn search_symbols(
+        &self,
+        query: String,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        metadata: MetadataFilter,
+        min_score: f32,
+        mode: SearchMode,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        if mode == SearchMode::Keyword {
+            let mut results = self
+                .search_symbols_by_keyword(&query, top_k, path_prefix, commit, metadata)
+                .await?;
+            results.retain(|(_, score)| *score >= min_score);
+            return Ok(results);
+        }
+
+        let query_embedding = embed_query(
+            self.embedding_manager.as_ref(),
+            self.query_preprocessing,
+            &query,
+        )
+        .await?;
+
+        if mode == SearchMode::Vector {
+            return self
+                .search_symbols_near(
+                    &query_embedding,
+                    top_k,
+                    path_prefix,
+                    commit,
+                    metadata,
+                    min_score,
+                )
+                .await;
+        }
+
+        // The threshold applies to the fused score, not to either list.
+        let by_vector = self
+            .search_symbols_near(
+                &query_embedding,
+                top_k,
+                path_prefix.clone(),
+                commit.clone(),
+                metadata.clone(),
+                0.0,
+            )
+            .await?;
+        let by_keyword = self
+            .search_symbols_by_keyword(&query, top_k, path_prefix, commit, metadata)
+            .await?;
+        let mut fused = reciprocal_rank_fusion(
+            vec![
+                by_vector.into_iter().map(|(symbol, _)| symbol).collect(),
+                by_keyword.into_iter().map(|(symbol, _)| symbol).collect(),
+            ],
+            |symbol: &StoredSymbol| symbol.id,
+        );
+        fused.retain(|(_, score)| *score >= min_score);
+        fused.truncate(top_k);
+        Ok(fused)
+    }
+
+    async fn search_symbols_by_vector(
+        &self,
+        embedding: Vec<f32>,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        self.search_symbols_near(
+            &embedding,
+            top_k,
+            path_prefix,
+            commit,
+            MetadataFilter::default(),
+            0.0,
+        )
+        .await
+    }
+
+    async fn top_symbols(
+        &self,
+        metric: SymbolMetric,
+        limit: usize,
+        offset: usize,
+        path_prefix: Option<String>,
+    ) -> Result<Vec<(StoredSymbol, i64)>, StorageError> {
+        let key = metric.metadata_key();
+        // Guard the cast so a non-numeric value in one row cannot fail the query.
+        let mut sql = format!(
+            r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at,
+                   (metadata->>'{key}')::int AS metric_value
+            FROM symbols
+            WHERE metadata->>'{key}' ~ '^-?[0-9]{{1,9}}$'
+            "#
+        );
+
+        let mut args = sqlx::postgres::PgArguments::default();
+        use sqlx::Arguments;
+        let mut param_idx = 1;
+
+        if let Some(prefix) = path_prefix {
+            sql.push_str(&format!(" AND path LIKE ${} ESCAPE '\\'", param_idx));
+            args.add(prefix_pattern(&prefix))
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            param_idx += 1;
+        }
+
+        sql.push_str(&format!(
+            " ORDER BY metric_value DESC, path ASC, start_line ASC, id ASC LIMIT ${} OFFSET ${}",
+            param_idx,
+            param_idx + 1
+        ));
+        args.add(limit as i64)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        args.add(offset as i64)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+
+        let rows: Vec<PgRow> = sqlx::query_with(&sql, args).fetch_all(&self.pool).await?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let symbol = StoredSymbol::from_row(&row)?;
+            let value: i32 = row.try_get("metric_value")?;
+            results.push((symbol, value as i64));
+        }
+
+        Ok(results)
+    }
+
+    async fn find_symbols_by_name(
+        &self,
+        query: String,
+        kind: Option<SymbolKind>,
+        limit: usize,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        let query = query.trim().to_string();
+        let candidates = (limit * NAME_MATCH_OVERFETCH_FACTOR)
+            .min(MAX_NAME_MATCH_CANDIDATES)
+            .max(limit);
+
+        // Substring hits and trigram neighbours (pg_trgm, migration 005) are
+        // fetched by similarity; the final order comes from name_match_score.
+        let mut sql = r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at
+            FROM symbols
+            WHERE (name ILIKE $1 ESCAPE '\' OR name % $2)
+            "#
+        .to_string();
+
+        let mut args = sqlx::postgres::PgArguments::default();
+        use sqlx::Arguments;
+        args.add(format!("%{}%", escape_like(&query)))
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        args.add(query.clone())
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        let mut param_idx = 3;
+
+        if let Some(kind) = kind {
+            sql.push_str(&format!(" AND kind = ${}", param_idx));
+            args.add(kind.to_string())
+                .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+            param_idx += 1;
+        }
+
+        sql.push_str(&format!(
+            " ORDER BY similarity(name, $2) DESC, length(name) ASC LIMIT ${}",
+            param_idx
+        ));
+        args.add(candidates as i64)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+
+        let rows: Vec<PgRow> = sqlx::query_with(&sql, args).fetch_all(&self.pool).await?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let symbol = StoredSymbol::from_row(&row)?;
+            if let Some(score) = name_match_score(&symbol.name, &query) {
+                results.push((symbol, score));
+            }
+        }
+        rank_name_matches(&mut results);
+        results.truncate(limit);
+
+        Ok(results)
+    }
+
+    async fn symbols_enclosing(
+        &self,
+        path: String,
+        line: i32,
+        commit_id: Option<String>,
+    ) -> Result<Vec<StoredSymbol>, StorageError> {
+        let symbols = sqlx::query_as::<_, StoredSymbol>(
+            r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at
+            FROM symbols
+            WHERE path = $1 AND start_line <= $2 AND end_line >= $2
+              AND ($3::TEXT IS NULL OR commit_id = $3)
+            ORDER BY end_line - start_line DESC, start_line ASC
+            "#,
+        )
+        .bind(path)
+        .bind(line)
+        .bind(commit_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(symbols)
+    }
+
+    async fn delete_path(
+        &self,
+        path: String,
+        commit_id: Option<String>,
+    ) -> Result<PathDeletion, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Hierarchy edges go with the symbols (ON DELETE CASCADE).
+        let symbols = sqlx::query(
+            "DELETE FROM symbols WHERE path = $1 AND ($2::TEXT IS NULL OR commit_id = $2)",
+        )
+        .bind(&path)
+        .bind(&commit_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let documents = sqlx::query(
+            "DELETE FROM documents WHERE path = $1 AND ($2::TEXT IS NULL OR commit_id = $2)",
+        )
+        .bind(&path)
+        .bind(&commit_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(PathDeletion { documents, symbols })
+    }
+
+    async fn touch(&self, path: String) -> Result<u64, StorageError> {
+        let result = sqlx::query("UPDATE documents SET updated_at = $1 WHERE path = $2")
+            .bind(self.clock.now())
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_by_commit(&self, commit_id: String) -> Result<CommitDeletion, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let symbol_versions = sqlx::query("DELETE FROM symbol_versions WHERE commit_id = $1")
+            .bind(&commit_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        // Relationships have no commit of their own; drop the ones that would
+        // otherwise point at deleted symbols.
+        sqlx::query(
+            r#"
+            DELETE FROM symbol_relationships
+            WHERE from_symbol_id IN (SELECT id FROM symbols WHERE commit_id = $1)
+               OR to_symbol_id IN (SELECT id FROM symbols WHERE commit_id = $1)
+            "#,
+        )
+        .bind(&commit_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let symbols = sqlx::query("DELETE FROM symbols WHERE commit_id = $1")
+            .bind(&commit_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let documents = sqlx::query("DELETE FROM documents WHERE commit_id = $1")
+            .bind(&commit_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(CommitDeletion {
+            documents,
+            symbols,
+            symbol_versions,
+        })
+    }
+
+    async fn health_check(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn find_orphans(&self) -> Result<OrphanReport, StorageError> {
+        // An orphaned symbol is only counted once, as orphaned.
+        let (orphaned, dangling, unembedded): (i64, i64, i64) = sqlx::query_as(&format!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM symbols s WHERE {ORPHAN_SYMBOL}),
+                (SELECT COUNT(*) FROM symbol_relationships s WHERE {DANGLING_EDGE}),
+                (SELECT COUNT(*) FROM symbols s WHERE {} AND NOT ({ORPHAN_SYMBOL}))
+            "#,
+            self.missing_embedding()
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(OrphanReport {
+            symbols_without_document: orphaned as u64,
+            dangling_edges: dangling as u64,
+            invalid_embeddings: unembedded as u64,
+        })
+    }
+
+    async fn delete_orphans(&self) -> Result<OrphanReport, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Symbols go first so edges left pointing at them are caught too.
+        let symbols_without_document =
+            sqlx::query(&format!("DELETE FROM symbols s WHERE {ORPHAN_SYMBOL}"))
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        let invalid_embeddings = sqlx::query(&format!(
+            "DELETE FROM symbols s WHERE {}",
+            self.missing_embedding()
+        ))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        let dangling_edges = sqlx::query(&format!(
+            "DELETE FROM symbol_relationships s WHERE {DANGLING_EDGE}"
+        ))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(OrphanReport {
+            symbols_without_document,
+            dangling_edges,
+            invalid_embeddings,
+        })
+    }
+
+    async fn get_symbol(&self, id: Uuid) -> Result<Option<StoredSymbol>, StorageError> {
+        let symbol = sqlx::query_as::<_, StoredSymbol>(
+            r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at
+            FROM symbols
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(symbol)
+    }
+
+    async fn query_all_symbols(&self) -> Result<Vec<StoredSymbol>, StorageError> {
+        let symbols = sqlx::query_as::<_, StoredSymbol>(
+            r#"
+            SELECT id, path, name, kind, content, commit_id, start_line, end_line, metadata, created_at, updated_at
+            FROM symbols
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(symbols)
+    }
+
+    async fn query_symbol_edges(&self) -> Result<Vec<SymbolEdge>, StorageError> {
+        let edges = sqlx::query_as::<_, SymbolEdge>(
+            "SELECT parent_id, child_id, relation FROM symbol_edges",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(edges)
+    }
+
+    async fn store_symbol(&self, symbol: &StoredSymbol) -> Result<(), StorageError> {
+        // Note: This method is used by SymbolRegistry to update symbols.
+        // It assumes the symbol already has an embedding if it was fetched from DB,
+        // but if it's new or updated, we might need to re-embed.
+        // For now, we'll assume the embedding is handled by the caller or we re-embed if empty.
+
+        let stored = if symbol.embedding.is_empty() {
+            let embedding = self
+                .embedding_manager
+                .embed(&symbol.content)
+                .await
+                .map_err(|e| StorageError::Embedding(e.to_string()))?;
+            self.precision.store(&embedding)
+        } else {
+            self.precision.store(&symbol.embedding)
+        };
+
+        sqlx::query(
+                r#"
+                INSERT INTO symbols (id, path, name, kind, content, embedding_vector, commit_id, start_line, end_line, metadata, created_at, updated_at, embedding_model, embedding_generated_at, embedding_half, language)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $12, $14, $15)
+                ON CONFLICT (id) DO UPDATE
+                SET path = $2,
+                name = $3,
+                kind = $4,
+                content = $5,
+                embedding_vector = $6,
+                embedding_half = $14,
+                commit_id = $7,
+                start_line = $8,
+                end_line = $9,
+                metadata = $10,
+                updated_at = $12,
+                embedding_generated_at = $12,
+                language = $15
+                "#
+            )
+            .bind(symbol.id)
+            .bind(symbol.path.clone())
+            .bind(symbol.name.clone())
+            .bind(symbol.kind.clone())
+            .bind(symbol.content.clone())
+            .bind(stored.full)
+            .bind(symbol.commit_id.clone())
+            .bind(symbol.start_line)
+            .bind(symbol.end_line)
+            .bind(symbol.metadata.clone())
+            .bind(symbol.created_at)
+            .bind(symbol.updated_at)
+            .bind(self.embedding_manager.model_name().to_string())
+            .bind(stored.half)
+            .bind(symbol.language())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn store_ci_event(&self, event: &CiEvent) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO ci_events (test_name, status, commit_id, timestamp, error_message)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&event.test_name)
+        .bind(event.status.as_str())
+        .bind(&event.commit_id)
+        .bind(event.timestamp)
+        .bind(&event.error_message)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn ci_events_for_commit(&self, commit_id: String) -> Result<Vec<CiEvent>, StorageError> {
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"
+            SELECT test_name, status, commit_id, timestamp, error_message
+            FROM ci_events
+            WHERE commit_id = $1
+            ORDER BY timestamp
+            "#,
+        )
+        .bind(commit_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let status: String = row.try_get("status")?;
+                Ok(CiEvent {
+                    test_name: row.try_get("test_name")?,
+                    status: CiStatus::parse(&status).ok_or_else(|| {
+                        sqlx::Error::Decode(format!("unknown CI status '{}'", status).into())
+                    })?,
+                    commit_id: row.try_get("commit_id")?,
+                    timestamp: row.try_get("timestamp")?,
+                    error_message: row.try_get("error_message")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_symbol_versions(
+        &self,
+        path: &str,
+        commit_id: &str,
+        versions: &[SymbolVersion],
+    ) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM symbol_versions WHERE path = $1 AND commit_id = $2")
+            .bind(path)
+            .bind(commit_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for version in versions {
+            sqlx::query(
+                r#"
+                INSERT INTO symbol_versions
+                    (path, symbol_id, commit_id, timestamp, change_type, author,
+                     commit_message, previous_path, content, symbol)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
+            )
+            .bind(path)
+            .bind(version.symbol_id)
+            .bind(&version.commit_id)
+            .bind(version.timestamp)
+            .bind(version.change_type.as_str().to_ascii_lowercase())
+            .bind(&version.author)
+            .bind(&version.commit_message)
+            .bind(&version.previous_path)
+            .bind(version.symbol.as_ref().map(|symbol| &symbol.content))
+            .bind(version.symbol.as_ref().map(sqlx::types::Json))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn symbol_versions_for_path(
+        &self,
+        path: String,
+    ) -> Result<Vec<SymbolVersion>, StorageError> {
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"
+            SELECT symbol_id, commit_id, timestamp, change_type, author,
+                   commit_message, previous_path, symbol
+            FROM symbol_versions
+            WHERE path = $1
+            ORDER BY timestamp DESC, commit_id, symbol_id
+            "#,
+        )
+        .bind(path)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let change_type: String = row.try_get("change_type")?;
+                let symbol: Option<sqlx::types::Json<StoredSymbol>> = row.try_get("symbol")?;
+                Ok(SymbolVersion {
+                    symbol_id: row.try_get("symbol_id")?,
+                    commit_id: row.try_get("commit_id")?,
+                    timestamp: row.try_get("timestamp")?,
+                    change_type: ChangeType::parse(&change_type).ok_or_else(|| {
+                        sqlx::Error::Decode(format!("unknown change type '{}'", change_type).into())
+                    })?,
+                    author: row.try_get("author")?,
+                    commit_message: row.try_get("commit_message")?,
+                    symbol: symbol.map(|symbol| symbol.0),
+                    previous_path: row.try_get("previous_path")?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Embeds a search query after the configured preprocessing.
+pub(crate) async fn embed_query(
+    embedder: &dyn EmbeddingProvider,
+    preprocessing: QueryPreprocessing,
+    query: &str,
+) -> Result<Vec<f32>, StorageError> {
+    embedder
+        .embed(&preprocessing.apply(query))
+        .await
+        .map_err(|e| StorageError::Embedding(e.to_string()))
+}
+
+/// Gives re-extracted `symbols` the ids of the `previous` rows they replace,
+/// so a symbol's id, and with it its history, survives re-indexing.
+///
+/// A symbol takes the id of a previous one with the same name and kind,
+/// pairing them in line order when a file has several. Of the rest, a symbol
+/// whose content is a previous one's of the same kind with only the name
+/// changed is taken to be that symbol renamed, and keeps its id as well. Reused ids keep their original
+/// `created_at`. Returns the ids the symbols were extracted with, mapped to
+/// the ids they now have, for anything (such as hierarchy edges) that refers
+/// to them.
+pub fn reuse_symbol_ids(
+    previous: &[StoredSymbol],
+    symbols: &mut [StoredSymbol],
+) -> HashMap<Uuid, Uuid> {
+    let mut previous: Vec<&StoredSymbol> = previous.iter().collect();
+    previous.sort_by_key(|symbol| symbol.start_line);
+    let mut taken = vec![false; previous.len()];
+    let mut matched = vec![false; symbols.len()];
+    let mut replaced = HashMap::new();
+
+    let mut order: Vec<usize> = (0..symbols.len()).collect();
+    order.sort_by_key(|&index| symbols[index].start_line);
+    fn same_key(old: &StoredSymbol, new: &StoredSymbol) -> bool {
+        old.name == new.name && old.kind == new.kind
+    }
+    fn renamed(old: &StoredSymbol, new: &StoredSymbol) -> bool {
+        old.kind == new.kind && old.content.replace(&old.name, &new.name) == new.content
+    }
+
+    for matches in [same_key, renamed] {
+        for &index in &order {
+            if matched[index] {
+                continue;
+            }
+            let symbol = &mut symbols[index];
+            let found = previous
+                .iter()
+                .enumerate()
+                .find(|(slot, old)| !taken[*slot] && matches(old, symbol));
+            if let Some((slot, old)) = found {
+                taken[slot] = true;
+                matched[index] = true;
+                replaced.insert(symbol.id, old.id);
+                symbol.id = old.id;
+                symbol.created_at = old.created_at;
+            }
+        }
+    }
+
+    replaced
+}
+
+/// Embedding requests kept in flight while indexing several symbols or
+/// documents.
+const EMBED_CONCURRENCY: usize = 4;
+
+/// Embeds symbols for storage, several at a time. Symbols whose trimmed
+/// content is shorter than `min_chars` are not sent to the embedder; they keep
+/// an empty embedding and are marked `embedding_skipped` in their metadata.
+/// The rest are embedded as rendered by `template`.
+pub(crate) async fn embed_symbols(
+    embedder: &dyn EmbeddingProvider,
+    symbols: Vec<StoredSymbol>,
+    min_chars: usize,
+    template: &EmbeddingTemplate,
+) -> Result<Vec<StoredSymbol>, StorageError> {
+    futures::stream::iter(symbols)
+        .map(|mut symbol| async move {
+            if symbol.content.trim().chars().count() < min_chars {
+                let mut metadata = symbol
+                    .metadata
+                    .as_ref()
+                    .map(SymbolMetadata::from_value)
+                    .unwrap_or_default();
+                metadata.embedding_skipped = true;
+                symbol.metadata = Some(metadata.to_value());
+                return Ok(symbol);
+            }
+
+            let metadata = symbol
+                .metadata
+                .as_ref()
+                .filter(|_| template.is_enabled())
+                .map(SymbolMetadata::from_value)
+                .unwrap_or_default();
+            let text = template.render(TemplateFields {
+                kind: &symbol.kind,
+                name: &symbol.name,
+                signature: metadata.signature.as_deref(),
+                doc: metadata.doc.as_deref(),
+                body: &symbol.content,
+            });
+            symbol.embedding = embedder
+                .embed(&text)
+                .await
+                .map_err(|e| StorageError::Embedding(e.to_string()))?;
+            Ok(symbol)
+        })
+        .buffer_unordered(EMBED_CONCURRENCY)
+        .collect::<Vec<Result<_, StorageError>>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// Metadata stored alongside each extracted symbol.
+pub(crate) fn symbol_metadata(
+    doc: Option<String>,
+    language: &str,
+    start_line: usize,
+    end_line: usize,
+    complexity: usize,
+    normalized: bool,
+    declarations_only: bool,
+) -> serde_json::Value {
+    SymbolMetadata {
+        doc,
+        language: Some(language.to_string()),
+        complexity: Some(complexity as i64),
+        line_count: Some((end_line.saturating_sub(start_line) + 1) as i64),
+        normalized,
+        declarations_only,
+        ..SymbolMetadata::default()
+    }
+    .to_value()
+}
+
+pub async fn create_storage(config: StorageConfig) -> Result<Arc<Storage>, StorageError> {
+    let storage = Storage::new(config).await?;
+    Ok(Arc::new(storage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddingError;
+    use chrono::TimeZone;
+    use parking_lot::Mutex;
+
+    /// Records every text it is asked to embed.
+    #[derive(Default)]
+    struct RecordingProvider {
+        embedded: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for RecordingProvider {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.embedded.lock().push(text.to_string());
+            Ok(vec![0.5; 4])
+        }
+
+        async fn health_check(&self) -> Result<(), EmbeddingError> {
+            Ok(())
+        }
+    }
+
+    fn symbol(name: &str, content: &str) -> StoredSymbol {
+        let now = Utc::now();
+        StoredSymbol {
+            id: Uuid::new_v4(),
+            path: "src/lib.rs".to_string(),
+            name: name.to_string(),
+            kind: "constant".to_string(),
+            content: content.to_string(),
+            embedding: vec![],
+            commit_id: None,
+            start_line: 0,
+            end_line: 0,
+            metadata: Some(symbol_metadata(None, "rust", 0, 0, 1, false, false)),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn language_filter_uses_the_language_column() {
+        let filter = MetadataFilter::parse([
+            ("visibility".to_string(), "pub".to_string()),
+            ("language".to_string(), "RS".to_string()),
+        ])
+        .unwrap();
+
+        let mut sql = String::new();
+        let mut args = sqlx::postgres::PgArguments::default();
+        let mut param_idx = 2;
+        filter
+            .clone()
+            .push_sql(&mut sql, &mut args, &mut param_idx)
+            .unwrap();
+        assert_eq!(sql, " AND language = $2 AND metadata->>$3 = $4");
+        assert_eq!(param_idx, 5);
+
+        // Symbols written without metadata.language get it from their path.
+        let mut rust = symbol("parse", "fn parse() {}");
+        rust.metadata = Some(serde_json::json!({ "visibility": "pub" }));
+        assert_eq!(rust.language().as_deref(), Some("rust"));
+        assert!(filter.matches(&rust));
+
+        let mut python = rust.clone();
+        python.path = "src/parse.py".to_string();
+        assert_eq!(python.language().as_deref(), Some("python"));
+        assert!(!filter.matches(&python));
+        assert_eq!(symbol("x", "1").language().as_deref(), Some("rust"));
+    }
+
+    #[tokio::test]
+    async fn trivial_symbols_are_not_embedded() {
+        let provider = RecordingProvider::default();
+        let body = "fn parse(input: &str) -> Result<Config, Error> { todo!() }";
+        let symbols = vec![symbol("X", "X"), symbol("parse", body)];
+
+        let embedded = embed_symbols(&provider, symbols, 16, &EmbeddingTemplate::default())
+            .await
+            .unwrap();
+
+        assert_eq!(*provider.embedded.lock(), vec![body.to_string()]);
+        let by_name = |name: &str| embedded.iter().find(|s| s.name == name).unwrap();
+        let skipped = by_name("X");
+        assert!(skipped.embedding.is_empty());
+        let metadata = SymbolMetadata::from_value(skipped.metadata.as_ref().unwrap());
+        assert!(metadata.embedding_skipped);
+        assert_eq!(metadata.language.as_deref(), Some("rust"));
+        assert_eq!(by_name("parse").embedding.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn zero_minimum_embeds_everything() {
+        let provider = RecordingProvider::default();
+        embed_symbols(
+            &provider,
+            vec![symbol("X", "X")],
+            0,
+            &EmbeddingTemplate::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(provider.embedded.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn template_embeds_kind_name_and_doc_with_the_body() {
+        let provider = RecordingProvider::default();
+        let mut documented = symbol("parse", "fn parse() {}");
+        documented.kind = "function".to_string();
+        documented.metadata = Some(symbol_metadata(
+            Some("Parses the config.".to_string()),
+            "rust",
+            0,
+            0,
+            1,
+            false,
+            false,
+        ));
+        let template = EmbeddingTemplate::new("{kind} {name}: {doc}\n{body}");
+
+        embed_symbols(&provider, vec![documented.clone()], 0, &template)
+            .await
+            .unwrap();
+        embed_symbols(
+            &provider,
+            vec![documented],
+            0,
+            &EmbeddingTemplate::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *provider.embedded.lock(),
+            vec![
+                "function parse: Parses the config.\nfn parse() {}".to_string(),
+                "fn parse() {}".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fenced_query_embeds_like_the_inner_code() {
+        let provider = RecordingProvider::default();
+        let strip = QueryPreprocessing {
+            strip_markdown: true,
+        };
+        let code = "fn foo(bar: &str) -> usize {\n    bar.len()\n}";
+
+        embed_query(&provider, strip, &format!("```rust\n{}\n```", code))
+            .await
+            .unwrap();
+        embed_query(&provider, strip, code).await.unwrap();
+        embed_query(
+            &provider,
+            QueryPreprocessing::default(),
+            "```rust\nfoo\n```",
+        )
+        .await
+        .unwrap();
+
+        let embedded = provider.embedded.lock();
+        assert_eq!(embedded[0], code);
+        assert_eq!(embedded[0], embedded[1]);
+        assert_eq!(embedded[2], "```rust\nfoo\n```");
+    }
+
+    #[test]
+    fn dedup_by_keeps_best_score_per_group() {
+        let rows = vec![
+            (("a.rs", Some("c1")), 0.5),
+            (("a.rs", Some("c2")), 0.9),
+            (("b.rs", Some("c1")), 0.7),
+            (("a.rs", Some("c1")), 0.8),
+        ];
+        fn key<'a>(row: &'a (&str, Option<&str>)) -> (&'a str, Option<&'a str>) {
+            (row.0, row.1)
+        }
+
+        let by_path = DedupBy::Path.apply(rows.clone(), 10, key);
+        assert_eq!(
+            by_path,
+            vec![(("a.rs", Some("c2")), 0.9), (("b.rs", Some("c1")), 0.7)]
+        );
+
+        let by_commit = DedupBy::PathCommit.apply(rows.clone(), 10, key);
+        assert_eq!(
+            by_commit,
+            vec![
+                (("a.rs", Some("c2")), 0.9),
+                (("a.rs", Some("c1")), 0.8),
+                (("b.rs", Some("c1")), 0.7),
+            ]
+        );
+
+        assert_eq!(DedupBy::None.apply(rows, 2, key).len(), 2);
+    }
+
+    #[test]
+    fn dedup_by_parses_modes_and_sizes_candidates() {
+        assert_eq!(DedupBy::parse("").unwrap(), DedupBy::None);
+        assert_eq!(DedupBy::parse("PATH").unwrap(), DedupBy::Path);
+        assert_eq!(DedupBy::parse("path_commit").unwrap(), DedupBy::PathCommit);
+        assert!(DedupBy::parse("chunk").is_err());
+
+        assert_eq!(DedupBy::None.candidates(10), 10);
+        assert_eq!(DedupBy::Path.candidates(10), 40);
+        assert_eq!(DedupBy::Path.candidates(100), DedupBy::MAX_CANDIDATES);
+    }
+
+    #[test]
+    fn name_matches_rank_exact_then_prefix_then_fuzzy() {
+        let score = |name| name_match_score(name, "IndexerService");
+        let exact = score("indexerservice").unwrap();
+        let prefix = score("IndexerServiceImpl").unwrap();
+        let contains = score("MockIndexerService").unwrap();
+        let typo = score("IndexerSevrice").unwrap();
+
+        assert_eq!(exact, 1.0);
+        assert!(exact > prefix && prefix > contains && contains > typo);
+        assert!(score("EmbeddingManager").is_none());
+        assert!(name_match_score("anything", "  ").is_none());
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like(r"a_b%c\d"), r"a\_b\%c\\d");
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn conflict_key_must_match_a_unique_index() {
+        let path = DocumentConflictKey::parse("path").unwrap();
+        let per_commit = DocumentConflictKey::parse("path, commit_id").unwrap();
+        assert_eq!(per_commit, DocumentConflictKey::PathAndCommit);
+        assert!(DocumentConflictKey::parse("commit_id").is_err());
+
+        let migration_001 = vec![vec!["id".to_string()], vec!["path".to_string()]];
+        assert!(path.check(&migration_001).is_ok());
+        let err = per_commit.check(&migration_001).unwrap_err();
+        assert!(matches!(err, StorageError::Configuration(_)));
+        assert!(err.to_string().contains("(id), (path)"));
+
+        let multi_commit = vec![vec!["commit_id".to_string(), "path".to_string()]];
+        assert!(per_commit.check(&multi_commit).is_ok());
+        assert!(path.check(&multi_commit).is_err());
+    }
+
+    #[test]
+    fn embedding_columns_must_match_the_model_dimension() {
+        let column =
+            |table: &str, column: &str, width| (table.to_string(), column.to_string(), width);
+        let schema = vec![
+            column("documents", "embedding_vector", Some(384)),
+            column("documents", "embedding_half", Some(384)),
+            column("symbols", "embedding_vector", None),
+        ];
+        assert!(check_embedding_dimension(&schema, "all-MiniLM-L6-v2", 384).is_ok());
+
+        let err = check_embedding_dimension(&schema, "bge-base-en-v1.5", 768).unwrap_err();
+        assert!(matches!(err, StorageError::Configuration(_)));
+        let message = err.to_string();
+        assert!(message.contains("bge-base-en-v1.5 produces 768-dimensional"));
+        assert!(
+            message.contains("documents.embedding_vector (384), documents.embedding_half (384)")
+        );
+        assert!(!message.contains("symbols"));
+    }
+
+    /// Runs against the Postgres in `DATABASE_URL` when one is set; a temporary
+    /// `documents` table shadows any real one for the session.
+    #[tokio::test]
+    async fn conflict_key_mismatch_is_reported_before_any_insert() {
+        let Ok(url) = env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TEMP TABLE documents (id UUID PRIMARY KEY, path TEXT NOT NULL UNIQUE, commit_id TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        DocumentConflictKey::Path.verify(&pool).await.unwrap();
+        let err = DocumentConflictKey::PathAndCommit
+            .verify(&pool)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Configuration(_)));
+    }
+
+    /// A [`Storage`] on the Postgres in `DATABASE_URL`, or `None` without one.
+    /// Temporary `symbols` and `symbol_edges` tables, with `constraint` added
+    /// to `symbols`, shadow any real ones for the session. Nothing is embedded.
+    async fn temp_symbol_storage(constraint: &str) -> Option<Storage> {
+        let url = env::var("DATABASE_URL").ok()?;
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::query(&format!(
+            "CREATE TEMP TABLE symbols (id UUID PRIMARY KEY, path TEXT NOT NULL, name TEXT NOT NULL, \
+             kind TEXT NOT NULL, content TEXT NOT NULL, embedding_vector vector, commit_id TEXT, \
+             start_line INTEGER NOT NULL, end_line INTEGER NOT NULL, metadata JSONB, \
+             created_at TIMESTAMPTZ NOT NULL, updated_at TIMESTAMPTZ NOT NULL, embedding_model TEXT, \
+             embedding_generated_at TIMESTAMPTZ, embedding_half halfvec, language TEXT{constraint})"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TEMP TABLE symbol_edges (\
+             parent_id UUID NOT NULL REFERENCES symbols(id) ON DELETE CASCADE, \
+             child_id UUID NOT NULL REFERENCES symbols(id) ON DELETE CASCADE, \
+             relation TEXT NOT NULL, PRIMARY KEY (parent_id, child_id, relation))",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        Some(storage_on(pool))
+    }
+
+    fn storage_on(pool: PgPool) -> Storage {
+        Storage {
+            pool,
+            embedding_manager: Arc::new(crate::embeddings::EmbeddingManager::Local(
+                crate::embeddings::LocalBertProvider::new(),
+            )),
+            clock: clock::system_clock(),
+            normalization: ContentNormalization::default(),
+            query_preprocessing: QueryPreprocessing::default(),
+            min_embed_chars: usize::MAX,
+            precision: EmbeddingPrecision::default(),
+            embedding_template: EmbeddingTemplate::default(),
+            document_conflict_key: DocumentConflictKey::default(),
+        }
+    }
+
+    async fn stored_symbol_names(storage: &Storage, path: &str) -> Vec<String> {
+        sqlx::query_scalar("SELECT name FROM symbols WHERE path = $1 ORDER BY start_line")
+            .bind(path)
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn failed_symbol_insert_keeps_the_previous_symbols() {
+        let Some(storage) =
+            temp_symbol_storage(", CONSTRAINT no_poison CHECK (name <> 'poison')").await
+        else {
+            return;
+        };
+        let index = |content: &str| {
+            storage.index_symbols(
+                "src/lib.rs".to_string(),
+                content.to_string(),
+                "rust".to_string(),
+                None,
+                None,
+                false,
+            )
+        };
+
+        index("fn keep() {}\nfn also_kept() {}\n").await.unwrap();
+        let err = index("fn replacement() {}\nfn poison() {}\n")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Database(_)), "{err}");
+
+        assert_eq!(
+            stored_symbol_names(&storage, "src/lib.rs").await,
+            vec!["keep", "also_kept"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_path_delete_keeps_the_symbols() {
+        let Some(storage) = temp_symbol_storage("").await else {
+            return;
+        };
+        // A row pinning the document makes its delete fail after the
+        // symbols' delete has run.
+        for statement in [
+            "CREATE TEMP TABLE documents (id UUID PRIMARY KEY, path TEXT NOT NULL UNIQUE, \
+             commit_id TEXT)",
+            "CREATE TEMP TABLE pins (path TEXT NOT NULL REFERENCES documents(path))",
+            "INSERT INTO documents VALUES (gen_random_uuid(), 'src/lib.rs', NULL)",
+            "INSERT INTO pins VALUES ('src/lib.rs')",
+        ] {
+            sqlx::query(statement).execute(&storage.pool).await.unwrap();
+        }
+        storage
+            .index_symbols(
+                "src/lib.rs".to_string(),
+                "fn keep() {}\n".to_string(),
+                "rust".to_string(),
+                None,
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let err = storage
+            .delete_path("src/lib.rs".to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Database(_)), "{err}");
+        assert_eq!(
+            stored_symbol_names(&storage, "src/lib.rs").await,
+            vec!["keep"]
+        );
+
+        sqlx::query("DELETE FROM pins")
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        let deleted = storage
+            .delete_path("src/lib.rs".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            deleted,
+            PathDeletion {
+                documents: 1,
+                symbols: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reindexing_a_file_replaces_its_symbols() {
+        let Some(storage) = temp_symbol_storage("").await else {
+            return;
+        };
+        let index = |path: &str, content: &str| {
+            storage.index_symbols(
+                path.to_string(),
+                content.to_string(),
+                "rust".to_string(),
+                None,
+                None,
+                false,
+            )
+        };
+
+        index("src/lib.rs", "fn old() {}\nmod gone { fn inner() {} }\n")
+            .await
+            .unwrap();
+        index("src/other.rs", "fn untouched() {}\n").await.unwrap();
+        index("src/lib.rs", "fn new() {}\n").await.unwrap();
+
+        assert_eq!(
+            stored_symbol_names(&storage, "src/lib.rs").await,
+            vec!["new"]
+        );
+        assert_eq!(
+            stored_symbol_names(&storage, "src/other.rs").await,
+            vec!["untouched"]
+        );
+        let edges: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM symbol_edges")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(edges, 0);
+    }
+
+    #[tokio::test]
+    async fn path_prefix_matches_underscores_literally() {
+        let Some(storage) = temp_symbol_storage("").await else {
+            return;
+        };
+        for path in ["src/a_b.rs", "src/axb.rs"] {
+            storage
+                .index_symbols(
+                    path.to_string(),
+                    "fn f() {}\n".to_string(),
+                    "rust".to_string(),
+                    None,
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+        }
+
+        let matched = storage
+            .top_symbols(SymbolMetric::LineCount, 10, 0, Some("src/a_".to_string()))
+            .await
+            .unwrap();
+        let paths: Vec<_> = matched.iter().map(|(s, _)| s.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/a_b.rs"]);
+    }
+
+    #[tokio::test]
+    async fn touch_bumps_updated_at_without_embedding() {
+        let Ok(url) = env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TEMP TABLE documents (id UUID PRIMARY KEY, path TEXT NOT NULL, \
+             content TEXT NOT NULL, embedding_vector vector, created_at TIMESTAMPTZ NOT NULL, \
+             updated_at TIMESTAMPTZ NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let created = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        sqlx::query(
+            "INSERT INTO documents VALUES ($1, 'src/lib.rs', 'fn main() {}', NULL, $2, $2)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(created)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Nothing listens on the discard port, so touch fails if it embeds.
+        let unreachable =
+            crate::embeddings::OrchestratorProvider::new(Some("http://127.0.0.1:9".to_string()))
+                .unwrap();
+        let now = created + chrono::Duration::hours(1);
+        let storage = Storage {
+            embedding_manager: Arc::new(crate::embeddings::EmbeddingManager::Orchestrator(
+                unreachable,
+            )),
+            clock: Arc::new(clock::MockClock::new(now)),
+            ..storage_on(pool)
+        };
+
+        assert_eq!(storage.touch("src/lib.rs".to_string()).await.unwrap(), 1);
+        assert_eq!(
+            storage.touch("src/missing.rs".to_string()).await.unwrap(),
+            0
+        );
+
+        let (created_at, updated_at): (DateTime<Utc>, DateTime<Utc>) =
+            sqlx::query_as("SELECT created_at, updated_at FROM documents")
+                .fetch_one(&storage.pool)
+                .await
+                .unwrap();
+        assert_eq!(created_at, created);
+        assert_eq!(updated_at, now);
+    }
 }
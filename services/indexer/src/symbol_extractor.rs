@@ -12,11 +12,151 @@ pub struct ExtractedSymbol {
     pub range: Range,
     #[allow(dead_code)]
     pub content: String,
+    /// `content` up to the body, e.g. `fn parse(input: &str) -> Ast`. Symbols
+    /// without a body (constants, fields, aliases) are their own signature.
+    pub signature: String,
     #[allow(dead_code)]
     pub doc_comment: Option<String>,
+    /// Cyclomatic complexity of the symbol's source; see [`complexity`].
+    pub complexity: usize,
     pub children: Vec<ExtractedSymbol>,
 }
 
+/// How a language marks documentation comments: the grammar's comment node
+/// kinds, and the prefixes that distinguish doc comments from ordinary ones.
+#[derive(Debug)]
+struct DocCommentStyle {
+    comment_kinds: &'static [&'static str],
+    doc_markers: &'static [&'static str],
+}
+
+impl DocCommentStyle {
+    fn is_doc_comment(&self, kind: &str, text: &str) -> bool {
+        self.comment_kinds.contains(&kind)
+            && self
+                .doc_markers
+                .iter()
+                .any(|marker| text.starts_with(marker))
+    }
+}
+
+/// Used for languages without an entry in [`DOC_COMMENT_STYLES`].
+const DEFAULT_DOC_COMMENT_STYLE: DocCommentStyle = DocCommentStyle {
+    comment_kinds: &["comment", "line_comment", "block_comment"],
+    doc_markers: &["/**", "///"],
+};
+
+/// Doc comment conventions by language id. Supporting a new convention is a
+/// matter of adding a row here.
+const DOC_COMMENT_STYLES: &[(&str, DocCommentStyle)] = &[
+    (
+        "rust",
+        DocCommentStyle {
+            comment_kinds: &["line_comment", "block_comment"],
+            doc_markers: &["///", "/**"],
+        },
+    ),
+    (
+        "typescript",
+        DocCommentStyle {
+            comment_kinds: &["comment"],
+            doc_markers: &["/**", "///"],
+        },
+    ),
+    (
+        "tsx",
+        DocCommentStyle {
+            comment_kinds: &["comment"],
+            doc_markers: &["/**", "///"],
+        },
+    ),
+    (
+        "javascript",
+        DocCommentStyle {
+            comment_kinds: &["comment"],
+            doc_markers: &["/**", "///"],
+        },
+    ),
+    (
+        // Go documents declarations with the plain `//` comments directly above them.
+        "go",
+        DocCommentStyle {
+            comment_kinds: &["comment"],
+            doc_markers: &["//"],
+        },
+    ),
+];
+
+fn doc_comment_style(language_id: &str) -> &'static DocCommentStyle {
+    DOC_COMMENT_STYLES
+        .iter()
+        .find(|(language, _)| *language == language_id)
+        .map(|(_, style)| style)
+        .unwrap_or(&DEFAULT_DOC_COMMENT_STYLE)
+}
+
+/// Zero-based, inclusive window of lines to index from a larger file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    /// The text of the covered lines, or why the range does not fit `source`.
+    pub fn slice<'a>(&self, source: &'a str) -> Result<&'a str, String> {
+        if self.start > self.end {
+            return Err(format!(
+                "start_line {} is after end_line {}",
+                self.start, self.end
+            ));
+        }
+
+        let mut begin = None;
+        let mut offset = 0;
+        for (index, line) in source.split_inclusive('\n').enumerate() {
+            if index == self.start {
+                begin = Some(offset);
+            }
+            offset += line.len();
+            if index == self.end {
+                return Ok(&source[begin.unwrap_or(0)..offset]);
+            }
+        }
+
+        Err(format!(
+            "end_line {} is beyond the last line of the content ({} lines)",
+            self.end,
+            source.split_inclusive('\n').count()
+        ))
+    }
+}
+
+/// Shifts symbol ranges, including nested children, down by `lines` so
+/// symbols extracted from a slice report absolute file lines.
+pub fn offset_lines(symbols: &mut [ExtractedSymbol], lines: usize) {
+    if lines == 0 {
+        return;
+    }
+    for symbol in symbols {
+        symbol.range.start.line += lines;
+        symbol.range.end.line += lines;
+        offset_lines(&mut symbol.children, lines);
+    }
+}
+
+/// Replaces each symbol's content, including nested children, with its doc
+/// comment and signature, for indexes that only need the API surface.
+pub fn declarations_only(symbols: &mut [ExtractedSymbol]) {
+    for symbol in symbols {
+        symbol.content = match &symbol.doc_comment {
+            Some(doc) => format!("{}\n{}", doc, symbol.signature),
+            None => symbol.signature.clone(),
+        };
+        declarations_only(&mut symbol.children);
+    }
+}
+
 /// Extract symbols from source code
 pub fn extract_symbols(source: &str, language_id: &str) -> Result<Vec<ExtractedSymbol>, AstError> {
     let (tree, _) = parse_tree(language_id, source)?;
@@ -25,6 +165,7 @@ pub fn extract_symbols(source: &str, language_id: &str) -> Result<Vec<ExtractedS
     let mut extractor = SymbolExtractor {
         source: source.as_bytes(),
         symbols: Vec::new(),
+        doc_style: doc_comment_style(language_id),
     };
 
     extractor.visit(root);
@@ -34,17 +175,29 @@ pub fn extract_symbols(source: &str, language_id: &str) -> Result<Vec<ExtractedS
 struct SymbolExtractor<'a> {
     source: &'a [u8],
     symbols: Vec<ExtractedSymbol>,
+    doc_style: &'static DocCommentStyle,
 }
 
 impl<'a> SymbolExtractor<'a> {
     fn visit(&mut self, node: Node) {
-        if let Some(symbol) = self.extract_symbol(node) {
-            self.symbols.push(symbol);
-        } else {
+        let symbols = self.extract_declared(node);
+        if symbols.is_empty() {
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
                 self.visit(child);
             }
+        } else {
+            self.symbols.extend(symbols);
+        }
+    }
+
+    /// Symbols declared by `node`. A JavaScript/TypeScript declaration such
+    /// as `const A = 1, B = 2;` declares one per declarator; every other node
+    /// declares at most one.
+    fn extract_declared(&mut self, node: Node) -> Vec<ExtractedSymbol> {
+        match node.kind() {
+            "lexical_declaration" | "variable_declaration" => self.extract_declarators(node),
+            _ => self.extract_symbol(node).into_iter().collect(),
         }
     }
 
@@ -56,8 +209,6 @@ impl<'a> SymbolExtractor<'a> {
             "interface_declaration" => self.extract_interface(node),
             "enum_declaration" => self.extract_enum(node),
             "method_definition" => self.extract_method(node),
-            "const_declaration" | "lexical_declaration" => self.extract_constant(node),
-            "variable_declarator" => self.extract_variable_declarator(node),
 
             // Rust
             "function_item" => self.extract_function(node),
@@ -67,10 +218,257 @@ impl<'a> SymbolExtractor<'a> {
             "impl_item" => self.extract_impl(node),
             "mod_item" => self.extract_module(node),
 
+            // Go; `function_declaration` is shared with TypeScript, and
+            // `type_declaration`/`const_declaration`/`var_declaration` groups
+            // are walked into so each spec becomes a symbol
+            "method_declaration" => self.extract_go_method(node),
+            "type_spec" | "type_alias" => self.extract_go_type(node),
+            "const_spec" => self.extract_go_value(node, SymbolKind::Constant),
+            "var_spec" => self.extract_go_value(node, SymbolKind::Variable),
+
+            // Python
+            "function_definition" => self.extract_python_function(node, SymbolKind::Function),
+            "class_definition" => self.extract_python_class(node),
+            "decorated_definition" => self.extract_python_decorated(node, SymbolKind::Function),
+            "expression_statement" if is_python_module_level(node) => {
+                self.extract_python_assignment(node, None)
+            }
+
             _ => None,
         }
     }
 
+    /// Methods are named after their receiver, `(*Server).Start` for pointer
+    /// receivers and `Server.Start` for value receivers, so methods of the
+    /// same name on different types stay distinct.
+    fn extract_go_method(&mut self, node: Node) -> Option<ExtractedSymbol> {
+        let name = node
+            .child_by_field_name("name")?
+            .utf8_text(self.source)
+            .ok()?;
+        let receiver = node
+            .child_by_field_name("receiver")
+            .and_then(|receiver| receiver.named_child(0))
+            .and_then(|param| param.child_by_field_name("type"));
+        let qualified = match receiver {
+            Some(ty) if ty.kind() == "pointer_type" => {
+                let base = ty.named_child(0).unwrap_or(ty);
+                format!("(*{}).{}", self.get_node_text(base), name)
+            }
+            Some(ty) => format!("{}.{}", self.get_node_text(ty), name),
+            None => name.to_string(),
+        };
+
+        Some(ExtractedSymbol {
+            name: qualified,
+            kind: SymbolKind::Method,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: self.extract_doc_comment(node),
+            complexity: complexity(node),
+            children: Vec::new(),
+        })
+    }
+
+    fn extract_go_type(&mut self, node: Node) -> Option<ExtractedSymbol> {
+        let name = node
+            .child_by_field_name("name")?
+            .utf8_text(self.source)
+            .ok()?;
+        let kind = match node.child_by_field_name("type").map(|ty| ty.kind()) {
+            Some("struct_type") if node.kind() == "type_spec" => SymbolKind::Struct,
+            Some("interface_type") if node.kind() == "type_spec" => SymbolKind::Interface,
+            _ => SymbolKind::Type,
+        };
+
+        Some(ExtractedSymbol {
+            name: name.to_string(),
+            kind,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: self.go_spec_doc_comment(node),
+            complexity: complexity(node),
+            children: Vec::new(),
+        })
+    }
+
+    /// A `const` or `var` spec, named after its first identifier.
+    fn extract_go_value(&mut self, node: Node, kind: SymbolKind) -> Option<ExtractedSymbol> {
+        let name = node
+            .child_by_field_name("name")?
+            .utf8_text(self.source)
+            .ok()?;
+
+        Some(ExtractedSymbol {
+            name: name.to_string(),
+            kind,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: self.go_spec_doc_comment(node),
+            complexity: complexity(node),
+            children: Vec::new(),
+        })
+    }
+
+    /// Go specs are documented either inside a grouped declaration or, for
+    /// the first spec, by the comment above the `type`/`const`/`var` keyword.
+    fn go_spec_doc_comment(&self, node: Node) -> Option<String> {
+        self.extract_doc_comment(node).or_else(|| {
+            let parent = node.parent()?;
+            let first_spec = parent.named_child(0)?.id() == node.id();
+            first_spec.then(|| self.extract_doc_comment(parent))?
+        })
+    }
+
+    fn extract_python_function(&mut self, node: Node, kind: SymbolKind) -> Option<ExtractedSymbol> {
+        let name = node
+            .child_by_field_name("name")?
+            .utf8_text(self.source)
+            .ok()?;
+        let body = node.child_by_field_name("body");
+        let mut symbol = ExtractedSymbol {
+            name: name.to_string(),
+            kind,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: body.and_then(|body| self.python_docstring(body)),
+            complexity: complexity(node),
+            children: Vec::new(),
+        };
+
+        // Nested functions and classes, as for TypeScript function bodies
+        if let Some(body) = body {
+            let mut cursor = body.walk();
+            for child in body.children(&mut cursor) {
+                let nested = match child.kind() {
+                    "function_definition" | "class_definition" | "decorated_definition" => {
+                        self.extract_symbol(child)
+                    }
+                    _ => None,
+                };
+                symbol.children.extend(nested);
+            }
+        }
+
+        Some(symbol)
+    }
+
+    fn extract_python_class(&mut self, node: Node) -> Option<ExtractedSymbol> {
+        let name = node
+            .child_by_field_name("name")?
+            .utf8_text(self.source)
+            .ok()?;
+        let body = node.child_by_field_name("body");
+        let mut symbol = ExtractedSymbol {
+            name: name.to_string(),
+            kind: SymbolKind::Class,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: body.and_then(|body| self.python_docstring(body)),
+            complexity: complexity(node),
+            children: Vec::new(),
+        };
+
+        if let Some(body) = body {
+            let mut cursor = body.walk();
+            for child in body.children(&mut cursor) {
+                let member = match child.kind() {
+                    "function_definition" => {
+                        self.extract_python_function(child, SymbolKind::Method)
+                    }
+                    "decorated_definition" => {
+                        self.extract_python_decorated(child, SymbolKind::Method)
+                    }
+                    "class_definition" => self.extract_python_class(child),
+                    "expression_statement" => {
+                        self.extract_python_assignment(child, Some(SymbolKind::Property))
+                    }
+                    _ => None,
+                };
+                symbol.children.extend(member);
+            }
+        }
+
+        Some(symbol)
+    }
+
+    /// A decorated function or class, reported with its decorators included
+    /// in the range and content. `function_kind` applies when the definition
+    /// is a function.
+    fn extract_python_decorated(
+        &mut self,
+        node: Node,
+        function_kind: SymbolKind,
+    ) -> Option<ExtractedSymbol> {
+        let definition = node.child_by_field_name("definition")?;
+        let mut symbol = match definition.kind() {
+            "function_definition" => self.extract_python_function(definition, function_kind)?,
+            "class_definition" => self.extract_python_class(definition)?,
+            _ => return None,
+        };
+        symbol.range = node_to_range(node);
+        symbol.content = self.get_node_text(node);
+        symbol.signature = self.signature(node);
+        Some(symbol)
+    }
+
+    /// `NAME = value` statements. Without an explicit `kind`, upper-case
+    /// names are reported as constants and the rest as variables.
+    fn extract_python_assignment(
+        &mut self,
+        node: Node,
+        kind: Option<SymbolKind>,
+    ) -> Option<ExtractedSymbol> {
+        let assignment = node.named_child(0).filter(|n| n.kind() == "assignment")?;
+        let target = assignment.child_by_field_name("left")?;
+        if target.kind() != "identifier" {
+            return None;
+        }
+        let name = target.utf8_text(self.source).ok()?;
+        let kind = kind.unwrap_or_else(|| {
+            if name.chars().any(|c| c.is_ascii_alphabetic())
+                && !name.chars().any(|c| c.is_ascii_lowercase())
+            {
+                SymbolKind::Constant
+            } else {
+                SymbolKind::Variable
+            }
+        });
+
+        Some(ExtractedSymbol {
+            name: name.to_string(),
+            kind,
+            range: node_to_range(node),
+            content: self.get_node_text(node),
+            signature: self.signature(node),
+            doc_comment: None,
+            complexity: complexity(node),
+            children: Vec::new(),
+        })
+    }
+
+    /// The docstring of a Python body: its first statement, when that is a
+    /// bare string literal. Returned without the surrounding quotes.
+    fn python_docstring(&self, body: Node) -> Option<String> {
+        let first = body.named_child(0)?;
+        if first.kind() != "expression_statement" || first.named_child_count() != 1 {
+            return None;
+        }
+        let literal = first.named_child(0).filter(|n| n.kind() == "string")?;
+        let text = literal.utf8_text(self.source).ok()?;
+        let text = text.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        let quote = ["\"\"\"", "'''", "\"", "'"]
+            .into_iter()
+            .find(|quote| text.starts_with(quote))?;
+        let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+        Some(inner.trim().to_string())
+    }
+
     fn extract_function(&mut self, node: Node) -> Option<ExtractedSymbol> {
         if let Some(name_node) = node.child_by_field_name("name") {
             if let Ok(name) = name_node.utf8_text(self.source) {
@@ -79,7 +477,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Function,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 };
 
@@ -106,7 +506,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Class,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 };
 
@@ -145,7 +547,9 @@ impl<'a> SymbolExtractor<'a> {
             kind,
             range: node_to_range(node),
             content: self.get_node_text(node),
+            signature: self.signature(node),
             doc_comment: self.extract_doc_comment(node),
+            complexity: complexity(node),
             children: Vec::new(),
         })
     }
@@ -158,7 +562,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Interface,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -174,7 +580,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Enum,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -190,7 +598,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Method,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -198,39 +608,41 @@ impl<'a> SymbolExtractor<'a> {
         None
     }
 
-    fn extract_constant(&mut self, _node: Node) -> Option<ExtractedSymbol> {
-        // For const/let declarations, extract each variable
-        // This is tricky because one declaration can have multiple declarators.
-        // But extract_symbol returns Option<ExtractedSymbol> (single).
-        // If we encounter a variable_declarator, we extract it.
-        // If we encounter a lexical_declaration, we should visit children.
-
-        // Wait, extract_symbol is called on "const_declaration" or "lexical_declaration".
-        // If I return None, visit will recurse.
-        // So I should return None here and let visit recurse to find "variable_declarator"?
-        // But "variable_declarator" is not in extract_symbol match arms.
-
-        // I should add "variable_declarator" to extract_symbol match arms.
-        // And remove "const_declaration" / "lexical_declaration" from match arms so they recurse.
-
-        // Let's adjust extract_symbol match arms.
-        None
-    }
+    /// One symbol per `variable_declarator` of a `const`, `let` or `var`
+    /// statement: `const` declarators are constants, the rest variables.
+    /// Destructuring patterns have no single name and are skipped. The doc
+    /// comment sits above the statement, or above `export` when exported.
+    fn extract_declarators(&mut self, node: Node) -> Vec<ExtractedSymbol> {
+        let kind = match node.child(0).map(|keyword| keyword.kind()) {
+            Some("const") => SymbolKind::Constant,
+            _ => SymbolKind::Variable,
+        };
+        let documented = node
+            .parent()
+            .filter(|parent| parent.kind() == "export_statement")
+            .unwrap_or(node);
+        let doc_comment = self.extract_doc_comment(documented);
 
-    fn extract_variable_declarator(&mut self, node: Node) -> Option<ExtractedSymbol> {
-        if let Some(name_node) = node.child_by_field_name("name") {
-            if let Ok(name) = name_node.utf8_text(self.source) {
-                return Some(ExtractedSymbol {
-                    name: name.to_string(),
-                    kind: SymbolKind::Constant,
-                    range: node_to_range(node),
-                    content: self.get_node_text(node),
-                    doc_comment: self.extract_doc_comment(node.parent().unwrap_or(node)), // Doc comment is on parent declaration
+        let mut cursor = node.walk();
+        node.named_children(&mut cursor)
+            .filter(|child| child.kind() == "variable_declarator")
+            .filter_map(|declarator| {
+                let name = declarator.child_by_field_name("name")?;
+                if name.kind() != "identifier" {
+                    return None;
+                }
+                Some(ExtractedSymbol {
+                    name: self.get_node_text(name),
+                    kind,
+                    range: node_to_range(declarator),
+                    content: self.get_node_text(declarator),
+                    signature: self.signature(declarator),
+                    doc_comment: doc_comment.clone(),
+                    complexity: complexity(declarator),
                     children: Vec::new(),
-                });
-            }
-        }
-        None
+                })
+            })
+            .collect()
     }
 
     fn extract_struct(&mut self, node: Node) -> Option<ExtractedSymbol> {
@@ -241,7 +653,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Struct,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -257,7 +671,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Trait,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -273,7 +689,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Impl,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -289,7 +707,9 @@ impl<'a> SymbolExtractor<'a> {
                     kind: SymbolKind::Module,
                     range: node_to_range(node),
                     content: self.get_node_text(node),
+                    signature: self.signature(node),
                     doc_comment: self.extract_doc_comment(node),
+                    complexity: complexity(node),
                     children: Vec::new(),
                 });
             }
@@ -301,28 +721,51 @@ impl<'a> SymbolExtractor<'a> {
         node.utf8_text(self.source).unwrap_or("").to_string()
     }
 
+    /// Text of `node` before its body, with trailing whitespace dropped. A
+    /// decorated Python definition keeps its decorators.
+    fn signature(&self, node: Node) -> String {
+        let body = node.child_by_field_name("body").or_else(|| {
+            node.child_by_field_name("definition")
+                .and_then(|definition| definition.child_by_field_name("body"))
+        });
+        match body {
+            Some(body) => {
+                String::from_utf8_lossy(&self.source[node.start_byte()..body.start_byte()])
+                    .trim_end()
+                    .to_string()
+            }
+            None => self.get_node_text(node),
+        }
+    }
+
+    /// Collects the run of doc comments directly above `node`, top to bottom.
+    /// Each comment is its own sibling node, so the walk goes back one sibling
+    /// at a time; a blank line, an ordinary comment or any other node ends the
+    /// run. Rust attributes between the comments and the item are stepped over.
     fn extract_doc_comment(&self, node: Node) -> Option<String> {
-        // Look for comment nodes before this node
-        let mut prev = node.prev_sibling();
         let mut comments = Vec::new();
+        let mut next_row = node.start_position().row;
+        let mut prev = node.prev_sibling();
 
         while let Some(sibling) = prev {
-            if sibling.kind() == "comment"
-                || sibling.kind() == "line_comment"
-                || sibling.kind() == "block_comment"
-            {
-                if let Ok(text) = sibling.utf8_text(self.source) {
-                    // Check if it's a doc comment (/** ... */ or ///)
-                    if text.starts_with("/**") || text.starts_with("///") {
-                        comments.push(text.to_string());
-                    }
+            // Line comments may end at column 0 of the following row.
+            if sibling.end_position().row + 1 < next_row {
+                break;
+            }
+
+            let kind = sibling.kind();
+            if self.doc_style.comment_kinds.contains(&kind) {
+                let text = sibling.utf8_text(self.source).unwrap_or("");
+                if !self.doc_style.is_doc_comment(kind, text) {
+                    break;
                 }
-                prev = sibling.prev_sibling();
-            } else if !sibling.kind().contains("whitespace") {
+                comments.push(text.trim_end().to_string());
+            } else if kind != "attribute_item" {
                 break;
-            } else {
-                prev = sibling.prev_sibling();
             }
+
+            next_row = sibling.start_position().row;
+            prev = sibling.prev_sibling();
         }
 
         if comments.is_empty() {
@@ -334,6 +777,65 @@ impl<'a> SymbolExtractor<'a> {
     }
 }
 
+fn is_python_module_level(node: Node) -> bool {
+    node.parent()
+        .is_some_and(|parent| parent.kind() == "module")
+}
+
+/// Node kinds that open another path through the code: conditionals, loops,
+/// match/switch arms, exception handlers and short-circuit operators, across
+/// the supported grammars.
+const DECISION_POINTS: &[&str] = &[
+    // Rust
+    "if_expression",
+    "while_expression",
+    "for_expression",
+    "match_arm",
+    // TypeScript/JavaScript and Go
+    "if_statement",
+    "for_statement",
+    "for_in_statement",
+    "while_statement",
+    "do_statement",
+    "switch_case",
+    "catch_clause",
+    "ternary_expression",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    // Python
+    "elif_clause",
+    "except_clause",
+    "conditional_expression",
+    "case_clause",
+    // Short-circuit operators
+    "&&",
+    "||",
+    "??",
+    "and",
+    "or",
+];
+
+/// One plus the number of decision points in `node`'s subtree, so a body
+/// without branches scores 1.
+fn complexity(node: Node) -> usize {
+    let mut count = 1;
+    let mut cursor = node.walk();
+    loop {
+        if DECISION_POINTS.contains(&cursor.node().kind()) {
+            count += 1;
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return count;
+            }
+        }
+    }
+}
+
 fn node_to_range(node: Node) -> Range {
     let start_point = node.start_position();
     let end_point = node.end_position();
@@ -354,6 +856,21 @@ fn node_to_range(node: Node) -> Range {
 mod tests {
     use super::*;
 
+    #[test]
+    fn range_slice_keeps_whole_lines() {
+        let source = "zero\none\ntwo\nthree";
+        assert_eq!(
+            LineRange { start: 1, end: 2 }.slice(source).unwrap(),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            LineRange { start: 3, end: 3 }.slice(source).unwrap(),
+            "three"
+        );
+        assert!(LineRange { start: 2, end: 1 }.slice(source).is_err());
+        assert!(LineRange { start: 0, end: 4 }.slice(source).is_err());
+    }
+
     #[test]
     fn extracts_typescript_function() {
         let source = r#"
@@ -368,6 +885,53 @@ mod tests {
         assert!(matches!(symbols[0].kind, SymbolKind::Function));
     }
 
+    #[test]
+    fn counts_decision_points_as_complexity() {
+        let rust = extract_symbols(
+            "fn flat() -> i32 { 1 }\n\
+             fn branchy(x: Option<i32>, y: bool) -> i32 {\n\
+                 if y && x.is_some() { return 1; }\n\
+                 for _ in 0..3 {}\n\
+                 match x { Some(v) => v, None => 0 }\n\
+             }\n",
+            "rust",
+        )
+        .unwrap();
+        let scores: Vec<_> = rust
+            .iter()
+            .map(|s| (s.name.as_str(), s.complexity))
+            .collect();
+        assert_eq!(scores, vec![("flat", 1), ("branchy", 6)]);
+
+        let python = extract_symbols(
+            "def f(a):\n    if a or not a:\n        pass\n    elif a:\n        pass\n",
+            "python",
+        )
+        .unwrap();
+        assert_eq!(python[0].complexity, 4);
+    }
+
+    #[test]
+    fn signatures_stop_at_the_body() {
+        let rust = extract_symbols(
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+            "rust",
+        )
+        .expect("extraction failed");
+        assert_eq!(rust[0].signature, "pub fn add(a: i32, b: i32) -> i32");
+
+        let python = extract_symbols(
+            "@cached\ndef load(path):\n    \"\"\"Reads path.\"\"\"\n    return open(path)\n",
+            "python",
+        )
+        .expect("extraction failed");
+        assert_eq!(python[0].signature, "@cached\ndef load(path):");
+
+        let constant =
+            extract_symbols("const LIMIT = 10;\n", "typescript").expect("extraction failed");
+        assert_eq!(constant[0].signature, "LIMIT = 10");
+    }
+
     #[test]
     fn extracts_typescript_class_with_methods() {
         let source = r#"
@@ -405,8 +969,87 @@ pub struct Person {
         assert_eq!(symbols.len(), 1);
         assert_eq!(symbols[0].name, "Person");
         assert!(matches!(symbols[0].kind, SymbolKind::Struct));
-        // TODO: Fix doc comment extraction for Rust
-        assert!(symbols[0].doc_comment.is_some());
+        assert_eq!(
+            symbols[0].doc_comment.as_deref(),
+            Some("/// A person with a name and age")
+        );
+    }
+
+    #[test]
+    fn collects_multi_line_rust_doc_comments_in_order() {
+        let source = r#"
+/// Parses a config file.
+///
+/// Returns an error when the file is missing.
+pub fn parse(path: &str) -> Result<(), Error> { Ok(()) }
+"#;
+
+        let symbols = extract_symbols(source, "rust").expect("extraction failed");
+        assert_eq!(
+            symbols[0].doc_comment.as_deref(),
+            Some("/// Parses a config file.\n///\n/// Returns an error when the file is missing.")
+        );
+    }
+
+    #[test]
+    fn rust_doc_comments_span_attributes_but_not_gaps() {
+        let source = r#"
+/// Unrelated note about the module layout.
+
+/// A shape.
+#[derive(Debug, Clone)]
+pub enum Shape { Circle, Square }
+
+// Keep in sync with the proto.
+/// Drawable things.
+pub trait Draw { fn draw(&self); }
+
+/// Implementation detail.
+// not documentation
+pub struct Plain;
+"#;
+
+        let symbols = extract_symbols(source, "rust").expect("extraction failed");
+        let doc = |name: &str| {
+            symbols
+                .iter()
+                .find(|s| s.name == name)
+                .and_then(|s| s.doc_comment.clone())
+        };
+        assert_eq!(doc("Shape").as_deref(), Some("/// A shape."));
+        assert_eq!(doc("Draw").as_deref(), Some("/// Drawable things."));
+        assert_eq!(doc("Plain"), None);
+    }
+
+    #[test]
+    fn rust_doc_style_accepts_triple_slash_only() {
+        let style = doc_comment_style("rust");
+        assert!(style.is_doc_comment("line_comment", "/// Adds two numbers"));
+        assert!(!style.is_doc_comment("line_comment", "// implementation note"));
+        assert!(!style.is_doc_comment("comment", "/// wrong node kind"));
+    }
+
+    #[test]
+    fn go_doc_style_accepts_plain_line_comments() {
+        let style = doc_comment_style("go");
+        assert!(style.is_doc_comment("comment", "// Add returns the sum of a and b."));
+        assert!(!style.is_doc_comment("line_comment", "// Add returns the sum"));
+    }
+
+    #[test]
+    fn unknown_languages_use_default_doc_style() {
+        let style = doc_comment_style("cobol");
+        assert!(style.is_doc_comment("comment", "/** docs */"));
+        assert!(!style.is_doc_comment("comment", "// not docs"));
+    }
+
+    #[test]
+    fn extracts_rust_triple_slash_doc_comment_text() {
+        let source = "/// Adds two numbers\nfn add(a: i32, b: i32) -> i32 { a + b }\n";
+
+        let symbols = extract_symbols(source, "rust").expect("extraction failed");
+        let doc = symbols[0].doc_comment.as_deref().expect("doc comment");
+        assert!(doc.starts_with("/// Adds two numbers"));
     }
 
     #[test]
@@ -471,4 +1114,139 @@ pub struct Person {
             .unwrap()
             .contains("Adds two numbers"));
     }
+
+    #[test]
+    fn extracts_each_declarator_of_a_declaration() {
+        let source = r#"
+/** Limits shared by the client. */
+const A = 1, B = 2;
+let counter = 0;
+/** Default export target. */
+export const TARGET = "es2020";
+const { x, y } = point;
+"#;
+
+        let symbols = extract_symbols(source, "typescript").expect("extraction failed");
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "counter", "TARGET"]);
+
+        assert!(matches!(symbols[0].kind, SymbolKind::Constant));
+        assert!(matches!(symbols[1].kind, SymbolKind::Constant));
+        assert!(matches!(symbols[2].kind, SymbolKind::Variable));
+        assert!(matches!(symbols[3].kind, SymbolKind::Constant));
+
+        assert_eq!(symbols[0].content, "A = 1");
+        assert_eq!(symbols[1].content, "B = 2");
+        for symbol in &symbols[..2] {
+            assert_eq!(
+                symbol.doc_comment.as_deref(),
+                Some("/** Limits shared by the client. */")
+            );
+        }
+        assert_eq!(symbols[2].doc_comment, None);
+        assert_eq!(
+            symbols[3].doc_comment.as_deref(),
+            Some("/** Default export target. */")
+        );
+    }
+
+    #[cfg(feature = "lang-python")]
+    #[test]
+    fn extracts_python_class_methods_and_module_function() {
+        let source = r#"
+MAX_RETRIES = 3
+
+class Calculator:
+    """Adds and subtracts."""
+
+    precision = 2
+
+    def add(self, a, b):
+        """Return a + b."""
+        return a + b
+
+    @staticmethod
+    def subtract(a, b):
+        return a - b
+
+
+def main():
+    print(Calculator().add(1, 2))
+"#;
+
+        let symbols = extract_symbols(source, "python").expect("extraction failed");
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["MAX_RETRIES", "Calculator", "main"]);
+        assert!(matches!(symbols[0].kind, SymbolKind::Constant));
+
+        let class = &symbols[1];
+        assert!(matches!(class.kind, SymbolKind::Class));
+        assert_eq!(class.doc_comment.as_deref(), Some("Adds and subtracts."));
+        let members: Vec<&str> = class.children.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(members, vec!["precision", "add", "subtract"]);
+        assert!(matches!(class.children[1].kind, SymbolKind::Method));
+        assert_eq!(
+            class.children[1].doc_comment.as_deref(),
+            Some("Return a + b.")
+        );
+        // Decorators belong to the symbol they decorate.
+        assert!(class.children[2].content.starts_with("@staticmethod"));
+
+        assert!(matches!(symbols[2].kind, SymbolKind::Function));
+        assert!(symbols[2].doc_comment.is_none());
+    }
+
+    #[cfg(feature = "lang-go")]
+    #[test]
+    fn extracts_go_method_with_receiver_in_name() {
+        let source = r#"
+package server
+
+// Server accepts connections.
+type Server struct {
+	addr string
+}
+
+const (
+	DefaultPort = 8080
+	maxConns    = 64
+)
+
+// Start begins listening.
+func (s *Server) Start() error {
+	return listen(s.addr)
+}
+
+func (s Server) Addr() string { return s.addr }
+
+func listen(addr string) error { return nil }
+"#;
+
+        let symbols = extract_symbols(source, "go").expect("extraction failed");
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Server",
+                "DefaultPort",
+                "maxConns",
+                "(*Server).Start",
+                "Server.Addr",
+                "listen"
+            ]
+        );
+
+        assert!(matches!(symbols[0].kind, SymbolKind::Struct));
+        assert_eq!(
+            symbols[0].doc_comment.as_deref(),
+            Some("// Server accepts connections.")
+        );
+        assert!(matches!(symbols[1].kind, SymbolKind::Constant));
+        assert!(matches!(symbols[3].kind, SymbolKind::Method));
+        assert_eq!(
+            symbols[3].doc_comment.as_deref(),
+            Some("// Start begins listening.")
+        );
+        assert!(matches!(symbols[5].kind, SymbolKind::Function));
+    }
 }
@@ -0,0 +1,157 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Typed shape of `symbols.metadata`. Every write path builds one of these so
+/// stored JSON always uses the same keys and types; absent fields are omitted
+/// rather than written as `null`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<i64>,
+    /// Content was cut short before storage.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub truncated: bool,
+    /// Content went through line-ending/whitespace normalization.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub normalized: bool,
+    /// Too short to be worth embedding; stored without a vector and left out
+    /// of vector search.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub embedding_skipped: bool,
+    /// Indexed with `declarations_only`: content is the doc comment and
+    /// signature, without the body. A full index of the file replaces it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub declarations_only: bool,
+    /// Names of the symbols found in a file-level record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extracted_symbols: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extracted_symbols_count: Option<i64>,
+    /// `symlink` or `submodule` for git tree entries that are not files,
+    /// `binary` for files that are not text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_type: Option<String>,
+    /// Symlink target path or submodule commit id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl SymbolMetadata {
+    /// Reads stored metadata without failing on legacy rows: fields that are
+    /// missing or have the wrong type are left unset, unknown keys are ignored.
+    pub fn from_value(value: &Value) -> Self {
+        let Some(fields) = value.as_object() else {
+            return Self::default();
+        };
+        let string = |key: &str| fields.get(key).and_then(Value::as_str).map(str::to_string);
+        let int = |key: &str| fields.get(key).and_then(Value::as_i64);
+        let flag = |key: &str| fields.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+        Self {
+            doc: string("doc"),
+            language: string("language"),
+            signature: string("signature"),
+            visibility: string("visibility"),
+            complexity: int("complexity"),
+            line_count: int("line_count"),
+            truncated: flag("truncated"),
+            normalized: flag("normalized"),
+            embedding_skipped: flag("embedding_skipped"),
+            declarations_only: flag("declarations_only"),
+            extracted_symbols: fields
+                .get("extracted_symbols")
+                .and_then(Value::as_array)
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                }),
+            extracted_symbols_count: int("extracted_symbols_count"),
+            entry_type: string("entry_type"),
+            target: string("target"),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_else(|_| Value::Object(Map::new()))
+    }
+
+    /// True when `value` round-trips through the schema unchanged, i.e. it
+    /// uses only known keys with the expected types.
+    pub fn conforms(value: &Value) -> bool {
+        value.is_object() && Self::from_value(value).to_value() == *value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn index_and_temporal_paths_write_conforming_metadata() {
+        let indexed = crate::storage::symbol_metadata(
+            Some("/// Adds.".to_string()),
+            "rust",
+            3,
+            7,
+            2,
+            true,
+            false,
+        );
+        assert!(SymbolMetadata::conforms(&indexed), "{indexed}");
+        let indexed = SymbolMetadata::from_value(&indexed);
+        assert_eq!(indexed.line_count, Some(5));
+        assert_eq!(indexed.language.as_deref(), Some("rust"));
+        assert!(indexed.normalized);
+
+        let extracted =
+            crate::symbol_extractor::extract_symbols("fn add() {}\nfn sub() {}\n", "rust").unwrap();
+        let file = crate::temporal::file_symbol_metadata("rust", &extracted);
+        assert!(SymbolMetadata::conforms(&file), "{file}");
+        let file = SymbolMetadata::from_value(&file);
+        assert_eq!(file.extracted_symbols_count, Some(2));
+        assert_eq!(file.language.as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn legacy_rows_read_leniently() {
+        let legacy = json!({
+            "doc": "Old doc",
+            "complexity": "high",
+            "extracted_symbols": ["a", 1, "b"],
+            "owner": "team-x",
+        });
+        let metadata = SymbolMetadata::from_value(&legacy);
+        assert_eq!(metadata.doc.as_deref(), Some("Old doc"));
+        assert_eq!(metadata.complexity, None);
+        assert_eq!(
+            metadata.extracted_symbols,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(!SymbolMetadata::conforms(&legacy));
+
+        assert_eq!(
+            SymbolMetadata::from_value(&json!("text")),
+            SymbolMetadata::default()
+        );
+    }
+}
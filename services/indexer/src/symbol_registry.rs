@@ -2,12 +2,16 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::storage::{IndexStorage, StorageError, StoredSymbol};
+use crate::clock::{self, SharedClock};
+use crate::storage::{IndexStorage, StorageError, StoredSymbol, SymbolEdge, CONTAINS_RELATION};
+use crate::symbol_metadata::SymbolMetadata;
 
 /// Unique identifier for a symbol based on path, name, and kind
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -39,6 +43,25 @@ pub enum SymbolKind {
 use std::fmt;
 use std::str::FromStr;
 
+impl SymbolKind {
+    pub const ALL: [SymbolKind; 14] = [
+        SymbolKind::Function,
+        SymbolKind::Class,
+        SymbolKind::Interface,
+        SymbolKind::Enum,
+        SymbolKind::Constant,
+        SymbolKind::Variable,
+        SymbolKind::Type,
+        SymbolKind::Module,
+        SymbolKind::Method,
+        SymbolKind::Property,
+        SymbolKind::Namespace,
+        SymbolKind::Trait,
+        SymbolKind::Impl,
+        SymbolKind::Struct,
+    ];
+}
+
 impl fmt::Display for SymbolKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -122,6 +145,14 @@ pub struct Position {
     pub character: usize,
 }
 
+/// Delay before the first background retry of a failed startup load; it
+/// doubles on each failure up to `MAX_LOAD_RETRY_DELAY`.
+const INITIAL_LOAD_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_LOAD_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+type SymbolIndex = RwLock<HashMap<SymbolKey, Uuid>>;
+type SymbolMap = RwLock<HashMap<Uuid, Symbol>>;
+
 /// Registry for managing symbols with stable UUID mappings
 pub struct SymbolRegistry {
     /// Path + name + kind -> UUID mapping
@@ -130,74 +161,91 @@ pub struct SymbolRegistry {
     symbols: Arc<RwLock<HashMap<Uuid, Symbol>>>,
     /// Storage backend
     storage: Arc<dyn IndexStorage>,
+    /// Time source for created_at/updated_at
+    clock: SharedClock,
+    /// Whether stored symbols have been loaded; false while a failed startup
+    /// load is being retried
+    loaded: Arc<AtomicBool>,
 }
 
 impl SymbolRegistry {
-    pub async fn new(storage: Arc<dyn IndexStorage>) -> Result<Self, StorageError> {
+    pub async fn new(storage: Arc<dyn IndexStorage>) -> Self {
+        Self::with_clock(storage, clock::system_clock()).await
+    }
+
+    /// Creates the registry and loads existing symbols. If storage cannot be
+    /// read the registry starts empty and keeps retrying in the background,
+    /// so a database hiccup at startup does not take the service down.
+    pub async fn with_clock(storage: Arc<dyn IndexStorage>, clock: SharedClock) -> Self {
         let registry = Self {
             symbol_index: Arc::new(RwLock::new(HashMap::new())),
             symbols: Arc::new(RwLock::new(HashMap::new())),
             storage,
+            clock,
+            loaded: Arc::new(AtomicBool::new(false)),
         };
 
-        // Load existing symbols from storage
-        registry.load_from_storage().await?;
+        if let Err(e) = registry.reload().await {
+            tracing::warn!(
+                error = %e,
+                "Failed to load symbols from storage; starting with an empty registry"
+            );
+            registry.spawn_load_retry();
+        }
 
-        Ok(registry)
+        registry
     }
 
-    /// Load all symbols from storage into the in-memory registry
-    async fn load_from_storage(&self) -> Result<(), StorageError> {
-        // Query all symbols from storage
-        let stored_symbols = self.storage.query_all_symbols().await?;
-
-        let mut index = self.symbol_index.write().await;
-        let mut symbols = self.symbols.write().await;
-
-        for stored_symbol in stored_symbols {
-            // Parse symbol kind
-            let kind = SymbolKind::from_str(&stored_symbol.kind).unwrap_or(SymbolKind::Function);
-
-            let key = SymbolKey {
-                path: stored_symbol.path.clone(),
-                name: stored_symbol.name.clone(),
-                kind,
-            };
-
-            // Convert StoredSymbol to Symbol
-            let symbol = Symbol {
-                id: stored_symbol.id,
-                key: key.clone(),
-                content: stored_symbol.content,
-                location: Range {
-                    start: Position {
-                        line: stored_symbol.start_line as usize,
-                        character: 0, // Not stored, default to 0
-                    },
-                    end: Position {
-                        line: stored_symbol.end_line as usize,
-                        character: 0, // Not stored, default to 0
-                    },
-                },
-                doc_comment: None,    // Not stored in current schema
-                children: Vec::new(), // Would need separate table for hierarchy
-                parent: None,         // Would need separate table for hierarchy
-                commit_id: stored_symbol.commit_id,
-                created_at: stored_symbol.created_at,
-                updated_at: stored_symbol.updated_at,
-            };
-
-            // Add to index and symbols map
-            index.insert(key, stored_symbol.id);
-            symbols.insert(stored_symbol.id, symbol);
-        }
+    /// Whether stored symbols are in the registry. Until then lookups only
+    /// see symbols created since startup.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.load(Ordering::Acquire)
+    }
 
-        tracing::info!(
-            "Loaded {} symbols from storage into registry",
-            symbols.len()
-        );
+    /// Loads all symbols from storage into the registry, keeping any entry
+    /// already present. Returns the number of symbols added.
+    pub async fn reload(&self) -> Result<usize, StorageError> {
+        let (stored_symbols, edges) = load_stored(self.storage.as_ref()).await?;
+        let added =
+            merge_stored_symbols(&self.symbol_index, &self.symbols, stored_symbols, edges).await;
+        self.loaded.store(true, Ordering::Release);
+        Ok(added)
+    }
 
-        Ok(())
+    /// Retries the startup load with exponential backoff until it succeeds,
+    /// `reload` succeeds first, or the registry is dropped.
+    fn spawn_load_retry(&self) {
+        let storage = self.storage.clone();
+        let index = Arc::downgrade(&self.symbol_index);
+        let symbols = Arc::downgrade(&self.symbols);
+        let loaded = Arc::downgrade(&self.loaded);
+
+        tokio::spawn(async move {
+            let mut delay = INITIAL_LOAD_RETRY_DELAY;
+            loop {
+                tokio::time::sleep(delay).await;
+                let (Some(index), Some(symbols), Some(loaded)) =
+                    (index.upgrade(), symbols.upgrade(), loaded.upgrade())
+                else {
+                    return;
+                };
+                if loaded.load(Ordering::Acquire) {
+                    return;
+                }
+
+                match load_stored(storage.as_ref()).await {
+                    Ok((stored_symbols, edges)) => {
+                        merge_stored_symbols(&index, &symbols, stored_symbols, edges).await;
+                        loaded.store(true, Ordering::Release);
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, retry_in = ?delay, "Symbol registry load failed");
+                        delay = (delay * 2).min(MAX_LOAD_RETRY_DELAY);
+                    }
+                }
+            }
+        });
     }
 
     /// Get or create a symbol with stable UUID
@@ -220,6 +268,7 @@ impl SymbolRegistry {
             return Ok(*existing_id);
         }
 
+        let now = self.clock.now();
         let symbol = Symbol {
             id,
             key: key.clone(),
@@ -238,8 +287,8 @@ impl SymbolRegistry {
             children: Vec::new(),
             parent: None,
             commit_id: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            created_at: now,
+            updated_at: now,
         };
 
         index.insert(key, id);
@@ -248,6 +297,11 @@ impl SymbolRegistry {
         Ok(id)
     }
 
+    /// Id of the symbol `key` names, without creating one.
+    pub async fn lookup(&self, key: &SymbolKey) -> Option<Uuid> {
+        self.symbol_index.read().await.get(key).copied()
+    }
+
     /// Update symbol data
     pub async fn update_symbol(
         &self,
@@ -264,7 +318,7 @@ impl SymbolRegistry {
             symbol.location = location;
             symbol.doc_comment = doc_comment.clone();
             symbol.commit_id = commit_id;
-            symbol.updated_at = Utc::now();
+            symbol.updated_at = self.clock.now();
 
             // Convert to StoredSymbol for persistence
             let stored = StoredSymbol {
@@ -277,7 +331,13 @@ impl SymbolRegistry {
                 commit_id: symbol.commit_id.clone(),
                 start_line: symbol.location.start.line as i32,
                 end_line: symbol.location.end.line as i32,
-                metadata: doc_comment.map(|doc| serde_json::json!({"doc": doc})),
+                metadata: doc_comment.map(|doc| {
+                    SymbolMetadata {
+                        doc: Some(doc),
+                        ..SymbolMetadata::default()
+                    }
+                    .to_value()
+                }),
                 created_at: symbol.created_at,
                 updated_at: symbol.updated_at,
             };
@@ -288,13 +348,53 @@ impl SymbolRegistry {
         Ok(())
     }
 
+    /// Gives an existing symbol a new name while keeping its id, so clients
+    /// holding the id still resolve it. Returns `false` for an unknown id.
+    pub async fn rename_symbol(&self, id: Uuid, new_name: String) -> Result<bool, StorageError> {
+        let mut index = self.symbol_index.write().await;
+        let mut symbols = self.symbols.write().await;
+
+        let Some(symbol) = symbols.get_mut(&id) else {
+            return Ok(false);
+        };
+
+        index.remove(&symbol.key);
+        symbol.key.name = new_name;
+        symbol.updated_at = self.clock.now();
+        index.insert(symbol.key.clone(), id);
+
+        let stored = StoredSymbol {
+            id,
+            path: symbol.key.path.clone(),
+            name: symbol.key.name.clone(),
+            kind: symbol.key.kind.to_string(),
+            content: symbol.content.clone(),
+            embedding: vec![], // Content may mention the new name; re-embed.
+            commit_id: symbol.commit_id.clone(),
+            start_line: symbol.location.start.line as i32,
+            end_line: symbol.location.end.line as i32,
+            metadata: symbol.doc_comment.clone().map(|doc| {
+                SymbolMetadata {
+                    doc: Some(doc),
+                    ..SymbolMetadata::default()
+                }
+                .to_value()
+            }),
+            created_at: symbol.created_at,
+            updated_at: symbol.updated_at,
+        };
+        self.storage.store_symbol(&stored).await?;
+
+        Ok(true)
+    }
+
     /// Mark a symbol as deleted in a specific commit
     pub async fn mark_deleted(&self, id: Uuid, commit_id: String) -> Result<(), StorageError> {
         let mut symbols = self.symbols.write().await;
 
         if let Some(symbol) = symbols.get_mut(&id) {
             symbol.commit_id = Some(commit_id);
-            symbol.updated_at = Utc::now();
+            symbol.updated_at = self.clock.now();
             // In a real implementation, we might add a "deleted" flag
             // or move to a separate deleted symbols collection
         }
@@ -302,6 +402,38 @@ impl SymbolRegistry {
         Ok(())
     }
 
+    /// Drops the symbols of `path`, only those recorded at `commit_id` when
+    /// given, along with their keys and any parent or child links to them.
+    /// Returns the number dropped.
+    pub async fn remove_path(&self, path: &str, commit_id: Option<&str>) -> usize {
+        let mut index = self.symbol_index.write().await;
+        let mut symbols = self.symbols.write().await;
+
+        let removed: HashSet<Uuid> = symbols
+            .values()
+            .filter(|s| s.key.path == path)
+            .filter(|s| commit_id.is_none() || s.commit_id.as_deref() == commit_id)
+            .map(|s| s.id)
+            .collect();
+        if removed.is_empty() {
+            return 0;
+        }
+
+        symbols.retain(|id, _| !removed.contains(id));
+        index.retain(|_, id| !removed.contains(id));
+        for symbol in symbols.values_mut() {
+            symbol.children.retain(|child| !removed.contains(child));
+            if symbol
+                .parent
+                .is_some_and(|parent| removed.contains(&parent))
+            {
+                symbol.parent = None;
+            }
+        }
+
+        removed.len()
+    }
+
     /// Find symbols by name
     pub async fn find_by_name(&self, name: &str) -> Vec<Uuid> {
         let symbols = self.symbols.read().await;
@@ -345,3 +477,87 @@ impl SymbolRegistry {
             .collect()
     }
 }
+
+async fn load_stored(
+    storage: &dyn IndexStorage,
+) -> Result<(Vec<StoredSymbol>, Vec<SymbolEdge>), StorageError> {
+    Ok((
+        storage.query_all_symbols().await?,
+        storage.query_symbol_edges().await?,
+    ))
+}
+
+/// Adds stored symbols to the registry maps, skipping keys and ids already
+/// present, then links parents and children along the `contains` edges.
+/// Returns the number added.
+async fn merge_stored_symbols(
+    symbol_index: &SymbolIndex,
+    symbol_map: &SymbolMap,
+    stored_symbols: Vec<StoredSymbol>,
+    edges: Vec<SymbolEdge>,
+) -> usize {
+    let mut index = symbol_index.write().await;
+    let mut symbols = symbol_map.write().await;
+    let mut added = 0;
+
+    for stored_symbol in stored_symbols {
+        // Parse symbol kind
+        let kind = SymbolKind::from_str(&stored_symbol.kind).unwrap_or(SymbolKind::Function);
+
+        let key = SymbolKey {
+            path: stored_symbol.path.clone(),
+            name: stored_symbol.name.clone(),
+            kind,
+        };
+
+        // Convert StoredSymbol to Symbol
+        let symbol = Symbol {
+            id: stored_symbol.id,
+            key: key.clone(),
+            content: stored_symbol.content,
+            location: Range {
+                start: Position {
+                    line: stored_symbol.start_line as usize,
+                    character: 0, // Not stored, default to 0
+                },
+                end: Position {
+                    line: stored_symbol.end_line as usize,
+                    character: 0, // Not stored, default to 0
+                },
+            },
+            doc_comment: None, // Not stored in current schema
+            children: Vec::new(),
+            parent: None,
+            commit_id: stored_symbol.commit_id,
+            created_at: stored_symbol.created_at,
+            updated_at: stored_symbol.updated_at,
+        };
+
+        // Symbols created or renamed since startup are newer than storage
+        if index.contains_key(&key) || symbols.contains_key(&stored_symbol.id) {
+            continue;
+        }
+        index.insert(key, stored_symbol.id);
+        symbols.insert(stored_symbol.id, symbol);
+        added += 1;
+    }
+
+    for edge in edges {
+        if edge.relation != CONTAINS_RELATION || !symbols.contains_key(&edge.child_id) {
+            continue;
+        }
+        let Some(parent) = symbols.get_mut(&edge.parent_id) else {
+            continue;
+        };
+        if !parent.children.contains(&edge.child_id) {
+            parent.children.push(edge.child_id);
+        }
+        if let Some(child) = symbols.get_mut(&edge.child_id) {
+            child.parent = Some(edge.parent_id);
+        }
+    }
+
+    tracing::info!("Loaded {} symbols from storage into registry", added);
+
+    added
+}
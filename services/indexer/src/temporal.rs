@@ -1,17 +1,25 @@
 #![allow(dead_code)]
 
 use chrono::{DateTime, Utc};
-use git2::{Commit, DiffOptions, Oid, Repository};
+use git2::{Commit, DiffFindOptions, DiffOptions, Oid, Repository};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use super::symbol_extractor;
+use super::symbol_extractor::{self, LineRange};
+use crate::clock::{self, SharedClock};
+use crate::embeddings::EmbeddingProvider;
+use crate::security::{normalize_path, SecurityConfig};
 use crate::storage::{IndexStorage, StoredSymbol};
+use crate::symbol_metadata::SymbolMetadata;
+use crate::symbol_registry::{SymbolKey, SymbolKind, SymbolRegistry};
 
 /// Error types for temporal operations
 #[derive(Error, Debug)]
@@ -36,8 +44,51 @@ pub enum TemporalError {
 
     #[error("Task join error: {0}")]
     JoinError(#[from] tokio::task::JoinError),
+
+    #[error("Commit {0} has more than {1} ancestors")]
+    TooManyAncestors(String, usize),
+
+    #[error("Semantic correlation requires an embedding provider")]
+    EmbeddingUnavailable,
+
+    #[error("Embedding error: {0}")]
+    Embedding(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("path '{0}' is not permitted by ACL policy")]
+    AccessDenied(String),
+
+    #[error("File {0} is {1} bytes, over the limit of {2}")]
+    FileTooLarge(String, u64, u64),
 }
 
+/// Upper bound on the ancestry set resolved for commit-scoped searches.
+pub const MAX_ANCESTOR_COMMITS: usize = 10_000;
+
+/// Default cap on concurrent git operations per temporal index.
+pub const DEFAULT_MAX_CONCURRENT_GIT_OPS: usize = 4;
+
+/// Default number of CI events kept in memory.
+pub const DEFAULT_CI_EVENT_RETENTION: usize = 1000;
+
+/// Default age after which in-memory CI events are dropped.
+pub const DEFAULT_CI_EVENT_MAX_AGE_HOURS: u32 = 24 * 7;
+
+/// Minimum relevance for a changed file to be reported as a suspect.
+const SUSPECT_THRESHOLD: f32 = 0.3;
+
+/// Share of the blended relevance score taken by embedding similarity when
+/// semantic correlation is requested; the rest is the lexical heuristic.
+const SEMANTIC_WEIGHT: f32 = 0.6;
+
+/// Changed files embedded per semantic correlation, highest heuristic first.
+const MAX_SEMANTIC_CANDIDATES: usize = 50;
+
+/// Characters of each changed file sent to the embedder.
+const MAX_SEMANTIC_CONTENT_CHARS: usize = 4_000;
+
 /// Type of change made to a symbol
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ChangeType {
@@ -47,6 +98,41 @@ pub enum ChangeType {
     Renamed,
 }
 
+impl ChangeType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeType::Added => "Added",
+            ChangeType::Modified => "Modified",
+            ChangeType::Deleted => "Deleted",
+            ChangeType::Renamed => "Renamed",
+        }
+    }
+
+    /// Inverse of [`ChangeType::as_str`], ignoring case; `symbol_versions`
+    /// stores the names in lower case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "added" => Some(ChangeType::Added),
+            "modified" => Some(ChangeType::Modified),
+            "deleted" => Some(ChangeType::Deleted),
+            "renamed" => Some(ChangeType::Renamed),
+            _ => None,
+        }
+    }
+}
+
+/// The commit that last changed one line of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Zero-based line number.
+    pub line: usize,
+    pub commit_id: String,
+    pub author: String,
+    pub author_email: String,
+    /// Author time of the commit.
+    pub timestamp: DateTime<Utc>,
+}
+
 /// A version of a symbol at a specific commit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolVersion {
@@ -60,6 +146,17 @@ pub struct SymbolVersion {
     pub previous_path: Option<String>, // For renames
 }
 
+/// A file that changed in the same commits as another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoChange {
+    pub path: String,
+    /// Commits that changed both files.
+    pub support: usize,
+    /// Share of the commits changing the queried file that also changed
+    /// this one.
+    pub confidence: f64,
+}
+
 /// Information about a CI/CD event correlated with code changes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CiEvent {
@@ -77,6 +174,25 @@ pub enum CiStatus {
     Skipped,
 }
 
+impl CiStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CiStatus::Passed => "Passed",
+            CiStatus::Failed => "Failed",
+            CiStatus::Skipped => "Skipped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "Passed" => Some(CiStatus::Passed),
+            "Failed" => Some(CiStatus::Failed),
+            "Skipped" => Some(CiStatus::Skipped),
+            _ => None,
+        }
+    }
+}
+
 /// A suspect change that may have caused a CI failure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuspectChange {
@@ -100,6 +216,140 @@ pub struct TemporalConfig {
 
     /// Whether to index merge commits
     pub include_merge_commits: bool,
+
+    /// Maximum git operations running on the blocking pool at once; further
+    /// operations wait for a slot
+    pub max_concurrent_git_ops: usize,
+
+    /// Most recent CI events kept in memory; every event is also persisted
+    pub ci_event_retention: usize,
+
+    /// Age (in hours) after which in-memory CI events are dropped
+    pub ci_event_max_age_hours: Option<u32>,
+
+    /// How `CorrelateFailure` weighs its signals
+    pub correlation: CorrelationConfig,
+}
+
+/// Weights of the signals `correlate_ci_failure` blends into a changed
+/// file's relevance. The score is their weighted mean, so it stays in 0..=1
+/// whatever the weights; when the failure message names no `path:line`
+/// locations only the keyword signal is used.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CorrelationConfig {
+    /// Path and keyword heuristic: test name parts in the path, the path in
+    /// the message, test-looking files.
+    pub keyword_weight: f32,
+    /// The file appears as a `path:line` location in the failure message.
+    pub location_weight: f32,
+    /// Share of the lines around those locations last changed by a commit in
+    /// the correlated range, by blame at the failing commit.
+    pub blame_weight: f32,
+    /// Lines blamed on either side of each location.
+    pub blame_context_lines: usize,
+    /// Terms of the path/keyword heuristic.
+    pub relevance_weights: RelevanceWeights,
+}
+
+/// What each match found by the path/keyword heuristic adds to a changed
+/// file's keyword score, which is capped at 1.0.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RelevanceWeights {
+    /// Per part of the test name (split on non-alphanumerics) found in the path.
+    pub test_name_part: f32,
+    /// The failure message contains the path.
+    pub path_in_message: f32,
+    /// The path contains `test` or `spec`.
+    pub test_file: f32,
+}
+
+impl Default for RelevanceWeights {
+    fn default() -> Self {
+        Self {
+            test_name_part: 0.3,
+            path_in_message: 0.5,
+            test_file: 0.2,
+        }
+    }
+}
+
+impl RelevanceWeights {
+    /// Reads `CORRELATE_WEIGHT_TEST_NAME_PART`,
+    /// `CORRELATE_WEIGHT_PATH_IN_MESSAGE` and `CORRELATE_WEIGHT_TEST_FILE`;
+    /// unset or invalid values keep the defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            test_name_part: env_weight("CORRELATE_WEIGHT_TEST_NAME_PART", defaults.test_name_part),
+            path_in_message: env_weight(
+                "CORRELATE_WEIGHT_PATH_IN_MESSAGE",
+                defaults.path_in_message,
+            ),
+            test_file: env_weight("CORRELATE_WEIGHT_TEST_FILE", defaults.test_file),
+        }
+    }
+}
+
+/// A non-negative, finite weight from `name`, or `default`.
+fn env_weight(name: &str, default: f32) -> f32 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|weight: &f32| weight.is_finite() && *weight >= 0.0)
+        .unwrap_or(default)
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            keyword_weight: 0.4,
+            location_weight: 0.3,
+            blame_weight: 0.3,
+            blame_context_lines: 2,
+            relevance_weights: RelevanceWeights::default(),
+        }
+    }
+}
+
+impl CorrelationConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            keyword_weight: env_weight(
+                "TEMPORAL_CORRELATION_KEYWORD_WEIGHT",
+                defaults.keyword_weight,
+            ),
+            location_weight: env_weight(
+                "TEMPORAL_CORRELATION_LOCATION_WEIGHT",
+                defaults.location_weight,
+            ),
+            blame_weight: env_weight("TEMPORAL_CORRELATION_BLAME_WEIGHT", defaults.blame_weight),
+            blame_context_lines: std::env::var("TEMPORAL_CORRELATION_BLAME_CONTEXT_LINES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.blame_context_lines),
+            relevance_weights: RelevanceWeights::from_env(),
+        }
+    }
+
+    /// Relevance of a changed file from its keyword score and, when the
+    /// failure message has locations, what they say about the file: `None`
+    /// if it is not among them, otherwise the share of blamed lines changed
+    /// in the range.
+    fn relevance(&self, keyword: f32, has_locations: bool, located: Option<f32>) -> f32 {
+        let total = self.keyword_weight + self.location_weight + self.blame_weight;
+        if !has_locations || total <= 0.0 {
+            return keyword;
+        }
+        let (location, blame) = match located {
+            Some(share) => (1.0, share),
+            None => (0.0, 0.0),
+        };
+        (self.keyword_weight * keyword
+            + self.location_weight * location
+            + self.blame_weight * blame)
+            / total
+    }
 }
 
 impl Default for TemporalConfig {
@@ -109,6 +359,10 @@ impl Default for TemporalConfig {
             batch_size: 100,
             max_age_days: Some(90), // 3 months
             include_merge_commits: false,
+            max_concurrent_git_ops: DEFAULT_MAX_CONCURRENT_GIT_OPS,
+            ci_event_retention: DEFAULT_CI_EVENT_RETENTION,
+            ci_event_max_age_hours: Some(DEFAULT_CI_EVENT_MAX_AGE_HOURS),
+            correlation: CorrelationConfig::default(),
         }
     }
 }
@@ -129,11 +383,30 @@ impl TemporalConfig {
             .and_then(|v| v.parse().ok())
             .or(Some(90));
 
+        let max_concurrent_git_ops = std::env::var("TEMPORAL_MAX_CONCURRENT_GIT_OPS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_GIT_OPS);
+
+        let ci_event_retention = std::env::var("TEMPORAL_CI_EVENT_RETENTION")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CI_EVENT_RETENTION);
+
+        let ci_event_max_age_hours = std::env::var("TEMPORAL_CI_EVENT_MAX_AGE_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .or(Some(DEFAULT_CI_EVENT_MAX_AGE_HOURS));
+
         Self {
             repo_path,
             batch_size,
             max_age_days,
             include_merge_commits: false,
+            max_concurrent_git_ops,
+            ci_event_retention,
+            ci_event_max_age_hours,
+            correlation: CorrelationConfig::from_env(),
         }
     }
 }
@@ -143,8 +416,56 @@ pub struct TemporalIndex {
     #[allow(dead_code)]
     storage: Arc<dyn IndexStorage>,
     config: TemporalConfig,
-    symbol_history: Arc<parking_lot::RwLock<HashMap<String, Vec<SymbolVersion>>>>,
+    symbol_history: parking_lot::RwLock<HistoryCache>,
     ci_events: Arc<parking_lot::RwLock<Vec<CiEvent>>>,
+    clock: SharedClock,
+    embedder: Option<Arc<dyn EmbeddingProvider>>,
+    registry: Option<Arc<SymbolRegistry>>,
+    git_ops: GitOpLimiter,
+}
+
+/// Persisted symbol history already read from storage, by path. Indexing
+/// drops the paths it wrote and bumps `generation`, so a read that started
+/// before the write cannot cache what it loaded.
+#[derive(Default)]
+struct HistoryCache {
+    by_path: HashMap<String, Vec<SymbolVersion>>,
+    generation: u64,
+}
+
+/// Caps how many git operations occupy tokio's blocking pool at once, so a
+/// burst of git calls queues here instead of taking the blocking threads
+/// other work (such as local embedding) needs.
+#[derive(Clone)]
+struct GitOpLimiter {
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+impl GitOpLimiter {
+    fn new(max_concurrent: usize) -> Self {
+        // A limit of zero would park every git operation forever.
+        Self {
+            permits: Arc::new(tokio::sync::Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    async fn run<T, F>(&self, op: F) -> Result<T, TemporalError>
+    where
+        F: FnOnce() -> Result<T, TemporalError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("git semaphore is never closed");
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            op()
+        })
+        .await?
+    }
 }
 
 impl TemporalIndex {
@@ -166,42 +487,64 @@ impl TemporalIndex {
 
         Ok(Self {
             storage,
+            git_ops: GitOpLimiter::new(config.max_concurrent_git_ops),
             config,
-            symbol_history: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            symbol_history: parking_lot::RwLock::new(HistoryCache::default()),
             ci_events: Arc::new(parking_lot::RwLock::new(Vec::new())),
+            clock: clock::system_clock(),
+            embedder: None,
+            registry: None,
         })
     }
 
-    /// Index a range of commits
+    /// Replace the time source used for commit age filtering and symbol timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Provider used to embed failures and changed files for semantic correlation
+    pub fn with_embedder(mut self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Registry whose ids history versions take for symbols it knows
+    pub fn with_symbol_registry(mut self, registry: Arc<SymbolRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Whether `CorrelateFailure` can rank suspects semantically.
+    pub fn has_embedder(&self) -> bool {
+        self.embedder.is_some()
+    }
+
+    /// Index a range of commits, persisting the versions found in each
     pub async fn index_commit_range(
         &self,
         start_commit: Option<String>,
         end_commit: Option<String>,
     ) -> Result<usize, TemporalError> {
         let config = self.config.clone();
-        let history = self.symbol_history.clone();
+        let now = self.clock.now();
+        // Each batch's versions are written while the walk continues, so a
+        // long range is never held in memory at once.
+        let (batches, mut written) = tokio::sync::mpsc::channel(2);
+        let send = move |versions| {
+            batches
+                .blocking_send(versions)
+                .map_err(|_| TemporalError::Storage("symbol history writer stopped".to_string()))
+        };
 
-        tokio::task::spawn_blocking(move || {
+        let walk = self.git_ops.run(move || {
             let repo = Repository::open(&config.repo_path)?;
-            let mut revwalk = repo.revwalk()?;
-
-            // Configure the walk
-            if let Some(end) = end_commit {
-                let oid = Oid::from_str(&end)?;
-                revwalk.push(oid)?;
-            } else {
-                revwalk.push_head()?;
-            }
-
-            if let Some(start) = start_commit {
-                let oid = Oid::from_str(&start)?;
-                revwalk.hide(oid)?;
-            }
-
-            // Optionally filter out merge commits
-            if !config.include_merge_commits {
-                revwalk.simplify_first_parent()?;
-            }
+            let revwalk = commit_walk(
+                &repo,
+                &config,
+                start_commit.as_deref(),
+                end_commit.as_deref(),
+            )?;
 
             let mut indexed_count = 0;
             let mut batch = Vec::new();
@@ -209,41 +552,131 @@ impl TemporalIndex {
             for oid in revwalk {
                 let oid = oid?;
                 let commit = repo.find_commit(oid)?;
-
-                // Check age filter
-                if let Some(max_age_days) = config.max_age_days {
-                    let commit_time = DateTime::from_timestamp(commit.time().seconds(), 0)
-                        .ok_or_else(|| {
-                            TemporalError::ParseError("Invalid commit timestamp".to_string())
-                        })?;
-                    let age_days = (Utc::now() - commit_time).num_days();
-
-                    if age_days > max_age_days as i64 {
-                        debug!("Skipping commit {} (too old: {} days)", oid, age_days);
-                        continue;
-                    }
+                if !within_max_age(&config, &commit, now)? {
+                    continue;
                 }
 
                 batch.push(commit);
 
                 if batch.len() >= config.batch_size {
-                    indexed_count += process_commit_batch(&repo, &batch, &history)?;
+                    let mut versions = Vec::new();
+                    indexed_count += process_commit_batch(&repo, &batch, &mut versions)?;
+                    send(versions)?;
                     batch.clear();
                 }
             }
 
             // Process remaining commits
             if !batch.is_empty() {
-                indexed_count += process_commit_batch(&repo, &batch, &history)?;
+                let mut versions = Vec::new();
+                indexed_count += process_commit_batch(&repo, &batch, &mut versions)?;
+                send(versions)?;
             }
 
-            info!("Indexed {} commits", indexed_count);
             Ok(indexed_count)
-        })
-        .await?
+        });
+        let store = async move {
+            while let Some(versions) = written.recv().await {
+                self.store_versions(versions).await?;
+            }
+            Ok::<_, TemporalError>(())
+        };
+
+        let (walked, stored) = tokio::join!(walk, store);
+        // A failed write stops the walk too; report the write.
+        stored?;
+        let indexed_count = walked?;
+
+        info!("Indexed {} commits", indexed_count);
+        Ok(indexed_count)
     }
 
-    /// Get symbol at a specific commit
+    /// Writes `(path, version)` pairs grouped by path and commit, then drops
+    /// the cached history of every path written.
+    async fn store_versions(
+        &self,
+        mut versions: Vec<(String, SymbolVersion)>,
+    ) -> Result<(), TemporalError> {
+        if let Some(registry) = &self.registry {
+            for (_, version) in &mut versions {
+                resolve_registry_id(registry, version).await;
+            }
+        }
+
+        let mut grouped: HashMap<(String, String), Vec<SymbolVersion>> = HashMap::new();
+        for (path, version) in versions {
+            grouped
+                .entry((path, version.commit_id.clone()))
+                .or_default()
+                .push(version);
+        }
+
+        let result = async {
+            for ((path, commit_id), versions) in &grouped {
+                self.storage
+                    .store_symbol_versions(path, commit_id, versions)
+                    .await
+                    .map_err(|e| TemporalError::Storage(e.to_string()))?;
+            }
+            Ok(())
+        }
+        .await;
+
+        // Invalidate even after a failed write; earlier groups may have landed.
+        let mut cache = self.symbol_history.write();
+        cache.generation += 1;
+        for (path, _) in grouped.keys() {
+            cache.by_path.remove(path);
+        }
+        result
+    }
+
+    /// Forgets every cached history, for when rows were removed behind the
+    /// index's back (such as by `DeleteByCommit`).
+    pub fn invalidate_history(&self) {
+        let mut cache = self.symbol_history.write();
+        cache.generation += 1;
+        cache.by_path.clear();
+    }
+
+    /// Ids of `commit_id` and every commit reachable from it, so searches can be
+    /// scoped to the history visible at that commit.
+    pub async fn ancestor_commits(&self, commit_id: &str) -> Result<Vec<String>, TemporalError> {
+        let config = self.config.clone();
+        let commit_id = commit_id.to_string();
+
+        self.git_ops
+            .run(move || {
+                let repo = Repository::open(&config.repo_path)?;
+                let oid = Oid::from_str(&commit_id)
+                    .ok()
+                    .filter(|oid| repo.find_commit(*oid).is_ok())
+                    .ok_or_else(|| TemporalError::CommitNotFound(commit_id.clone()))?;
+
+                let mut revwalk = repo.revwalk()?;
+                revwalk.push(oid)?;
+
+                let mut ancestors = Vec::new();
+                for oid in revwalk {
+                    if ancestors.len() >= MAX_ANCESTOR_COMMITS {
+                        return Err(TemporalError::TooManyAncestors(
+                            commit_id,
+                            MAX_ANCESTOR_COMMITS,
+                        ));
+                    }
+                    ancestors.push(oid?.to_string());
+                }
+                Ok(ancestors)
+            })
+            .await
+    }
+
+    /// Get symbol at a specific commit.
+    ///
+    /// Returns `Ok(None)` when the path is not in the commit's tree (or names a
+    /// directory) and [`TemporalError::CommitNotFound`] when the commit does
+    /// not exist. Symlinks and submodules come back as empty `Symlink` and
+    /// `Submodule` records with their target in metadata.
     pub async fn get_symbol_at_commit(
         &self,
         path: &str,
@@ -252,99 +685,128 @@ impl TemporalIndex {
         let config = self.config.clone();
         let path = path.to_string();
         let commit_id = commit_id.to_string();
+        let now = self.clock.now();
+
+        self.git_ops
+            .run(move || {
+                let repo = Repository::open(&config.repo_path)?;
+                let symbol = get_symbol_at_commit_blocking(&repo, &path, &commit_id, now)?;
+                if let Some(symbol) = &symbol {
+                    debug!("Retrieved {} {} at commit {}", symbol.kind, path, commit_id);
+                }
+                Ok(symbol)
+            })
+            .await
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let repo = Repository::open(&config.repo_path)?;
-            let oid = Oid::from_str(&commit_id)?;
-            let commit = repo.find_commit(oid)?;
-            let tree = commit.tree()?;
-
-            // Try to get the file from the tree
-            let entry = match tree.get_path(Path::new(&path)) {
-                Ok(e) => e,
-                Err(_) => return Ok(None),
-            };
-
-            let object = entry.to_object(&repo)?;
-
-            if let Some(blob) = object.as_blob() {
-                let content = String::from_utf8_lossy(blob.content()).to_string();
-
-                // Determine language from extension
-                let language = if path.ends_with(".rs") {
-                    "rust"
-                } else if path.ends_with(".ts") || path.ends_with(".tsx") {
-                    "typescript"
-                } else if path.ends_with(".js") || path.ends_with(".jsx") {
-                    "javascript"
-                } else {
-                    "unknown"
-                };
-
-                // Extract symbols
-                let extracted = if language != "unknown" {
-                    symbol_extractor::extract_symbols(&content, language).unwrap_or_default()
-                } else {
-                    Vec::new()
-                };
-
-                let symbol = StoredSymbol {
-                    id: Uuid::new_v4(),
-                    path: path.to_string(),
-                    name: Path::new(&path)
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string(),
-                    kind: "File".to_string(),
-                    content: content.clone(),
-                    embedding: Vec::new(), // No embedding for now
-                    commit_id: Some(commit_id.to_string()),
-                    start_line: 0,
-                    end_line: content.lines().count() as i32,
-                    metadata: Some(serde_json::json!({
-                        "extracted_symbols_count": extracted.len(),
-                        "extracted_symbols": extracted.iter().map(|s| &s.name).collect::<Vec<_>>(),
-                        "language": language
-                    })),
-                    created_at: Utc::now(),
-                    updated_at: Utc::now(),
+    /// Contents of `path` in the working tree of the configured repository,
+    /// uncommitted edits included. `None` when the file does not exist or is
+    /// not a regular file; binary files read as empty. Paths that resolve
+    /// outside the repository, through `..` or a symlink, or into `.git` are
+    /// rejected as `InvalidPath`. The ACL is checked again on the resolved
+    /// path, so a symlink cannot reach a denied file, and files over
+    /// `max_bytes` are `FileTooLarge` rather than read.
+    pub async fn read_working_tree(
+        &self,
+        path: &str,
+        security: &SecurityConfig,
+        max_bytes: u64,
+    ) -> Result<Option<String>, TemporalError> {
+        let relative = normalize_path(path)
+            .and_then(|normalized| normalized.strip_prefix("/").ok().map(Path::to_path_buf))
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .ok_or_else(|| TemporalError::InvalidPath(path.to_string()))?;
+        let repo_path = self.config.repo_path.clone();
+        let path = path.to_string();
+        let security = security.clone();
+
+        self.git_ops
+            .run(move || {
+                let root = repo_path.canonicalize()?;
+                let target = match root.join(&relative).canonicalize() {
+                    Ok(target) => target,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                    Err(e) => return Err(e.into()),
                 };
+                let inside = target
+                    .strip_prefix(&root)
+                    .map_err(|_| TemporalError::InvalidPath(path.clone()))?;
+                if inside.starts_with(".git") {
+                    return Err(TemporalError::InvalidPath(path));
+                }
+                let resolved = inside.to_string_lossy().replace('\\', "/");
+                if security.check_path(&resolved).is_err() {
+                    return Err(TemporalError::AccessDenied(path));
+                }
+                let metadata = std::fs::metadata(&target)?;
+                if !metadata.is_file() {
+                    return Ok(None);
+                }
+                if metadata.len() > max_bytes {
+                    return Err(TemporalError::FileTooLarge(path, metadata.len(), max_bytes));
+                }
 
-                debug!(
-                    "Retrieved file {} at commit {} with {} symbols",
-                    path,
-                    commit_id,
-                    extracted.len()
-                );
+                let bytes = std::fs::read(&target)?;
+                if bytes.contains(&0) {
+                    return Ok(Some(String::new()));
+                }
+                debug!("Read {} from the working tree", path);
+                Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+            })
+            .await
+    }
 
-                Ok(Some(symbol))
-            } else {
-                Ok(None)
+    /// Get history of changes for a symbol/file, newest first. Served from
+    /// the cache when `path` was read before, otherwise from storage.
+    pub async fn get_symbol_history(
+        &self,
+        path: &str,
+    ) -> Result<Vec<SymbolVersion>, TemporalError> {
+        let generation = {
+            let cache = self.symbol_history.read();
+            if let Some(history) = cache.by_path.get(path) {
+                return Ok(history.clone());
             }
-        })
-        .await?
-    }
+            cache.generation
+        };
+
+        let history = self
+            .storage
+            .symbol_versions_for_path(path.to_string())
+            .await
+            .map_err(|e| TemporalError::Storage(e.to_string()))?;
 
-    /// Get history of changes for a symbol/file
-    pub fn get_symbol_history(&self, path: &str) -> Vec<SymbolVersion> {
-        let history = self.symbol_history.read();
-        history.get(path).cloned().unwrap_or_default()
+        let mut cache = self.symbol_history.write();
+        if cache.generation == generation {
+            cache.by_path.insert(path.to_string(), history.clone());
+        }
+        Ok(history)
     }
 
-    /// Correlate a CI/CD failure with recent code changes
+    /// Correlate a CI/CD failure with recent code changes.
+    ///
+    /// With `semantic` set, changed files are also ranked by embedding
+    /// similarity to the failure message, which costs one embedding per file.
     pub async fn correlate_ci_failure(
         &self,
         test_name: &str,
         failure_message: &str,
         commit_id: &str,
         previous_commit_id: Option<&str>,
+        semantic: bool,
     ) -> Result<Vec<SuspectChange>, TemporalError> {
+        let embedder = match (semantic, &self.embedder) {
+            (false, _) => None,
+            (true, Some(embedder)) => Some(embedder.clone()),
+            (true, None) => return Err(TemporalError::EmbeddingUnavailable),
+        };
         let config = self.config.clone();
         let test_name = test_name.to_string();
         let failure_message = failure_message.to_string();
+        let message = failure_message.clone();
         let commit_id = commit_id.to_string();
         let previous_commit_id = previous_commit_id.map(|s| s.to_string());
+        let now = self.clock.now();
 
         // We need to call get_symbol_at_commit inside, which is async.
         // But we are inside spawn_blocking, so we can't await easily unless we use a runtime.
@@ -352,143 +814,249 @@ impl TemporalIndex {
         // So we can duplicate the logic or extract it.
         // Let's extract the logic to a private helper.
 
-        tokio::task::spawn_blocking(move || {
-            let repo = Repository::open(&config.repo_path)?;
-            debug!(
-                "Correlating CI failure for test {} at commit {}",
-                test_name, commit_id
-            );
+        let candidates = self
+            .git_ops
+            .run(move || {
+                let repo = Repository::open(&config.repo_path)?;
+                debug!(
+                    "Correlating CI failure for test {} at commit {}",
+                    test_name, commit_id
+                );
 
-            let mut suspects = Vec::new();
-
-            // Get the commit range
-            let current_oid = Oid::from_str(&commit_id)?;
-            let current_commit = repo.find_commit(current_oid)?;
-
-            if let Some(prev_id) = previous_commit_id {
-                let prev_oid = Oid::from_str(&prev_id)?;
-                let prev_commit = repo.find_commit(prev_oid)?;
-
-                // Diff between the two commits
-                let current_tree = current_commit.tree()?;
-                let prev_tree = prev_commit.tree()?;
-
-                let mut diff_opts = DiffOptions::new();
-                let diff = repo.diff_tree_to_tree(
-                    Some(&prev_tree),
-                    Some(&current_tree),
-                    Some(&mut diff_opts),
-                )?;
-
-                // Analyze changed files
-                let mut changed_paths = Vec::new();
-                diff.foreach(
-                    &mut |delta, _progress| {
-                        if let Some(path) = delta.new_file().path() {
-                            changed_paths.push((path.display().to_string(), delta.status()));
-                        }
-                        true
-                    },
-                    None,
-                    None,
-                    None,
-                )?;
-
-                for (path_str, status) in changed_paths {
-                    let relevance_score =
-                        calculate_relevance(&path_str, &test_name, &failure_message);
-
-                    if relevance_score > 0.3 {
-                        // Get symbol (file content)
-                        // We use the blocking logic directly here
-                        if let Ok(Some(symbol)) =
-                            get_symbol_at_commit_blocking(&repo, &path_str, &commit_id)
-                        {
-                            let reason = format!(
-                                "File {} was modified and may be related to test {}",
-                                path_str, test_name
-                            );
-
-                            let change_type = match status {
-                                git2::Delta::Added => ChangeType::Added,
-                                git2::Delta::Modified => ChangeType::Modified,
-                                git2::Delta::Deleted => ChangeType::Deleted,
-                                git2::Delta::Renamed => ChangeType::Renamed,
-                                _ => ChangeType::Modified,
-                            };
-
-                            debug!("Suspect change: {} (score: {})", path_str, relevance_score);
-
-                            suspects.push(SuspectChange {
-                                symbol,
-                                relevance_score,
-                                reason,
-                                change_type,
-                            });
+                let mut suspects = Vec::new();
+
+                // Get the commit range
+                let current_commit = find_commit(&repo, &commit_id)?;
+
+                if let Some(prev_id) = previous_commit_id {
+                    let prev_commit = find_commit(&repo, &prev_id)?;
+
+                    // Diff between the two commits
+                    let current_tree = current_commit.tree()?;
+                    let prev_tree = prev_commit.tree()?;
+
+                    let mut diff_opts = DiffOptions::new();
+                    let diff = repo.diff_tree_to_tree(
+                        Some(&prev_tree),
+                        Some(&current_tree),
+                        Some(&mut diff_opts),
+                    )?;
+
+                    // Analyze changed files
+                    let mut changed_paths = Vec::new();
+                    diff.foreach(
+                        &mut |delta, _progress| {
+                            if let Some(path) = delta.new_file().path() {
+                                changed_paths.push((path.display().to_string(), delta.status()));
+                            }
+                            true
+                        },
+                        None,
+                        None,
+                        None,
+                    )?;
+
+                    // Locations in the failure are blamed at the failing
+                    // commit; lines last changed inside the range count.
+                    let locations = failure_locations(&failure_message);
+                    let range_commits = if locations.is_empty() {
+                        HashSet::new()
+                    } else {
+                        commits_between(&repo, &prev_commit, &current_commit)?
+                    };
+
+                    for (path_str, status) in changed_paths {
+                        let keyword = calculate_relevance(
+                            &path_str,
+                            &test_name,
+                            &failure_message,
+                            &config.correlation.relevance_weights,
+                        );
+                        let lines: Vec<usize> = locations
+                            .iter()
+                            .filter(|(path, _)| location_names(path, &path_str))
+                            .map(|(_, line)| *line)
+                            .collect();
+                        let located = if lines.is_empty() {
+                            None
+                        } else {
+                            Some(blamed_share(
+                                &repo,
+                                &path_str,
+                                &commit_id,
+                                &lines,
+                                config.correlation.blame_context_lines,
+                                &range_commits,
+                            )?)
+                        };
+                        let relevance_score =
+                            config
+                                .correlation
+                                .relevance(keyword, !locations.is_empty(), located);
+
+                        // Semantic mode scores every changed file below, so the
+                        // lexical cut-off only applies without it.
+                        if semantic || relevance_score > SUSPECT_THRESHOLD {
+                            // Get symbol (file content)
+                            // We use the blocking logic directly here
+                            if let Ok(Some(symbol)) =
+                                get_symbol_at_commit_blocking(&repo, &path_str, &commit_id, now)
+                            {
+                                let mut reason = format!(
+                                    "File {} was modified and may be related to test {}",
+                                    path_str, test_name
+                                );
+                                if let Some(share) = located {
+                                    reason.push_str(&format!(
+                                        "; named in the failure at line {}, {:.0}% of the \
+                                         surrounding lines changed in this range",
+                                        lines[0] + 1,
+                                        share * 100.0
+                                    ));
+                                }
+
+                                let change_type = match status {
+                                    git2::Delta::Added => ChangeType::Added,
+                                    git2::Delta::Modified => ChangeType::Modified,
+                                    git2::Delta::Deleted => ChangeType::Deleted,
+                                    git2::Delta::Renamed => ChangeType::Renamed,
+                                    _ => ChangeType::Modified,
+                                };
+
+                                debug!("Suspect change: {} (score: {})", path_str, relevance_score);
+
+                                suspects.push(SuspectChange {
+                                    symbol,
+                                    relevance_score,
+                                    reason,
+                                    change_type,
+                                });
+                            }
                         }
                     }
                 }
-            }
 
-            // Sort by relevance score
-            suspects.sort_by(|a: &SuspectChange, b| {
-                b.relevance_score.partial_cmp(&a.relevance_score).unwrap()
-            });
+                Ok(suspects)
+            })
+            .await?;
 
-            Ok(suspects)
-        })
-        .await?
+        let mut suspects = match embedder {
+            Some(embedder) => rank_semantically(embedder.as_ref(), &message, candidates).await?,
+            None => candidates,
+        };
+
+        // Sort by relevance score
+        suspects.sort_by(|a: &SuspectChange, b| b.relevance_score.total_cmp(&a.relevance_score));
+
+        Ok(suspects)
     }
 
-    /// Record a CI/CD event
-    pub fn record_ci_event(&self, event: CiEvent) {
+    /// Record a CI/CD event, persisting it and adding it to the in-memory window
+    pub async fn record_ci_event(&self, event: CiEvent) -> Result<(), TemporalError> {
+        self.storage
+            .store_ci_event(&event)
+            .await
+            .map_err(|e| TemporalError::Storage(e.to_string()))?;
+
         let mut events = self.ci_events.write();
         events.push(event);
+        self.prune_ci_events(&mut events);
+        Ok(())
+    }
 
-        // Keep only recent events (last 1000)
-        if events.len() > 1000 {
-            let drain_count = events.len() - 1000;
+    /// Drops in-memory events older than the configured age, then the oldest
+    /// beyond the configured count
+    fn prune_ci_events(&self, events: &mut Vec<CiEvent>) {
+        if let Some(cutoff) = self.ci_event_cutoff() {
+            events.retain(|e| e.timestamp >= cutoff);
+        }
+        let retention = self.config.ci_event_retention;
+        if events.len() > retention {
+            let drain_count = events.len() - retention;
             events.drain(0..drain_count);
         }
     }
 
-    /// Get CI events for a commit
+    fn ci_event_cutoff(&self) -> Option<DateTime<Utc>> {
+        self.config
+            .ci_event_max_age_hours
+            .map(|hours| self.clock.now() - chrono::Duration::hours(i64::from(hours)))
+    }
+
+    /// Get CI events for a commit from the in-memory window
     pub fn get_ci_events_for_commit(&self, commit_id: &str) -> Vec<CiEvent> {
+        let cutoff = self.ci_event_cutoff();
         let events = self.ci_events.read();
         events
             .iter()
             .filter(|e| e.commit_id == commit_id)
+            .filter(|e| cutoff.is_none_or(|cutoff| e.timestamp >= cutoff))
             .cloned()
             .collect()
     }
 
-    /// Perform blame analysis for a file
-    pub async fn blame(&self, path: &str) -> Result<HashMap<usize, String>, TemporalError> {
+    /// Get every persisted CI event for a commit, including those that have
+    /// left the in-memory window
+    pub async fn ci_event_history(&self, commit_id: &str) -> Result<Vec<CiEvent>, TemporalError> {
+        self.storage
+            .ci_events_for_commit(commit_id.to_string())
+            .await
+            .map_err(|e| TemporalError::Storage(e.to_string()))
+    }
+
+    /// Files changed in the same commits as `path`, in at least
+    /// `min_support` of them, most frequent first. Also returns how many
+    /// commits changed `path`, the denominator of each confidence.
+    pub async fn co_changes(
+        &self,
+        path: &str,
+        min_support: usize,
+    ) -> Result<(usize, Vec<CoChange>), TemporalError> {
         let config = self.config.clone();
         let path = path.to_string();
+        let now = self.clock.now();
+
+        let (commits, counts) = self
+            .git_ops
+            .run(move || {
+                let repo = Repository::open(&config.repo_path)?;
+                co_change_counts(&repo, &config, &path, now)
+            })
+            .await?;
+
+        let mut related: Vec<CoChange> = counts
+            .into_iter()
+            .filter(|(_, support)| *support >= min_support.max(1))
+            .map(|(path, support)| CoChange {
+                path,
+                support,
+                confidence: support as f64 / commits as f64,
+            })
+            .collect();
+        related.sort_by(|a, b| b.support.cmp(&a.support).then_with(|| a.path.cmp(&b.path)));
+        Ok((commits, related))
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let repo = Repository::open(&config.repo_path)?;
-            let blame = repo.blame_file(Path::new(&path), None)?;
-            let mut line_authors = HashMap::new();
-
-            for i in 0..blame.len() {
-                if let Some(hunk) = blame.get_index(i) {
-                    if let Ok(commit) = repo.find_commit(hunk.final_commit_id()) {
-                        let author = commit.author().name().unwrap_or("unknown").to_string();
-                        let start_line = hunk.final_start_line();
-                        let lines = hunk.lines_in_hunk();
-
-                        for line in start_line..(start_line + lines) {
-                            line_authors.insert(line, author.clone());
-                        }
-                    }
-                }
-            }
-
-            Ok(line_authors)
-        })
-        .await?
+    /// Commit and author that last changed each line of `path` as of
+    /// `commit_id` (HEAD when `None`), in line order. `lines` limits the
+    /// result to a zero-based inclusive range, clamped to the file. `None`
+    /// when the path is not a file in the commit's tree.
+    pub async fn blame(
+        &self,
+        path: &str,
+        commit_id: Option<&str>,
+        lines: Option<LineRange>,
+    ) -> Result<Option<Vec<BlameLine>>, TemporalError> {
+        let config = self.config.clone();
+        let path = path.to_string();
+        let commit_id = commit_id.map(str::to_string);
+
+        self.git_ops
+            .run(move || {
+                let repo = Repository::open(&config.repo_path)?;
+                blame_blocking(&repo, &path, commit_id.as_deref(), lines)
+            })
+            .await
     }
 }
 
@@ -497,12 +1065,12 @@ impl TemporalIndex {
 fn process_commit_batch(
     repo: &Repository,
     commits: &[Commit<'_>],
-    history: &Arc<parking_lot::RwLock<HashMap<String, Vec<SymbolVersion>>>>,
+    versions: &mut Vec<(String, SymbolVersion)>,
 ) -> Result<usize, TemporalError> {
     let mut count = 0;
 
     for commit in commits {
-        if let Err(e) = process_commit(repo, commit, history) {
+        if let Err(e) = process_commit(repo, commit, versions) {
             warn!("Failed to process commit {}: {}", commit.id(), e);
             continue;
         }
@@ -512,10 +1080,13 @@ fn process_commit_batch(
     Ok(count)
 }
 
+/// Appends a version for each symbol `commit` added, changed or removed to
+/// `versions`, paired with the path of its file. A file nothing can be
+/// extracted from gets one file-level version without a symbol.
 fn process_commit(
     repo: &Repository,
     commit: &Commit<'_>,
-    history: &Arc<parking_lot::RwLock<HashMap<String, Vec<SymbolVersion>>>>,
+    versions: &mut Vec<(String, SymbolVersion)>,
 ) -> Result<(), TemporalError> {
     let commit_id = commit.id().to_string();
     let timestamp = DateTime::from_timestamp(commit.time().seconds(), 0)
@@ -537,65 +1108,406 @@ fn process_commit(
         let parent_tree = parent.tree()?;
 
         let mut diff_opts = DiffOptions::new();
-        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), Some(&mut diff_opts))?;
-
-        // Analyze each changed file
-        diff.foreach(
-            &mut |delta, _progress| {
-                let new_file = delta.new_file();
-                let old_file = delta.old_file();
-
-                let path = new_file.path().or_else(|| old_file.path());
-
-                if let Some(path) = path {
-                    let change_type = match delta.status() {
-                        git2::Delta::Added => ChangeType::Added,
-                        git2::Delta::Modified => ChangeType::Modified,
-                        git2::Delta::Deleted => ChangeType::Deleted,
-                        git2::Delta::Renamed => ChangeType::Renamed,
-                        _ => return true, // Skip other types
-                    };
-
-                    // Record symbol version
-                    let version = SymbolVersion {
-                        symbol_id: Uuid::new_v4(), // TODO: Link to actual symbol
-                        commit_id: commit_id.clone(),
-                        timestamp,
-                        change_type,
-                        author: author.clone(),
-                        commit_message: message.clone(),
-                        symbol: None, // TODO: Extract symbol from file
-                        previous_path: if change_type == ChangeType::Renamed {
-                            old_file.path().map(|p| p.display().to_string())
-                        } else {
-                            None
-                        },
-                    };
+        let mut diff =
+            repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), Some(&mut diff_opts))?;
+        // Pair deletes with adds of similar content so moves show as renames.
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        for delta in diff.deltas() {
+            let change_type = match delta.status() {
+                git2::Delta::Added => ChangeType::Added,
+                git2::Delta::Modified => ChangeType::Modified,
+                git2::Delta::Deleted => ChangeType::Deleted,
+                git2::Delta::Renamed => ChangeType::Renamed,
+                _ => continue, // Skip other types
+            };
+            let old_path = delta.old_file().path().map(|p| p.display().to_string());
+            let Some(path) = delta
+                .new_file()
+                .path()
+                .map(|p| p.display().to_string())
+                .or_else(|| old_path.clone())
+            else {
+                continue;
+            };
 
-                    // Store in history
-                    let path_str = path.display().to_string();
-                    let mut history = history.write();
-                    history.entry(path_str).or_default().push(version);
-                }
+            let old = match (&old_path, change_type) {
+                (
+                    Some(old_path),
+                    ChangeType::Modified | ChangeType::Deleted | ChangeType::Renamed,
+                ) => blob_symbols(repo, &delta.old_file(), old_path, &commit_id, timestamp)?,
+                _ => Vec::new(),
+            };
+            let new = if change_type == ChangeType::Deleted {
+                Vec::new()
+            } else {
+                blob_symbols(repo, &delta.new_file(), &path, &commit_id, timestamp)?
+            };
+            let file_level = old.is_empty() && new.is_empty();
+
+            let version = |symbol: Option<StoredSymbol>, change_type: ChangeType| SymbolVersion {
+                symbol_id: symbol
+                    .as_ref()
+                    .map_or_else(|| file_symbol_id(&path), |symbol| symbol.id),
+                commit_id: commit_id.clone(),
+                timestamp,
+                change_type,
+                author: author.clone(),
+                commit_message: message.clone(),
+                symbol,
+                previous_path: if change_type == ChangeType::Renamed {
+                    old_path.clone()
+                } else {
+                    None
+                },
+            };
 
-                true
-            },
-            None,
-            None,
-            None,
-        )?;
+            // Files nothing can be extracted from keep a single file-level version.
+            if file_level {
+                versions.push((path.clone(), version(None, change_type)));
+                continue;
+            }
+            for (symbol, change_type) in symbol_changes(change_type, old, new) {
+                versions.push((path.clone(), version(Some(symbol), change_type)));
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Swaps the history id of a version's symbol for the id the registry gives
+/// its key, so history and the live index agree on ids.
+async fn resolve_registry_id(registry: &SymbolRegistry, version: &mut SymbolVersion) {
+    let Some(symbol) = &mut version.symbol else {
+        return;
+    };
+    let Ok(kind) = symbol.kind.parse::<SymbolKind>() else {
+        return;
+    };
+    let key = SymbolKey {
+        path: symbol.path.clone(),
+        name: symbol.name.clone(),
+        kind,
+    };
+    if let Some(id) = registry.lookup(&key).await {
+        symbol.id = id;
+        version.symbol_id = id;
+    }
+}
+
+/// Pairs the symbols of a file before and after a change with what happened
+/// to each. Within a modified file, symbols are matched by name and kind and
+/// only those whose content changed are reported; symbols that disappear are
+/// reported as deleted with their prior content.
+fn symbol_changes(
+    file_change: ChangeType,
+    old: Vec<StoredSymbol>,
+    new: Vec<StoredSymbol>,
+) -> Vec<(StoredSymbol, ChangeType)> {
+    let mut old_by_key: HashMap<(String, String), StoredSymbol> = old
+        .into_iter()
+        .map(|symbol| ((symbol.name.clone(), symbol.kind.clone()), symbol))
+        .collect();
+
+    let mut changes = Vec::new();
+    for symbol in new {
+        let previous = old_by_key.remove(&(symbol.name.clone(), symbol.kind.clone()));
+        let change_type = match (file_change, previous) {
+            (ChangeType::Renamed, _) => ChangeType::Renamed,
+            (_, None) => ChangeType::Added,
+            (_, Some(previous)) if previous.content != symbol.content => ChangeType::Modified,
+            (_, Some(_)) => continue,
+        };
+        changes.push((symbol, change_type));
+    }
+
+    let mut removed: Vec<StoredSymbol> = old_by_key.into_values().collect();
+    removed.sort_by_key(|symbol| symbol.start_line);
+    changes.extend(
+        removed
+            .into_iter()
+            .map(|symbol| (symbol, ChangeType::Deleted)),
+    );
+    changes
+}
+
+/// The symbols of one side of a diff, flattened in source order with ids
+/// from [`history_symbol_id`]. Symlinks, submodules, binary files and files
+/// in languages without an extractor have none.
+fn blob_symbols(
+    repo: &Repository,
+    file: &git2::DiffFile<'_>,
+    path: &str,
+    commit_id: &str,
+    timestamp: DateTime<Utc>,
+) -> Result<Vec<StoredSymbol>, TemporalError> {
+    if matches!(file.mode(), git2::FileMode::Link | git2::FileMode::Commit) {
+        return Ok(Vec::new());
+    }
+    let blob = repo.find_blob(file.id())?;
+    if blob.is_binary() {
+        return Ok(Vec::new());
+    }
+    let content = String::from_utf8_lossy(blob.content());
+    let language = blob_language(path);
+
+    fn flatten(
+        extracted: Vec<symbol_extractor::ExtractedSymbol>,
+        path: &str,
+        commit_id: &str,
+        metadata: &serde_json::Value,
+        timestamp: DateTime<Utc>,
+        out: &mut Vec<StoredSymbol>,
+    ) {
+        for symbol in extracted {
+            out.push(StoredSymbol {
+                id: history_symbol_id(&SymbolKey {
+                    path: path.to_string(),
+                    name: symbol.name.clone(),
+                    kind: symbol.kind,
+                }),
+                path: path.to_string(),
+                name: symbol.name,
+                kind: symbol.kind.to_string(),
+                content: symbol.content,
+                embedding: Vec::new(),
+                commit_id: Some(commit_id.to_string()),
+                start_line: symbol.range.start.line as i32,
+                end_line: symbol.range.end.line as i32,
+                metadata: Some(metadata.clone()),
+                created_at: timestamp,
+                updated_at: timestamp,
+            });
+            flatten(symbol.children, path, commit_id, metadata, timestamp, out);
+        }
+    }
+
+    let metadata = SymbolMetadata {
+        language: Some(language.to_string()),
+        ..SymbolMetadata::default()
+    }
+    .to_value();
+    let mut symbols = Vec::new();
+    flatten(
+        extract_blob_symbols(&content, language),
+        path,
+        commit_id,
+        &metadata,
+        timestamp,
+        &mut symbols,
+    );
+    Ok(symbols)
+}
+
+/// Id recorded in history for the symbol `key` names when the registry does
+/// not know it: the same key always gets the same id, so every version of a
+/// symbol shares one.
+fn history_symbol_id(key: &SymbolKey) -> Uuid {
+    let digest = Sha256::new()
+        .chain_update(key.path.as_bytes())
+        .chain_update([0])
+        .chain_update(key.kind.to_string().as_bytes())
+        .chain_update([0])
+        .chain_update(key.name.as_bytes())
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// History id of a file-level version, for files without symbols.
+fn file_symbol_id(path: &str) -> Uuid {
+    history_symbol_id(&SymbolKey {
+        path: path.to_string(),
+        name: String::new(),
+        kind: SymbolKind::Module,
+    })
+}
+
+/// Revwalk from `end_commit` (HEAD when `None`) back to, but excluding,
+/// `start_commit`, following first parents only unless merge commits are
+/// included.
+fn commit_walk<'r>(
+    repo: &'r Repository,
+    config: &TemporalConfig,
+    start_commit: Option<&str>,
+    end_commit: Option<&str>,
+) -> Result<git2::Revwalk<'r>, TemporalError> {
+    let mut revwalk = repo.revwalk()?;
+
+    if let Some(end) = end_commit {
+        revwalk.push(Oid::from_str(end)?)?;
+    } else {
+        revwalk.push_head()?;
+    }
+
+    if let Some(start) = start_commit {
+        revwalk.hide(Oid::from_str(start)?)?;
+    }
+
+    // Optionally filter out merge commits
+    if !config.include_merge_commits {
+        revwalk.simplify_first_parent()?;
+    }
+
+    Ok(revwalk)
+}
+
+/// Whether `commit` is recent enough for `config.max_age_days` as of `now`.
+fn within_max_age(
+    config: &TemporalConfig,
+    commit: &Commit<'_>,
+    now: DateTime<Utc>,
+) -> Result<bool, TemporalError> {
+    let Some(max_age_days) = config.max_age_days else {
+        return Ok(true);
+    };
+    let commit_time = DateTime::from_timestamp(commit.time().seconds(), 0)
+        .ok_or_else(|| TemporalError::ParseError("Invalid commit timestamp".to_string()))?;
+    let age_days = (now - commit_time).num_days();
+
+    if age_days > max_age_days as i64 {
+        debug!(
+            "Skipping commit {} (too old: {} days)",
+            commit.id(),
+            age_days
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Walks history counting the commits that change `path` and, for every
+/// other file, how many of those commits change it too. Root commits and,
+/// unless configured, merge commits are skipped.
+fn co_change_counts(
+    repo: &Repository,
+    config: &TemporalConfig,
+    path: &str,
+    now: DateTime<Utc>,
+) -> Result<(usize, HashMap<String, usize>), TemporalError> {
+    let mut commits = 0;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for oid in commit_walk(repo, config, None, None)? {
+        let commit = repo.find_commit(oid?)?;
+        if !within_max_age(config, &commit, now)? {
+            continue;
+        }
+        if commit.parent_count() == 0
+            || (commit.parent_count() > 1 && !config.include_merge_commits)
+        {
+            continue;
+        }
+
+        let parent_tree = commit.parent(0)?.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+        let changed: HashSet<String> = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .map(|p| p.display().to_string())
+            .collect();
+        if !changed.contains(path) {
+            continue;
+        }
+
+        commits += 1;
+        for other in changed {
+            if other != path {
+                *counts.entry(other).or_default() += 1;
+            }
+        }
+    }
+
+    Ok((commits, counts))
+}
+
+/// Looks up a commit, reporting malformed and unknown ids alike as
+/// [`TemporalError::CommitNotFound`].
+fn find_commit<'r>(repo: &'r Repository, commit_id: &str) -> Result<Commit<'r>, TemporalError> {
+    Oid::from_str(commit_id)
+        .ok()
+        .and_then(|oid| repo.find_commit(oid).ok())
+        .ok_or_else(|| TemporalError::CommitNotFound(commit_id.to_string()))
+}
+
+fn blame_blocking(
+    repo: &Repository,
+    path: &str,
+    commit_id: Option<&str>,
+    lines: Option<LineRange>,
+) -> Result<Option<Vec<BlameLine>>, TemporalError> {
+    let commit = match commit_id {
+        Some(commit_id) => find_commit(repo, commit_id)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+    let entry = match commit.tree()?.get_path(Path::new(path)) {
+        Ok(entry) if entry.kind() == Some(git2::ObjectType::Blob) => entry,
+        _ => return Ok(None),
+    };
+    let line_count = String::from_utf8_lossy(repo.find_blob(entry.id())?.content())
+        .lines()
+        .count();
+    let range = lines.unwrap_or(LineRange {
+        start: 0,
+        end: usize::MAX,
+    });
+    if line_count == 0 || range.start >= line_count {
+        return Ok(Some(Vec::new()));
+    }
+    let end = range.end.min(line_count - 1);
+
+    // git2 counts lines from 1.
+    let mut options = git2::BlameOptions::new();
+    options
+        .newest_commit(commit.id())
+        .min_line(range.start + 1)
+        .max_line(end + 1);
+    let blame = repo.blame_file(Path::new(path), Some(&mut options))?;
+
+    let mut authors: HashMap<Oid, (String, String, DateTime<Utc>)> = HashMap::new();
+    let mut blamed = Vec::new();
+    for hunk in blame.iter() {
+        let id = hunk.final_commit_id();
+        if let std::collections::hash_map::Entry::Vacant(slot) = authors.entry(id) {
+            let commit = repo.find_commit(id)?;
+            let author = commit.author();
+            let timestamp = DateTime::from_timestamp(author.when().seconds(), 0)
+                .ok_or_else(|| TemporalError::ParseError("Invalid commit timestamp".to_string()))?;
+            slot.insert((
+                author.name().unwrap_or("unknown").to_string(),
+                author.email().unwrap_or("").to_string(),
+                timestamp,
+            ));
+        }
+        let (author, author_email, timestamp) = &authors[&id];
+
+        let first = hunk.final_start_line() - 1;
+        for line in first..first + hunk.lines_in_hunk() {
+            if (range.start..=end).contains(&line) {
+                blamed.push(BlameLine {
+                    line,
+                    commit_id: id.to_string(),
+                    author: author.clone(),
+                    author_email: author_email.clone(),
+                    timestamp: *timestamp,
+                });
+            }
+        }
+    }
+    blamed.sort_by_key(|blamed| blamed.line);
+
+    Ok(Some(blamed))
+}
+
 fn get_symbol_at_commit_blocking(
     repo: &Repository,
     path: &str,
     commit_id: &str,
+    now: DateTime<Utc>,
 ) -> Result<Option<StoredSymbol>, TemporalError> {
-    let oid = Oid::from_str(commit_id)?;
-    let commit = repo.find_commit(oid)?;
+    let commit = find_commit(repo, commit_id)?;
     let tree = commit.tree()?;
 
     // Try to get the file from the tree
@@ -604,77 +1516,268 @@ fn get_symbol_at_commit_blocking(
         Err(_) => return Ok(None),
     };
 
+    let file = |kind: &str, content: String, metadata: serde_json::Value| StoredSymbol {
+        id: Uuid::new_v4(),
+        path: path.to_string(),
+        name: Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        kind: kind.to_string(),
+        end_line: content.lines().count() as i32,
+        content,
+        embedding: Vec::new(), // No embedding for now
+        commit_id: Some(commit_id.to_string()),
+        start_line: 0,
+        metadata: Some(metadata),
+        created_at: now,
+        updated_at: now,
+    };
+
+    match entry.filemode() {
+        // A symlink blob holds the target path, not code; a gitlink points at
+        // a commit in another repository that is not in this object store.
+        FILEMODE_LINK => {
+            let blob = repo.find_blob(entry.id())?;
+            let target = String::from_utf8_lossy(blob.content()).to_string();
+            return Ok(Some(file(
+                "Symlink",
+                String::new(),
+                special_entry_metadata("symlink", target),
+            )));
+        }
+        FILEMODE_COMMIT => {
+            return Ok(Some(file(
+                "Submodule",
+                String::new(),
+                special_entry_metadata("submodule", entry.id().to_string()),
+            )));
+        }
+        _ => {}
+    }
+
     let object = entry.to_object(repo)?;
 
     if let Some(blob) = object.as_blob() {
+        // Images and build artifacts would only turn into replacement
+        // characters; record that the file exists, not its bytes.
+        if blob.is_binary() {
+            let metadata = SymbolMetadata {
+                entry_type: Some("binary".to_string()),
+                ..SymbolMetadata::default()
+            };
+            return Ok(Some(file("Binary", String::new(), metadata.to_value())));
+        }
+
         let content = String::from_utf8_lossy(blob.content()).to_string();
+        let language = blob_language(path);
+        let extracted = extract_blob_symbols(&content, language);
 
-        // Determine language from extension
-        let language = if path.ends_with(".rs") {
-            "rust"
-        } else if path.ends_with(".ts") || path.ends_with(".tsx") {
-            "typescript"
-        } else if path.ends_with(".js") || path.ends_with(".jsx") {
-            "javascript"
-        } else {
-            "unknown"
-        };
+        let metadata = file_symbol_metadata(language, &extracted);
+        Ok(Some(file("File", content, metadata)))
+    } else {
+        Ok(None)
+    }
+}
 
-        // Extract symbols
-        let extracted = if language != "unknown" {
-            symbol_extractor::extract_symbols(&content, language).unwrap_or_default()
-        } else {
-            Vec::new()
-        };
+/// Language of a file in history, from its extension; "unknown" when no
+/// extractor handles it.
+fn blob_language(path: &str) -> &'static str {
+    if path.ends_with(".rs") {
+        "rust"
+    } else if path.ends_with(".ts") || path.ends_with(".tsx") {
+        "typescript"
+    } else if path.ends_with(".js") || path.ends_with(".jsx") {
+        "javascript"
+    } else {
+        "unknown"
+    }
+}
 
-        let symbol = StoredSymbol {
-            id: Uuid::new_v4(),
-            path: path.to_string(),
-            name: Path::new(path)
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            kind: "File".to_string(),
-            content: content.clone(),
-            embedding: Vec::new(), // No embedding for now
-            commit_id: Some(commit_id.to_string()),
-            start_line: 0,
-            end_line: content.lines().count() as i32,
-            metadata: Some(serde_json::json!({
-                "extracted_symbols_count": extracted.len(),
-                "extracted_symbols": extracted.iter().map(|s| &s.name).collect::<Vec<_>>(),
-                "language": language
-            })),
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+fn extract_blob_symbols(content: &str, language: &str) -> Vec<symbol_extractor::ExtractedSymbol> {
+    if language == "unknown" {
+        return Vec::new();
+    }
+    symbol_extractor::extract_symbols(content, language).unwrap_or_default()
+}
 
-        Ok(Some(symbol))
-    } else {
-        Ok(None)
+/// Git tree entry modes for symlinks and submodules (gitlinks).
+const FILEMODE_LINK: i32 = 0o120000;
+const FILEMODE_COMMIT: i32 = 0o160000;
+
+/// Metadata for a symlink or submodule entry, which is recorded but never
+/// parsed or embedded.
+fn special_entry_metadata(entry_type: &str, target: String) -> serde_json::Value {
+    SymbolMetadata {
+        entry_type: Some(entry_type.to_string()),
+        target: Some(target),
+        ..SymbolMetadata::default()
+    }
+    .to_value()
+}
+
+/// Metadata for the file-level symbol built from a blob at a commit.
+pub(crate) fn file_symbol_metadata(
+    language: &str,
+    extracted: &[symbol_extractor::ExtractedSymbol],
+) -> serde_json::Value {
+    SymbolMetadata {
+        language: (language != "unknown").then(|| language.to_string()),
+        extracted_symbols: Some(extracted.iter().map(|s| s.name.clone()).collect()),
+        extracted_symbols_count: Some(extracted.len() as i64),
+        ..SymbolMetadata::default()
     }
+    .to_value()
 }
 
-fn calculate_relevance(file_path: &str, test_name: &str, failure_message: &str) -> f32 {
+/// Blends embedding similarity between the failure message and each changed
+/// file into its heuristic score, dropping files that fall below the threshold.
+async fn rank_semantically(
+    embedder: &dyn EmbeddingProvider,
+    failure_message: &str,
+    mut candidates: Vec<SuspectChange>,
+) -> Result<Vec<SuspectChange>, TemporalError> {
+    let embed = |text: &str| {
+        let text: String = text.chars().take(MAX_SEMANTIC_CONTENT_CHARS).collect();
+        async move {
+            embedder
+                .embed(&text)
+                .await
+                .map_err(|e| TemporalError::Embedding(e.to_string()))
+        }
+    };
+
+    candidates.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+    candidates.truncate(MAX_SEMANTIC_CANDIDATES);
+
+    let failure = embed(failure_message).await?;
+    let mut suspects = Vec::with_capacity(candidates.len());
+    for mut suspect in candidates {
+        let similarity = cosine_similarity(&failure, &embed(&suspect.symbol.content).await?);
+        suspect.relevance_score = (1.0 - SEMANTIC_WEIGHT) * suspect.relevance_score
+            + SEMANTIC_WEIGHT * similarity.max(0.0);
+        if suspect.relevance_score > SUSPECT_THRESHOLD {
+            suspect.reason = format!("{} (semantic similarity {:.2})", suspect.reason, similarity);
+            suspects.push(suspect);
+        }
+    }
+    Ok(suspects)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    (dot / denominator).clamp(-1.0, 1.0)
+}
+
+/// Most commits collected for a correlation range; a longer range is
+/// treated as if its older commits were outside it.
+const MAX_RANGE_COMMITS: usize = 10_000;
+
+static FAILURE_LOCATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"([\w./\\-]+\.[A-Za-z]\w*):(\d+)").expect("failure location pattern is valid")
+});
+
+/// `path:line` locations in a failure message, such as the frames of a
+/// stack trace (`src/foo.rs:42:5`), with zero-based lines.
+fn failure_locations(message: &str) -> Vec<(String, usize)> {
+    FAILURE_LOCATION
+        .captures_iter(message)
+        .filter_map(|captures| {
+            let line: usize = captures[2].parse().ok()?;
+            let path = captures[1].trim_start_matches("./").replace('\\', "/");
+            Some((path, line.checked_sub(1)?))
+        })
+        .collect()
+}
+
+/// Whether a location's path, possibly absolute (CI checkouts), is
+/// `repo_path` in the repository.
+fn location_names(location: &str, repo_path: &str) -> bool {
+    location == repo_path
+        || location
+            .strip_suffix(repo_path)
+            .is_some_and(|prefix| prefix.ends_with('/'))
+}
+
+/// Commits reachable from `current` but not from `previous`.
+fn commits_between(
+    repo: &Repository,
+    previous: &Commit<'_>,
+    current: &Commit<'_>,
+) -> Result<HashSet<String>, TemporalError> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(current.id())?;
+    revwalk.hide(previous.id())?;
+    revwalk
+        .take(MAX_RANGE_COMMITS)
+        .map(|oid| Ok(oid?.to_string()))
+        .collect()
+}
+
+/// Share of the lines within `context` of each of `lines` in `path` that
+/// blame at `commit_id` attributes to one of `range_commits`.
+fn blamed_share(
+    repo: &Repository,
+    path: &str,
+    commit_id: &str,
+    lines: &[usize],
+    context: usize,
+    range_commits: &HashSet<String>,
+) -> Result<f32, TemporalError> {
+    let mut blamed = 0;
+    let mut touched = 0;
+    for line in lines {
+        let range = LineRange {
+            start: line.saturating_sub(context),
+            end: line.saturating_add(context),
+        };
+        for blame in blame_blocking(repo, path, Some(commit_id), Some(range))?.unwrap_or_default() {
+            blamed += 1;
+            if range_commits.contains(&blame.commit_id) {
+                touched += 1;
+            }
+        }
+    }
+    Ok(if blamed == 0 {
+        0.0
+    } else {
+        touched as f32 / blamed as f32
+    })
+}
+
+fn calculate_relevance(
+    file_path: &str,
+    test_name: &str,
+    failure_message: &str,
+    weights: &RelevanceWeights,
+) -> f32 {
     let mut score: f32 = 0.0;
 
     // Check if file path contains test name keywords
     let test_parts: Vec<&str> = test_name.split(|c: char| !c.is_alphanumeric()).collect();
     for part in &test_parts {
         if !part.is_empty() && file_path.to_lowercase().contains(&part.to_lowercase()) {
-            score += 0.3;
+            score += weights.test_name_part;
         }
     }
 
     // Check if failure message mentions the file
     if failure_message.contains(file_path) {
-        score += 0.5;
+        score += weights.path_in_message;
     }
 
     // Check file extension relevance (e.g., test files)
     if file_path.contains("test") || file_path.contains("spec") {
-        score += 0.2;
+        score += weights.test_file;
     }
 
     score.min(1.0)
@@ -690,6 +1793,17 @@ mod tests {
         let json = serde_json::to_string(&ct).unwrap();
         let deserialized: ChangeType = serde_json::from_str(&json).unwrap();
         assert_eq!(ct, deserialized);
+
+        for ct in [
+            ChangeType::Added,
+            ChangeType::Modified,
+            ChangeType::Deleted,
+            ChangeType::Renamed,
+        ] {
+            assert_eq!(ChangeType::parse(ct.as_str()), Some(ct));
+            assert_eq!(ChangeType::parse(&ct.as_str().to_lowercase()), Some(ct));
+        }
+        assert_eq!(ChangeType::parse("copied"), None);
     }
 
     #[test]
@@ -700,14 +1814,99 @@ mod tests {
         assert_eq!(status, deserialized);
     }
 
+    #[test]
+    fn test_failure_locations_and_relevance_blend() {
+        let locations = failure_locations(
+            "panicked at ./src/a.rs:10:5\n  at src\\web\\b.ts:1\n  see line:0 or x.rs:0",
+        );
+        assert_eq!(
+            locations,
+            vec![("src/a.rs".to_string(), 9), ("src/web/b.ts".to_string(), 0)]
+        );
+        assert!(location_names("/home/ci/src/a.rs", "src/a.rs"));
+        assert!(!location_names("/home/ci/xsrc/a.rs", "src/a.rs"));
+
+        let config = CorrelationConfig::default();
+        // Without locations the keyword heuristic stands alone.
+        assert_eq!(config.relevance(0.7, false, None), 0.7);
+        assert!(config.relevance(0.5, true, Some(1.0)) > config.relevance(1.0, true, None));
+    }
+
     #[test]
     fn test_relevance_calculation() {
         // Test with matching file path
-        let score1 = calculate_relevance("src/foo.rs", "test_foo", "error");
+        let weights = RelevanceWeights::default();
+        let score1 = calculate_relevance("src/foo.rs", "test_foo", "error", &weights);
         assert!(score1 > 0.0);
 
         // Test with matching failure message
-        let score2 = calculate_relevance("src/bar.rs", "test_baz", "error in src/bar.rs");
+        let score2 = calculate_relevance("src/bar.rs", "test_baz", "error in src/bar.rs", &weights);
         assert!(score2 > 0.0);
     }
+
+    #[test]
+    fn test_relevance_weights_override_defaults() {
+        let score = |weights: &RelevanceWeights| {
+            calculate_relevance("src/bar.rs", "test_baz", "error in src/bar.rs", weights)
+        };
+        // Defaults match the original constants.
+        assert_eq!(score(&RelevanceWeights::default()), 0.5);
+
+        let weights = RelevanceWeights {
+            path_in_message: 0.1,
+            ..RelevanceWeights::default()
+        };
+        assert_eq!(score(&weights), 0.1);
+
+        // Teams whose test files are not named `test`/`spec` can drop that term.
+        let no_test_file = RelevanceWeights {
+            test_file: 0.0,
+            ..RelevanceWeights::default()
+        };
+        let test_file = |weights: &RelevanceWeights| {
+            calculate_relevance("spec/helpers.rb", "run", "boom", weights)
+        };
+        assert_eq!(test_file(&RelevanceWeights::default()), 0.2);
+        assert_eq!(test_file(&no_test_file), 0.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_git_ops_queue_beyond_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let limiter = GitOpLimiter::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    limiter
+                        .run(move || {
+                            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(now, Ordering::SeqCst);
+                            std::thread::sleep(std::time::Duration::from_millis(20));
+                            running.fetch_sub(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_cosine_similarity_handles_unnormalized_vectors() {
+        assert!((cosine_similarity(&[2.0, 0.0], &[5.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 1.0]), 0.0);
+    }
 }
@@ -0,0 +1,182 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tree_sitter::Tree;
+
+use crate::ast::{self, AstError};
+use crate::normalize::content_hash;
+
+pub const DEFAULT_TREE_CACHE_ENTRIES: usize = 64;
+
+struct Entry {
+    /// The parsed source, kept so a hit can be checked against the caller's
+    /// content before byte offsets in the tree are trusted.
+    source: Arc<str>,
+    tree: Tree,
+    sequence: u64,
+}
+
+struct Entries {
+    by_key: HashMap<(String, String), Entry>,
+    next_sequence: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Parsed syntax trees keyed by language and content hash, so navigation
+/// requests against an unchanged file share one parse.
+///
+/// `Tree` is `Send` but not `Sync`, so entries live behind the mutex and
+/// callers get their own copy (`ts_tree_copy` only bumps a reference count).
+pub struct TreeCache {
+    max_entries: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl TreeCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::new(Entries {
+                by_key: HashMap::new(),
+                next_sequence: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the tree for `source`, parsing only on a miss. A zero-sized
+    /// cache parses every time.
+    pub fn parse(&self, language: &str, source: &str) -> Result<Tree, AstError> {
+        let key = (language.to_string(), content_hash(source));
+
+        if let Some(tree) = self.lookup(&key, source) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(tree);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let (tree, _) = ast::parse_tree(language, source)?;
+        self.insert(key, source, tree.clone());
+        Ok(tree)
+    }
+
+    fn lookup(&self, key: &(String, String), source: &str) -> Option<Tree> {
+        let mut entries = self.entries.lock();
+        let sequence = entries.next_sequence;
+        let entry = entries.by_key.get_mut(key)?;
+        if &*entry.source != source {
+            return None;
+        }
+        entry.sequence = sequence;
+        let tree = entry.tree.clone();
+        entries.next_sequence += 1;
+        Some(tree)
+    }
+
+    fn insert(&self, key: (String, String), source: &str, tree: Tree) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut guard = self.entries.lock();
+        let sequence = guard.next_sequence;
+        guard.next_sequence += 1;
+
+        let entries = &mut guard.by_key;
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.sequence)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            key,
+            Entry {
+                source: Arc::from(source),
+                tree,
+                sequence,
+            },
+        );
+    }
+
+    pub fn stats(&self) -> TreeCacheStats {
+        TreeCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_tree_for_identical_content() {
+        let cache = TreeCache::new(4);
+        let source = "fn main() { run(); }";
+
+        let first = cache.parse("rust", source).unwrap();
+        let second = cache.parse("rust", source).unwrap();
+
+        assert_eq!(cache.stats(), TreeCacheStats { hits: 1, misses: 1 });
+        assert_eq!(first.root_node().to_sexp(), second.root_node().to_sexp());
+    }
+
+    #[test]
+    fn changed_content_is_a_miss() {
+        let cache = TreeCache::new(4);
+        cache.parse("rust", "fn a() {}").unwrap();
+        cache.parse("rust", "fn b() {}").unwrap();
+
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entry() {
+        let cache = TreeCache::new(2);
+        cache.parse("rust", "fn a() {}").unwrap();
+        cache.parse("rust", "fn b() {}").unwrap();
+        cache.parse("rust", "fn a() {}").unwrap();
+        cache.parse("rust", "fn c() {}").unwrap();
+
+        assert_eq!(cache.len(), 2);
+        cache.parse("rust", "fn a() {}").unwrap();
+        assert_eq!(cache.stats(), TreeCacheStats { hits: 2, misses: 3 });
+    }
+
+    #[test]
+    fn zero_capacity_never_caches() {
+        let cache = TreeCache::new(0);
+        cache.parse("rust", "fn a() {}").unwrap();
+        cache.parse("rust", "fn a() {}").unwrap();
+
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().misses, 2);
+    }
+}
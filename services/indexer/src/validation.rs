@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use std::env;
 use std::fmt::Display;
 
 use serde::de::{self, Deserializer};
@@ -7,6 +8,57 @@ use serde::Deserialize;
 
 pub const MAX_PATH_LENGTH: usize = 4 * 1024;
 pub const MAX_QUERY_LENGTH: usize = 8 * 1024;
+pub const MAX_BATCH_POSITIONS: usize = 512;
+pub const MAX_BATCH_DOCUMENTS: usize = 256;
+
+/// What to do with content containing control characters other than tab,
+/// newline, carriage return and form feed (`INDEXER_CONTROL_CHARS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlCharPolicy {
+    /// Index the content as sent.
+    #[default]
+    Allow,
+    /// Refuse the content.
+    Reject,
+}
+
+impl ControlCharPolicy {
+    pub fn from_env() -> Self {
+        match env::var("INDEXER_CONTROL_CHARS") {
+            Ok(v) if v.eq_ignore_ascii_case("reject") => Self::Reject,
+            _ => Self::Allow,
+        }
+    }
+}
+
+/// Checks content before it is indexed, by any write path: it must not be
+/// blank or contain a null byte, nor other control characters under
+/// [`ControlCharPolicy::Reject`].
+pub fn validate_content(content: &str, control_chars: ControlCharPolicy) -> Result<(), String> {
+    if content.trim().is_empty() {
+        return Err("content cannot be blank".to_string());
+    }
+    // Nulls truncate content in the C-string based layers (tree-sitter,
+    // Postgres text), and usually mean a binary file was sent as text.
+    if let Some(offset) = content.find('\0') {
+        return Err(format!(
+            "content contains a null byte at offset {}; binary content cannot be indexed",
+            offset
+        ));
+    }
+    if control_chars == ControlCharPolicy::Reject {
+        if let Some((offset, c)) = content
+            .char_indices()
+            .find(|(_, c)| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+        {
+            return Err(format!(
+                "content contains control character U+{:04X} at offset {}",
+                c as u32, offset
+            ));
+        }
+    }
+    Ok(())
+}
 
 fn ensure_not_blank<T, E>(value: &str, field: T) -> Result<&str, E>
 where
@@ -119,6 +171,21 @@ mod tests {
         commit_id: Option<String>,
     }
 
+    #[test]
+    fn content_control_characters() {
+        assert!(validate_content("fn main() {}\n", ControlCharPolicy::Allow).is_ok());
+        assert!(validate_content("a\0b", ControlCharPolicy::Allow)
+            .unwrap_err()
+            .contains("null byte at offset 1"));
+
+        let bell = "let s = \"\x07\";\tfoo\r\n\x0c";
+        assert!(validate_content(bell, ControlCharPolicy::Allow).is_ok());
+        assert!(validate_content(bell, ControlCharPolicy::Reject)
+            .unwrap_err()
+            .contains("U+0007"));
+        assert!(validate_content("a\tb\r\n\x0c", ControlCharPolicy::Reject).is_ok());
+    }
+
     #[test]
     fn trims_document_path() {
         let input: DocumentInput =
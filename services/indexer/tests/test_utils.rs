@@ -1,12 +1,27 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+use chrono::Utc;
+use git2::{Repository, Signature};
+use tempfile::TempDir;
 use uuid::Uuid;
 
 use ossaat_indexer::request_context::{clear_request_context, set_request_context, RequestContext};
-use ossaat_indexer::storage::{IndexStorage, StorageError, StoredDocument, StoredSymbol};
+use ossaat_indexer::similarity::{reciprocal_rank_fusion, SearchMode};
+use ossaat_indexer::storage::{
+    name_match_score, rank_name_matches, reuse_symbol_ids, CommitDeletion, CommitFilter,
+    IndexStorage, MetadataFilter, NewDocument, OrphanReport, PathDeletion, StorageError,
+    StoredDocument, StoredSymbol, SymbolEdge, SymbolMetric, CONTAINS_RELATION,
+};
+use ossaat_indexer::symbol_extractor::{self, ExtractedSymbol, LineRange};
+use ossaat_indexer::symbol_metadata::SymbolMetadata;
+use ossaat_indexer::symbol_registry::SymbolKind;
+use ossaat_indexer::temporal::{CiEvent, SymbolVersion};
 
 pub fn install_test_context(request_id: Uuid, trace_id: Option<&str>, client_ip: Option<IpAddr>) {
     clear_request_context();
@@ -24,58 +39,819 @@ pub fn reset_context() {
     clear_request_context();
 }
 
-pub struct MockStorage;
+/// In-memory storage used by the integration tests.
+///
+/// Search returns every stored row matching the filters with a fixed score,
+/// which is enough to exercise the gRPC handlers without a database.
+#[derive(Default)]
+pub struct MockStorage {
+    pub documents: Mutex<Vec<StoredDocument>>,
+    pub symbols: Mutex<Vec<StoredSymbol>>,
+    pub symbol_edges: Mutex<Vec<SymbolEdge>>,
+    /// How long `index_document` takes, standing in for embedding latency.
+    pub index_delay: Mutex<Option<std::time::Duration>>,
+    /// `index_document` calls currently running, and the most seen at once.
+    pub in_flight: AtomicUsize,
+    pub peak_in_flight: AtomicUsize,
+    pub ci_events: Mutex<Vec<CiEvent>>,
+    /// Temporal history as `(path, version)`, in the order written.
+    pub symbol_versions: Mutex<Vec<(String, SymbolVersion)>>,
+    /// Number of upcoming `query_all_symbols` calls that fail.
+    pub failing_symbol_queries: AtomicUsize,
+    /// Position of the symbol whose insert fails in the next `index_symbols`
+    /// call, which then stores nothing.
+    pub failing_symbol_insert: Mutex<Option<usize>>,
+    /// Search score of the documents and symbols under each path; 1.0 when
+    /// not listed.
+    pub scores: Mutex<HashMap<String, f32>>,
+    /// Number of `batch_index_documents` calls.
+    pub batch_writes: AtomicUsize,
+    /// Makes every search that needs a query embedding fail as if the
+    /// embedding provider were unreachable.
+    pub embedder_down: AtomicBool,
+    /// Number of `search_documents` and `search_symbols` calls.
+    pub search_queries: AtomicUsize,
+}
+
+impl MockStorage {
+    pub fn seed_document(&self, path: &str, content: &str, commit_id: Option<&str>) -> Uuid {
+        let id = Uuid::new_v4();
+        self.documents.lock().unwrap().push(StoredDocument {
+            id,
+            path: path.to_string(),
+            content: content.to_string(),
+            embedding: vec![],
+            commit_id: commit_id.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        id
+    }
+
+    pub fn seed_symbol(&self, symbol: StoredSymbol) {
+        self.symbols.lock().unwrap().push(symbol);
+    }
+
+    /// Fails searches in `mode` that would embed the query while
+    /// `embedder_down` is set.
+    fn check_embedder(&self, mode: SearchMode) -> Result<(), StorageError> {
+        if mode != SearchMode::Keyword && self.embedder_down.load(Ordering::SeqCst) {
+            return Err(StorageError::Embedding(
+                "embedding provider unavailable".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Mirrors the Postgres orphan checks; the mock keeps no relationships.
+    fn orphans(&self, delete: bool) -> OrphanReport {
+        let documents: Vec<String> = self
+            .documents
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|doc| doc.path.clone())
+            .collect();
+        let orphaned = |symbol: &StoredSymbol| !documents.contains(&symbol.path);
+        let unembedded = |symbol: &StoredSymbol| {
+            symbol.embedding.is_empty()
+                && !symbol
+                    .metadata
+                    .as_ref()
+                    .is_some_and(|m| SymbolMetadata::from_value(m).embedding_skipped)
+        };
+
+        let mut symbols = self.symbols.lock().unwrap();
+        let report = OrphanReport {
+            symbols_without_document: symbols.iter().filter(|s| orphaned(s)).count() as u64,
+            dangling_edges: 0,
+            invalid_embeddings: symbols
+                .iter()
+                .filter(|s| !orphaned(s) && unembedded(s))
+                .count() as u64,
+        };
+        if delete {
+            symbols.retain(|s| !orphaned(s) && !unembedded(s));
+        }
+        report
+    }
+}
+
+pub fn test_symbol(path: &str, name: &str, kind: &str, content: &str) -> StoredSymbol {
+    StoredSymbol {
+        id: Uuid::new_v4(),
+        path: path.to_string(),
+        name: name.to_string(),
+        kind: kind.to_string(),
+        content: content.to_string(),
+        embedding: vec![],
+        commit_id: None,
+        start_line: 0,
+        end_line: content.lines().count() as i32,
+        metadata: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn score_of(scores: &HashMap<String, f32>, path: &str) -> f32 {
+    scores.get(path).copied().unwrap_or(1.0)
+}
+
+/// Stand-in for full-text ranking: symbols containing every query word,
+/// name matches (score 1.0) ahead of content-only ones (0.5).
+fn keyword_matches(query: &str, symbols: &[StoredSymbol]) -> Vec<(StoredSymbol, f32)> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut matches: Vec<(StoredSymbol, f32)> = symbols
+        .iter()
+        .filter_map(|symbol| {
+            let name = symbol.name.to_lowercase();
+            let content = symbol.content.to_lowercase();
+            if words.iter().all(|word| name.contains(word.as_str())) {
+                Some((symbol.clone(), 1.0))
+            } else if words
+                .iter()
+                .all(|word| name.contains(word.as_str()) || content.contains(word.as_str()))
+            {
+                Some((symbol.clone(), 0.5))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+/// Documents whose path or content holds every word of `query`, path
+/// matches first.
+fn document_keyword_matches(
+    query: &str,
+    documents: &[StoredDocument],
+) -> Vec<(StoredDocument, f32)> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut matches: Vec<(StoredDocument, f32)> = documents
+        .iter()
+        .filter_map(|doc| {
+            let path = doc.path.to_lowercase();
+            let content = doc.content.to_lowercase();
+            if words.iter().all(|word| path.contains(word.as_str())) {
+                Some((doc.clone(), 1.0))
+            } else if words
+                .iter()
+                .all(|word| path.contains(word.as_str()) || content.contains(word.as_str()))
+            {
+                Some((doc.clone(), 0.5))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+fn matches_filters(
+    path: &str,
+    commit: Option<&String>,
+    path_prefix: Option<&String>,
+    commit_filter: Option<&CommitFilter>,
+) -> bool {
+    path_prefix.is_none_or(|prefix| path.starts_with(prefix.as_str()))
+        && commit_filter.is_none_or(|filter| filter.matches(commit.map(String::as_str)))
+}
 
 #[async_trait::async_trait]
 impl IndexStorage for MockStorage {
     async fn index_document(
         &self,
-        _path: String,
-        _content: String,
-        _commit_id: Option<String>,
+        path: String,
+        content: String,
+        commit_id: Option<String>,
     ) -> Result<Uuid, StorageError> {
-        Ok(Uuid::new_v4())
+        let running = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(running, Ordering::SeqCst);
+        let delay = *self.index_delay.lock().unwrap();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(self.seed_document(&path, &content, commit_id.as_deref()))
+    }
+
+    async fn batch_index_documents(
+        &self,
+        documents: Vec<NewDocument>,
+    ) -> Result<Vec<Result<Uuid, StorageError>>, StorageError> {
+        self.batch_writes.fetch_add(1, Ordering::SeqCst);
+        let mut results = Vec::with_capacity(documents.len());
+        for document in documents {
+            results.push(
+                self.index_document(document.path, document.content, document.commit_id)
+                    .await,
+            );
+        }
+        Ok(results)
     }
 
     async fn index_symbols(
         &self,
-        _path: String,
-        _content: String,
-        _language: String,
-        _commit_id: Option<String>,
-    ) -> Result<usize, StorageError> {
-        Ok(0)
+        path: String,
+        content: String,
+        language: String,
+        commit_id: Option<String>,
+        range: Option<LineRange>,
+        declarations_only: bool,
+    ) -> Result<Vec<Uuid>, StorageError> {
+        let source = match range {
+            Some(range) => range.slice(&content).map_err(StorageError::InvalidInput)?,
+            None => &content,
+        };
+        let mut extracted = symbol_extractor::extract_symbols(source, &language)
+            .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+        symbol_extractor::offset_lines(&mut extracted, range.map_or(0, |r| r.start));
+        if declarations_only {
+            symbol_extractor::declarations_only(&mut extracted);
+        }
+
+        // Each symbol with the position of its parent in the flattened list.
+        fn flatten(
+            extracted: Vec<ExtractedSymbol>,
+            parent: Option<usize>,
+            out: &mut Vec<(ExtractedSymbol, Option<usize>)>,
+        ) {
+            for mut symbol in extracted {
+                let children = std::mem::take(&mut symbol.children);
+                out.push((symbol, parent));
+                flatten(children, Some(out.len() - 1), out);
+            }
+        }
+        let mut flat = Vec::new();
+        flatten(extracted, None, &mut flat);
+
+        // Staged and swapped in at the end, like the Postgres transaction.
+        let failing = self.failing_symbol_insert.lock().unwrap().take();
+        let mut staged: Vec<StoredSymbol> = Vec::with_capacity(flat.len());
+        let mut edges = Vec::new();
+        for (position, (symbol, parent)) in flat.into_iter().enumerate() {
+            if failing == Some(position) {
+                return Err(StorageError::Database(sqlx::Error::Protocol(
+                    "insert failed".to_string(),
+                )));
+            }
+            let mut stored = test_symbol(
+                &path,
+                &symbol.name,
+                &symbol.kind.to_string(),
+                &symbol.content,
+            );
+            stored.commit_id = commit_id.clone();
+            stored.start_line = symbol.range.start.line as i32;
+            stored.end_line = symbol.range.end.line as i32;
+            stored.metadata = Some(
+                SymbolMetadata {
+                    complexity: Some(symbol.complexity as i64),
+                    declarations_only,
+                    ..SymbolMetadata::default()
+                }
+                .to_value(),
+            );
+            if let Some(parent) = parent {
+                edges.push(SymbolEdge {
+                    parent_id: staged[parent].id,
+                    child_id: stored.id,
+                    relation: CONTAINS_RELATION.to_string(),
+                });
+            }
+            staged.push(stored);
+        }
+
+        let mut symbols = self.symbols.lock().unwrap();
+        let (kept, previous): (Vec<StoredSymbol>, Vec<StoredSymbol>) =
+            symbols.drain(..).partition(|stored| {
+                stored.path != path
+                    || stored.commit_id != commit_id
+                    || range.is_some_and(|range| {
+                        (stored.start_line as usize) < range.start
+                            || (stored.end_line as usize) > range.end
+                    })
+            });
+        *symbols = kept;
+        let reused = reuse_symbol_ids(&previous, &mut staged);
+        for edge in &mut edges {
+            for id in [&mut edge.parent_id, &mut edge.child_id] {
+                *id = reused.get(id).copied().unwrap_or(*id);
+            }
+        }
+        let ids = staged.iter().map(|symbol| symbol.id).collect();
+        symbols.extend(staged);
+
+        let mut symbol_edges = self.symbol_edges.lock().unwrap();
+        symbol_edges.retain(|edge| {
+            symbols.iter().any(|s| s.id == edge.parent_id)
+                && symbols.iter().any(|s| s.id == edge.child_id)
+        });
+        symbol_edges.extend(edges);
+        Ok(ids)
     }
 
     async fn search_documents(
         &self,
-        _query: String,
-        _top_k: usize,
-        _path_prefix: Option<String>,
-        _commit_id: Option<String>,
+        query: String,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        min_score: f32,
+        mode: SearchMode,
     ) -> Result<Vec<(StoredDocument, f32)>, StorageError> {
-        Ok(vec![])
+        self.search_queries.fetch_add(1, Ordering::SeqCst);
+        self.check_embedder(mode)?;
+        let scores = self.scores.lock().unwrap();
+        let candidates: Vec<StoredDocument> = self
+            .documents
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|doc| {
+                matches_filters(
+                    &doc.path,
+                    doc.commit_id.as_ref(),
+                    path_prefix.as_ref(),
+                    commit.as_ref(),
+                )
+            })
+            .cloned()
+            .collect();
+
+        let by_vector: Vec<(StoredDocument, f32)> = candidates
+            .iter()
+            .map(|doc| (doc.clone(), score_of(&scores, &doc.path)))
+            .collect();
+
+        let mut results = match mode {
+            SearchMode::Vector => by_vector,
+            SearchMode::Keyword => document_keyword_matches(&query, &candidates),
+            SearchMode::Hybrid => reciprocal_rank_fusion(
+                vec![
+                    by_vector.into_iter().map(|(doc, _)| doc).collect(),
+                    document_keyword_matches(&query, &candidates)
+                        .into_iter()
+                        .map(|(doc, _)| doc)
+                        .collect(),
+                ],
+                |doc: &StoredDocument| doc.id,
+            ),
+        };
+        results.retain(|(_, score)| *score >= min_score);
+        results.truncate(top_k);
+        Ok(results)
     }
 
     async fn search_symbols(
         &self,
-        _query: String,
-        _top_k: usize,
-        _path_prefix: Option<String>,
-        _commit_id: Option<String>,
+        query: String,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+        metadata: MetadataFilter,
+        min_score: f32,
+        mode: SearchMode,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        self.search_queries.fetch_add(1, Ordering::SeqCst);
+        self.check_embedder(mode)?;
+        let scores = self.scores.lock().unwrap();
+        let candidates: Vec<StoredSymbol> = self
+            .symbols
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|symbol| {
+                matches_filters(
+                    &symbol.path,
+                    symbol.commit_id.as_ref(),
+                    path_prefix.as_ref(),
+                    commit.as_ref(),
+                ) && metadata.matches(symbol)
+            })
+            .cloned()
+            .collect();
+
+        let mut by_vector: Vec<(StoredSymbol, f32)> = candidates
+            .iter()
+            .map(|symbol| (symbol.clone(), score_of(&scores, &symbol.path)))
+            .collect();
+        by_vector.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut results = match mode {
+            SearchMode::Vector => by_vector,
+            SearchMode::Keyword => keyword_matches(&query, &candidates),
+            SearchMode::Hybrid => reciprocal_rank_fusion(
+                vec![
+                    by_vector.into_iter().map(|(symbol, _)| symbol).collect(),
+                    keyword_matches(&query, &candidates)
+                        .into_iter()
+                        .map(|(symbol, _)| symbol)
+                        .collect(),
+                ],
+                |symbol: &StoredSymbol| symbol.id,
+            ),
+        };
+        results.retain(|(_, score)| *score >= min_score);
+        results.truncate(top_k);
+        Ok(results)
+    }
+
+    async fn search_symbols_by_vector(
+        &self,
+        _embedding: Vec<f32>,
+        top_k: usize,
+        path_prefix: Option<String>,
+        commit: Option<CommitFilter>,
+    ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
+        self.search_symbols(
+            String::new(),
+            top_k,
+            path_prefix,
+            commit,
+            MetadataFilter::default(),
+            0.0,
+            SearchMode::Vector,
+        )
+        .await
+    }
+
+    async fn top_symbols(
+        &self,
+        metric: SymbolMetric,
+        limit: usize,
+        offset: usize,
+        path_prefix: Option<String>,
+    ) -> Result<Vec<(StoredSymbol, i64)>, StorageError> {
+        let mut ranked: Vec<(StoredSymbol, i64)> = self
+            .symbols
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|symbol| matches_filters(&symbol.path, None, path_prefix.as_ref(), None))
+            .filter_map(|symbol| metric.value_of(symbol).map(|value| (symbol.clone(), value)))
+            .collect();
+        ranked.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+        Ok(ranked.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn find_symbols_by_name(
+        &self,
+        query: String,
+        kind: Option<SymbolKind>,
+        limit: usize,
     ) -> Result<Vec<(StoredSymbol, f32)>, StorageError> {
-        Ok(vec![])
+        let kind = kind.map(|kind| kind.to_string());
+        let mut matches: Vec<(StoredSymbol, f32)> = self
+            .symbols
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|symbol| kind.as_ref().is_none_or(|kind| &symbol.kind == kind))
+            .filter_map(|symbol| {
+                name_match_score(&symbol.name, &query).map(|score| (symbol.clone(), score))
+            })
+            .collect();
+        rank_name_matches(&mut matches);
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    async fn symbols_enclosing(
+        &self,
+        path: String,
+        line: i32,
+        commit_id: Option<String>,
+    ) -> Result<Vec<StoredSymbol>, StorageError> {
+        let mut enclosing: Vec<StoredSymbol> = self
+            .symbols
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|symbol| symbol.path == path)
+            .filter(|symbol| symbol.start_line <= line && line <= symbol.end_line)
+            .filter(|symbol| commit_id.is_none() || symbol.commit_id == commit_id)
+            .cloned()
+            .collect();
+        enclosing.sort_by_key(|symbol| {
+            (
+                std::cmp::Reverse(symbol.end_line - symbol.start_line),
+                symbol.start_line,
+            )
+        });
+        Ok(enclosing)
+    }
+
+    async fn delete_path(
+        &self,
+        path: String,
+        commit_id: Option<String>,
+    ) -> Result<PathDeletion, StorageError> {
+        let mut symbols = self.symbols.lock().unwrap();
+        let before = symbols.len();
+        symbols.retain(|symbol| {
+            symbol.path != path
+                || commit_id
+                    .as_ref()
+                    .is_some_and(|c| symbol.commit_id.as_ref() != Some(c))
+        });
+        self.symbol_edges.lock().unwrap().retain(|edge| {
+            symbols.iter().any(|s| s.id == edge.parent_id)
+                && symbols.iter().any(|s| s.id == edge.child_id)
+        });
+        let symbols_deleted = before - symbols.len();
+
+        let mut documents = self.documents.lock().unwrap();
+        let before = documents.len();
+        documents.retain(|doc| {
+            doc.path != path
+                || commit_id
+                    .as_ref()
+                    .is_some_and(|c| doc.commit_id.as_ref() != Some(c))
+        });
+
+        Ok(PathDeletion {
+            documents: (before - documents.len()) as u64,
+            symbols: symbols_deleted as u64,
+        })
+    }
+
+    async fn touch(&self, path: String) -> Result<u64, StorageError> {
+        let mut touched = 0;
+        for doc in self.documents.lock().unwrap().iter_mut() {
+            if doc.path == path {
+                doc.updated_at = Utc::now();
+                touched += 1;
+            }
+        }
+        Ok(touched)
+    }
+
+    async fn delete_by_commit(&self, commit_id: String) -> Result<CommitDeletion, StorageError> {
+        let in_commit = |commit: &Option<String>| commit.as_deref() == Some(commit_id.as_str());
+
+        let mut documents = self.documents.lock().unwrap();
+        let before = documents.len();
+        documents.retain(|doc| !in_commit(&doc.commit_id));
+        let documents_deleted = before - documents.len();
+
+        let mut symbols = self.symbols.lock().unwrap();
+        let before = symbols.len();
+        symbols.retain(|symbol| !in_commit(&symbol.commit_id));
+        let symbols_deleted = before - symbols.len();
+
+        let mut versions = self.symbol_versions.lock().unwrap();
+        let before = versions.len();
+        versions.retain(|(_, version)| version.commit_id != commit_id);
+        let versions_deleted = before - versions.len();
+
+        Ok(CommitDeletion {
+            documents: documents_deleted as u64,
+            symbols: symbols_deleted as u64,
+            symbol_versions: versions_deleted as u64,
+        })
+    }
+
+    async fn find_orphans(&self) -> Result<OrphanReport, StorageError> {
+        Ok(self.orphans(false))
+    }
+
+    async fn delete_orphans(&self) -> Result<OrphanReport, StorageError> {
+        Ok(self.orphans(true))
+    }
+
+    async fn get_symbol(&self, id: Uuid) -> Result<Option<StoredSymbol>, StorageError> {
+        let symbols = self.symbols.lock().unwrap();
+        Ok(symbols.iter().find(|symbol| symbol.id == id).cloned())
     }
 
     async fn query_all_symbols(&self) -> Result<Vec<StoredSymbol>, StorageError> {
-        Ok(vec![])
+        let failing =
+            self.failing_symbol_queries
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if failing.is_ok() {
+            return Err(StorageError::Database(sqlx::Error::PoolTimedOut));
+        }
+        Ok(self.symbols.lock().unwrap().clone())
     }
 
-    async fn store_symbol(&self, _symbol: &StoredSymbol) -> Result<(), StorageError> {
+    async fn query_symbol_edges(&self) -> Result<Vec<SymbolEdge>, StorageError> {
+        Ok(self.symbol_edges.lock().unwrap().clone())
+    }
+
+    async fn store_symbol(&self, symbol: &StoredSymbol) -> Result<(), StorageError> {
+        let mut symbols = self.symbols.lock().unwrap();
+        symbols.retain(|existing| existing.id != symbol.id);
+        symbols.push(symbol.clone());
         Ok(())
     }
+
+    async fn store_ci_event(&self, event: &CiEvent) -> Result<(), StorageError> {
+        self.ci_events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+
+    async fn ci_events_for_commit(&self, commit_id: String) -> Result<Vec<CiEvent>, StorageError> {
+        Ok(self
+            .ci_events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.commit_id == commit_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn store_symbol_versions(
+        &self,
+        path: &str,
+        commit_id: &str,
+        versions: &[SymbolVersion],
+    ) -> Result<(), StorageError> {
+        let mut stored = self.symbol_versions.lock().unwrap();
+        stored.retain(|(p, version)| !(p == path && version.commit_id == commit_id));
+        stored.extend(
+            versions
+                .iter()
+                .map(|version| (path.to_string(), version.clone())),
+        );
+        Ok(())
+    }
+
+    async fn symbol_versions_for_path(
+        &self,
+        path: String,
+    ) -> Result<Vec<SymbolVersion>, StorageError> {
+        let mut versions: Vec<SymbolVersion> = self
+            .symbol_versions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(p, _)| *p == path)
+            .map(|(_, version)| version.clone())
+            .collect();
+        versions.sort_by_key(|version| std::cmp::Reverse(version.timestamp));
+        Ok(versions)
+    }
 }
 
 pub async fn create_test_storage() -> Arc<dyn IndexStorage> {
-    Arc::new(MockStorage)
+    Arc::new(MockStorage::default())
+}
+
+/// Creates a throwaway git repository with a single commit containing `files`
+/// and returns it together with the commit id.
+pub fn init_git_repo(files: &[(&str, &str)]) -> (TempDir, String) {
+    let dir = TempDir::new().expect("create temp dir");
+    let repo = Repository::init(dir.path()).expect("init repo");
+
+    let mut index = repo.index().expect("open index");
+    for (path, content) in files {
+        let full_path = dir.path().join(path);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent).expect("create parent dirs");
+        }
+        std::fs::write(&full_path, content).expect("write file");
+        index.add_path(Path::new(path)).expect("stage file");
+    }
+    index.write().expect("write index");
+
+    let tree_id = index.write_tree().expect("write tree");
+    let tree = repo.find_tree(tree_id).expect("find tree");
+    let signature = Signature::now("Test", "test@example.com").expect("signature");
+    let commit_id = repo
+        .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+        .expect("commit");
+
+    (dir, commit_id.to_string())
+}
+
+/// Creates a repository whose single commit has a flat tree of raw entries:
+/// `(name, data, filemode)`. A symlink's data is its target path and a
+/// submodule's (`0o160000`) is the hex id of the commit it pins.
+pub fn init_git_repo_with_entries(entries: &[(&str, &str, i32)]) -> (TempDir, String) {
+    let dir = TempDir::new().expect("create temp dir");
+    let repo = Repository::init(dir.path()).expect("init repo");
+
+    let mut builder = repo.treebuilder(None).expect("tree builder");
+    for (name, data, mode) in entries {
+        let oid = if *mode == 0o160000 {
+            git2::Oid::from_str(data).expect("submodule commit id")
+        } else {
+            repo.blob(data.as_bytes()).expect("write blob")
+        };
+        builder.insert(name, oid, *mode).expect("insert entry");
+    }
+    let tree_id = builder.write().expect("write tree");
+
+    let tree = repo.find_tree(tree_id).expect("find tree");
+    let signature = Signature::now("Test", "test@example.com").expect("signature");
+    let commit_id = repo
+        .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+        .expect("commit");
+
+    (dir, commit_id.to_string())
+}
+
+/// Writes `files` into the repository created by [`init_git_repo`] and commits
+/// them on top of HEAD, returning the new commit id.
+pub fn commit_files(dir: &Path, files: &[(&str, &str)]) -> String {
+    let repo = Repository::open(dir).expect("open repo");
+    let mut index = repo.index().expect("open index");
+    for (path, content) in files {
+        let full_path = dir.join(path);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent).expect("create parent dirs");
+        }
+        std::fs::write(&full_path, content).expect("write file");
+        index.add_path(Path::new(path)).expect("stage file");
+    }
+    index.write().expect("write index");
+
+    let tree_id = index.write_tree().expect("write tree");
+    let tree = repo.find_tree(tree_id).expect("find tree");
+    let parent = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .expect("head commit");
+    let signature = Signature::now("Test", "test@example.com").expect("signature");
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "update",
+        &tree,
+        &[&parent],
+    )
+    .expect("commit")
+    .to_string()
+}
+
+/// Removes `path` from the repository created by [`init_git_repo`] and
+/// commits the removal on top of HEAD, returning the new commit id.
+pub fn commit_removal(dir: &Path, path: &str) -> String {
+    let repo = Repository::open(dir).expect("open repo");
+    std::fs::remove_file(dir.join(path)).expect("remove file");
+
+    let mut index = repo.index().expect("open index");
+    index.remove_path(Path::new(path)).expect("unstage file");
+    index.write().expect("write index");
+
+    let tree_id = index.write_tree().expect("write tree");
+    let tree = repo.find_tree(tree_id).expect("find tree");
+    let parent = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .expect("head commit");
+    let signature = Signature::now("Test", "test@example.com").expect("signature");
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "remove",
+        &tree,
+        &[&parent],
+    )
+    .expect("commit")
+    .to_string()
+}
+
+/// Moves `from` to `to` in the repository created by [`init_git_repo`] and
+/// commits the move on top of HEAD, returning the new commit id.
+pub fn commit_rename(dir: &Path, from: &str, to: &str) -> String {
+    let repo = Repository::open(dir).expect("open repo");
+    let target = dir.join(to);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).expect("create parent dirs");
+    }
+    std::fs::rename(dir.join(from), &target).expect("move file");
+
+    let mut index = repo.index().expect("open index");
+    index
+        .remove_path(Path::new(from))
+        .expect("unstage old path");
+    index.add_path(Path::new(to)).expect("stage new path");
+    index.write().expect("write index");
+
+    let tree_id = index.write_tree().expect("write tree");
+    let tree = repo.find_tree(tree_id).expect("find tree");
+    let parent = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .expect("head commit");
+    let signature = Signature::now("Test", "test@example.com").expect("signature");
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "rename",
+        &tree,
+        &[&parent],
+    )
+    .expect("commit")
+    .to_string()
 }
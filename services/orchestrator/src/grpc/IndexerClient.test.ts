@@ -1,5 +1,6 @@
 import { describe, it, expect, beforeEach, afterEach, vi } from 'vitest';
-import { IndexerClient, SymbolKind } from './IndexerClient';
+import * as grpc from '@grpc/grpc-js';
+import { IndexerClient, SymbolKind, apiKeyInterceptor } from './IndexerClient';
 import pino from 'pino';
 
 describe('IndexerClient', () => {
@@ -85,4 +86,18 @@ describe('IndexerClient', () => {
     expect(deadlineTime).toBeGreaterThan(now);
     expect(deadlineTime).toBeLessThanOrEqual(now + 10000);
   });
+
+  it('should send the API key as a bearer token', () => {
+    let sent: grpc.Metadata | undefined;
+    const nextCall = {
+      start: (metadata: grpc.Metadata) => {
+        sent = metadata;
+      },
+    } as unknown as grpc.InterceptingCall;
+
+    const call = apiKeyInterceptor('s3cret')({} as any, () => nextCall);
+    call.start(new grpc.Metadata(), {});
+
+    expect(sent?.get('authorization')).toEqual(['Bearer s3cret']);
+  });
 });
@@ -73,6 +73,8 @@ export interface IndexerClientConfig {
   maxRetries?: number;
   retryDelayMs?: number;
   timeoutMs?: number;
+  /** Sent as a bearer token on every call; defaults to `INDEXER_API_KEY`. */
+  apiKey?: string;
 }
 
 // Proto message interfaces
@@ -234,6 +236,20 @@ interface IndexerServiceClient extends grpc.Client {
   ): grpc.ClientUnaryCall;
 }
 
+/**
+ * Adds `authorization: Bearer <apiKey>` metadata to every call, as the
+ * indexer's API key authentication expects.
+ */
+export function apiKeyInterceptor(apiKey: string): grpc.Interceptor {
+  return (options, nextCall) =>
+    new grpc.InterceptingCall(nextCall(options), {
+      start(metadata, listener, next) {
+        metadata.set("authorization", `Bearer ${apiKey}`);
+        next(metadata, listener);
+      },
+    });
+}
+
 /**
  * Client for interacting with the indexer gRPC service
  */
@@ -247,6 +263,7 @@ export class IndexerClient {
       maxRetries: 3,
       retryDelayMs: 1000,
       timeoutMs: 30000,
+      apiKey: process.env.INDEXER_API_KEY,
       ...config,
     };
     this.logger = config.logger.child({ component: "IndexerClient" });
@@ -269,11 +286,18 @@ export class IndexerClient {
     const indexerProto = protoDescriptor.indexer.v1;
 
     const address = `${this.config.host}:${this.config.port}`;
+    const apiKey = this.config.apiKey;
+    if (!apiKey) {
+      this.logger.warn(
+        "INDEXER_API_KEY is not set; the indexer will reject calls unless its authentication is disabled",
+      );
+    }
     this.logger.info({ address }, "Creating indexer gRPC client");
 
     return new indexerProto.IndexerService(
       address,
       grpc.credentials.createInsecure(),
+      apiKey ? { interceptors: [apiKeyInterceptor(apiKey)] } : {},
     ) as IndexerServiceClient;
   }
 